@@ -0,0 +1,65 @@
+extern crate glib;
+extern crate gstreamer as gst;
+
+use gst::prelude::*;
+
+/// Display names of the audio output devices.
+pub fn audio_output_names() -> Vec<String> {
+    let monitor = gst::DeviceMonitor::new();
+    if monitor.add_filter(Some("Audio/Sink"), None).is_none() {
+        return vec![];
+    }
+    monitor
+        .get_devices()
+        .iter()
+        .map(|device| device.get_display_name().to_string())
+        .collect()
+}
+
+/// Create a sink playing on the audio output device with this display name.
+pub fn create_audio_sink(name: &str) -> Option<gst::Element> {
+    let monitor = gst::DeviceMonitor::new();
+    monitor.add_filter(Some("Audio/Sink"), None)?;
+    monitor
+        .get_devices()
+        .into_iter()
+        .find(|device| device.get_display_name() == name)?
+        .create_element(None)
+        .ok()
+}
+
+/// Watch the audio output devices, such as headphones or Bluetooth
+/// speakers, for removals.
+pub struct AudioDeviceMonitor {
+    monitor: gst::DeviceMonitor,
+}
+
+impl AudioDeviceMonitor {
+    /// Start monitoring. `on_removed` is called from the main context with
+    /// the display name of every removed device.
+    pub fn new<F: Fn(&str) + 'static>(on_removed: F) -> Option<Self> {
+        let monitor = gst::DeviceMonitor::new();
+        monitor.add_filter(Some("Audio/Sink"), None)?;
+        let bus = monitor.get_bus();
+        bus.add_watch_local(move |_, msg| {
+            if let gst::MessageView::DeviceRemoved(removed) = msg.view() {
+                on_removed(&removed.get_device().get_display_name());
+            }
+            glib::Continue(true)
+        })
+        .ok()?;
+        if monitor.start().is_err() {
+            eprintln!("Unable to monitor the audio devices");
+            let _ = bus.remove_watch();
+            return None;
+        }
+        Some(Self { monitor })
+    }
+}
+
+impl Drop for AudioDeviceMonitor {
+    fn drop(&mut self) {
+        self.monitor.stop();
+        let _ = self.monitor.get_bus().remove_watch();
+    }
+}
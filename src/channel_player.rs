@@ -1,4 +1,5 @@
 extern crate gdk;
+extern crate gdk_pixbuf;
 extern crate glib;
 extern crate gstreamer as gst;
 extern crate gstreamer_player as gst_player;
@@ -11,9 +12,11 @@ use self::sha2::{Digest, Sha256};
 use failure::Error;
 use gdk::prelude::*;
 use gst::prelude::*;
+use gst_player::PlayerStreamInfoExt;
 use gtk::prelude::*;
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::fs;
 use std::fs::File;
 use std::io::Read;
 use std::io::Write;
@@ -21,6 +24,26 @@ use std::os::raw::c_void;
 use std::path;
 use std::process;
 use std::string;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::audio_devices;
+use crate::chapter_files::{self, Chapter};
+use crate::constants;
+use crate::downloader::{Download, DownloadResult};
+use crate::element_properties::ElementProperties;
+use crate::embedded_fonts::EmbeddedFonts;
+use crate::playlist::{expand_playlist_files, read_playlist_file};
+use crate::seek_thumbnails::SeekThumbnails;
+use crate::settings::{
+    ColorRange, Deinterlace, LiveResume, RepeatMode, ReplayGainMode, ResumeMode, SubtitleBackground, SubtitleScaleBase,
+    SubtitleScript,
+};
+use crate::subtitle_files;
+use crate::tone_mapping;
+
+// Peak luminance assumed for HDR content lacking mastering display metadata.
+const HDR_PEAK_NITS: f64 = 1000.0;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub enum PlaybackState {
@@ -41,6 +64,109 @@ pub enum SubtitleTrack {
 
 pub struct AudioVisualization(pub string::String);
 
+/// Color adjustments of the video, in the ranges of videobalance:
+/// brightness and hue from -1 to 1, contrast and saturation from 0 to 2.
+#[derive(Clone, Copy, PartialEq)]
+pub struct VideoBalance {
+    pub brightness: f64,
+    pub contrast: f64,
+    pub saturation: f64,
+    pub hue: f64,
+}
+
+impl Default for VideoBalance {
+    fn default() -> Self {
+        VideoBalance {
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            hue: 0.0,
+        }
+    }
+}
+
+impl VideoBalance {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn clamped(self) -> Self {
+        VideoBalance {
+            brightness: self.brightness.max(-1.0).min(1.0),
+            contrast: self.contrast.max(0.0).min(2.0),
+            saturation: self.saturation.max(0.0).min(2.0),
+            hue: self.hue.max(-1.0).min(1.0),
+        }
+    }
+}
+
+/// Shape of the video picture, overriding the one of the media.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AspectRatio {
+    Auto,
+    Ratio(i32, i32),
+    /// Fill the video area, whatever its shape.
+    Stretch,
+}
+
+impl AspectRatio {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(AspectRatio::Auto),
+            "stretch" => Some(AspectRatio::Stretch),
+            _ => parse_ratio(name)
+                .or_else(|| parse_decimal_ratio(name))
+                .map(|(n, d)| AspectRatio::Ratio(n, d)),
+        }
+    }
+
+    pub fn name(self) -> string::String {
+        match self {
+            AspectRatio::Auto => "auto".to_string(),
+            AspectRatio::Ratio(n, 100) => format!("{}:1", f64::from(n) / 100.0),
+            AspectRatio::Ratio(n, d) => format!("{}:{}", n, d),
+            AspectRatio::Stretch => "stretch".to_string(),
+        }
+    }
+}
+
+// "2.35:1" as (235, 100), for the ratios of films usually written with
+// decimals.
+fn parse_decimal_ratio(ratio: &str) -> Option<(i32, i32)> {
+    let mut parts = ratio.splitn(2, ':');
+    let numerator = parts.next()?.trim().parse::<f64>().ok()?;
+    if parts.next()?.trim() != "1" || !(numerator > 0.0 && numerator < 100.0) {
+        return None;
+    }
+    Some(((numerator * 100.0).round() as i32, 100))
+}
+
+// "32:27" as (32, 27).
+pub fn parse_ratio(ratio: &str) -> Option<(i32, i32)> {
+    let mut parts = ratio.splitn(2, ':');
+    let numerator = parts.next()?.trim().parse::<i32>().ok()?;
+    let denominator = parts.next()?.trim().parse::<i32>().ok()?;
+    if numerator > 0 && denominator > 0 {
+        Some((numerator, denominator))
+    } else {
+        None
+    }
+}
+
+#[derive(Clone)]
+pub struct SubtitleStyle {
+    pub background: SubtitleBackground,
+    pub background_opacity: f64,
+    pub scale: f64,
+    pub scale_base: SubtitleScaleBase,
+    pub script: SubtitleScript,
+    /// Pango font description replacing the families of the script, and the
+    /// default size when it has one.
+    pub font: Option<string::String>,
+    /// ARGB color of the text.
+    pub color: Option<u32>,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub enum PlayerEvent {
     MediaInfoUpdated,
@@ -53,15 +179,218 @@ pub enum PlayerEvent {
     Error(string::String),
     AudioVideoOffsetChanged(i64),
     SubtitleVideoOffsetChanged(i64),
+    DownloadProgress(f64),
+    Buffering(i32),
+    PlaylistItemSkipped(string::String, string::String),
+    // RMS level of each audio channel, in dB.
+    AudioLevels(Vec<f64>),
+    // Gain applied from the ReplayGain tags of the stream, in dB.
+    ReplayGainApplied(f64),
+    ResumedAt(u64),
+    ResumeAvailable(u64),
+    LiveResumeApplied(LiveResume),
+    ChaptersUpdated,
+    RateChanged(f64),
+    // Around the recreation of the video sink after a display change.
+    VideoSinkRebuilding,
+    VideoSinkRebuilt,
+    // Path of the PNG file, or the reason it couldn't be written.
+    SnapshotSaved(string::String),
+    SnapshotFailed(string::String),
+    // Whether the video of the media turned out to be HDR, or no longer is.
+    HdrDetected(bool),
+    #[cfg(feature = "clip-export")]
+    ClipExportProgress(f64),
+    #[cfg(feature = "clip-export")]
+    ClipExported(string::String),
+    #[cfg(feature = "clip-export")]
+    ClipExportFailed(string::String),
+    #[cfg(feature = "subtitle-auto-sync")]
+    SubtitleSyncSuggested(i64),
+    #[cfg(feature = "subtitle-auto-sync")]
+    SubtitleSyncFailed(string::String),
+}
+
+/// When set, remote media are fully downloaded to `directory` before being
+/// played. Media larger than `max_size` bytes, or of unknown size such as
+/// live streams, are streamed as usual.
+#[derive(Clone)]
+pub struct DownloadConfig {
+    pub directory: path::PathBuf,
+    pub max_size: u64,
+}
+
+// Track choices carried from a playlist item to the next one. Languages are
+// used because track indices are rarely consistent across files. The
+// subtitle size needs no carrying, it is a setting shared by all media.
+struct TrackSelection {
+    subtitles_enabled: bool,
+    subtitle_language: Option<glib::GString>,
+    audio_language: Option<glib::GString>,
+}
+
+/// Frames shown and dropped by the video sink for the current media, unknown
+/// for sinks not counting them.
+#[derive(Clone, Copy, Default)]
+pub struct FrameStats {
+    pub rendered: Option<u64>,
+    pub dropped: Option<u64>,
+}
+
+pub struct MirrorOutput {
+    tee_pad: gst::Pad,
+    branch: gst::Element,
 }
 
 pub struct ChannelPlayer {
     player: gst_player::Player,
-    video_area: gtk::Widget,
+    renderer: gst_player::PlayerVideoOverlayVideoRenderer,
+    video_tee: gst::Element,
+    video_sink: VideoSink,
+    subtitle_style: Arc<Mutex<SubtitleStyle>>,
+    // Height of the video area, followed by SubtitleScaleBase::Window.
+    subtitle_area_height: Arc<Mutex<i32>>,
+    color_range: Arc<Mutex<ColorRange>>,
+    deinterlace: Arc<Mutex<Deinterlace>>,
+    // Forced pixel aspect ratio, for anamorphic videos with wrong metadata.
+    pixel_aspect_ratio: Arc<Mutex<Option<gst::Fraction>>>,
+    // Forced display aspect ratio, for the current media only.
+    aspect_ratio: RefCell<AspectRatio>,
+    display_aspect_ratio: Arc<Mutex<Option<gst::Fraction>>>,
+    // Caps of the video, as negotiated before any color range override.
+    video_caps: Arc<Mutex<Option<gst::Caps>>>,
+    audio_level: Option<gst::Element>,
+    replay_gain: Option<gst::Element>,
+    replay_gain_mode: Arc<Mutex<ReplayGainMode>>,
+    // scaletempo, or identity to let the pitch follow the playback rate.
+    pitch_filter: Option<Arc<Mutex<gst::Element>>>,
+    // The volume normalization, or identity when disabled.
+    normalization: Option<Arc<Mutex<gst::Element>>>,
+    // equalizer-10bands, or identity when disabled.
+    equalizer: Option<Arc<Mutex<gst::Element>>>,
+    // The color adjustments, or identity while they are all at defaults.
+    video_balance: Option<Arc<Mutex<gst::Element>>>,
+    video_balance_values: RefCell<VideoBalance>,
+    // Maps HDR video to SDR, identity when disabled or for SDR video.
+    tone_mapping: Option<Arc<Mutex<gst::Element>>>,
+    tone_mapping_enabled: Cell<bool>,
+    channel_remap: Option<ChannelRemapFilter>,
+    // Media and position to restore after releasing the pipeline, see
+    // release_while_paused().
+    released_at: RefCell<Option<(glib::GString, gst::ClockTime)>>,
+    chapters: Arc<Mutex<MediaChapters>>,
+    video_zoom: Arc<Mutex<VideoZoom>>,
+    seek_thumbnails: RefCell<Option<SeekThumbnails>>,
+}
+
+// Chapters of the current media, from its table of contents and from a
+// chapter file next to it.
+#[derive(Default)]
+struct MediaChapters {
+    embedded: Vec<Chapter>,
+    sidecar: Vec<Chapter>,
+}
+
+// Playlist items, by index and URI, for gapless transitions, see
+// connect_gapless().
+#[derive(Default)]
+struct GaplessItems {
+    // The item to give to the playbin once the current one is about to end.
+    next: Option<(usize, string::String)>,
+    // The item given to the playbin, until it starts.
+    queued: Option<(usize, string::String)>,
+}
+
+fn normal_rate() -> f64 {
+    1.0
+}
+
+/// Settings chosen for a single media, or the media of a folder, overriding
+/// the global ones.
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct MediaOverrides {
+    /// Name of the aspect ratio, see `AspectRatio`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<string::String>,
+    /// Subtitle offset set manually, in nanoseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle_offset: Option<i64>,
+    /// Character encoding forced for the external subtitle files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle_encoding: Option<string::String>,
+}
+
+impl MediaOverrides {
+    fn is_empty(&self) -> bool {
+        *self == MediaOverrides::default()
+    }
+}
+
+fn is_normal_rate(rate: &f64) -> bool {
+    (rate - normal_rate()).abs() < std::f64::EPSILON
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+struct MediaCacheEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<u64>,
+    #[serde(skip_serializing_if = "is_normal_rate")]
+    rate: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    volume: Option<f64>,
+    #[serde(skip_serializing_if = "MediaOverrides::is_empty")]
+    overrides: MediaOverrides,
+}
+
+impl Default for MediaCacheEntry {
+    fn default() -> Self {
+        Self {
+            position: None,
+            rate: normal_rate(),
+            volume: None,
+            overrides: MediaOverrides::default(),
+        }
+    }
+}
+
+impl MediaCacheEntry {
+    fn is_empty(&self) -> bool {
+        self.position.is_none() && is_normal_rate(&self.rate) && self.volume.is_none() && self.overrides.is_empty()
+    }
+}
+
+// Entries were a bare position before playback rates were stored.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredMediaCacheEntry {
+    Position(u64),
+    Entry(MediaCacheEntry),
+}
+
+impl From<StoredMediaCacheEntry> for MediaCacheEntry {
+    fn from(entry: StoredMediaCacheEntry) -> Self {
+        match entry {
+            StoredMediaCacheEntry::Position(position) => Self {
+                position: Some(position),
+                ..Self::default()
+            },
+            StoredMediaCacheEntry::Entry(entry) => entry,
+        }
+    }
+}
+
+fn deserialize_entries<'de, D>(deserializer: D) -> Result<HashMap<string::String, MediaCacheEntry>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let entries: HashMap<string::String, StoredMediaCacheEntry> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(entries.into_iter().map(|(id, entry)| (id, entry.into())).collect())
 }
 
 #[derive(Serialize, Deserialize)]
-struct MediaCacheData(pub HashMap<string::String, u64>);
+struct MediaCacheData(#[serde(deserialize_with = "deserialize_entries")] pub HashMap<string::String, MediaCacheEntry>);
 
 struct MediaCache {
     path: path::PathBuf,
@@ -73,7 +402,61 @@ struct PlayerDataHolder {
     playlist: Vec<string::String>,
     current_uri: glib::GString,
     index: usize,
+    auto_advance: bool,
+    carry_tracks: bool,
+    carried_tracks: Option<TrackSelection>,
+    skip_unplayable: bool,
+    load_retries: u32,
+    retries_left: u32,
     cache: Option<MediaCache>,
+    resume_mode: ResumeMode,
+    live_resume: LiveResume,
+    // Position left in the live stream being loaded, applied once its
+    // seekability is known, see resume_live_stream().
+    live_resume_position: Option<gst::ClockTime>,
+    // Stay paused on the first frame, or the resume position, after loading.
+    start_paused: bool,
+    rescan_missing_subtitles: bool,
+    // Encoding of external subtitle files, detected when none is forced.
+    subtitle_encoding: Option<string::String>,
+    // Resume choices made in this session, by URI.
+    resume_choices: HashMap<string::String, bool>,
+    download_config: Option<DownloadConfig>,
+    download: Option<Download>,
+    embedded_fonts: Option<EmbeddedFonts>,
+    // Set while a new media prerolls, telling whether to play it once its
+    // first frame is shown.
+    play_after_preroll: Option<bool>,
+    // MediaInfoUpdated is held back until the first frame is shown.
+    media_info_pending: bool,
+    // The last two media loaded, in or out of the playlist, and the
+    // positions to restore when toggling between them.
+    loaded_uri: Option<string::String>,
+    previous_uri: Option<string::String>,
+    toggled_positions: HashMap<string::String, gst::ClockTime>,
+    // Media forgotten while loaded, whose position is not stored again until
+    // they are loaded anew.
+    forgotten_uris: HashSet<string::String>,
+    // Start and end of the A-B loop of the loaded media.
+    loop_points: (Option<gst::ClockTime>, Option<gst::ClockTime>),
+    // Indices of the playlist items in the order they are played, the
+    // playlist order unless shuffled.
+    order: Vec<usize>,
+    shuffle: bool,
+    repeat: RepeatMode,
+    gapless: bool,
+    gapless_items: Arc<Mutex<GaplessItems>>,
+    // The item playing since a gapless transition, GstPlayer still reporting
+    // the URI of the one it loaded.
+    gapless_uri: Option<string::String>,
+    // Volume of the media whose volume was never changed.
+    default_volume: f64,
+    // Clockwise rotation of the video in degrees, and its dimensions before
+    // rotation.
+    video_rotation: u32,
+    video_dimensions: (i32, i32),
+    // Transfer function of the video, if HDR.
+    hdr_transfer: Option<tone_mapping::HdrTransfer>,
 }
 
 thread_local!(
@@ -123,30 +506,95 @@ impl MediaCache {
         })
     }
 
+    // Change an entry, which is removed once left without anything to store.
+    fn modify<F: FnOnce(&mut MediaCacheEntry)>(&mut self, id: string::String, f: F) {
+        let mut entry = self.data.0.remove(&id).unwrap_or_default();
+        f(&mut entry);
+        if !entry.is_empty() {
+            self.data.0.insert(id, entry);
+        }
+    }
+
     fn update<K: Into<String>>(&mut self, id: K, value: u64) {
-        self.data.0.insert(id.into(), value);
+        self.modify(id.into(), |entry| entry.position = Some(value));
+    }
+
+    // Forget the position, keeping the playback rate, volume and overrides.
+    fn clear_position(&mut self, id: &str) {
+        self.modify(id.to_string(), |entry| entry.position = None);
+    }
+
+    fn update_rate(&mut self, uri: &str, rate: f64) {
+        self.modify(uri_to_sha256(uri), |entry| entry.rate = rate);
+    }
+
+    fn update_volume(&mut self, uri: &str, volume: f64) {
+        self.modify(uri_to_sha256(uri), |entry| entry.volume = Some(volume));
+    }
+
+    fn update_overrides<F: FnOnce(&mut MediaOverrides)>(&mut self, id: string::String, f: F) {
+        self.modify(id, |entry| f(&mut entry.overrides));
+    }
+
+    fn find_overrides(&self, id: &str) -> MediaOverrides {
+        self.data
+            .0
+            .get(id)
+            .map(|entry| entry.overrides.clone())
+            .unwrap_or_default()
+    }
+
+    fn find_last_rate(&self, uri: &str) -> f64 {
+        self.data
+            .0
+            .get(&uri_to_sha256(uri))
+            .map_or(normal_rate(), |entry| entry.rate)
+    }
+
+    fn find_last_volume(&self, uri: &str) -> Option<f64> {
+        self.data.0.get(&uri_to_sha256(uri)).and_then(|entry| entry.volume)
+    }
+
+    fn forget(&mut self, uri: &str) {
+        self.data.0.remove(&uri_to_sha256(uri));
+        self.data.0.remove(&live_stream_id(uri));
+    }
+
+    fn clear(&mut self) {
+        self.data.0.clear();
     }
 
+    // Written next to the cache and renamed over it, so that an interrupted
+    // write does not lose all the positions.
     fn write(&self) -> Result<(), Error> {
-        let mut file = File::create(&self.path)?;
+        let temporary_path = self.path.with_extension("json.tmp");
+        let mut file = File::create(&temporary_path)?;
 
         let json = serde_json::to_string(&self.data)?;
         file.write_all(json.as_bytes())?;
         file.sync_all()?;
+        fs::rename(&temporary_path, &self.path)?;
         Ok(())
     }
 
     fn find_last_position(&self, uri: &str) -> gst::ClockTime {
-        let id = uri_to_sha256(uri);
-        if let Some(position) = self.data.0.get(&id) {
-            return gst::ClockTime::from_nseconds(*position);
+        self.find_position(&uri_to_sha256(uri))
+    }
+
+    fn find_last_live_position(&self, uri: &str) -> gst::ClockTime {
+        self.find_position(&live_stream_id(uri))
+    }
+
+    fn find_position(&self, id: &str) -> gst::ClockTime {
+        if let Some(position) = self.data.0.get(id).and_then(|entry| entry.position) {
+            return gst::ClockTime::from_nseconds(position);
         }
 
         gst::ClockTime::none()
     }
 }
 
-fn uri_to_sha256(uri: &str) -> string::String {
+pub fn uri_to_sha256(uri: &str) -> string::String {
     let mut sh = Sha256::new();
     sh.update(uri.as_bytes());
     sh.finalize()
@@ -156,6 +604,113 @@ fn uri_to_sha256(uri: &str) -> string::String {
         .concat()
 }
 
+// Positions in live streams are stored apart, so that they are not offered
+// like the ones of other media.
+fn live_stream_id(uri: &str) -> string::String {
+    format!("live-{}", uri_to_sha256(uri))
+}
+
+// Overrides shared by the media of a folder are stored apart from the ones
+// of single media.
+fn folder_id(folder: &path::Path) -> string::String {
+    format!("folder-{}", uri_to_sha256(&folder.to_string_lossy()))
+}
+
+// The playlist item to queue once the one at `position` of the play order
+// is about to end, if any. A repeated item is sought back instead, and
+// remote media to download first can't be queued.
+fn gapless_next_item(
+    playlist: &[string::String],
+    order: &[usize],
+    position: usize,
+    repeat: RepeatMode,
+    download_first: bool,
+) -> Option<(usize, string::String)> {
+    if repeat == RepeatMode::One {
+        return None;
+    }
+    let index = match order.get(position + 1) {
+        Some(index) => *index,
+        None if repeat == RepeatMode::All => *order.first()?,
+        None => return None,
+    };
+    let uri = playlist.get(index)?;
+    if download_first && is_remote_uri(uri) {
+        return None;
+    }
+    Some((index, uri.clone()))
+}
+
+// Fisher-Yates shuffle.
+fn shuffle<T>(items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        let j = glib::random_int_range(0, i as i32 + 1) as usize;
+        items.swap(i, j);
+    }
+}
+
+fn is_remote_uri(uri: &str) -> bool {
+    match glib::uri_parse_scheme(uri) {
+        Some(scheme) => scheme == "http" || scheme == "https",
+        None => false,
+    }
+}
+
+// Downloads are stored under a per-URI directory, keeping the original file
+// name so that it can still be used as window title.
+fn download_destination(directory: &path::Path, uri: &str) -> path::PathBuf {
+    let uri_path = uri.split(|c| c == '?' || c == '#').next().unwrap_or(uri);
+    let file_name = uri_path
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .and_then(|name| glib::uri_unescape_string(name, Some("/")))
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| "media".to_string());
+    directory.join(uri_to_sha256(uri)).join(file_name)
+}
+
+fn start_download(
+    player: &gst_player::Player,
+    uri: &str,
+    destination: path::PathBuf,
+    max_size: u64,
+) -> Result<Download, Error> {
+    let player_weak = player.downgrade();
+    let on_progress = move |fraction| {
+        if let Some(player) = player_weak.upgrade() {
+            with_player!(player {
+                player.notify(PlayerEvent::DownloadProgress(fraction));
+            });
+        }
+    };
+
+    let player_weak = player.downgrade();
+    let remote_uri = uri.to_string();
+    let on_done = move |result| {
+        let player = match player_weak.upgrade() {
+            Some(player) => player,
+            None => return,
+        };
+        let uri = match result {
+            DownloadResult::Finished(path) => glib::filename_to_uri(&path, None)
+                .map(|uri| uri.to_string())
+                .unwrap_or_else(|_| remote_uri.clone()),
+            DownloadResult::Unsuitable => remote_uri.clone(),
+            DownloadResult::Failed(message) => {
+                eprintln!("Download of {} failed, streaming it instead: {}", remote_uri, message);
+                remote_uri.clone()
+            }
+        };
+        with_mut_player!(player player_data {
+            player_data.download = None;
+        });
+        player.set_property("uri", &glib::Value::from(&uri)).unwrap();
+    };
+
+    Download::start(uri, destination, max_size, on_progress, on_done)
+}
+
 fn prepare_video_overlay(video_area: &gtk::DrawingArea, video_overlay: &gst_player::PlayerVideoOverlayVideoRenderer) {
     let gdk_window = video_area.get_window().unwrap();
     if !gdk_window.ensure_native() {
@@ -206,9 +761,61 @@ fn prepare_video_overlay(video_area: &gtk::DrawingArea, video_overlay: &gst_play
 }
 
 impl PlayerDataHolder {
-    fn set_playlist(&mut self, playlist: Vec<string::String>) {
+    fn set_playlist(&mut self, playlist: Vec<string::String>, index: usize) {
         self.playlist = playlist;
-        self.index = 0;
+        self.index = index;
+        self.carried_tracks = None;
+        self.retries_left = self.load_retries;
+        self.reorder();
+    }
+
+    // When shuffled, the current item comes first so that it isn't played
+    // again right away.
+    fn reorder(&mut self) {
+        if !self.shuffle {
+            self.order = (0..self.playlist.len()).collect();
+            self.update_gapless_next();
+            return;
+        }
+        let mut order = (0..self.playlist.len())
+            .filter(|index| *index != self.index)
+            .collect::<Vec<_>>();
+        shuffle(&mut order);
+        if self.index < self.playlist.len() {
+            order.insert(0, self.index);
+        }
+        self.order = order;
+        self.update_gapless_next();
+    }
+
+    fn order_position(&self) -> Option<usize> {
+        self.order.iter().position(|index| *index == self.index)
+    }
+
+    fn append_to_playlist(&mut self, uris: Vec<string::String>) {
+        // Media opened on their own aren't part of the playlist, which then
+        // starts over with them.
+        let current = self.playlist.get(self.index);
+        if let Some(ref uri) = self.loaded_uri {
+            if current != Some(uri) {
+                self.playlist = vec![uri.clone()];
+                self.index = 0;
+                self.order = vec![0];
+            }
+        }
+        // Shuffled, the new items are spread among those not played yet.
+        let start = self.playlist.len();
+        self.playlist.extend(uris);
+        let played = self.order_position().map_or(0, |position| position + 1);
+        for index in start..self.playlist.len() {
+            let position = if self.shuffle {
+                glib::random_int_range(played as i32, self.order.len() as i32 + 1) as usize
+            } else {
+                self.order.len()
+            };
+            self.order.insert(position, index);
+        }
+        self.update_gapless_next();
     }
 
     #[allow(dead_code)]
@@ -225,355 +832,3075 @@ impl PlayerDataHolder {
     fn media_info_updated(&mut self, info: &gst_player::PlayerMediaInfo) {
         let uri = info.get_uri();
 
+        // Attachments are global tags, which might only show up in later
+        // updates.
+        if self
+            .embedded_fonts
+            .as_ref()
+            .map_or(true, |fonts| fonts.uri != uri.as_str())
+        {
+            // Unregister the fonts of the previous media first.
+            self.embedded_fonts = None;
+            if let Some(tags) = info.get_tags() {
+                if tags.get::<gst::tags::Attachment>().is_some() {
+                    self.embedded_fonts = Some(EmbeddedFonts::register(&uri, &tags));
+                }
+            }
+        }
+
+        // The caps of the video might only be known in later updates.
+        let hdr_transfer = stream_hdr_transfer(info);
+        if hdr_transfer != self.hdr_transfer {
+            self.hdr_transfer = hdr_transfer;
+            self.notify(PlayerEvent::HdrDetected(hdr_transfer.is_some()));
+        }
+
         // Call this only once per asset.
         if self.current_uri != *uri {
             self.current_uri = uri;
+            if self.play_after_preroll.is_some() {
+                self.media_info_pending = true;
+            } else {
+                self.notify(PlayerEvent::MediaInfoUpdated);
+            }
+        }
+    }
+
+    // Called when the pipeline reached PAUSED. Returns true if the media
+    // just loaded should now play.
+    fn prerolled(&mut self) -> bool {
+        let play = match self.play_after_preroll.take() {
+            Some(play) => play,
+            None => return false,
+        };
+        if self.media_info_pending {
+            self.media_info_pending = false;
             self.notify(PlayerEvent::MediaInfoUpdated);
         }
+        play
     }
 
-    fn end_of_stream(&mut self, player: &gst_player::Player) {
-        if let Some(uri) = player.get_uri() {
-            self.notify(PlayerEvent::EndOfStream(uri.into()));
-            self.index += 1;
+    fn load_uri(&mut self, player: &gst_player::Player, uri: &str) {
+        // Cancel any download of the previous media.
+        self.download = None;
+        self.gapless_items.lock().unwrap().queued = None;
+        self.gapless_uri = None;
 
-            if self.index < self.playlist.len() {
-                let next_uri = &*self.playlist[self.index];
-                player.set_property("uri", &glib::Value::from(&next_uri)).unwrap();
-            } else {
-                self.notify(PlayerEvent::EndOfPlaylist);
+        if self.loaded_uri.as_deref() != Some(uri) {
+            self.previous_uri = self.loaded_uri.replace(uri.to_string());
+            self.loop_points = (None, None);
+            // An audio delay is specific to the media.
+            player
+                .set_property("audio-video-offset", &glib::Value::from(&0i64))
+                .unwrap();
+            if self.video_rotation != 0 {
+                self.video_rotation = 0;
+                set_video_flip(&player.get_pipeline(), 0);
             }
         }
-    }
+        self.forgotten_uris.remove(uri);
+        self.update_gapless_next();
 
-    fn update_cache_and_write(&mut self, id: string::String, position: u64) {
-        if let Some(ref mut cache) = self.cache {
-            cache.update(id, position);
-            cache.write().unwrap();
+        let mut uri = uri.to_string();
+        if let Some(ref config) = self.download_config {
+            if is_remote_uri(&uri) {
+                let destination = download_destination(&config.directory, &uri);
+                if destination.exists() {
+                    if let Ok(local_uri) = glib::filename_to_uri(&destination, None) {
+                        uri = local_uri.to_string();
+                    }
+                } else {
+                    match start_download(player, &uri, destination, config.max_size) {
+                        Ok(download) => {
+                            self.download = Some(download);
+                            return;
+                        }
+                        Err(e) => eprintln!("Unable to download {}: {}", uri, e),
+                    };
+                }
+            }
         }
+        player.set_property("uri", &glib::Value::from(&uri)).unwrap();
     }
-}
 
-fn create_renderer() -> (Option<gst_player::PlayerVideoOverlayVideoRenderer>, Option<gtk::Widget>) {
-    if let Ok(gtkglsink) = gst::ElementFactory::make("gtkglsink", None) {
-        let glsinkbin = gst::ElementFactory::make("glsinkbin", None).unwrap();
-        glsinkbin.set_property("sink", &gtkglsink.to_value()).unwrap();
+    fn last_position(&self, uri: &str) -> gst::ClockTime {
+        match self.cache {
+            Some(ref cache) => cache.find_last_position(uri),
+            None => gst::ClockTime::none(),
+        }
+    }
 
-        let widget = gtkglsink.get_property("widget").unwrap();
-        (
-            Some(gst_player::PlayerVideoOverlayVideoRenderer::with_sink(&glsinkbin)),
-            widget
-                .get::<gtk::Widget>()
-                .expect("Widget property should be a Widget..."),
-        )
-    } else if let Ok(sink) = gst::ElementFactory::make("glimagesink", None) {
-        let video_area = gtk::DrawingArea::new();
+    // Resume the media from its last known position, according to the resume
+    // mode. Returns false if playback should wait for the user to choose.
+    fn uri_loaded(&mut self, player: &gst_player::Player, uri: &str) -> bool {
+        if let Some(ref cache) = self.cache {
+            let rate = cache.find_last_rate(uri);
+            if (player.get_rate() - rate).abs() > std::f64::EPSILON {
+                player.set_rate(rate);
+                self.notify(PlayerEvent::RateChanged(rate));
+            }
+            // GstPlayer notifies the volume change itself.
+            let volume = cache.find_last_volume(uri).unwrap_or(self.default_volume);
+            if (player.get_volume() - volume).abs() > std::f64::EPSILON {
+                player.set_volume(volume);
+            }
+        }
+        if let Some(position) = self.toggled_positions.remove(uri) {
+            player.seek(position);
+            return !self.start_paused;
+        }
+        self.live_resume_position = match self.cache {
+            Some(ref cache) if is_remote_uri(uri) => Some(cache.find_last_live_position(uri)),
+            _ => None,
+        }
+        .filter(|position| position.nseconds().is_some());
+        let position = self.last_position(uri);
+        let nanoseconds = match position.nseconds() {
+            // Positions that close to the start were stored by older versions.
+            Some(nanoseconds) if nanoseconds >= RESUME_MARGIN => nanoseconds,
+            _ => return !self.start_paused,
+        };
+        let resume = match self.resume_choices.get(uri) {
+            Some(resume) => *resume,
+            None => match self.resume_mode {
+                ResumeMode::Always => true,
+                ResumeMode::Never => false,
+                ResumeMode::Ask => {
+                    self.notify(PlayerEvent::ResumeAvailable(nanoseconds));
+                    return false;
+                }
+            },
+        };
+        if resume {
+            player.seek(position);
+            self.notify(PlayerEvent::ResumedAt(nanoseconds));
+        }
+        !self.start_paused
+    }
 
-        let renderer = gst_player::PlayerVideoOverlayVideoRenderer::with_sink(&sink);
-        let renderer_weak = renderer.downgrade();
-        video_area.connect_realize(move |video_area| {
-            let renderer = match renderer_weak.upgrade() {
-                Some(renderer) => renderer,
-                None => return,
+    // Called once a live stream with a known position prerolled, seeking
+    // back to that position if allowed and possible. It otherwise plays from
+    // the live edge.
+    fn resume_live_stream(&mut self, player: &gst_player::Player) {
+        let position = match self.live_resume_position.take() {
+            Some(position) => position,
+            None => return,
+        };
+        let seekable = player.get_media_info().map_or(false, |info| info.is_seekable());
+        let applied =
+            if seekable && self.live_resume == LiveResume::LastPosition && self.resume_mode != ResumeMode::Never {
+                player.seek(position);
+                LiveResume::LastPosition
+            } else {
+                LiveResume::LiveEdge
             };
-            prepare_video_overlay(&video_area, &renderer);
-        });
+        self.notify(PlayerEvent::LiveResumeApplied(applied));
+    }
 
-        (Some(renderer), Some(video_area.upcast::<gtk::Widget>()))
-    } else {
-        (None, None)
+    fn has_next(&self) -> bool {
+        self.order_position().map_or(false, |position| {
+            position + 1 < self.order.len() || self.repeat == RepeatMode::All
+        })
     }
-}
 
-impl ChannelPlayer {
-    pub fn new(sender: glib::Sender<PlayerEvent>, cache_file_path: Option<path::PathBuf>) -> Result<Self, Error> {
-        let (renderer, video_area) = create_renderer();
-        if renderer.is_none() {
-            return Err(failure::err_msg("Neither gtkglsink nor glimagesink found. Make sure to install gst-plugins-good with GTK support enabled, or gst-plugins-base"));
-        }
-        let renderer1 = Some(
-            renderer
-                .as_ref()
-                .unwrap()
-                .clone()
-                .upcast::<gst_player::PlayerVideoRenderer>(),
-        );
-        let dispatcher = gst_player::PlayerGMainContextSignalDispatcher::new(None);
-        let player = gst_player::Player::new(
-            renderer1.as_ref(),
-            Some(&dispatcher.upcast::<gst_player::PlayerSignalDispatcher>()),
-        );
+    fn next(&mut self, player: &gst_player::Player) -> bool {
+        self.advance(player, self.repeat == RepeatMode::All)
+    }
 
-        // Get position updates every 250ms.
-        let mut config = player.get_config();
-        config.set_position_update_interval(250);
-        player.set_config(config).unwrap();
+    // Load the item after the current one in the play order, or the first
+    // one at the end of the playlist if wrapping.
+    fn advance(&mut self, player: &gst_player::Player, wrap: bool) -> bool {
+        let index = match self.order_position() {
+            Some(position) if position + 1 < self.order.len() => self.order[position + 1],
+            Some(_) if wrap => self.order[0],
+            _ => return false,
+        };
+        self.load_item(player, index);
+        true
+    }
+
+    fn previous(&mut self, player: &gst_player::Player) -> bool {
+        let index = match self.order_position() {
+            Some(position) if position > 0 => self.order[position - 1],
+            Some(_) if self.repeat == RepeatMode::All => self.order[self.order.len() - 1],
+            _ => return false,
+        };
+        self.load_item(player, index);
+        true
+    }
 
-        let video_area = video_area.unwrap();
-        video_area.connect_draw(move |video_area, cairo_context| {
-            let width = video_area.get_allocated_width();
-            let height = video_area.get_allocated_height();
+    fn load_item(&mut self, player: &gst_player::Player, index: usize) {
+        if self.carry_tracks {
+            let subtitle_track = player.get_current_subtitle_track();
+            self.carried_tracks = Some(TrackSelection {
+                subtitles_enabled: subtitle_track.is_some(),
+                subtitle_language: subtitle_track.and_then(|t| t.get_language()),
+                audio_language: player.get_current_audio_track().and_then(|t| t.get_language()),
+            });
+        }
+        self.index = index;
+        self.retries_left = self.load_retries;
+        let uri = self.playlist[self.index].clone();
+        self.load_uri(player, &uri);
+    }
 
-            // Paint some black borders
-            cairo_context.rectangle(0., 0., f64::from(width), f64::from(height));
-            cairo_context.fill();
+    // The URI of the item playing, which GstPlayer doesn't know of after a
+    // gapless transition.
+    fn playing_uri(&self, player: &gst_player::Player) -> Option<string::String> {
+        self.gapless_uri
+            .clone()
+            .or_else(|| player.get_uri().map(string::String::from))
+    }
 
-            Inhibit(false)
+    // Queue the item following the current one for a gapless transition, if
+    // it is to be loaded right when the current one ends. Media to download
+    // are loaded as usual.
+    fn update_gapless_next(&self) {
+        let enabled = self.gapless && self.auto_advance && self.loaded_uri.as_ref() == self.playlist.get(self.index);
+        let next = self.order_position().filter(|_| enabled).and_then(|position| {
+            let download_first = self.download_config.is_some();
+            gapless_next_item(&self.playlist, &self.order, position, self.repeat, download_first)
         });
+        self.gapless_items.lock().unwrap().next = next;
+    }
 
-        let player_weak = player.downgrade();
-        let renderer_weak = renderer.unwrap().downgrade();
-        video_area.connect_configure_event(move |video_area, event| -> bool {
-            let (width, height) = event.get_size();
-            let (x, y) = event.get_position();
-            let rect = gst_video::VideoRectangle::new(x, y, width as i32, height as i32);
-
-            let player = match player_weak.upgrade() {
-                Some(player) => player,
-                None => return true,
-            };
-            if let Ok(video_track) = player.get_property("current-video-track") {
-                let video_track = video_track
-                    .get::<gst_player::PlayerVideoInfo>()
-                    .expect("current-video-track should be a PlayerVideoInfo");
-                if let Some(video_track) = video_track {
-                    let video_width = video_track.get_width();
-                    let video_height = video_track.get_height();
-                    let src_rect = gst_video::VideoRectangle::new(0, 0, video_width, video_height);
-
-                    let rect = gst_video::center_video_rectangle(&src_rect, &rect, true);
-                    let renderer = match renderer_weak.upgrade() {
-                        Some(renderer) => renderer,
-                        None => return true,
-                    };
-                    renderer.set_render_rectangle(rect.x, rect.y, rect.w, rect.h);
-                    renderer.expose();
-                    video_area.queue_draw();
-                }
+    // The queued item started playing, the previous one played until its
+    // end.
+    fn gapless_advanced(&mut self, player: &gst_player::Player, index: usize, uri: string::String) {
+        if let Some(previous) = self.playing_uri(player) {
+            if !self.forgotten_uris.contains(&previous) {
+                self.clear_cached_position_and_write(&uri_to_sha256(&previous));
             }
-            true
-        });
+            self.notify(PlayerEvent::EndOfStream(previous));
+        }
+        if self.playlist.get(index) == Some(&uri) {
+            self.index = index;
+        } else if let Some(index) = self.playlist.iter().position(|item| *item == uri) {
+            self.index = index;
+        }
+        self.retries_left = self.load_retries;
+        self.previous_uri = self.loaded_uri.replace(uri.clone());
+        self.loop_points = (None, None);
+        self.gapless_uri = Some(uri);
+        self.notify(PlayerEvent::MediaInfoUpdated);
+        self.update_gapless_next();
+    }
 
-        player.connect_uri_loaded(|player, uri| {
-            player.pause();
-            with_mut_player!(player player_data {
-                if let Some(ref cache) = player_data.cache {
-                    let position = cache.find_last_position(uri);
-                    if position.is_some() {
-                        player.seek(position);
-                    }
-                }
-            });
-            player.play();
-        });
+    fn end_of_stream(&mut self, player: &gst_player::Player) {
+        if let Some(uri) = self.playing_uri(player) {
+            self.notify(PlayerEvent::EndOfStream(uri));
 
-        player.connect_end_of_stream(|player| {
-            with_mut_player!(player player_data {
-                player_data.end_of_stream(player);
-            });
-        });
+            if self.repeat == RepeatMode::One {
+                player.seek(gst::ClockTime::from_seconds(0));
+                player.play();
+            } else if !self.has_next() {
+                self.notify(PlayerEvent::EndOfPlaylist);
+            } else if self.auto_advance {
+                self.next(player);
+            }
+        }
+    }
 
-        player.connect_media_info_updated(|player, info| {
-            with_mut_player!(player player_data {
-                player_data.media_info_updated(&info);
-            });
-        });
+    // Errors of playlist items are handled here by retrying or skipping them,
+    // returns false if the error should be reported instead.
+    fn error(&mut self, player: &gst_player::Player, message: &str) -> bool {
+        if !self.skip_unplayable || self.playlist.len() < 2 {
+            return false;
+        }
+        let uri = self.playlist[self.index].clone();
+        if self.retries_left > 0 {
+            self.retries_left -= 1;
+            self.load_uri(player, &uri);
+            return true;
+        }
 
-        player.connect_position_updated(|player, _| {
-            with_player!(player {
-                player.notify(PlayerEvent::PositionUpdated);
-            });
-        });
+        self.notify(PlayerEvent::PlaylistItemSkipped(uri, message.to_string()));
+        // Not wrapping around, which would go on forever if no item plays.
+        if !self.advance(player, false) {
+            self.notify(PlayerEvent::EndOfPlaylist);
+        }
+        true
+    }
 
-        player.connect_video_dimensions_changed(|player, width, height| {
-            with_player!(player {
-                player.notify(PlayerEvent::VideoDimensionsChanged(width, height));
+    fn update_cache_and_write(&mut self, id: string::String, position: u64) {
+        if let Some(ref mut cache) = self.cache {
+            cache.update(id, position);
+            cache.write().unwrap();
+        }
+    }
+
+    fn clear_cached_position_and_write(&mut self, id: &str) {
+        if let Some(ref mut cache) = self.cache {
+            cache.clear_position(id);
+            cache.write().unwrap();
+        }
+    }
+
+    fn update_cache_rate_and_write(&mut self, uri: &str, rate: f64) {
+        if let Some(ref mut cache) = self.cache {
+            cache.update_rate(uri, rate);
+            cache.write().unwrap();
+        }
+    }
+
+    fn update_cache_volume_and_write(&mut self, uri: &str, volume: f64) {
+        if let Some(ref mut cache) = self.cache {
+            cache.update_volume(uri, volume);
+            cache.write().unwrap();
+        }
+    }
+
+    fn update_overrides_and_write<F: FnOnce(&mut MediaOverrides)>(&mut self, id: string::String, f: F) {
+        if let Some(ref mut cache) = self.cache {
+            cache.update_overrides(id, f);
+            if let Err(e) = cache.write() {
+                eprintln!("Unable to save the media settings: {}", e);
+            }
+        }
+    }
+
+    fn forget(&mut self, uri: Option<&str>) -> Result<(), Error> {
+        match uri {
+            Some(uri) => {
+                self.resume_choices.remove(uri);
+                self.toggled_positions.remove(uri);
+                self.forgotten_uris.insert(uri.to_string());
+            }
+            None => {
+                self.resume_choices.clear();
+                self.toggled_positions.clear();
+                self.forgotten_uris.extend(self.loaded_uri.clone());
+            }
+        }
+        if let Some(ref mut cache) = self.cache {
+            match uri {
+                Some(uri) => cache.forget(uri),
+                None => cache.clear(),
+            }
+            cache.write()?;
+        }
+        Ok(())
+    }
+}
+
+fn element_factory_name(element: &gst::Element) -> Option<glib::GString> {
+    element.get_factory().map(|factory| factory.get_name())
+}
+
+// fpsdisplaysink counts the frames itself, other sinks report them in the
+// statistics of GstBaseSink. Sink bins such as glsinkbin are looked into.
+fn frame_stats(element: &gst::Element) -> Option<FrameStats> {
+    if element.find_property("frames-rendered").is_some() {
+        let count = |name: &str| {
+            let value = element.get_property(name).ok()?;
+            value.get_some::<u32>().ok().map(u64::from)
+        };
+        return Some(FrameStats {
+            rendered: count("frames-rendered"),
+            dropped: count("frames-dropped"),
+        });
+    }
+    if element.find_property("stats").is_some() {
+        let stats = element.get_property("stats").ok()?.get::<gst::Structure>().ok()??;
+        return Some(FrameStats {
+            rendered: stats.get_some::<u64>("rendered").ok(),
+            dropped: stats.get_some::<u64>("dropped").ok(),
+        });
+    }
+    let bin = element.downcast_ref::<gst::Bin>()?;
+    bin.get_children().iter().find_map(frame_stats)
+}
+
+fn for_each_element_from_factory<F: Fn(&gst::Element)>(bin: &gst::Bin, factory_name: &str, f: &F) {
+    for element in bin.get_children() {
+        if let Some(name) = element_factory_name(&element) {
+            if name.as_str() == factory_name {
+                f(&element);
+            }
+        }
+        if let Some(child_bin) = element.downcast_ref::<gst::Bin>() {
+            for_each_element_from_factory(child_bin, factory_name, f);
+        }
+    }
+}
+
+// Font size of plain-text subtitles at scale 1, for a 640 pixels wide video
+// with SubtitleScaleBase::Video, which textoverlay then resizes with the
+// video, or for a video area of SUBTITLE_REFERENCE_HEIGHT pixels.
+const SUBTITLE_FONT_SIZE: f64 = 18.0;
+const SUBTITLE_REFERENCE_HEIGHT: f64 = 480.0;
+// Default color of textoverlay, opaque white.
+const SUBTITLE_DEFAULT_COLOR: u32 = 0xffff_ffff;
+
+// Families tried after Sans, which keeps drawing Latin text, for the
+// characters it lacks. Without a script, fontconfig picks the fallback
+// fonts from the locale.
+fn subtitle_font_families(script: SubtitleScript) -> &'static str {
+    match script {
+        SubtitleScript::Auto => "Sans",
+        SubtitleScript::Japanese => "Sans,Noto Sans CJK JP,Source Han Sans JP,IPAGothic",
+        SubtitleScript::SimplifiedChinese => "Sans,Noto Sans CJK SC,Source Han Sans SC,WenQuanYi Micro Hei",
+        SubtitleScript::TraditionalChinese => "Sans,Noto Sans CJK TC,Source Han Sans TC,AR PL UMing TW",
+        SubtitleScript::Korean => "Sans,Noto Sans CJK KR,Source Han Sans KR,NanumGothic",
+        SubtitleScript::Arabic => "Sans,Noto Sans Arabic,Noto Naskh Arabic,KacstOne",
+        SubtitleScript::Hebrew => "Sans,Noto Sans Hebrew,Culmus",
+    }
+}
+
+// Split the size off a font description such as "Sans Bold 28", the rest
+// being the families and style.
+fn split_font_size(font: &str) -> (&str, Option<f64>) {
+    let font = font.trim();
+    if let Some(index) = font.rfind(char::is_whitespace) {
+        if let Ok(size) = font[index..].trim().parse::<f64>() {
+            if size > 0.0 {
+                return (font[..index].trim_end(), Some(size));
+            }
+        }
+    }
+    (font, None)
+}
+
+// The subtitle overlay is only a textoverlay for plain-text formats (SRT,
+// WebVTT, ...), styled formats such as ASS are rendered as authored, by
+// assrender, scaled with the picture.
+fn apply_subtitle_style(overlay: &gst::Element, style: &SubtitleStyle, area_height: i32) {
+    let (shaded, outline) = match style.background {
+        SubtitleBackground::None => (false, false),
+        SubtitleBackground::Box => (true, true),
+        SubtitleBackground::Outline => (false, true),
+    };
+    let shading_value = (style.background_opacity.max(0.0).min(1.0) * 255.0) as u32;
+    overlay.set_property("shaded-background", &shaded).unwrap();
+    overlay.set_property("shading-value", &shading_value.max(1)).unwrap();
+    overlay.set_property("draw-outline", &outline).unwrap();
+    overlay
+        .set_property("color", &style.color.unwrap_or(SUBTITLE_DEFAULT_COLOR))
+        .unwrap();
+
+    let (families, font_size) = match style.font {
+        Some(ref font) => split_font_size(font),
+        None => ("", None),
+    };
+    let families = if families.is_empty() {
+        subtitle_font_families(style.script)
+    } else {
+        families
+    };
+    let font_size = font_size.unwrap_or(SUBTITLE_FONT_SIZE);
+    let scale = style.scale.max(0.1);
+    let size = match style.scale_base {
+        SubtitleScaleBase::Video => font_size * scale,
+        SubtitleScaleBase::Window => font_size * scale * f64::from(area_height.max(1)) / SUBTITLE_REFERENCE_HEIGHT,
+    };
+    let auto_resize = style.scale_base == SubtitleScaleBase::Video;
+    overlay.set_property("auto-resize", &auto_resize).unwrap();
+    let font_desc = format!("{} {:.1}", families, size);
+    overlay.set_property("font-desc", &font_desc).unwrap();
+}
+
+/// Convert a video sample to an 8-bit sRGB pixbuf. Samples using the PQ or
+/// HLG transfer functions are tone-mapped, unless `raw` is set, in which
+/// case they are kept as decoded.
+fn srgb_pixbuf(sample: &gst::Sample, raw: bool) -> Option<gdk_pixbuf::Pixbuf> {
+    let transfer = sample
+        .get_caps()
+        .and_then(|caps| caps.get_structure(0))
+        .and_then(|s| s.get::<&str>("colorimetry").ok().and_then(|c| c))
+        .and_then(tone_mapping::hdr_transfer_from_colorimetry);
+
+    let caps = gst::Caps::builder("video/x-raw")
+        .field("format", &"RGB")
+        .field("colorimetry", &"sRGB")
+        .field("pixel-aspect-ratio", &gst::Fraction::new(1, 1))
+        .build();
+    let sample = gst_video::convert_sample(sample, &caps, gst::ClockTime::from_seconds(5)).ok()?;
+    let info = gst_video::VideoInfo::from_caps(sample.get_caps()?).ok()?;
+    let buffer = sample.get_buffer()?;
+    let map = buffer.map_readable().ok()?;
+    let mut data = map.as_slice().to_vec();
+
+    if let Some(transfer) = transfer.filter(|_| !raw) {
+        tone_mapping::tone_map_samples(&mut data, transfer, HDR_PEAK_NITS);
+    }
+
+    Some(gdk_pixbuf::Pixbuf::from_mut_slice(
+        data,
+        gdk_pixbuf::Colorspace::Rgb,
+        false,
+        8,
+        info.width() as i32,
+        info.height() as i32,
+        info.stride()[0],
+    ))
+}
+
+fn apply_subtitle_style_to_pipeline(pipeline: &gst::Element, style: &SubtitleStyle, area_height: i32) {
+    if let Some(pipeline) = pipeline.downcast_ref::<gst::Bin>() {
+        for_each_element_from_factory(pipeline, "textoverlay", &|overlay| {
+            apply_subtitle_style(overlay, style, area_height);
+        });
+    }
+}
+
+// The deinterlace element of the playbin, added with the deinterlace flag,
+// follows the interlacing flags of the frames in its auto mode.
+fn apply_deinterlace_mode(deinterlace: &gst::Element, mode: Deinterlace) {
+    let mode = if mode == Deinterlace::On { "interlaced" } else { "auto" };
+    deinterlace.set_property_from_str("mode", mode);
+}
+
+fn set_playbin_flag(pipeline: &gst::Element, flag: &str, enabled: bool) -> Option<()> {
+    let flags = pipeline.get_property("flags").ok()?;
+    let class = glib::FlagsClass::new(flags.type_())?;
+    let builder = class.builder_with_value(flags)?;
+    let builder = if enabled {
+        builder.set_by_nick(flag)
+    } else {
+        builder.unset_by_nick(flag)
+    };
+    pipeline.set_property("flags", &builder.build()?).ok()
+}
+
+// Follow the size of the video area, resized with the window or when
+// switching to fullscreen, for SubtitleScaleBase::Window.
+fn connect_subtitle_scaling(
+    video_area: &gtk::Widget,
+    player: &gst_player::Player,
+    subtitle_style: &Arc<Mutex<SubtitleStyle>>,
+    area_height: &Arc<Mutex<i32>>,
+) {
+    let player_weak = player.downgrade();
+    let subtitle_style = subtitle_style.clone();
+    let area_height = area_height.clone();
+    video_area.connect_size_allocate(move |_, allocation| {
+        {
+            let mut height = area_height.lock().unwrap();
+            if *height == allocation.height {
+                return;
+            }
+            *height = allocation.height;
+        }
+        let style = subtitle_style.lock().unwrap().clone();
+        if style.scale_base != SubtitleScaleBase::Window {
+            return;
+        }
+        if let Some(player) = player_weak.upgrade() {
+            apply_subtitle_style_to_pipeline(&player.get_pipeline(), &style, allocation.height);
+        }
+    });
+}
+
+// Interval between AudioLevels events.
+const AUDIO_LEVEL_INTERVAL_MS: u64 = 100;
+
+// The level element posts its measurements on the bus, which GstPlayer
+// dispatches from its own thread, so forward them to the main context.
+fn create_audio_level(pipeline: &gst::Element, sender: glib::Sender<PlayerEvent>) -> Option<gst::Element> {
+    let level = gst::ElementFactory::make("level", None).ok()?;
+    level
+        .set_property("interval", &(AUDIO_LEVEL_INTERVAL_MS * 1_000_000))
+        .ok()?;
+    level.set_property("post-messages", &false).ok()?;
+
+    let sender = Mutex::new(sender);
+    let level_weak = level.downgrade();
+    let bus = pipeline.get_bus()?;
+    bus.connect_message(move |_, msg| {
+        let element = match msg.view() {
+            gst::MessageView::Element(element) => element,
+            _ => return,
+        };
+        let from_level = match (msg.get_src(), level_weak.upgrade()) {
+            (Some(src), Some(level)) => src == level.upcast::<gst::Object>(),
+            _ => false,
+        };
+        if !from_level {
+            return;
+        }
+        if let Some(Ok(Some(rms))) = element.get_structure().map(|s| s.get::<glib::ValueArray>("rms")) {
+            let levels = (0..)
+                .map(|i| rms.get_nth(i))
+                .take_while(Option::is_some)
+                .flatten()
+                .filter_map(|v| v.get_some::<f64>().ok())
+                .collect::<Vec<_>>();
+            let _ = sender.lock().unwrap().send(PlayerEvent::AudioLevels(levels));
+        }
+    });
+    Some(level)
+}
+
+fn toc_chapters(entries: &[gst::TocEntry], chapters: &mut Vec<Chapter>) {
+    for entry in entries {
+        if entry.get_entry_type() == gst::TocEntryType::Chapter {
+            if let Some((start, _)) = entry.get_start_stop_times().filter(|(start, _)| *start >= 0) {
+                let title = entry
+                    .get_tags()
+                    .and_then(|tags| {
+                        tags.get::<gst::tags::Title>()
+                            .and_then(|t| t.get().map(string::String::from))
+                    })
+                    .unwrap_or_default();
+                chapters.push(Chapter {
+                    start: start as u64,
+                    title,
+                });
+            }
+        }
+        toc_chapters(&entry.get_sub_entries(), chapters);
+    }
+}
+
+// Demuxers post their table of contents on the bus, from the GstPlayer
+// thread.
+fn connect_chapters(pipeline: &gst::Element, chapters: &Arc<Mutex<MediaChapters>>, sender: glib::Sender<PlayerEvent>) {
+    let bus = match pipeline.get_bus() {
+        Some(bus) => bus,
+        None => return,
+    };
+    let chapters = chapters.clone();
+    let sender = Mutex::new(sender);
+    bus.connect_message(move |_, msg| {
+        if let gst::MessageView::Toc(toc) = msg.view() {
+            let (toc, _) = toc.get_toc();
+            let mut embedded = vec![];
+            toc_chapters(&toc.get_entries(), &mut embedded);
+            embedded.sort_by_key(|chapter| chapter.start);
+            chapters.lock().unwrap().embedded = embedded;
+            let _ = sender.lock().unwrap().send(PlayerEvent::ChaptersUpdated);
+        }
+    });
+}
+
+// Frame steps are done by the video sink, which then posts a step-done
+// message, while GstPlayer doesn't report positions when paused.
+fn connect_step_done(pipeline: &gst::Element, sender: glib::Sender<PlayerEvent>) {
+    let bus = match pipeline.get_bus() {
+        Some(bus) => bus,
+        None => return,
+    };
+    let sender = Mutex::new(sender);
+    bus.connect_message(move |_, msg| {
+        if let gst::MessageView::StepDone(_) = msg.view() {
+            let _ = sender.lock().unwrap().send(PlayerEvent::PositionUpdated);
+        }
+    });
+}
+
+// The playbin asks for the next URI from its streaming thread when the
+// current one is about to end, and posts stream-start once it plays it.
+// GstPlayer isn't involved, its URI stays the one it loaded, so the items
+// following are only resumed from the start.
+fn connect_gapless(player: &gst_player::Player, items: &Arc<Mutex<GaplessItems>>) {
+    let pipeline = player.get_pipeline();
+    let next_items = items.clone();
+    let connected = pipeline.connect("about-to-finish", false, move |args| {
+        let playbin = args[0].get::<gst::Element>().ok().flatten()?;
+        let mut items = next_items.lock().unwrap();
+        if let Some((index, uri)) = items.next.take() {
+            if playbin.set_property("uri", &uri).is_ok() {
+                items.queued = Some((index, uri));
+            }
+        }
+        None
+    });
+    if let Err(e) = connected {
+        eprintln!("Gapless playback unavailable: {}", e);
+        return;
+    }
+
+    let bus = match pipeline.get_bus() {
+        Some(bus) => bus,
+        None => return,
+    };
+    let items = items.clone();
+    let player = player.downgrade();
+    bus.connect_message(move |_, msg| {
+        if let gst::MessageView::StreamStart(_) = msg.view() {
+            let item = items.lock().unwrap().queued.take();
+            if let (Some((index, uri)), Some(player)) = (item, player.upgrade()) {
+                glib::MainContext::default().invoke(move || {
+                    with_mut_player!(player player_data {
+                        player_data.gapless_advanced(&player, index, uri);
+                    });
+                });
+            }
+        }
+    });
+}
+
+// Positions closer than this to the start or to the end of the media are
+// not resumed, in nanoseconds.
+const RESUME_MARGIN: u64 = 30_000_000_000;
+
+// Going to the previous chapter that soon after the start of the current
+// one skips the latter, in nanoseconds.
+const CHAPTER_RESTART_THRESHOLD: u64 = 2_000_000_000;
+
+// Used to step backward when the framerate of the video is unknown.
+const DEFAULT_FRAME_DURATION: u64 = 40_000_000;
+
+fn sidecar_chapters(uri: &str) -> Vec<Chapter> {
+    let path = match glib::filename_from_uri(uri) {
+        Ok((path, _)) => path,
+        Err(_) => return vec![],
+    };
+    let chapter_file = match chapter_files::find_chapter_file(&path) {
+        Some(chapter_file) => chapter_file,
+        None => return vec![],
+    };
+    chapter_files::read_chapter_file(&chapter_file).unwrap_or_else(|e| {
+        eprintln!("Unable to read chapters from {}: {}", chapter_file.display(), e);
+        vec![]
+    })
+}
+
+// GST_TAG_TRACK_GAIN, GST_TAG_TRACK_PEAK, GST_TAG_ALBUM_GAIN and
+// GST_TAG_ALBUM_PEAK.
+const REPLAY_GAIN_TAGS: [&str; 4] = [
+    "replaygain-track-gain",
+    "replaygain-track-peak",
+    "replaygain-album-gain",
+    "replaygain-album-peak",
+];
+
+fn has_replay_gain_tags(tags: &gst::TagListRef) -> bool {
+    tags.get::<gst::tags::TrackGain>().is_some() || tags.get::<gst::tags::AlbumGain>().is_some()
+}
+
+// Tags can't be removed from a TagList with this version of the bindings, so
+// copy all the others instead.
+fn without_replay_gain_tags(tags: &gst::TagListRef) -> gst::TagList {
+    let mut stripped = gst::TagList::new();
+    {
+        let stripped = stripped.get_mut().unwrap();
+        stripped.set_scope(tags.get_scope());
+        for (name, values) in tags.iter_generic() {
+            if REPLAY_GAIN_TAGS.contains(&name) {
+                continue;
+            }
+            for value in values {
+                let _ = stripped.add_generic(name, value, gst::TagMergeMode::Append);
+            }
+        }
+    }
+    stripped
+}
+
+// rgvolume applies the gain of the ReplayGain tags flowing through it, so
+// strip them when disabled.
+fn create_replay_gain(mode: &Arc<Mutex<ReplayGainMode>>, sender: glib::Sender<PlayerEvent>) -> Option<gst::Element> {
+    let rgvolume = gst::ElementFactory::make("rgvolume", None).ok()?;
+    let has_tags = Arc::new(AtomicBool::new(false));
+
+    let probe_mode = mode.clone();
+    let probe_has_tags = has_tags.clone();
+    let pad = rgvolume.get_static_pad("sink")?;
+    pad.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+        let tags = match info.data {
+            Some(gst::PadProbeData::Event(ref event)) => match event.view() {
+                gst::EventView::StreamStart(_) => {
+                    probe_has_tags.store(false, Ordering::SeqCst);
+                    return gst::PadProbeReturn::Ok;
+                }
+                gst::EventView::Tag(tag) => tag.get_tag().to_owned(),
+                _ => return gst::PadProbeReturn::Ok,
+            },
+            _ => return gst::PadProbeReturn::Ok,
+        };
+        if !has_replay_gain_tags(&tags) {
+            return gst::PadProbeReturn::Ok;
+        }
+        probe_has_tags.store(true, Ordering::SeqCst);
+        if *probe_mode.lock().unwrap() == ReplayGainMode::Off {
+            let tags = without_replay_gain_tags(&tags);
+            info.data = Some(gst::PadProbeData::Event(gst::Event::new_tag(tags).build()));
+        }
+        gst::PadProbeReturn::Ok
+    })?;
+
+    let mode = mode.clone();
+    let sender = Mutex::new(sender);
+    rgvolume.connect_notify(Some("result-gain"), move |rgvolume, _| {
+        if *mode.lock().unwrap() == ReplayGainMode::Off || !has_tags.load(Ordering::SeqCst) {
+            return;
+        }
+        let gain = rgvolume
+            .get_property("result-gain")
+            .ok()
+            .and_then(|v| v.get_some::<f64>().ok());
+        if let Some(gain) = gain {
+            let _ = sender.lock().unwrap().send(PlayerEvent::ReplayGainApplied(gain));
+        }
+    });
+    Some(rgvolume)
+}
+
+// Chain the audio filters in a bin set as the playbin audio-filter.
+// Converting first, as most filters only support some raw formats.
+fn pitch_filter_factory(preserve_pitch: bool) -> &'static str {
+    if preserve_pitch {
+        "scaletempo"
+    } else {
+        "identity"
+    }
+}
+
+// Replace scaletempo with identity in the audio filters, or the other way
+// around. Called from a blocking probe, so nothing flows through them.
+fn swap_pitch_filter(pitch_filter: &Arc<Mutex<gst::Element>>, preserve_pitch: bool) -> Option<()> {
+    let factory = pitch_filter_factory(preserve_pitch);
+    let mut current = pitch_filter.lock().unwrap();
+    if element_factory_name(&current).map_or(false, |name| name.as_str() == factory) {
+        return Some(());
+    }
+    let filter = gst::ElementFactory::make(factory, None).ok()?;
+    swap_filter(&mut current, filter)
+}
+
+const NORMALIZATION_FILTER_NAME: &str = "normalization";
+
+// EBU R128 loudness normalization with audioloudnorm, from gst-plugins-rs,
+// which only handles 192 kHz audio. Without it, a compressor evens out the
+// loud passages. The limiter keeps the peaks from clipping in both cases.
+fn create_normalization_filter(enabled: bool) -> Option<gst::Element> {
+    if !enabled {
+        return gst::ElementFactory::make("identity", None).ok();
+    }
+    let description = if gst::ElementFactory::find("audioloudnorm").is_some() {
+        "audioconvert ! audioresample ! audioloudnorm ! audioconvert ! rglimiter"
+    } else {
+        "audioconvert ! audiodynamic characteristics=soft-knee mode=compressor threshold=0.3 ratio=0.5 \
+         ! volume volume=1.5 ! rglimiter"
+    };
+    let bin = gst::parse_bin_from_description(description, true).ok()?;
+    bin.set_property("name", &NORMALIZATION_FILTER_NAME).ok()?;
+    Some(bin.upcast())
+}
+
+// Like swap_pitch_filter(), for the normalization filter.
+fn swap_normalization_filter(normalization: &Arc<Mutex<gst::Element>>, enabled: bool) -> Option<()> {
+    let mut current = normalization.lock().unwrap();
+    if (current.get_name() == NORMALIZATION_FILTER_NAME) == enabled {
+        return Some(());
+    }
+    let filter = create_normalization_filter(enabled)?;
+    swap_filter(&mut current, filter)
+}
+
+fn swap_filter(current: &mut gst::Element, filter: gst::Element) -> Option<()> {
+    let bin = current.get_parent()?.downcast::<gst::Bin>().ok()?;
+    let sink_pad = current.get_static_pad("sink")?;
+    let src_pad = current.get_static_pad("src")?;
+    let upstream = sink_pad.get_peer()?;
+    let downstream = src_pad.get_peer()?;
+
+    upstream.unlink(&sink_pad).ok()?;
+    src_pad.unlink(&downstream).ok()?;
+    let _ = current.set_state(gst::State::Null);
+    bin.remove(&*current).ok()?;
+    bin.add(&filter).ok()?;
+    upstream.link(&filter.get_static_pad("sink")?).ok()?;
+    filter.get_static_pad("src")?.link(&downstream).ok()?;
+    filter.sync_state_with_parent().ok()?;
+    *current = filter;
+    Some(())
+}
+
+fn is_equalizer(element: &gst::Element) -> bool {
+    element_factory_name(element).map_or(false, |name| name.as_str() == "equalizer-10bands")
+}
+
+// Gains in dB, clamped to the range of equalizer-10bands.
+fn set_equalizer_gains(equalizer: &gst::Element, gains: &[f64]) {
+    for (band, gain) in gains.iter().take(10).enumerate() {
+        let name = format!("band{}", band);
+        let _ = equalizer.set_property(name.as_str(), &gain.max(-24.0).min(12.0));
+    }
+}
+
+// The equalizer is replaced by identity when disabled, so that the audio
+// goes through untouched.
+fn create_equalizer(gains: Option<&[f64]>) -> Option<gst::Element> {
+    match gains {
+        Some(gains) => {
+            let equalizer = gst::ElementFactory::make("equalizer-10bands", None).ok()?;
+            set_equalizer_gains(&equalizer, gains);
+            Some(equalizer)
+        }
+        None => gst::ElementFactory::make("identity", None).ok(),
+    }
+}
+
+// Like swap_pitch_filter(), for the equalizer.
+fn swap_equalizer(equalizer: &Arc<Mutex<gst::Element>>, gains: Option<&[f64]>) -> Option<()> {
+    let mut current = equalizer.lock().unwrap();
+    if is_equalizer(&current) == gains.is_some() {
+        if let Some(gains) = gains {
+            set_equalizer_gains(&current, gains);
+        }
+        return Some(());
+    }
+    let filter = create_equalizer(gains)?;
+    swap_filter(&mut current, filter)
+}
+
+// Run the swap from a probe blocking the input of the filter. Without any
+// data flowing, it happens when the next media starts.
+fn swap_from_blocking_probe<F>(filter: &Arc<Mutex<gst::Element>>, swap: F)
+where
+    F: Fn() -> Option<()> + Send + Sync + 'static,
+{
+    let upstream = filter
+        .lock()
+        .unwrap()
+        .get_static_pad("sink")
+        .and_then(|pad| pad.get_peer());
+    if let Some(upstream) = upstream {
+        upstream.add_probe(gst::PadProbeType::BLOCK_DOWNSTREAM, move |_, _| {
+            if swap().is_none() {
+                eprintln!("Unable to switch a filter");
+            }
+            gst::PadProbeReturn::Remove
+        });
+    }
+}
+
+fn set_audio_filters(pipeline: &gst::Element, filters: &[&gst::Element]) -> Option<()> {
+    let bin = gst::Bin::new(Some("audio-filter-bin"));
+    let convert = gst::ElementFactory::make("audioconvert", None).ok()?;
+    let mut elements = vec![&convert];
+    elements.extend_from_slice(filters);
+    bin.add_many(&elements).ok()?;
+    gst::Element::link_many(&elements).ok()?;
+
+    let sink_pad = gst::GhostPad::with_target(Some("sink"), &convert.get_static_pad("sink")?).ok()?;
+    bin.add_pad(&sink_pad).ok()?;
+    let src_pad = gst::GhostPad::with_target(Some("src"), &elements.last()?.get_static_pad("src")?).ok()?;
+    bin.add_pad(&src_pad).ok()?;
+    pipeline.set_property("audio-filter", &bin).ok()
+}
+
+// Converts the audio to as many channels as the mixing matrix has columns,
+// then mixes them into as many channels as it has rows.
+struct ChannelRemapFilter {
+    bin: gst::Element,
+    capsfilter: gst::Element,
+    mixer: gst::Element,
+}
+
+fn create_channel_remap() -> Option<ChannelRemapFilter> {
+    let bin = gst::Bin::new(Some("channel-remap"));
+    let convert = gst::ElementFactory::make("audioconvert", None).ok()?;
+    let capsfilter = gst::ElementFactory::make("capsfilter", None).ok()?;
+    let mixer = gst::ElementFactory::make("audioconvert", None).ok()?;
+    bin.add_many(&[&convert, &capsfilter, &mixer]).ok()?;
+    gst::Element::link_many(&[&convert, &capsfilter, &mixer]).ok()?;
+    let sink_pad = gst::GhostPad::with_target(Some("sink"), &convert.get_static_pad("sink")?).ok()?;
+    bin.add_pad(&sink_pad).ok()?;
+    let src_pad = gst::GhostPad::with_target(Some("src"), &mixer.get_static_pad("src")?).ok()?;
+    bin.add_pad(&src_pad).ok()?;
+    Some(ChannelRemapFilter {
+        bin: bin.upcast::<gst::Element>(),
+        capsfilter,
+        mixer,
+    })
+}
+
+// Feed the video sink through a tee, so that more outputs can be attached
+// later on, see ChannelPlayer::add_mirror_output().
+fn create_tee_sink_bin(sink: &gst::Element) -> (gst::Element, gst::Element) {
+    let bin = gst::Bin::new(Some("video-sink-bin"));
+    let tee = gst::ElementFactory::make("tee", Some("video-tee")).unwrap();
+    tee.set_property("allow-not-linked", &true).unwrap();
+    let queue = gst::ElementFactory::make("queue", Some("video-sink-queue")).unwrap();
+    // Zooming crops the frames, see set_video_zoom().
+    let crop = gst::ElementFactory::make("videocrop", Some("video-crop")).ok();
+
+    let mut elements = vec![&tee, &queue];
+    elements.extend(crop.iter());
+    elements.push(sink);
+    bin.add_many(&elements).unwrap();
+    gst::Element::link_many(&elements).unwrap();
+
+    let pad = tee.get_static_pad("sink").unwrap();
+    let ghost_pad = gst::GhostPad::with_target(Some("sink"), &pad).unwrap();
+    bin.add_pad(&ghost_pad).unwrap();
+
+    (bin.upcast::<gst::Element>(), tee)
+}
+
+/// Video sinks that can be embedded in the main window, by order of
+/// preference.
+pub const VIDEO_SINKS: [&str; 3] = ["gtkglsink", "glimagesink", "gtksink"];
+
+/// The supported video sinks installed on this system.
+pub fn available_video_sinks() -> Vec<&'static str> {
+    VIDEO_SINKS
+        .iter()
+        .cloned()
+        .filter(|name| gst::ElementFactory::find(name).is_some())
+        .collect()
+}
+
+struct VideoSink {
+    name: &'static str,
+    element: gst::Element,
+    widget: gtk::Widget,
+    // Whether the widget window handle has to be passed through the video
+    // overlay interface.
+    overlay: bool,
+}
+
+fn create_video_sink(name: &'static str) -> Option<VideoSink> {
+    let (element, widget, overlay) = match name {
+        "gtkglsink" | "gtksink" => {
+            let sink = gst::ElementFactory::make(name, None).ok()?;
+            let widget = sink.get_property("widget").ok()?.get::<gtk::Widget>().ok()??;
+            if name == "gtkglsink" {
+                let glsinkbin = gst::ElementFactory::make("glsinkbin", None).ok()?;
+                glsinkbin.set_property("sink", &sink.to_value()).ok()?;
+                (glsinkbin, widget, false)
+            } else {
+                (sink, widget, false)
+            }
+        }
+        "glimagesink" => {
+            let sink = gst::ElementFactory::make(name, None).ok()?;
+            (sink, gtk::DrawingArea::new().upcast::<gtk::Widget>(), true)
+        }
+        _ => return None,
+    };
+    Some(VideoSink {
+        name,
+        element,
+        widget,
+        overlay,
+    })
+}
+
+fn connect_video_area(
+    video_area: &gtk::Widget,
+    player: &gst_player::Player,
+    renderer: &gst_player::PlayerVideoOverlayVideoRenderer,
+    overlay: bool,
+) {
+    if overlay {
+        let renderer_weak = renderer.downgrade();
+        video_area.connect_realize(move |video_area| {
+            let renderer = match renderer_weak.upgrade() {
+                Some(renderer) => renderer,
+                None => return,
+            };
+            if let Some(video_area) = video_area.downcast_ref::<gtk::DrawingArea>() {
+                prepare_video_overlay(video_area, &renderer);
+            }
+        });
+    }
+
+    let renderer_weak = renderer.downgrade();
+    video_area.connect_draw(move |video_area, cairo_context| {
+        let width = video_area.get_allocated_width();
+        let height = video_area.get_allocated_height();
+
+        // Paint some black borders
+        cairo_context.rectangle(0., 0., f64::from(width), f64::from(height));
+        cairo_context.fill();
+
+        // Overlay sinks only draw new frames, so redraw the last one over
+        // the borders, otherwise paused videos stay black.
+        if overlay {
+            if let Some(renderer) = renderer_weak.upgrade() {
+                renderer.expose();
+            }
+        }
+
+        Inhibit(false)
+    });
+
+    let player_weak = player.downgrade();
+    let renderer_weak = renderer.downgrade();
+    video_area.connect_configure_event(move |video_area, event| -> bool {
+        let (width, height) = event.get_size();
+        let (x, y) = event.get_position();
+        let rect = gst_video::VideoRectangle::new(x, y, width as i32, height as i32);
+
+        let player = match player_weak.upgrade() {
+            Some(player) => player,
+            None => return true,
+        };
+        if let Ok(video_track) = player.get_property("current-video-track") {
+            let video_track = video_track
+                .get::<gst_player::PlayerVideoInfo>()
+                .expect("current-video-track should be a PlayerVideoInfo");
+            if let Some(video_track) = video_track {
+                let video_width = video_track.get_width();
+                let video_height = video_track.get_height();
+                let src_rect = gst_video::VideoRectangle::new(0, 0, video_width, video_height);
+
+                let rect = gst_video::center_video_rectangle(&src_rect, &rect, true);
+                let renderer = match renderer_weak.upgrade() {
+                    Some(renderer) => renderer,
+                    None => return true,
+                };
+                renderer.set_render_rectangle(rect.x, rect.y, rect.w, rect.h);
+                renderer.expose();
+                video_area.queue_draw();
+            }
+        }
+        true
+    });
+}
+
+// Caps declaring the given range instead of the one of the decoded video, if
+// they differ. RGB formats are always full range.
+fn override_color_range(caps: &gst::CapsRef, range: ColorRange) -> Option<gst::Caps> {
+    let range = match range {
+        ColorRange::Auto => return None,
+        ColorRange::Full => gst_video::VideoColorRange::Range0255,
+        ColorRange::Limited => gst_video::VideoColorRange::Range16235,
+    };
+    let info = gst_video::VideoInfo::from_caps(caps).ok()?;
+    let colorimetry = info.colorimetry();
+    if info.format_info().is_rgb() || colorimetry.range() == range {
+        return None;
+    }
+    let colorimetry = gst_video::VideoColorimetry::new(
+        range,
+        colorimetry.matrix(),
+        colorimetry.transfer(),
+        colorimetry.primaries(),
+    );
+    let mut caps = caps.to_owned();
+    caps.get_mut()?.set_simple(&[("colorimetry", &colorimetry.to_string())]);
+    Some(caps)
+}
+
+// Caps declaring the given pixel aspect ratio instead of the one of the
+// decoded video, if they differ.
+// Caps with the forced pixel aspect ratio or, for a forced display aspect
+// ratio, the pixel aspect ratio giving it at the size of the video.
+fn override_pixel_aspect_ratio(
+    caps: &gst::CapsRef,
+    ratio: Option<gst::Fraction>,
+    display_ratio: Option<gst::Fraction>,
+) -> Option<gst::Caps> {
+    let structure = caps.get_structure(0)?;
+    let ratio = match display_ratio {
+        Some(display_ratio) => {
+            let width = structure.get_some::<i32>("width").ok()?;
+            let height = structure.get_some::<i32>("height").ok()?;
+            gst::Fraction::new(*display_ratio.numer() * height, *display_ratio.denom() * width)
+        }
+        None => ratio?,
+    };
+    if structure.get_some::<gst::Fraction>("pixel-aspect-ratio").ok() == Some(ratio) {
+        return None;
+    }
+    let mut caps = caps.to_owned();
+    caps.get_mut()?.set_simple(&[("pixel-aspect-ratio", &ratio)]);
+    Some(caps)
+}
+
+// The sink converts the video to RGB according to the colorimetry of the
+// caps, and scales it according to their pixel aspect ratio, so rewrite
+// those on the way to it.
+fn connect_caps_overrides(
+    video_tee: &gst::Element,
+    color_range: &Arc<Mutex<ColorRange>>,
+    pixel_aspect_ratio: &Arc<Mutex<Option<gst::Fraction>>>,
+    display_aspect_ratio: &Arc<Mutex<Option<gst::Fraction>>>,
+    video_caps: &Arc<Mutex<Option<gst::Caps>>>,
+) -> Option<()> {
+    let bin = video_tee.get_parent()?.downcast::<gst::Bin>().ok()?;
+    let pad = bin.get_by_name("video-sink-queue")?.get_static_pad("src")?;
+    let color_range = color_range.clone();
+    let pixel_aspect_ratio = pixel_aspect_ratio.clone();
+    let display_aspect_ratio = display_aspect_ratio.clone();
+    let video_caps = video_caps.clone();
+    pad.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+        let caps = match info.data {
+            Some(gst::PadProbeData::Event(ref event)) => match event.view() {
+                gst::EventView::Caps(caps) => caps.get_caps_owned(),
+                _ => return gst::PadProbeReturn::Ok,
+            },
+            _ => return gst::PadProbeReturn::Ok,
+        };
+        let ranged = override_color_range(&caps, *color_range.lock().unwrap());
+        let current = ranged.as_deref().unwrap_or(&caps);
+        let overridden = override_pixel_aspect_ratio(
+            current,
+            *pixel_aspect_ratio.lock().unwrap(),
+            *display_aspect_ratio.lock().unwrap(),
+        )
+        .or(ranged);
+        *video_caps.lock().unwrap() = Some(caps);
+        if let Some(caps) = overridden {
+            info.data = Some(gst::PadProbeData::Event(gst::Event::new_caps(&caps).build()));
+        }
+        gst::PadProbeReturn::Ok
+    })?;
+    Some(())
+}
+
+// Zoom of the video within the video area, applied by cropping the parts
+// falling outside of the area.
+#[derive(Clone, Copy)]
+struct VideoZoom {
+    // 1.0 fits the whole video in the area.
+    factor: f64,
+    // Zoom just enough for the video to cover the area, removing the black
+    // bars, whatever the factor.
+    fill: bool,
+    area: (i32, i32),
+}
+
+// Pixels to remove on the left and right, and on the top and bottom, of the
+// frames described by the caps.
+fn video_crop(caps: &gst::CapsRef, zoom: &VideoZoom) -> Option<(i32, i32)> {
+    let s = caps.get_structure(0)?;
+    let width = s.get_some::<i32>("width").ok()?;
+    let height = s.get_some::<i32>("height").ok()?;
+    let par = s
+        .get_some::<gst::Fraction>("pixel-aspect-ratio")
+        .unwrap_or_else(|_| gst::Fraction::new(1, 1));
+    let (area_width, area_height) = zoom.area;
+    if width <= 0 || height <= 0 || area_width <= 0 || area_height <= 0 || *par.denom() == 0 {
+        return None;
+    }
+    let video_aspect = f64::from(width) * f64::from(*par.numer()) / (f64::from(height) * f64::from(*par.denom()));
+    let area_aspect = f64::from(area_width) / f64::from(area_height);
+    let factor = if zoom.fill {
+        (video_aspect / area_aspect).max(area_aspect / video_aspect)
+    } else {
+        zoom.factor.max(1.0)
+    };
+    // Fraction of the zoomed video still visible in the area.
+    let visible_width = ((area_aspect / video_aspect).max(1.0) / factor).min(1.0);
+    let visible_height = ((video_aspect / area_aspect).max(1.0) / factor).min(1.0);
+    let horizontal = (f64::from(width) * (1.0 - visible_width) / 2.0).round() as i32;
+    let vertical = (f64::from(height) * (1.0 - visible_height) / 2.0).round() as i32;
+    Some((horizontal, vertical))
+}
+
+fn apply_video_crop(crop: &gst::Element, caps: &gst::CapsRef, zoom: &VideoZoom) {
+    let (horizontal, vertical) = video_crop(caps, zoom).unwrap_or((0, 0));
+    for (property, value) in [
+        ("left", horizontal),
+        ("right", horizontal),
+        ("top", vertical),
+        ("bottom", vertical),
+    ]
+    .iter()
+    {
+        if crop.get_property(*property).ok().and_then(|v| v.get_some::<i32>().ok()) != Some(*value) {
+            let _ = crop.set_property(*property, value);
+        }
+    }
+}
+
+fn video_crop_element(video_tee: &gst::Element) -> Option<gst::Element> {
+    video_tee
+        .get_parent()?
+        .downcast::<gst::Bin>()
+        .ok()?
+        .get_by_name("video-crop")
+}
+
+// Crop the frames again as the video area is resized.
+fn connect_zoom_area(video_area: &gtk::Widget, video_tee: &gst::Element, zoom: &Arc<Mutex<VideoZoom>>) {
+    let crop_weak = match video_crop_element(video_tee) {
+        Some(crop) => crop.downgrade(),
+        None => return,
+    };
+    let zoom = zoom.clone();
+    video_area.connect_size_allocate(move |_, allocation| {
+        let zoom = {
+            let mut zoom = zoom.lock().unwrap();
+            if zoom.area == (allocation.width, allocation.height) {
+                return;
+            }
+            zoom.area = (allocation.width, allocation.height);
+            *zoom
+        };
+        if let Some(crop) = crop_weak.upgrade() {
+            if let Some(caps) = crop.get_static_pad("sink").and_then(|pad| pad.get_current_caps()) {
+                apply_video_crop(&crop, &caps, &zoom);
+            }
+        }
+    });
+}
+
+// Crop the frames of new media according to the zoom.
+fn connect_video_zoom(video_tee: &gst::Element, zoom: &Arc<Mutex<VideoZoom>>) -> Option<()> {
+    let crop = video_crop_element(video_tee)?;
+    let pad = crop.get_static_pad("sink")?;
+    let crop_weak = crop.downgrade();
+    let zoom = zoom.clone();
+    pad.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+        if let Some(gst::PadProbeData::Event(ref event)) = info.data {
+            if let gst::EventView::Caps(caps) = event.view() {
+                if let Some(crop) = crop_weak.upgrade() {
+                    apply_video_crop(&crop, caps.get_caps(), &zoom.lock().unwrap());
+                }
+            }
+        }
+        gst::PadProbeReturn::Ok
+    })?;
+    Some(())
+}
+
+// Chain the video filters in a bin set as the playbin video-filter. The
+// playbin converts the frames to formats they support.
+fn set_video_filters(pipeline: &gst::Element, filters: &[&gst::Element]) -> Option<()> {
+    let bin = gst::Bin::new(Some("video-filter-bin"));
+    bin.add_many(filters).ok()?;
+    gst::Element::link_many(filters).ok()?;
+
+    let sink_pad = gst::GhostPad::with_target(Some("sink"), &filters.first()?.get_static_pad("sink")?).ok()?;
+    bin.add_pad(&sink_pad).ok()?;
+    let src_pad = gst::GhostPad::with_target(Some("src"), &filters.last()?.get_static_pad("src")?).ok()?;
+    bin.add_pad(&src_pad).ok()?;
+    pipeline.set_property("video-filter", &bin).ok()
+}
+
+fn video_flip_element(pipeline: &gst::Element) -> Option<gst::Element> {
+    pipeline
+        .get_property("video-filter")
+        .ok()?
+        .get::<gst::Element>()
+        .ok()??
+        .downcast::<gst::Bin>()
+        .ok()?
+        .get_by_name("video-flip")
+}
+
+const VIDEO_BALANCE_FILTER_NAME: &str = "video-balance";
+
+fn apply_video_balance(filter: &gst::Element, balance: &VideoBalance) -> Option<()> {
+    let videobalance = filter.downcast_ref::<gst::Bin>()?.get_by_name("balance")?;
+    videobalance.set_property("brightness", &balance.brightness).ok()?;
+    videobalance.set_property("contrast", &balance.contrast).ok()?;
+    videobalance.set_property("saturation", &balance.saturation).ok()?;
+    videobalance.set_property("hue", &balance.hue).ok()
+}
+
+// Like the normalization filter, videobalance is only part of the video
+// filters while needed, identity standing in for it otherwise. The frames
+// are converted back to their format for the elements downstream.
+fn create_video_balance(balance: &VideoBalance) -> Option<gst::Element> {
+    if balance.is_default() {
+        return gst::ElementFactory::make("identity", None).ok();
+    }
+    let bin = gst::parse_bin_from_description("videoconvert ! videobalance name=balance ! videoconvert", true).ok()?;
+    bin.set_property("name", &VIDEO_BALANCE_FILTER_NAME).ok()?;
+    let bin = bin.upcast::<gst::Element>();
+    apply_video_balance(&bin, balance)?;
+    Some(bin)
+}
+
+// Like swap_pitch_filter(), for the color adjustments.
+fn swap_video_balance(video_balance: &Arc<Mutex<gst::Element>>, balance: VideoBalance) -> Option<()> {
+    let mut current = video_balance.lock().unwrap();
+    if (current.get_name() == VIDEO_BALANCE_FILTER_NAME) != balance.is_default() {
+        if !balance.is_default() {
+            apply_video_balance(&current, &balance)?;
+        }
+        return Some(());
+    }
+    let filter = create_video_balance(&balance)?;
+    swap_filter(&mut current, filter)
+}
+
+fn stream_hdr_transfer(info: &gst_player::PlayerMediaInfo) -> Option<tone_mapping::HdrTransfer> {
+    let caps = info.get_video_streams().first()?.get_caps()?;
+    let colorimetry = caps.get_structure(0)?.get::<&str>("colorimetry").ok()??;
+    tone_mapping::hdr_transfer_from_colorimetry(colorimetry)
+}
+
+fn tone_mapping_filter_name(transfer: tone_mapping::HdrTransfer) -> &'static str {
+    match transfer {
+        tone_mapping::HdrTransfer::Pq => "tone-mapping-pq",
+        tone_mapping::HdrTransfer::Hlg => "tone-mapping-hlg",
+    }
+}
+
+// Tone-map the frames converted to 8-bit RGB with the lookup table of the
+// snapshots, then convert them back for the elements downstream. Like the
+// other swappable filters, identity stands in for it when not needed.
+fn create_tone_mapping_filter(transfer: Option<tone_mapping::HdrTransfer>) -> Option<gst::Element> {
+    let transfer = match transfer {
+        Some(transfer) => transfer,
+        None => return gst::ElementFactory::make("identity", None).ok(),
+    };
+    let bin = gst::parse_bin_from_description(
+        "videoconvert ! video/x-raw,format=RGBx ! identity name=lut ! videoconvert",
+        true,
+    )
+    .ok()?;
+    bin.set_property("name", &tone_mapping_filter_name(transfer)).ok()?;
+    let lut = tone_mapping::build_sdr_lut(transfer, HDR_PEAK_NITS);
+    let pad = bin.get_by_name("lut")?.get_static_pad("src")?;
+    pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+        if let Some(gst::PadProbeData::Buffer(ref mut buffer)) = info.data {
+            if let Ok(mut map) = buffer.make_mut().map_writable() {
+                for pixel in map.as_mut_slice().chunks_exact_mut(4) {
+                    for sample in pixel.iter_mut().take(3) {
+                        *sample = lut[*sample as usize];
+                    }
+                }
+            }
+        }
+        gst::PadProbeReturn::Ok
+    })?;
+    Some(bin.upcast())
+}
+
+// Like swap_pitch_filter(), for the tone mapping.
+fn swap_tone_mapping_filter(
+    tone_mapping: &Arc<Mutex<gst::Element>>,
+    transfer: Option<tone_mapping::HdrTransfer>,
+) -> Option<()> {
+    let mut current = tone_mapping.lock().unwrap();
+    let name = current.get_name();
+    let unchanged = match transfer {
+        Some(transfer) => name == tone_mapping_filter_name(transfer),
+        None => !name.starts_with("tone-mapping-"),
+    };
+    if unchanged {
+        return Some(());
+    }
+    let filter = create_tone_mapping_filter(transfer)?;
+    swap_filter(&mut current, filter)
+}
+
+// Rotate the video clockwise by a multiple of 90 degrees.
+fn set_video_flip(pipeline: &gst::Element, degrees: u32) -> Option<()> {
+    let direction = match degrees {
+        90 => "90r",
+        180 => "180",
+        270 => "90l",
+        _ => "identity",
+    };
+    video_flip_element(pipeline)?.set_property_from_str("video-direction", direction);
+    Some(())
+}
+
+fn rotated_dimensions(width: i32, height: i32, degrees: u32) -> (i32, i32) {
+    if degrees % 180 == 90 {
+        (height, width)
+    } else {
+        (width, height)
+    }
+}
+
+// Stretch the video to the whole video area when disabled.
+fn set_force_aspect_ratio(sink: &gst::Element, force: bool) {
+    if sink.find_property("force-aspect-ratio").is_some() {
+        let _ = sink.set_property("force-aspect-ratio", &force);
+    }
+}
+
+// Use the preferred sink if it works, the first working one otherwise.
+fn create_renderer(
+    preferred_sink: Option<&str>,
+) -> Option<(gst_player::PlayerVideoOverlayVideoRenderer, gst::Element, VideoSink)> {
+    let preferred = VIDEO_SINKS.iter().cloned().filter(|name| Some(*name) == preferred_sink);
+    let sink = preferred
+        .chain(VIDEO_SINKS.iter().cloned())
+        .find_map(create_video_sink)?;
+    let (bin, tee) = create_tee_sink_bin(&sink.element);
+    let renderer = gst_player::PlayerVideoOverlayVideoRenderer::with_sink(&bin);
+    Some((renderer, tee, sink))
+}
+
+impl ChannelPlayer {
+    pub fn new(
+        sender: glib::Sender<PlayerEvent>,
+        cache_file_path: Option<path::PathBuf>,
+        element_properties: ElementProperties,
+        preferred_video_sink: Option<&str>,
+    ) -> Result<Self, Error> {
+        let (renderer, video_tee, video_sink) =
+            match create_renderer(preferred_video_sink) {
+                Some(renderer) => renderer,
+                None => return Err(failure::err_msg("Neither gtkglsink nor glimagesink found. Make sure to install gst-plugins-good with GTK support enabled, or gst-plugins-base")),
+            };
+        let dispatcher = gst_player::PlayerGMainContextSignalDispatcher::new(None);
+        let player = gst_player::Player::new(
+            Some(&renderer.clone().upcast::<gst_player::PlayerVideoRenderer>()),
+            Some(&dispatcher.upcast::<gst_player::PlayerSignalDispatcher>()),
+        );
+
+        // Get position updates every 250ms.
+        let mut config = player.get_config();
+        config.set_position_update_interval(250);
+        player.set_config(config).unwrap();
+
+        connect_video_area(&video_sink.widget, &player, &renderer, video_sink.overlay);
+
+        let chapters = Arc::new(Mutex::new(MediaChapters::default()));
+        connect_chapters(&player.get_pipeline(), &chapters, sender.clone());
+        connect_step_done(&player.get_pipeline(), sender.clone());
+        let gapless_items = Arc::new(Mutex::new(GaplessItems::default()));
+        connect_gapless(&player, &gapless_items);
+
+        // Preroll new media, so that their first frame is shown, before
+        // reporting them and starting playback, see prerolled().
+        let uri_chapters = chapters.clone();
+        player.connect_uri_loaded(move |player, uri| {
+            *uri_chapters.lock().unwrap() = MediaChapters {
+                embedded: vec![],
+                sidecar: sidecar_chapters(uri),
+            };
+            player.pause();
+            with_mut_player!(player player_data {
+                player_data.notify(PlayerEvent::ChaptersUpdated);
+                let play = player_data.uri_loaded(player, uri);
+                player_data.play_after_preroll = Some(play);
+            });
+        });
+
+        player.connect_end_of_stream(|player| {
+            with_mut_player!(player player_data {
+                player_data.end_of_stream(player);
+            });
+        });
+
+        player.connect_media_info_updated(|player, info| {
+            with_mut_player!(player player_data {
+                player_data.media_info_updated(&info);
+            });
+        });
+
+        player.connect_position_updated(|player, _| {
+            with_player!(player {
+                player.notify(PlayerEvent::PositionUpdated);
+            });
+        });
+
+        player.connect_buffering(|player, percent| {
+            with_player!(player {
+                player.notify(PlayerEvent::Buffering(percent));
+            });
+        });
+
+        player.connect_video_dimensions_changed(|player, width, height| {
+            with_mut_player!(player player_data {
+                player_data.video_dimensions = (width, height);
+                let (width, height) = rotated_dimensions(width, height, player_data.video_rotation);
+                player_data.notify(PlayerEvent::VideoDimensionsChanged(width, height));
+            });
+        });
+
+        let overlay_renderer = if video_sink.overlay {
+            Some(renderer.clone())
+        } else {
+            None
+        };
+        player.connect_state_changed(move |player, state| {
+            if state == gst_player::PlayerState::Paused {
+                if let Some(ref renderer) = overlay_renderer {
+                    renderer.expose();
+                }
+                let mut play = false;
+                with_mut_player!(player player_data {
+                    play = player_data.prerolled();
+                    player_data.resume_live_stream(player);
+                });
+                if play {
+                    player.play();
+                }
+            }
+            let state = match state {
+                gst_player::PlayerState::Playing => Some(PlaybackState::Playing),
+                gst_player::PlayerState::Paused => Some(PlaybackState::Paused),
+                gst_player::PlayerState::Stopped => Some(PlaybackState::Stopped),
+                _ => None,
+            };
+            if let Some(s) = state {
+                with_player!(player {
+                    player.notify(PlayerEvent::StateChanged(s));
+                });
+            }
+        });
+
+        player.connect_volume_changed(|player| {
+            with_player!(player player_data {
+                player_data.notify(PlayerEvent::VolumeChanged(player.get_volume()));
+            });
+        });
+
+        player.connect_error(|player, error| {
+            with_mut_player!(player player_data {
+                if !player_data.error(player, &error.to_string()) {
+                    // FIXME: Pass error to enum.
+                    player_data.notify(PlayerEvent::Error(error.to_string()));
+                }
+            });
+        });
+
+        player.connect_property_audio_video_offset_notify(|player| {
+            with_player!(player player_data {
+                player_data.notify(PlayerEvent::AudioVideoOffsetChanged(player.get_audio_video_offset()));
+            });
+        });
+
+        player.connect_property_subtitle_video_offset_notify(|player| {
+            with_player!(player player_data {
+                player_data.notify(PlayerEvent::SubtitleVideoOffsetChanged(player.get_subtitle_video_offset()));
+            });
+        });
+
+        let subtitle_style = Arc::new(Mutex::new(SubtitleStyle {
+            background: SubtitleBackground::Outline,
+            background_opacity: 0.5,
+            scale: 1.0,
+            scale_base: SubtitleScaleBase::Video,
+            script: SubtitleScript::Auto,
+            font: None,
+            color: None,
+        }));
+        let subtitle_area_height = Arc::new(Mutex::new(video_sink.widget.get_allocated_height()));
+        connect_subtitle_scaling(&video_sink.widget, &player, &subtitle_style, &subtitle_area_height);
+        let deinterlace = Arc::new(Mutex::new(Deinterlace::Off));
+        if let Ok(pipeline) = player.get_pipeline().downcast::<gst::Bin>() {
+            let subtitle_style = subtitle_style.clone();
+            let subtitle_area_height = subtitle_area_height.clone();
+            let deinterlace = deinterlace.clone();
+            pipeline.connect_deep_element_added(move |_, _, element| {
+                element_properties.apply(element);
+                if let Some(name) = element_factory_name(element) {
+                    match name.as_str() {
+                        "textoverlay" => {
+                            let area_height = *subtitle_area_height.lock().unwrap();
+                            apply_subtitle_style(element, &*subtitle_style.lock().unwrap(), area_height);
+                        }
+                        // The playbin creates it anew as the video chain is
+                        // rebuilt, on track changes.
+                        "deinterlace" => apply_deinterlace_mode(element, *deinterlace.lock().unwrap()),
+                        _ => {}
+                    }
+                }
             });
+        }
+
+        let color_range = Arc::new(Mutex::new(ColorRange::Auto));
+        let pixel_aspect_ratio = Arc::new(Mutex::new(None));
+        let display_aspect_ratio = Arc::new(Mutex::new(None));
+        let video_caps = Arc::new(Mutex::new(None));
+        connect_caps_overrides(
+            &video_tee,
+            &color_range,
+            &pixel_aspect_ratio,
+            &display_aspect_ratio,
+            &video_caps,
+        );
+
+        let video_zoom = Arc::new(Mutex::new(VideoZoom {
+            factor: 1.0,
+            fill: false,
+            area: (
+                video_sink.widget.get_allocated_width(),
+                video_sink.widget.get_allocated_height(),
+            ),
+        }));
+        connect_zoom_area(&video_sink.widget, &video_tee, &video_zoom);
+        connect_video_zoom(&video_tee, &video_zoom);
+
+        let video_flip = gst::ElementFactory::make("videoflip", Some("video-flip")).ok();
+        if video_flip.is_none() {
+            eprintln!("videoflip is missing, the video can't be rotated");
+        }
+        let tone_mapping = create_tone_mapping_filter(None);
+        let video_balance = create_video_balance(&VideoBalance::default());
+        let video_filters = video_flip
+            .iter()
+            .chain(tone_mapping.iter())
+            .chain(video_balance.iter())
+            .collect::<Vec<_>>();
+        if !video_filters.is_empty() && set_video_filters(&player.get_pipeline(), &video_filters).is_none() {
+            eprintln!("Unable to set up the video filters");
+        }
+
+        let audio_level = create_audio_level(&player.get_pipeline(), sender.clone());
+        let replay_gain_mode = Arc::new(Mutex::new(ReplayGainMode::Off));
+        let replay_gain = create_replay_gain(&replay_gain_mode, sender.clone());
+        let pitch_filter = gst::ElementFactory::make(pitch_filter_factory(true), None).ok();
+        let normalization = create_normalization_filter(false);
+        let equalizer = create_equalizer(None);
+        let channel_remap = create_channel_remap();
+        let audio_filters = pitch_filter
+            .iter()
+            .chain(replay_gain.iter())
+            .chain(normalization.iter())
+            .chain(equalizer.iter())
+            .chain(channel_remap.as_ref().map(|remap| &remap.bin))
+            .chain(audio_level.iter())
+            .collect::<Vec<_>>();
+        if !audio_filters.is_empty() && set_audio_filters(&player.get_pipeline(), &audio_filters).is_none() {
+            eprintln!("Unable to set up the audio filters");
+        }
+
+        let player_id = player.get_name();
+        let mut subscribers = Vec::new();
+        subscribers.push(sender);
+        let mut cache = None;
+        if let Some(ref path) = cache_file_path {
+            cache = Some(MediaCache::open(path).unwrap());
+        }
+        let player_data = PlayerDataHolder {
+            subscribers,
+            playlist: vec![],
+            current_uri: "".into(),
+            index: 0,
+            auto_advance: true,
+            carry_tracks: true,
+            carried_tracks: None,
+            skip_unplayable: true,
+            load_retries: 0,
+            retries_left: 0,
+            cache,
+            resume_mode: ResumeMode::Always,
+            live_resume: LiveResume::LiveEdge,
+            live_resume_position: None,
+            start_paused: false,
+            rescan_missing_subtitles: true,
+            subtitle_encoding: None,
+            resume_choices: HashMap::new(),
+            download_config: None,
+            download: None,
+            embedded_fonts: None,
+            play_after_preroll: None,
+            media_info_pending: false,
+            loaded_uri: None,
+            previous_uri: None,
+            toggled_positions: HashMap::new(),
+            forgotten_uris: HashSet::new(),
+            loop_points: (None, None),
+            order: vec![],
+            shuffle: false,
+            repeat: RepeatMode::Off,
+            gapless: false,
+            gapless_items,
+            gapless_uri: None,
+            default_volume: 1.0,
+            video_rotation: 0,
+            video_dimensions: (0, 0),
+            hdr_transfer: None,
+        };
+
+        PLAYER_REGISTRY.with(move |registry| {
+            registry.borrow_mut().insert(player_id, player_data);
+        });
+
+        Ok(Self {
+            player,
+            renderer,
+            video_tee,
+            video_sink,
+            subtitle_style,
+            subtitle_area_height,
+            color_range,
+            deinterlace,
+            pixel_aspect_ratio,
+            aspect_ratio: RefCell::new(AspectRatio::Auto),
+            display_aspect_ratio,
+            video_caps,
+            audio_level,
+            replay_gain,
+            replay_gain_mode,
+            pitch_filter: pitch_filter.map(|filter| Arc::new(Mutex::new(filter))),
+            normalization: normalization.map(|filter| Arc::new(Mutex::new(filter))),
+            equalizer: equalizer.map(|filter| Arc::new(Mutex::new(filter))),
+            channel_remap,
+            video_balance: video_balance.map(|filter| Arc::new(Mutex::new(filter))),
+            video_balance_values: RefCell::new(VideoBalance::default()),
+            tone_mapping: tone_mapping.map(|filter| Arc::new(Mutex::new(filter))),
+            tone_mapping_enabled: Cell::new(false),
+            released_at: RefCell::new(None),
+            chapters,
+            video_zoom,
+            seek_thumbnails: RefCell::new(None),
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn register_event_handler(&mut self, sender: glib::Sender<PlayerEvent>) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.register_event_handler(sender);
+        });
+    }
+
+    /// Chapters of the current media, embedded or read from a chapter file
+    /// next to it, sorted by start time.
+    pub fn get_chapters(&self) -> Vec<Chapter> {
+        let chapters = self.chapters.lock().unwrap();
+        chapter_files::merge_chapters(&chapters.embedded, &chapters.sidecar)
+    }
+
+    pub fn load_playlist(&self, playlist: Vec<string::String>) {
+        self.load_playlist_at(playlist, 0);
+    }
+
+    /// Load the playlist, starting with its media at `index`. Playlist files
+    /// are replaced by their items.
+    pub fn load_playlist_at(&self, playlist: Vec<string::String>, index: usize) {
+        assert!(index < playlist.len());
+        let (playlist, index) = expand_playlist_files(playlist, index);
+        let player = &self.player;
+        let uri = playlist[index].clone();
+        with_mut_player!(player player_data {
+            player_data.set_playlist(playlist, index);
+            player_data.load_uri(player, &uri);
+        });
+    }
+
+    /// The playlist and the index of the loaded media in it, or the media
+    /// alone when opened outside of the playlist.
+    pub fn get_playlist(&self) -> (Vec<string::String>, usize) {
+        let mut playlist = (vec![], 0);
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            playlist = match player_data.loaded_uri {
+                Some(ref uri) if player_data.playlist.get(player_data.index) != Some(uri) => (vec![uri.clone()], 0),
+                _ => (player_data.playlist.clone(), player_data.index),
+            };
+        });
+        playlist
+    }
+
+    /// Add media at the end of the playlist, or load them as the playlist if
+    /// nothing is loaded.
+    pub fn append_to_playlist(&self, uris: Vec<string::String>) {
+        if uris.is_empty() {
+            return;
+        }
+        if self.player.get_uri().is_none() {
+            self.load_playlist(uris);
+            return;
+        }
+        let (uris, _) = expand_playlist_files(uris, 0);
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.append_to_playlist(uris);
+        });
+    }
+
+    pub fn has_next(&self) -> bool {
+        let player = &self.player;
+        let mut has_next = false;
+        with_player!(player player_data {
+            has_next = player_data.has_next();
+        });
+        has_next
+    }
+
+    pub fn next(&self) -> bool {
+        let player = &self.player;
+        let mut loaded = false;
+        with_mut_player!(player player_data {
+            loaded = player_data.next(player);
+        });
+        loaded
+    }
+
+    /// Play the playlist in a random order, the current item first. The
+    /// order is shuffled again whenever enabled or a playlist is loaded.
+    pub fn set_shuffle(&self, enabled: bool) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.shuffle = enabled;
+            player_data.reorder();
+        });
+    }
+
+    /// With `RepeatMode::One`, the current item is played again on
+    /// end-of-stream. With `RepeatMode::All`, the next and previous items
+    /// wrap around the ends of the playlist.
+    pub fn set_repeat_mode(&self, mode: RepeatMode) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.repeat = mode;
+            player_data.update_gapless_next();
+        });
+    }
+
+    /// When enabled, the next playlist item is given to the pipeline before
+    /// the current one ends, so that there is no gap between them. Only
+    /// applies when auto-advancing without a delay, and not repeating the
+    /// current item.
+    pub fn set_gapless(&self, enabled: bool) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.gapless = enabled;
+            player_data.update_gapless_next();
+        });
+    }
+
+    /// Load the playlist item before the current one, if any.
+    pub fn previous(&self) -> bool {
+        let player = &self.player;
+        let mut loaded = false;
+        with_mut_player!(player player_data {
+            loaded = player_data.previous(player);
+        });
+        loaded
+    }
+
+    /// When disabled, the next playlist item is not loaded on end-of-stream
+    /// and advancing is left to the caller, using `next()`.
+    pub fn set_auto_advance(&self, enabled: bool) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.auto_advance = enabled;
+            player_data.update_gapless_next();
+        });
+    }
+
+    /// When enabled, playlist items failing to play are reloaded up to
+    /// `retries` times, then skipped, with a PlaylistItemSkipped event.
+    pub fn set_skip_unplayable(&self, enabled: bool, retries: u32) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.skip_unplayable = enabled;
+            player_data.load_retries = retries;
+        });
+    }
+
+    /// When an external subtitle file to attach is missing, fall back to the
+    /// best match next to the media instead of disabling subtitles.
+    pub fn set_rescan_missing_subtitles(&self, enabled: bool) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.rescan_missing_subtitles = enabled;
+        });
+    }
+
+    /// Force the encoding of the external subtitle files configured next,
+    /// instead of guessing it.
+    pub fn set_subtitle_encoding(&self, encoding: Option<&str>) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.subtitle_encoding = encoding.map(string::String::from);
+        });
+    }
+
+    pub fn set_resume_mode(&self, mode: ResumeMode) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.resume_mode = mode;
+        });
+    }
+
+    pub fn set_live_resume(&self, live_resume: LiveResume) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.live_resume = live_resume;
+        });
+    }
+
+    pub fn set_start_paused(&self, enabled: bool) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.start_paused = enabled;
+        });
+    }
+
+    /// Answer a ResumeAvailable event, starting playback from the last known
+    /// position or from the start. The choice is kept for the session.
+    pub fn answer_resume(&self, resume: bool) {
+        let uri = match self.get_current_uri() {
+            Some(uri) => uri,
+            None => return,
+        };
+        let player = &self.player;
+        let mut position = gst::ClockTime::none();
+        with_mut_player!(player player_data {
+            player_data.resume_choices.insert(uri.to_string(), resume);
+            position = player_data.last_position(&uri);
+        });
+        if resume && position.is_some() {
+            self.player.seek(position);
+        }
+        self.player.play();
+    }
+
+    /// Carry the subtitle visibility and the subtitle and audio languages of
+    /// a playlist item to the next one, see `restore_carried_tracks()`. The
+    /// subtitle style, size included, applies to all media regardless.
+    pub fn set_carry_tracks(&self, enabled: bool) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.carry_tracks = enabled;
+            if !enabled {
+                player_data.carried_tracks = None;
+            }
+        });
+    }
+
+    /// Apply the track choices carried from the previous playlist item to the
+    /// current media. Languages missing from the media are left at their
+    /// defaults. Returns whether subtitles should be visible, if known.
+    pub fn restore_carried_tracks(&self) -> Option<bool> {
+        let player = &self.player;
+        let mut selection = None;
+        with_mut_player!(player player_data {
+            selection = player_data.carried_tracks.take();
+        });
+        let selection = selection?;
+        let info = self.player.get_media_info()?;
+
+        if let Some(ref language) = selection.audio_language {
+            let streams = info.get_audio_streams();
+            if let Some(idx) = streams.iter().position(|s| s.get_language().as_ref() == Some(language)) {
+                self.set_audio_track_index(idx as i32);
+            }
+        }
+
+        if !selection.subtitles_enabled {
+            self.configure_subtitle_track(None);
+        } else if let Some(ref language) = selection.subtitle_language {
+            let streams = info.get_subtitle_streams();
+            if let Some(idx) = streams.iter().position(|s| s.get_language().as_ref() == Some(language)) {
+                self.configure_subtitle_track(Some(SubtitleTrack::Inband(idx as i32)));
+            }
+        }
+        Some(selection.subtitles_enabled)
+    }
+
+    pub fn video_area(&self) -> &gtk::Widget {
+        &self.video_sink.widget
+    }
+
+    pub fn video_sink_name(&self) -> &'static str {
+        self.video_sink.name
+    }
+
+    pub fn get_frame_stats(&self) -> FrameStats {
+        frame_stats(&self.video_sink.element).unwrap_or_default()
+    }
+
+    /// Render the video with another sink. The pipeline is restarted, from
+    /// the same position, and the new video area has to be put in place of
+    /// the previous one. If the new sink can't be used, the current one is
+    /// kept.
+    pub fn set_video_sink(&mut self, name: &str) -> Result<(), Error> {
+        let sink = VIDEO_SINKS
+            .iter()
+            .cloned()
+            .find(|n| *n == name)
+            .and_then(create_video_sink)
+            .ok_or_else(|| failure::format_err!("Unable to create {}", name))?;
+        let bin = self
+            .video_tee
+            .get_parent()
+            .and_then(|p| p.downcast::<gst::Bin>().ok())
+            .ok_or_else(|| failure::err_msg("Video sink bin not found"))?;
+        let queue = bin
+            .get_by_name("video-crop")
+            .or_else(|| bin.get_by_name("video-sink-queue"))
+            .ok_or_else(|| failure::err_msg("Video sink queue not found"))?;
+
+        let pipeline = self.player.get_pipeline();
+        let (_, state, _) = pipeline.get_state(gst::ClockTime::from_seconds(0));
+        let position = self.player.get_position();
+        self.player.stop();
+        pipeline.set_state(gst::State::Null)?;
+
+        let current = &self.video_sink.element;
+        queue.unlink(current);
+        bin.remove(current)?;
+        let linked = bin.add(&sink.element).and_then(|_| queue.link(&sink.element));
+        let result = if let Err(e) = linked {
+            let _ = bin.remove(&sink.element);
+            bin.add(current)?;
+            queue.link(current)?;
+            Err(failure::format_err!("Unable to use {}: {}", name, e))
+        } else {
+            connect_video_area(&sink.widget, &self.player, &self.renderer, sink.overlay);
+            set_force_aspect_ratio(&sink.element, *self.aspect_ratio.borrow() != AspectRatio::Stretch);
+            connect_subtitle_scaling(
+                &sink.widget,
+                &self.player,
+                &self.subtitle_style,
+                &self.subtitle_area_height,
+            );
+            connect_zoom_area(&sink.widget, &self.video_tee, &self.video_zoom);
+            self.video_sink = sink;
+            Ok(())
+        };
+
+        if self.player.get_uri().is_some() {
+            self.restart(state, position);
+        }
+        result
+    }
+
+    // Resume the current media after the pipeline was stopped. An item moved
+    // to by a gapless transition is loaded again, GstPlayer would restart
+    // the one it knows of.
+    fn restart(&self, state: gst::State, position: gst::ClockTime) {
+        let player = &self.player;
+        let mut gapless_uri = None;
+        with_mut_player!(player player_data {
+            gapless_uri = player_data.gapless_uri.clone();
+            if let Some(ref uri) = gapless_uri {
+                if position.nanoseconds().is_some() {
+                    player_data.toggled_positions.insert(uri.clone(), position);
+                }
+            }
+        });
+        if let Some(uri) = gapless_uri {
+            self.load_uri(&uri);
+            return;
+        }
+        if state == gst::State::Playing {
+            self.player.play();
+        } else {
+            self.player.pause();
+        }
+        if !position.is_none() {
+            self.player.seek(position);
+        }
+    }
+
+    /// Recreate the video sink, which some sinks need to render again at the
+    /// right size after a change of display or scale factor. Subscribers are
+    /// notified before and after, and the new video area has to be put in
+    /// place of the previous one.
+    pub fn rebuild_video_sink(&mut self) -> Result<(), Error> {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.notify(PlayerEvent::VideoSinkRebuilding);
+        });
+        let result = self.set_video_sink(self.video_sink.name);
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.notify(PlayerEvent::VideoSinkRebuilt);
+        });
+        result
+    }
+
+    /// Play the audio on the output device with this display name, or on the
+    /// default one. The pipeline is restarted, from the same position.
+    pub fn set_audio_device(&self, device: Option<&str>) -> Result<(), Error> {
+        let sink = match device {
+            Some(name) => Some(audio_devices::create_audio_sink(name).ok_or_else(|| {
+                failure::format_err!(
+                    "Audio output {} not found, the available outputs are: {}",
+                    name,
+                    audio_devices::audio_output_names().join(", ")
+                )
+            })?),
+            None => None,
+        };
+
+        let pipeline = self.player.get_pipeline();
+        let (_, state, _) = pipeline.get_state(gst::ClockTime::from_seconds(0));
+        let position = self.player.get_position();
+        let restart = self.player.get_uri().is_some() && self.released_position().is_none();
+        self.player.stop();
+        pipeline.set_state(gst::State::Null)?;
+        pipeline.set_property("audio-sink", &sink)?;
+
+        if restart {
+            self.restart(state, position);
+        }
+        Ok(())
+    }
+
+    /// Attach a new video output to the pipeline, rendering to the returned
+    /// widget, in sync with the main video area.
+    pub fn add_mirror_output(&self) -> Option<(gtk::Widget, MirrorOutput)> {
+        let sink = gst::ElementFactory::make("gtksink", None).ok()?;
+        let widget = sink.get_property("widget").ok()?.get::<gtk::Widget>().ok()??;
+
+        let branch = gst::Bin::new(None);
+        let queue = gst::ElementFactory::make("queue", None).ok()?;
+        let convert = gst::ElementFactory::make("videoconvert", None).ok()?;
+        branch.add_many(&[&queue, &convert, &sink]).ok()?;
+        gst::Element::link_many(&[&queue, &convert, &sink]).ok()?;
+        let ghost_pad = gst::GhostPad::with_target(Some("sink"), &queue.get_static_pad("sink")?).ok()?;
+        branch.add_pad(&ghost_pad).ok()?;
+        let branch = branch.upcast::<gst::Element>();
+
+        let bin = self.video_tee.get_parent()?.downcast::<gst::Bin>().ok()?;
+        bin.add(&branch).ok()?;
+        let tee_pad = self.video_tee.get_request_pad("src_%u")?;
+        tee_pad.link(&branch.get_static_pad("sink")?).ok()?;
+        branch.sync_state_with_parent().ok()?;
+
+        Some((widget, MirrorOutput { tee_pad, branch }))
+    }
+
+    /// Detach a mirror output once its tee branch is idle. `done` is called
+    /// from a streaming thread after the branch was removed.
+    pub fn remove_mirror_output<F: FnOnce() + Send + 'static>(&self, mirror: MirrorOutput, done: F) {
+        let tee = self.video_tee.clone();
+        let MirrorOutput { tee_pad, branch } = mirror;
+        let done = Mutex::new(Some(done));
+        tee_pad.add_probe(gst::PadProbeType::IDLE, move |pad, _| {
+            if let Some(peer) = pad.get_peer() {
+                let _ = pad.unlink(&peer);
+            }
+            tee.release_request_pad(pad);
+            let _ = branch.set_state(gst::State::Null);
+            if let Some(bin) = branch.get_parent().and_then(|p| p.downcast::<gst::Bin>().ok()) {
+                let _ = bin.remove(&branch);
+            }
+            if let Some(done) = done.lock().unwrap().take() {
+                done();
+            }
+            gst::PadProbeReturn::Remove
         });
+    }
 
-        player.connect_state_changed(|player, state| {
-            let state = match state {
-                gst_player::PlayerState::Playing => Some(PlaybackState::Playing),
-                gst_player::PlayerState::Paused => Some(PlaybackState::Paused),
-                gst_player::PlayerState::Stopped => Some(PlaybackState::Stopped),
-                _ => None,
-            };
-            if let Some(s) = state {
-                with_player!(player {
-                    player.notify(PlayerEvent::StateChanged(s));
-                });
+    /// A playlist file is loaded as the playlist instead.
+    pub fn load_uri(&self, uri: &str) {
+        if read_playlist_file(uri).is_some() {
+            self.load_playlist(vec![uri.to_string()]);
+            return;
+        }
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.load_uri(player, uri);
+        });
+    }
+
+    /// Load the media played before the current one, remembering the
+    /// position of the current one for when toggling back. Returns the URI
+    /// of the media loaded, if there was a previous one.
+    pub fn toggle_previous_item(&self) -> Option<string::String> {
+        let current_uri = self.get_current_uri()?;
+        let position = self.released_position().unwrap_or_else(|| self.player.get_position());
+        self.write_last_known_media_position();
+
+        let player = &self.player;
+        let mut previous_uri = None;
+        with_mut_player!(player player_data {
+            if let Some(uri) = player_data.previous_uri.clone() {
+                if position.nanoseconds().is_some() {
+                    player_data.toggled_positions.insert(current_uri.to_string(), position);
+                }
+                // Keep the playlist navigation consistent.
+                if let Some(index) = player_data.playlist.iter().position(|u| *u == uri) {
+                    player_data.index = index;
+                }
+                player_data.load_uri(player, &uri);
+                previous_uri = Some(uri);
             }
         });
+        previous_uri
+    }
 
-        player.connect_volume_changed(|player| {
-            with_player!(player player_data {
-                player_data.notify(PlayerEvent::VolumeChanged(player.get_volume()));
-            });
+    pub fn set_download_config(&self, config: Option<DownloadConfig>) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.download_config = config;
         });
+    }
 
-        player.connect_error(|player, error| {
-            with_player!(player {
-                // FIXME: Pass error to enum.
-                player.notify(PlayerEvent::Error(error.to_string()));
-            });
+    /// Remove the completed downloads, partial ones are kept so that they
+    /// can be resumed later on.
+    pub fn clear_downloads(&self) {
+        let player = &self.player;
+        let mut directory = None;
+        with_player!(player player_data {
+            directory = player_data.download_config.as_ref().map(|c| c.directory.clone());
         });
+        let entries = match directory.and_then(|d| fs::read_dir(d).ok()) {
+            Some(entries) => entries,
+            None => return,
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let media_dir = entry.path();
+            if let Ok(files) = fs::read_dir(&media_dir) {
+                for file in files.filter_map(|f| f.ok()) {
+                    if file.path().extension().map_or(true, |e| e != "part") {
+                        let _ = fs::remove_file(file.path());
+                    }
+                }
+            }
+            // Only succeeds when no partial download was left.
+            let _ = fs::remove_dir(&media_dir);
+        }
+    }
 
-        player.connect_property_audio_video_offset_notify(|player| {
-            with_player!(player player_data {
-                player_data.notify(PlayerEvent::AudioVideoOffsetChanged(player.get_audio_video_offset()));
-            });
+    pub fn get_current_uri(&self) -> Option<glib::GString> {
+        let player = &self.player;
+        let mut uri = None;
+        with_player!(player player_data {
+            uri = player_data.playing_uri(player);
         });
+        uri.map(glib::GString::from)
+    }
 
-        player.connect_property_subtitle_video_offset_notify(|player| {
-            with_player!(player player_data {
-                player_data.notify(PlayerEvent::SubtitleVideoOffsetChanged(player.get_subtitle_video_offset()));
-            });
+    pub fn stop(&self) {
+        self.player.stop();
+    }
+
+    pub fn get_media_info(&self) -> Option<gst_player::PlayerMediaInfo> {
+        self.player.get_media_info()
+    }
+
+    /// Set the volume chosen by the user, remembered for the current media.
+    pub fn set_volume(&self, volume: f64) {
+        self.player.set_volume(volume);
+        self.write_media_volume();
+    }
+
+    /// Volume of the media loaded next whose volume was never changed.
+    pub fn set_default_volume(&self, volume: f64) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.default_volume = volume.max(0.0).min(1.0);
         });
+    }
 
-        let player_id = player.get_name();
-        let mut subscribers = Vec::new();
-        subscribers.push(sender);
-        let mut cache = None;
-        if let Some(ref path) = cache_file_path {
-            cache = Some(MediaCache::open(path).unwrap());
+    fn write_media_volume(&self) {
+        let uri = match self.get_current_uri() {
+            Some(uri) => uri,
+            None => return,
+        };
+        let volume = self.player.get_volume();
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            if !player_data.forgotten_uris.contains(uri.as_str()) {
+                player_data.update_cache_volume_and_write(&uri, volume);
+            }
+        });
+    }
+
+    pub fn play(&self) {
+        self.player.play();
+        self.restore_released_position();
+    }
+
+    /// Save the position and stop the pipeline, releasing the decoders and
+    /// the audio and video outputs, until playback is resumed.
+    pub fn release_while_paused(&self) {
+        let uri = match self.get_current_uri() {
+            Some(uri) => uri,
+            None => return,
+        };
+        // GstPlayer would restart the item it loaded rather than the one moved
+        // to by a gapless transition.
+        if self.released_position().is_some() || self.player.get_uri().as_ref() != Some(&uri) {
+            return;
         }
-        let player_data = PlayerDataHolder {
-            subscribers,
-            playlist: vec![],
-            current_uri: "".into(),
-            index: 0,
-            cache,
+        let position = self.player.get_position();
+        // Once stopped, the position is unknown, so store it right away in
+        // case the application is closed before playback resumes.
+        self.write_last_known_media_position();
+        *self.released_at.borrow_mut() = Some((uri, position));
+        self.player.stop();
+    }
+
+    // Position at which the current media was released, if it was.
+    fn released_position(&self) -> Option<gst::ClockTime> {
+        match *self.released_at.borrow() {
+            Some((ref uri, position)) if self.get_current_uri().as_ref() == Some(uri) => Some(position),
+            _ => None,
+        }
+    }
+
+    // GstPlayer applies the seek once the pipeline prerolled.
+    fn restore_released_position(&self) {
+        let position = self.released_position();
+        *self.released_at.borrow_mut() = None;
+        if let Some(position) = position.filter(|p| p.nanoseconds().is_some()) {
+            self.player.seek(position);
+        }
+    }
+
+    pub fn pause(&self) {
+        self.player.pause();
+    }
+
+    pub fn toggle_pause(&self, currently_paused: bool) {
+        if currently_paused {
+            self.play();
+        } else {
+            self.player.pause();
+        }
+    }
+
+    pub fn increase_volume(&self) {
+        let value = self.player.get_volume();
+        let offset = 0.07;
+        if value + offset < 1.0 {
+            self.player.set_volume(value + offset);
+        } else {
+            self.player.set_volume(1.0);
+        }
+        self.write_media_volume();
+    }
+
+    pub fn decrease_volume(&self) {
+        let value = self.player.get_volume();
+        let offset = 0.07;
+        if value >= offset {
+            self.player.set_volume(value - offset);
+        } else {
+            self.player.set_volume(0.0);
+        }
+        self.write_media_volume();
+    }
+
+    pub fn toggle_mute(&self, enabled: bool) {
+        self.player.set_mute(enabled);
+    }
+
+    /// Escape hatch giving access to the underlying playbin, for instance to
+    /// add pad probes or inspect elements. There are no stability guarantees
+    /// about the pipeline layout, and it must only be used from the main
+    /// context, like the rest of the ChannelPlayer API.
+    #[allow(dead_code)]
+    pub fn pipeline(&self) -> gst::Element {
+        self.player.get_pipeline()
+    }
+
+    pub fn dump_pipeline(&self, label: &str) {
+        let element = self.player.get_pipeline();
+        if let Ok(pipeline) = element.downcast::<gst::Pipeline>() {
+            gst::debug_bin_to_dot_file_with_ts(&pipeline, gst::DebugGraphDetails::all(), label);
+        }
+    }
+
+    /// Save the last rendered frame as a PNG file, then notify SnapshotSaved
+    /// or SnapshotFailed. Nothing happens without any frame, as for audio-only
+    /// media.
+    pub fn take_snapshot(&self, raw: bool, output: path::PathBuf) {
+        let pixbuf = match self.snapshot_pixbuf(raw) {
+            Some(pixbuf) => pixbuf,
+            None => return,
         };
+        let event = match pixbuf.savev(&output, "png", &[]) {
+            Ok(_) => PlayerEvent::SnapshotSaved(output.to_string_lossy().into()),
+            Err(e) => PlayerEvent::SnapshotFailed(e.to_string()),
+        };
+        let player = &self.player;
+        with_player!(player player_data {
+            player_data.notify(event);
+        });
+    }
 
-        PLAYER_REGISTRY.with(move |registry| {
-            registry.borrow_mut().insert(player_id, player_data);
+    /// Convert the last rendered frame to an 8-bit sRGB pixbuf, see
+    /// `srgb_pixbuf()`.
+    pub fn snapshot_pixbuf(&self, raw: bool) -> Option<gdk_pixbuf::Pixbuf> {
+        let sample = self
+            .player
+            .get_video_snapshot(gst_player::PlayerSnapshotFormat::RawNative, None)?;
+        srgb_pixbuf(&sample, raw)
+    }
+
+    /// Extract frames of the media in the background, for previews over the
+    /// position slider, cancelling the extraction for the previous media.
+    /// Only local files are handled, seeking in network streams being slow.
+    pub fn extract_seek_thumbnails(&self, uri: &str) {
+        let local = glib::uri_parse_scheme(uri).map_or(false, |scheme| scheme == "file");
+        *self.seek_thumbnails.borrow_mut() = if local {
+            Some(SeekThumbnails::extract(uri))
+        } else {
+            None
+        };
+    }
+
+    pub fn clear_seek_thumbnails(&self) {
+        *self.seek_thumbnails.borrow_mut() = None;
+    }
+
+    /// The frame extracted closest to the position, once available.
+    pub fn get_seek_thumbnail(&self, position: gst::ClockTime) -> Option<gdk_pixbuf::Pixbuf> {
+        self.seek_thumbnails.borrow().as_ref()?.get(position)
+    }
+
+    pub fn pipeline_dot_data(&self) -> Option<string::String> {
+        let element = self.player.get_pipeline();
+        element
+            .downcast::<gst::Pipeline>()
+            .ok()
+            .map(|pipeline| gst::debug_bin_to_dot_data(&pipeline, gst::DebugGraphDetails::all()).into())
+    }
+
+    pub fn seek(&self, direction: &SeekDirection) {
+        let position = self.player.get_position();
+        if position.is_none() {
+            return;
+        }
+
+        let duration = self.player.get_duration();
+        let destination = match direction {
+            SeekDirection::Backward(offset) if position >= *offset => Some(position - *offset),
+            SeekDirection::Forward(offset) if !duration.is_none() && position + *offset <= duration => {
+                Some(position + *offset)
+            }
+            _ => None,
+        };
+        if let Some(d) = destination {
+            self.player.seek(d)
+        }
+    }
+
+    pub fn seek_to(&self, position: gst::ClockTime) {
+        self.player.seek(position);
+    }
+
+    /// Flushing seek to the keyframe closest to `position`. Fast, but only
+    /// approximate.
+    pub fn preview_seek(&self, position: gst::ClockTime) {
+        let flags = gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT | gst::SeekFlags::SNAP_NEAREST;
+        let _ = self.player.get_pipeline().seek_simple(flags, position);
+    }
+
+    pub fn accurate_seek(&self, position: gst::ClockTime) {
+        let flags = gst::SeekFlags::FLUSH | gst::SeekFlags::ACCURATE;
+        let _ = self.player.get_pipeline().seek_simple(flags, position);
+    }
+
+    // Mark the current position as one end of the loop, keeping the start
+    // before the end.
+    fn set_loop_point(&self, start: bool) -> Option<gst::ClockTime> {
+        let position = self.player.get_position();
+        if position.is_none() {
+            return None;
+        }
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            let (ref mut a, ref mut b) = player_data.loop_points;
+            if start {
+                *a = Some(position);
+            } else {
+                *b = Some(position);
+            }
+            if let (Some(start), Some(end)) = (*a, *b) {
+                if end < start {
+                    std::mem::swap(a, b);
+                }
+            }
+        });
+        Some(position)
+    }
+
+    /// Start the A-B loop at the current position, returning it.
+    pub fn set_loop_point_a(&self) -> Option<gst::ClockTime> {
+        self.set_loop_point(true)
+    }
+
+    /// End the A-B loop at the current position, returning it.
+    pub fn set_loop_point_b(&self) -> Option<gst::ClockTime> {
+        self.set_loop_point(false)
+    }
+
+    pub fn clear_loop(&self) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.loop_points = (None, None);
+        });
+    }
+
+    /// Start and end of the A-B loop, once both are set.
+    pub fn get_loop(&self) -> Option<(gst::ClockTime, gst::ClockTime)> {
+        let mut points = None;
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            if let (Some(start), Some(end)) = player_data.loop_points {
+                points = Some((start, end));
+            }
+        });
+        points
+    }
+
+    /// Seek to the start of the first chapter after the current position,
+    /// returning it.
+    pub fn seek_to_next_chapter(&self) -> Option<Chapter> {
+        let position = self.player.get_position().nseconds()?;
+        let chapter = self
+            .get_chapters()
+            .into_iter()
+            .find(|chapter| chapter.start > position)?;
+        self.seek_to(gst::ClockTime::from_nseconds(chapter.start));
+        Some(chapter)
+    }
+
+    /// Seek to the start of the chapter before the current one, or of the
+    /// current one when more than `CHAPTER_RESTART_THRESHOLD` into it.
+    pub fn seek_to_previous_chapter(&self) -> Option<Chapter> {
+        let position = self.player.get_position().nseconds()?;
+        let chapter = self
+            .get_chapters()
+            .into_iter()
+            .rev()
+            .find(|chapter| chapter.start + CHAPTER_RESTART_THRESHOLD < position)?;
+        self.seek_to(gst::ClockTime::from_nseconds(chapter.start));
+        Some(chapter)
+    }
+
+    /// Pause and show the next video frame.
+    pub fn step_frame_forward(&self) {
+        self.player.pause();
+        let step = gst::Event::new_step(gst::format::Buffers(Some(1)), 1.0, true, false).build();
+        if !self.video_sink.element.send_event(step) {
+            eprintln!("Unable to step to the next frame");
+        }
+    }
+
+    /// Pause and go back by the duration of a frame. Only approximate, as
+    /// the video sink can't step backward.
+    pub fn step_frame_backward(&self) {
+        self.player.pause();
+        let framerate = self.player.get_current_video_track().map(|track| track.get_framerate());
+        let frame_duration = match framerate {
+            Some(framerate) if *framerate.numer() > 0 && *framerate.denom() > 0 => {
+                1_000_000_000 * *framerate.denom() as u64 / *framerate.numer() as u64
+            }
+            _ => DEFAULT_FRAME_DURATION,
+        };
+        if let Some(position) = self.player.get_position().nseconds() {
+            self.accurate_seek(gst::ClockTime::from_nseconds(position.saturating_sub(frame_duration)));
+            let player = &self.player;
+            with_mut_player!(player player_data {
+                player_data.notify(PlayerEvent::PositionUpdated);
+            });
+        }
+    }
+
+    pub fn seek_to_percent(&self, percent: f64) {
+        if let Some(duration) = self.player.get_duration().nanoseconds() {
+            let position = duration as f64 * percent.max(0.0).min(100.0) / 100.0;
+            self.player.seek(gst::ClockTime::from_nseconds(position as u64));
+        }
+    }
+
+    /// Save the given range of the current media to `output`, see
+    /// `clip_export::export_clip()`.
+    #[cfg(feature = "clip-export")]
+    pub fn export_clip(&self, start: gst::ClockTime, end: gst::ClockTime, output: path::PathBuf) {
+        let uri = match self.get_current_uri() {
+            Some(uri) => uri,
+            None => return,
+        };
+        let player = &self.player;
+        let mut subscribers = vec![];
+        with_player!(player player_data {
+            subscribers = player_data.subscribers.clone();
+        });
+        crate::clip_export::export_clip(&uri, start, end, output, subscribers);
+    }
+
+    /// Look for the subtitle offset best matching the speech of the current
+    /// media, see `subtitle_sync::suggest_offset()`. Only external subtitle
+    /// files are supported.
+    #[cfg(feature = "subtitle-auto-sync")]
+    pub fn suggest_subtitle_offset(&self) {
+        let player = &self.player;
+        let mut subscribers = vec![];
+        with_player!(player player_data {
+            subscribers = player_data.subscribers.clone();
+        });
+        match (self.get_current_uri(), self.player.get_subtitle_uri()) {
+            (Some(uri), Some(subtitle_uri)) => {
+                crate::subtitle_sync::suggest_offset(&uri, &subtitle_uri, self.player.get_position(), subscribers)
+            }
+            _ => {
+                let event = PlayerEvent::SubtitleSyncFailed("Auto-sync requires an external subtitle file".to_string());
+                for sender in &subscribers {
+                    let _ = sender.send(event.clone());
+                }
+            }
+        };
+    }
+
+    /// Change the playback rate, kept between `MIN_PLAYBACK_RATE` and
+    /// `MAX_PLAYBACK_RATE`.
+    pub fn set_playback_rate(&self, rate: f64) {
+        let rate = rate.max(constants::MIN_PLAYBACK_RATE).min(constants::MAX_PLAYBACK_RATE);
+        self.player.set_rate(rate);
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.notify(PlayerEvent::RateChanged(rate));
         });
+    }
 
-        Ok(Self { player, video_area })
+    pub fn get_playback_rate(&self) -> f64 {
+        self.player.get_rate()
     }
 
-    #[allow(dead_code)]
-    pub fn register_event_handler(&mut self, sender: glib::Sender<PlayerEvent>) {
+    /// Set a color balance channel, from 0 to 1 with 0.5 leaving it
+    /// untouched. Ignored until the pipeline is able to balance colors.
+    pub fn set_color_balance(&self, channel: gst_player::PlayerColorBalanceType, value: f64) {
+        if self.player.has_color_balance() {
+            self.player.set_color_balance(channel, value.max(0.0).min(1.0));
+        }
+    }
+
+    pub fn get_position(&self) -> gst::ClockTime {
+        self.player.get_position()
+    }
+
+    pub fn get_duration(&self) -> gst::ClockTime {
+        self.player.get_duration()
+    }
+
+    // External subtitle files might have been moved or deleted since they
+    // were attached. Look for a replacement next to the media in that case.
+    fn resolve_external_subtitle(&self, uri: &str) -> Option<glib::GString> {
+        let path = match glib::filename_from_uri(uri) {
+            Ok((path, _)) => path,
+            Err(_) => return Some(uri.into()),
+        };
+        if path.is_file() {
+            return Some(uri.into());
+        }
+        eprintln!("Subtitle file {} not found", path.display());
+
         let player = &self.player;
-        with_mut_player!(player player_data {
-            player_data.register_event_handler(sender);
+        let mut rescan = false;
+        with_player!(player player_data {
+            rescan = player_data.rescan_missing_subtitles;
         });
+        if !rescan {
+            return None;
+        }
+        let media_uri = self.get_current_uri()?;
+        let (media_path, _) = glib::filename_from_uri(&media_uri).ok()?;
+        let replacement = subtitle_files::find_matching_subtitle(&media_path)?;
+        glib::filename_to_uri(&replacement, None).ok()
     }
 
-    pub fn load_playlist(&self, playlist: Vec<string::String>) {
-        assert!(!playlist.is_empty());
+    // The encoding is read by the subtitle parser when it is created, so it
+    // must be set before the subtitle URI.
+    fn set_subtitle_encoding_for(&self, uri: &str) {
         let player = &self.player;
-        with_mut_player!(player player_data {
-            self.load_uri(&*playlist[0]);
-            player_data.set_playlist(playlist);
+        let mut encoding = None;
+        with_player!(player player_data {
+            encoding = player_data.subtitle_encoding.clone();
         });
+        let encoding = encoding.or_else(|| {
+            let (path, _) = glib::filename_from_uri(uri).ok()?;
+            subtitle_files::detect_encoding(&path).map(string::String::from)
+        });
+        self.player
+            .get_pipeline()
+            .set_property("subtitle-encoding", &encoding)
+            .unwrap();
     }
 
-    pub fn video_area(&self) -> &gtk::Widget {
-        &self.video_area
+    /// Hide or show the subtitles, the selected track being kept.
+    pub fn set_subtitle_track_enabled(&self, enabled: bool) {
+        self.player.set_subtitle_track_enabled(enabled);
     }
 
-    pub fn load_uri(&self, uri: &str) {
-        self.player.set_property("uri", &glib::Value::from(&uri)).unwrap();
+    pub fn configure_subtitle_track(&self, track: Option<SubtitleTrack>) {
+        let enabled = match track {
+            Some(track) => match track {
+                SubtitleTrack::External(uri) => match self.resolve_external_subtitle(&uri) {
+                    Some(uri) => {
+                        self.set_subtitle_encoding_for(&uri);
+                        self.player.set_subtitle_uri(&uri);
+                        true
+                    }
+                    None => false,
+                },
+                SubtitleTrack::Inband(idx) => {
+                    self.player.set_subtitle_track(idx).unwrap();
+                    true
+                }
+            },
+            None => false,
+        };
+        self.player.set_subtitle_track_enabled(enabled);
     }
 
-    pub fn get_current_uri(&self) -> Option<glib::GString> {
-        self.player.get_uri()
+    /// Switching between track and album gain is immediate, turning
+    /// ReplayGain on or off applies from the next tags, usually the next
+    /// track.
+    pub fn set_replay_gain_mode(&self, mode: ReplayGainMode) {
+        *self.replay_gain_mode.lock().unwrap() = mode;
+        if let Some(ref rgvolume) = self.replay_gain {
+            rgvolume
+                .set_property("album-mode", &(mode == ReplayGainMode::Album))
+                .unwrap();
+        }
     }
 
-    pub fn stop(&self) {
-        self.player.stop();
+    /// Mix the audio channels with the matrix, one row per output channel
+    /// with the gain of each input channel, the media being first converted
+    /// to as many channels as the rows have gains. Without a matrix the
+    /// channels are left untouched. Returns false if the matrix is invalid
+    /// or remapping isn't available.
+    pub fn set_channel_matrix(&self, matrix: Option<&[Vec<f64>]>) -> bool {
+        let remap = match self.channel_remap {
+            Some(ref remap) => remap,
+            None => return false,
+        };
+        let (caps, mix_matrix) = match matrix {
+            None => (gst::Caps::new_any(), gst::Array::new(&[])),
+            Some(matrix) => {
+                let inputs = matrix.first().map_or(0, Vec::len);
+                if inputs == 0 || matrix.iter().any(|row| row.len() != inputs) {
+                    return false;
+                }
+                let rows = matrix
+                    .iter()
+                    .map(|row| {
+                        let gains = row
+                            .iter()
+                            .map(|gain| (*gain as f32).to_send_value())
+                            .collect::<Vec<_>>();
+                        let gains = gains.iter().map(|gain| gain as &dyn ToSendValue).collect::<Vec<_>>();
+                        gst::Array::new(&gains).to_send_value()
+                    })
+                    .collect::<Vec<_>>();
+                let rows = rows.iter().map(|row| row as &dyn ToSendValue).collect::<Vec<_>>();
+                let caps = gst::Caps::builder("audio/x-raw")
+                    .field("channels", &(inputs as i32))
+                    .build();
+                (caps, gst::Array::new(&rows))
+            }
+        };
+        remap.capsfilter.set_property("caps", &caps).is_ok()
+            && remap.mixer.set_property("mix-matrix", &mix_matrix).is_ok()
     }
 
-    pub fn get_media_info(&self) -> Option<gst_player::PlayerMediaInfo> {
-        self.player.get_media_info()
+    /// Keep the pitch of the audio when the playback rate changes, or let it
+    /// follow the rate. The switch happens with the next audio data, without
+    /// interrupting playback.
+    pub fn set_preserve_pitch(&self, preserve_pitch: bool) {
+        let pitch_filter = match self.pitch_filter {
+            Some(ref pitch_filter) => pitch_filter.clone(),
+            None => return,
+        };
+        let filter = pitch_filter.clone();
+        swap_from_blocking_probe(&filter, move || swap_pitch_filter(&pitch_filter, preserve_pitch));
     }
 
-    pub fn set_volume(&self, volume: f64) {
-        self.player.set_volume(volume);
+    /// Even out the loudness of the media, from now on and for the next ones.
+    pub fn set_audio_normalization(&self, enabled: bool) {
+        let normalization = match self.normalization {
+            Some(ref normalization) => normalization.clone(),
+            None => return,
+        };
+        let filter = normalization.clone();
+        swap_from_blocking_probe(&filter, move || swap_normalization_filter(&normalization, enabled));
     }
 
-    pub fn toggle_pause(&self, currently_paused: bool) {
-        if currently_paused {
-            self.player.play();
-        } else {
-            self.player.pause();
+    /// Set the gains in dB of the ten equalizer bands, from 29 Hz to 15 kHz.
+    /// Without gains, the equalizer is taken out of the audio filters.
+    pub fn set_equalizer(&self, gains: Option<&[f64]>) {
+        let equalizer = match self.equalizer {
+            Some(ref equalizer) => equalizer.clone(),
+            None => return,
+        };
+        {
+            let current = equalizer.lock().unwrap();
+            if let (true, Some(gains)) = (is_equalizer(&current), gains) {
+                set_equalizer_gains(&current, gains);
+                return;
+            }
         }
+        let gains = gains.map(<[f64]>::to_vec);
+        let filter = equalizer.clone();
+        swap_from_blocking_probe(&filter, move || swap_equalizer(&equalizer, gains.as_deref()));
     }
 
-    pub fn increase_volume(&self) {
-        let value = self.player.get_volume();
-        let offset = 0.07;
-        if value + offset < 1.0 {
-            self.player.set_volume(value + offset);
-        } else {
-            self.player.set_volume(1.0);
+    /// Start or stop sending AudioLevels events.
+    pub fn set_audio_levels_enabled(&self, enabled: bool) {
+        if let Some(ref level) = self.audio_level {
+            level.set_property("post-messages", &enabled).unwrap();
         }
     }
 
-    pub fn decrease_volume(&self) {
-        let value = self.player.get_volume();
-        let offset = 0.07;
-        if value >= offset {
-            self.player.set_volume(value - offset);
-        } else {
-            self.player.set_volume(0.0);
+    /// Override the range of the video samples, the current video is updated
+    /// right away.
+    pub fn set_color_range(&self, range: ColorRange) {
+        *self.color_range.lock().unwrap() = range;
+        self.reapply_caps_overrides();
+    }
+
+    /// Deinterlace the video. Enabling or disabling it applies once the
+    /// playbin rebuilds its video chain, with the next media or track.
+    pub fn set_deinterlace(&self, mode: Deinterlace) {
+        *self.deinterlace.lock().unwrap() = mode;
+        let pipeline = self.player.get_pipeline();
+        if set_playbin_flag(&pipeline, "deinterlace", mode != Deinterlace::Off).is_none() {
+            eprintln!("Unable to set the deinterlace flag of the playbin");
+        }
+        if let Some(pipeline) = pipeline.downcast_ref::<gst::Bin>() {
+            for_each_element_from_factory(pipeline, "deinterlace", &|element| {
+                apply_deinterlace_mode(element, mode);
+            });
         }
     }
 
-    pub fn toggle_mute(&self, enabled: bool) {
-        self.player.set_mute(enabled);
+    /// Display the video with the given pixel aspect ratio, as numerator
+    /// and denominator, instead of the one it declares.
+    pub fn set_pixel_aspect_ratio(&self, ratio: Option<(i32, i32)>) {
+        *self.pixel_aspect_ratio.lock().unwrap() = ratio.map(|(n, d)| gst::Fraction::new(n, d));
+        self.reapply_caps_overrides();
     }
 
-    pub fn dump_pipeline(&self, label: &str) {
-        let element = self.player.get_pipeline();
-        if let Ok(pipeline) = element.downcast::<gst::Pipeline>() {
-            gst::debug_bin_to_dot_file_with_ts(&pipeline, gst::DebugGraphDetails::all(), label);
+    pub fn set_aspect_ratio(&self, ratio: AspectRatio) {
+        *self.aspect_ratio.borrow_mut() = ratio;
+        *self.display_aspect_ratio.lock().unwrap() = match ratio {
+            AspectRatio::Ratio(n, d) => Some(gst::Fraction::new(n, d)),
+            _ => None,
+        };
+        set_force_aspect_ratio(&self.video_sink.element, ratio != AspectRatio::Stretch);
+        self.reapply_caps_overrides();
+    }
+
+    pub fn get_aspect_ratio(&self) -> AspectRatio {
+        *self.aspect_ratio.borrow()
+    }
+
+    fn update_video_crop(&self) {
+        let crop = match video_crop_element(&self.video_tee) {
+            Some(crop) => crop,
+            None => return,
+        };
+        let zoom = *self.video_zoom.lock().unwrap();
+        if let Some(caps) = crop.get_static_pad("sink").and_then(|pad| pad.get_current_caps()) {
+            apply_video_crop(&crop, &caps, &zoom);
         }
     }
 
-    pub fn seek(&self, direction: &SeekDirection) {
-        let position = self.player.get_position();
-        if position.is_none() {
-            return;
+    /// Zoom into the video, 1.0 fitting it in the video area. The parts
+    /// falling outside of the area are cropped. Returns false if zooming
+    /// isn't available, videocrop being missing.
+    pub fn set_video_zoom(&self, factor: f64) -> bool {
+        if video_crop_element(&self.video_tee).is_none() {
+            return false;
         }
+        self.video_zoom.lock().unwrap().factor = factor.max(1.0);
+        self.update_video_crop();
+        true
+    }
 
-        let duration = self.player.get_duration();
-        let destination = match direction {
-            SeekDirection::Backward(offset) if position >= *offset => Some(position - *offset),
-            SeekDirection::Forward(offset) if !duration.is_none() && position + *offset <= duration => {
-                Some(position + *offset)
+    pub fn get_video_zoom(&self) -> f64 {
+        self.video_zoom.lock().unwrap().factor
+    }
+
+    /// Rotate the video clockwise by 0, 90, 180 or 270 degrees, until
+    /// another media is loaded. Returns false if videoflip is missing.
+    pub fn set_video_rotation(&self, degrees: u32) -> bool {
+        let degrees = degrees % 360 / 90 * 90;
+        if set_video_flip(&self.player.get_pipeline(), degrees).is_none() {
+            return false;
+        }
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.video_rotation = degrees;
+            let (width, height) = player_data.video_dimensions;
+            if width > 0 && height > 0 {
+                let (width, height) = rotated_dimensions(width, height, degrees);
+                player_data.notify(PlayerEvent::VideoDimensionsChanged(width, height));
             }
-            _ => None,
+        });
+        true
+    }
+
+    pub fn get_video_rotation(&self) -> u32 {
+        let player = &self.player;
+        let mut rotation = 0;
+        with_player!(player player_data {
+            rotation = player_data.video_rotation;
+        });
+        rotation
+    }
+
+    /// Adjust the colors of the video. videobalance only takes part in the
+    /// video filters while they differ from the defaults.
+    pub fn set_video_balance(&self, balance: VideoBalance) -> VideoBalance {
+        let balance = balance.clamped();
+        *self.video_balance_values.borrow_mut() = balance;
+        let video_balance = match self.video_balance {
+            Some(ref video_balance) => video_balance.clone(),
+            None => return balance,
         };
-        if let Some(d) = destination {
-            self.player.seek(d)
+        {
+            let current = video_balance.lock().unwrap();
+            if current.get_name() == VIDEO_BALANCE_FILTER_NAME && !balance.is_default() {
+                apply_video_balance(&current, &balance);
+                return balance;
+            }
         }
+        let filter = video_balance.clone();
+        swap_from_blocking_probe(&filter, move || swap_video_balance(&video_balance, balance));
+        balance
     }
 
-    pub fn seek_to(&self, position: gst::ClockTime) {
-        self.player.seek(position);
+    pub fn get_video_balance(&self) -> VideoBalance {
+        *self.video_balance_values.borrow()
     }
 
-    pub fn get_position(&self) -> gst::ClockTime {
-        self.player.get_position()
+    /// Map HDR video to the SDR range of the display, with the same tone
+    /// mapping as the snapshots. SDR video goes through untouched.
+    pub fn set_hdr_tone_mapping(&self, enabled: bool) {
+        self.tone_mapping_enabled.set(enabled);
+        self.update_tone_mapping();
     }
 
-    pub fn configure_subtitle_track(&self, track: Option<SubtitleTrack>) {
-        let enabled = match track {
-            Some(track) => match track {
-                SubtitleTrack::External(uri) => {
-                    self.player.set_subtitle_uri(&uri);
-                    true
-                }
-                SubtitleTrack::Inband(idx) => {
-                    self.player.set_subtitle_track(idx).unwrap();
-                    true
-                }
-            },
-            None => false,
+    /// Insert or remove the tone mapping according to the video of the
+    /// current media, after a PlayerEvent::HdrDetected.
+    pub fn update_tone_mapping(&self) {
+        let tone_mapping = match self.tone_mapping {
+            Some(ref tone_mapping) => tone_mapping.clone(),
+            None => return,
         };
-        self.player.set_subtitle_track_enabled(enabled);
+        let player = &self.player;
+        let mut transfer = None;
+        with_player!(player player_data {
+            transfer = player_data.hdr_transfer;
+        });
+        let transfer = transfer.filter(|_| self.tone_mapping_enabled.get());
+        let filter = tone_mapping.clone();
+        swap_from_blocking_probe(&filter, move || swap_tone_mapping_filter(&tone_mapping, transfer));
+    }
+
+    /// Zoom just enough for the video to cover the whole video area,
+    /// removing the black bars, and keep doing so as the area is resized.
+    /// Returns false if zooming isn't available, videocrop being missing.
+    pub fn set_zoom_to_fill(&self, enabled: bool) -> bool {
+        if video_crop_element(&self.video_tee).is_none() {
+            return false;
+        }
+        self.video_zoom.lock().unwrap().fill = enabled;
+        self.update_video_crop();
+        true
+    }
+
+    fn reapply_caps_overrides(&self) {
+        let caps = match self.video_caps.lock().unwrap().clone() {
+            Some(caps) => caps,
+            None => return,
+        };
+        let pad = self
+            .video_tee
+            .get_parent()
+            .and_then(|p| p.downcast::<gst::Bin>().ok())
+            .and_then(|bin| bin.get_by_name("video-sink-queue"))
+            .and_then(|queue| queue.get_static_pad("src"));
+        // Push the original caps again, they go through the override probe.
+        if let Some(pad) = pad {
+            pad.add_probe(gst::PadProbeType::IDLE, move |pad, _| {
+                pad.push_event(gst::Event::new_caps(&caps).build());
+                gst::PadProbeReturn::Remove
+            });
+        }
+    }
+
+    pub fn set_subtitle_style(&self, style: SubtitleStyle) {
+        *self.subtitle_style.lock().unwrap() = style.clone();
+        let area_height = *self.subtitle_area_height.lock().unwrap();
+        apply_subtitle_style_to_pipeline(&self.player.get_pipeline(), &style, area_height);
+    }
+
+    pub fn get_current_audio_track(&self) -> Option<gst_player::PlayerAudioInfo> {
+        self.player.get_current_audio_track()
     }
 
     pub fn get_current_subtitle_track(&self) -> Option<gst_player::PlayerSubtitleInfo> {
@@ -610,44 +3937,430 @@ impl ChannelPlayer {
         };
     }
 
+    /// Whether the player data is borrowed, as when panicking while updating
+    /// it, in which case the methods using it would panic again.
+    pub fn data_in_use(&self) -> bool {
+        PLAYER_REGISTRY
+            .try_with(|registry| registry.try_borrow_mut().is_err())
+            .unwrap_or(true)
+    }
+
     pub fn write_last_known_media_position(&self) {
-        if let Some(uri) = self.player.get_uri() {
+        // Already written when the pipeline was released.
+        if self.released_position().is_some() {
+            return;
+        }
+        if let Some(uri) = self.get_current_uri() {
             if let Some(scheme) = glib::uri_parse_scheme(&uri) {
                 if scheme == "fd" {
                     return;
                 }
             }
-            let id = uri_to_sha256(&uri);
+            let mut id = uri_to_sha256(&uri);
             let mut position = 0;
             if let Some(p) = self.player.get_position().nanoseconds() {
                 position = p;
             }
             if let Some(duration) = self.player.get_duration().nanoseconds() {
-                if position == duration {
+                // Not worth resuming, the media starts over next time.
+                if position < RESUME_MARGIN || position + RESUME_MARGIN > duration {
+                    let player = &self.player;
+                    with_mut_player!(player player_data {
+                        if !player_data.forgotten_uris.contains(uri.as_str()) {
+                            player_data.clear_cached_position_and_write(&id);
+                        }
+                    });
                     return;
                 }
             } else {
-                // This likely is a live stream. Seek to last known
-                // position will likely fail.
-                return;
+                // This likely is a live stream. Seeking back to the last
+                // known position only works if it is seekable, like DVR
+                // streams.
+                let seekable = self.player.get_media_info().map_or(false, |info| info.is_seekable());
+                if !seekable || !is_remote_uri(&uri) {
+                    return;
+                }
+                id = live_stream_id(&uri);
             }
 
             let player = &self.player;
             with_mut_player!(player player_data {
-                player_data.update_cache_and_write(id, position);
+                if !player_data.forgotten_uris.contains(uri.as_str()) {
+                    player_data.update_cache_and_write(id, position);
+                }
             });
         }
     }
 
+    /// Store the playback rate of the current media, restored when it's
+    /// loaded again.
+    pub fn write_media_playback_rate(&self) {
+        let uri = match self.get_current_uri() {
+            Some(uri) => uri,
+            None => return,
+        };
+        let rate = self.player.get_rate();
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            if !player_data.forgotten_uris.contains(uri.as_str()) {
+                player_data.update_cache_rate_and_write(&uri, rate);
+            }
+        });
+    }
+
+    /// Remove the position and overrides stored for the media, and the
+    /// choices made about it in this session. The position of the media, if loaded, is not
+    /// stored again until it is loaded anew.
+    pub fn forget_media(&self, uri: &str) -> Result<(), Error> {
+        let mut result = Ok(());
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            result = player_data.forget(Some(uri));
+        });
+        result
+    }
+
+    /// Settings chosen for the media, overriding the global ones. They are
+    /// stored with its position, so none are remembered in incognito mode.
+    pub fn media_overrides(&self, uri: &str) -> MediaOverrides {
+        let mut overrides = MediaOverrides::default();
+        let player = &self.player;
+        with_player!(player player_data {
+            if let Some(ref cache) = player_data.cache {
+                overrides = cache.find_overrides(&uri_to_sha256(uri));
+            }
+        });
+        overrides
+    }
+
+    /// Overrides applying to the media of the folder without their own.
+    pub fn folder_overrides(&self, folder: &path::Path) -> MediaOverrides {
+        let mut overrides = MediaOverrides::default();
+        let player = &self.player;
+        with_player!(player player_data {
+            if let Some(ref cache) = player_data.cache {
+                overrides = cache.find_overrides(&folder_id(folder));
+            }
+        });
+        overrides
+    }
+
+    /// Change the overrides of the media and save them.
+    pub fn update_media_overrides<F: FnOnce(&mut MediaOverrides)>(&self, uri: &str, f: F) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.update_overrides_and_write(uri_to_sha256(uri), f);
+        });
+    }
+
+    pub fn update_folder_overrides<F: FnOnce(&mut MediaOverrides)>(&self, folder: &path::Path, f: F) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.update_overrides_and_write(folder_id(folder), f);
+        });
+    }
+
+    /// Remove the positions and overrides stored for all media.
+    pub fn forget_all_media(&self) -> Result<(), Error> {
+        let mut result = Ok(());
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            result = player_data.forget(None);
+        });
+        result
+    }
+
     pub fn set_audio_offset(&self, offset: i64) {
         self.player
             .set_property("audio-video-offset", &glib::Value::from(&offset))
             .unwrap();
     }
 
+    pub fn get_audio_offset(&self) -> i64 {
+        self.player.get_audio_video_offset()
+    }
+
     pub fn set_subtitle_offset(&self, offset: i64) {
         self.player
             .set_property("subtitle-video-offset", &glib::Value::from(&offset))
             .unwrap();
     }
+
+    pub fn get_subtitle_offset(&self) -> i64 {
+        self.player.get_subtitle_video_offset()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // PQ code of the SDR reference white, 203 nits.
+    const PQ_REFERENCE_WHITE: u8 = 149;
+    // Full range, RGB matrix, SMPTE 2084 transfer and BT.2020 primaries.
+    const PQ_BT2020: &str = "1:1:14:7";
+
+    // A uniform 4x2 RGB frame, rows need no padding at this width.
+    fn rgb_sample(colorimetry: &str, value: u8) -> gst::Sample {
+        gst::init().unwrap();
+        let caps = gst::Caps::builder("video/x-raw")
+            .field("format", &"RGB")
+            .field("width", &4i32)
+            .field("height", &2i32)
+            .field("framerate", &gst::Fraction::new(0, 1))
+            .field("colorimetry", &colorimetry)
+            .build();
+        let buffer = gst::Buffer::from_slice(vec![value; 4 * 2 * 3]);
+        gst::Sample::builder().buffer(&buffer).caps(&caps).build()
+    }
+
+    fn pixels(pixbuf: &gdk_pixbuf::Pixbuf) -> Vec<u8> {
+        let stride = pixbuf.get_rowstride() as usize;
+        let width = pixbuf.get_width() as usize * 3;
+        let bytes = pixbuf.read_pixel_bytes().unwrap();
+        bytes
+            .chunks(stride)
+            .take(pixbuf.get_height() as usize)
+            .flat_map(|row| row[..width].to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn hdr_snapshot_is_tone_mapped() {
+        let sample = rgb_sample(PQ_BT2020, PQ_REFERENCE_WHITE);
+        let pixbuf = srgb_pixbuf(&sample, false).unwrap();
+        assert_eq!((pixbuf.get_width(), pixbuf.get_height()), (4, 2));
+
+        let expected = tone_mapping::build_sdr_lut(tone_mapping::HdrTransfer::Pq, HDR_PEAK_NITS);
+        let white = expected[PQ_REFERENCE_WHITE as usize];
+        assert!(white > 180 && white < 200, "reference white mapped to {}", white);
+        assert!(pixels(&pixbuf).iter().all(|value| *value == white));
+    }
+
+    #[test]
+    fn raw_hdr_snapshot_is_kept_as_decoded() {
+        let sample = rgb_sample(PQ_BT2020, PQ_REFERENCE_WHITE);
+        let pixbuf = srgb_pixbuf(&sample, true).unwrap();
+        assert!(pixels(&pixbuf).iter().all(|value| *value == PQ_REFERENCE_WHITE));
+    }
+
+    #[test]
+    fn sdr_snapshot_is_untouched() {
+        let sample = rgb_sample("sRGB", PQ_REFERENCE_WHITE);
+        let pixbuf = srgb_pixbuf(&sample, false).unwrap();
+        assert!(pixels(&pixbuf).iter().all(|value| *value == PQ_REFERENCE_WHITE));
+    }
+
+    #[test]
+    fn replay_gain_tags_are_stripped() {
+        gst::init().unwrap();
+        let mut tags = gst::TagList::new();
+        {
+            let tags = tags.get_mut().unwrap();
+            tags.add::<gst::tags::Title>(&"Track", gst::TagMergeMode::Append);
+            tags.add::<gst::tags::TrackGain>(&-6.5, gst::TagMergeMode::Append);
+            tags.add::<gst::tags::AlbumPeak>(&0.9, gst::TagMergeMode::Append);
+        }
+        assert!(has_replay_gain_tags(&tags));
+
+        let stripped = without_replay_gain_tags(&tags);
+        assert!(!has_replay_gain_tags(&stripped));
+        assert!(stripped.get::<gst::tags::AlbumPeak>().is_none());
+        let title = stripped.get::<gst::tags::Title>().unwrap();
+        assert_eq!(title.get(), Some("Track"));
+    }
+
+    // An anamorphic NTSC DVD, 720x480 stretched to 16:9.
+    fn dvd_caps(pixel_aspect_ratio: gst::Fraction) -> gst::Caps {
+        gst::init().unwrap();
+        gst::Caps::new_simple(
+            "video/x-raw",
+            &[
+                ("format", &"I420"),
+                ("width", &720i32),
+                ("height", &480i32),
+                ("framerate", &gst::Fraction::new(30000, 1001)),
+                ("pixel-aspect-ratio", &pixel_aspect_ratio),
+            ],
+        )
+    }
+
+    // Size the sink scales the video to, with square pixels.
+    fn displayed_size(caps: &gst::CapsRef) -> (i32, i32) {
+        let info = gst_video::VideoInfo::from_caps(caps).unwrap();
+        let par = info.par();
+        (info.width() as i32 * *par.numer() / *par.denom(), info.height() as i32)
+    }
+
+    #[test]
+    fn pixel_aspect_ratio_of_the_stream_is_kept() {
+        let caps = dvd_caps(gst::Fraction::new(32, 27));
+        assert!(override_pixel_aspect_ratio(&caps, None).is_none());
+        assert!(override_pixel_aspect_ratio(&caps, Some(gst::Fraction::new(32, 27))).is_none());
+        assert_eq!(displayed_size(&caps), (853, 480));
+    }
+
+    #[test]
+    fn pixel_aspect_ratio_override_fixes_missing_metadata() {
+        // Square pixels declared, the video looks squeezed at 720x480.
+        let caps = dvd_caps(gst::Fraction::new(1, 1));
+        assert_eq!(displayed_size(&caps), (720, 480));
+
+        let overridden = override_pixel_aspect_ratio(&caps, Some(gst::Fraction::new(32, 27))).unwrap();
+        assert_eq!(displayed_size(&overridden), (853, 480));
+
+        let overridden = override_pixel_aspect_ratio(&caps, Some(gst::Fraction::new(8, 9))).unwrap();
+        assert_eq!(displayed_size(&overridden), (640, 480));
+    }
+
+    #[test]
+    fn forgetting_a_media_keeps_the_other_entries() {
+        let path = std::env::temp_dir().join(format!("glide-media-cache-{}.json", std::process::id()));
+        let forgotten = "file:///videos/forgotten.mkv";
+        let kept = "file:///videos/kept.mkv";
+        let live = "https://example.com/live.m3u8";
+
+        let mut cache = MediaCache::open(&path).unwrap();
+        cache.update(uri_to_sha256(forgotten), 10);
+        cache.update(live_stream_id(forgotten), 20);
+        cache.update_rate(forgotten, 1.5);
+        cache.update(uri_to_sha256(kept), 30);
+        cache.update_volume(kept, 0.5);
+        cache.update(live_stream_id(live), 40);
+
+        cache.forget(forgotten);
+        cache.write().unwrap();
+
+        let cache = MediaCache::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(cache.data.0.len(), 2);
+        assert!(cache.find_last_position(forgotten).is_none());
+        assert!(cache.find_last_live_position(forgotten).is_none());
+        assert!((cache.find_last_rate(forgotten) - normal_rate()).abs() < std::f64::EPSILON);
+        assert_eq!(cache.find_last_position(kept), gst::ClockTime::from_nseconds(30));
+        assert_eq!(cache.find_last_volume(kept), Some(0.5));
+        assert_eq!(cache.find_last_live_position(live), gst::ClockTime::from_nseconds(40));
+    }
+
+    #[test]
+    fn bare_positions_are_still_read() {
+        let json = r#"{"old": 10, "slow": {"position": 20, "rate": 0.5}, "quiet": {"volume": 0.25}}"#;
+        let data: MediaCacheData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.0["old"].position, Some(10));
+        assert!(is_normal_rate(&data.0["old"].rate));
+        assert_eq!(data.0["slow"].position, Some(20));
+        assert!((data.0["slow"].rate - 0.5).abs() < std::f64::EPSILON);
+        assert_eq!(data.0["quiet"].position, None);
+        assert_eq!(data.0["quiet"].volume, Some(0.25));
+
+        let json = serde_json::to_string(&data).unwrap();
+        let data: MediaCacheData = serde_json::from_str(&json).unwrap();
+        assert_eq!(data.0["old"].position, Some(10));
+        assert_eq!(data.0.len(), 3);
+    }
+
+    #[test]
+    fn overrides_are_stored_with_the_positions() {
+        let path = std::env::temp_dir().join(format!("glide-media-overrides-{}.json", std::process::id()));
+        let uri = "file:///videos/anamorphic.mkv";
+        let folder = path::Path::new("/videos");
+
+        let mut cache = MediaCache::open(&path).unwrap();
+        cache.update(uri_to_sha256(uri), 10);
+        cache.update_overrides(uri_to_sha256(uri), |overrides| {
+            overrides.aspect_ratio = Some("16:9".to_string())
+        });
+        cache.update_overrides(folder_id(folder), |overrides| overrides.subtitle_offset = Some(500));
+        cache.write().unwrap();
+
+        let mut cache = MediaCache::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(cache.find_last_position(uri), gst::ClockTime::from_nseconds(10));
+        let overrides = cache.find_overrides(&uri_to_sha256(uri));
+        assert_eq!(overrides.aspect_ratio.as_deref(), Some("16:9"));
+        assert_eq!(cache.find_overrides(&folder_id(folder)).subtitle_offset, Some(500));
+
+        // Left without anything to store, the entry of the folder goes away.
+        cache.update_overrides(folder_id(folder), |overrides| overrides.subtitle_offset = None);
+        assert_eq!(cache.data.0.len(), 1);
+    }
+
+    fn subtitle_style(script: SubtitleScript) -> SubtitleStyle {
+        SubtitleStyle {
+            background: SubtitleBackground::Outline,
+            background_opacity: 0.5,
+            scale: 1.0,
+            scale_base: SubtitleScaleBase::Video,
+            script,
+            font: None,
+            color: None,
+        }
+    }
+
+    // Sans keeps drawing Latin text, the fonts of the script are tried next
+    // for the characters it lacks.
+    #[test]
+    fn subtitle_font_falls_back_to_the_script_fonts() {
+        gst::init().unwrap();
+        let overlay = gst::ElementFactory::make("textoverlay", None).unwrap();
+        let font_desc = |script| {
+            apply_subtitle_style(&overlay, &subtitle_style(script), 480);
+            let value = overlay.get_property("font-desc").unwrap();
+            value.get::<string::String>().unwrap().unwrap()
+        };
+        assert!(!font_desc(SubtitleScript::Auto).contains(','));
+
+        let scripts = [
+            (SubtitleScript::Japanese, "Noto Sans CJK JP"),
+            (SubtitleScript::SimplifiedChinese, "Noto Sans CJK SC"),
+            (SubtitleScript::TraditionalChinese, "Noto Sans CJK TC"),
+            (SubtitleScript::Korean, "Noto Sans CJK KR"),
+            (SubtitleScript::Arabic, "Noto Sans Arabic"),
+            (SubtitleScript::Hebrew, "Noto Sans Hebrew"),
+        ];
+        for (script, family) in scripts.iter() {
+            let font_desc = font_desc(*script);
+            assert!(font_desc.starts_with("Sans,"), "unexpected font {}", font_desc);
+            assert!(font_desc.contains(family), "{} lacks {}", font_desc, family);
+        }
+    }
+
+    fn uris(names: &[&str]) -> Vec<string::String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn gapless_next_item_follows_the_play_order() {
+        let playlist = uris(&["file:///a.flac", "file:///b.flac", "file:///c.flac"]);
+        let order = [0, 1, 2];
+        let next = gapless_next_item(&playlist, &order, 0, RepeatMode::Off, false);
+        assert_eq!(next, Some((1, "file:///b.flac".to_string())));
+        assert_eq!(gapless_next_item(&playlist, &order, 2, RepeatMode::Off, false), None);
+
+        // Shuffled, the next item of the order rather than of the playlist.
+        let shuffled = [2, 0, 1];
+        let next = gapless_next_item(&playlist, &shuffled, 0, RepeatMode::Off, false);
+        assert_eq!(next, Some((0, "file:///a.flac".to_string())));
+        assert_eq!(gapless_next_item(&playlist, &shuffled, 2, RepeatMode::Off, false), None);
+    }
+
+    #[test]
+    fn gapless_next_item_with_repeat() {
+        let playlist = uris(&["file:///a.flac", "file:///b.flac", "file:///c.flac"]);
+        let shuffled = [2, 0, 1];
+        let next = gapless_next_item(&playlist, &shuffled, 2, RepeatMode::All, false);
+        assert_eq!(next, Some((2, "file:///c.flac".to_string())));
+        assert_eq!(gapless_next_item(&playlist, &shuffled, 0, RepeatMode::One, false), None);
+    }
+
+    #[test]
+    fn gapless_next_item_isnt_downloaded() {
+        let playlist = uris(&["file:///a.flac", "https://example.com/b.flac", "file:///c.flac"]);
+        let order = [0, 1, 2];
+        assert_eq!(gapless_next_item(&playlist, &order, 0, RepeatMode::Off, true), None);
+        let next = gapless_next_item(&playlist, &order, 0, RepeatMode::Off, false);
+        assert_eq!(next, Some((1, "https://example.com/b.flac".to_string())));
+        let next = gapless_next_item(&playlist, &order, 1, RepeatMode::Off, true);
+        assert_eq!(next, Some((2, "file:///c.flac".to_string())));
+    }
 }
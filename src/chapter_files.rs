@@ -0,0 +1,339 @@
+use failure::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Names tried next to the media, after its file stem.
+const CHAPTER_FILE_SUFFIXES: [&str; 5] = [".chapters.xml", ".xml", ".chapters.txt", ".txt", ".ffmetadata"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chapter {
+    // In nanoseconds.
+    pub start: u64,
+    pub title: String,
+}
+
+/// Look for a chapter file named like the media, next to it.
+pub fn find_chapter_file(media_path: &Path) -> Option<PathBuf> {
+    let stem = media_path.file_stem()?;
+    let directory = media_path.parent()?;
+    CHAPTER_FILE_SUFFIXES.iter().find_map(|suffix| {
+        let mut name = stem.to_os_string();
+        name.push(suffix);
+        let candidate = directory.join(name);
+        if candidate.is_file() {
+            Some(candidate)
+        } else {
+            None
+        }
+    })
+}
+
+// "HH:MM:SS.fraction", the fraction having up to nine digits.
+fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let mut parts = timestamp.trim().splitn(3, ':');
+    let hours = parts.next()?.parse::<u64>().ok()?;
+    let minutes = parts.next()?.parse::<u64>().ok()?;
+    let mut seconds = parts.next()?.splitn(2, '.');
+    let whole_seconds = seconds.next()?.parse::<u64>().ok()?;
+    let fraction = match seconds.next() {
+        Some(digits) if digits.len() <= 9 && digits.chars().all(|c| c.is_ascii_digit()) => {
+            format!("{:0<9}", digits).parse::<u64>().ok()?
+        }
+        Some(_) => return None,
+        None => 0,
+    };
+    if minutes >= 60 || whole_seconds >= 60 {
+        return None;
+    }
+    Some(((hours * 60 + minutes) * 60 + whole_seconds) * 1_000_000_000 + fraction)
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn xml_element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start_tag = format!("<{}>", name);
+    let end_tag = format!("</{}>", name);
+    let start = xml.find(&start_tag)? + start_tag.len();
+    let end = xml[start..].find(&end_tag)? + start;
+    Some(&xml[start..end])
+}
+
+// Matroska chapter XML, as written by mkvextract or MKVToolNix. Only the
+// start time and the first title of each chapter are used, hidden and
+// disabled chapters are skipped.
+fn parse_matroska_xml(xml: &str) -> Vec<Chapter> {
+    let mut chapters = vec![];
+    for atom in xml.split("<ChapterAtom>").skip(1) {
+        let flag = |name| xml_element(atom, name).map(str::trim);
+        if flag("ChapterFlagHidden") == Some("1") || flag("ChapterFlagEnabled") == Some("0") {
+            continue;
+        }
+        let start = match xml_element(atom, "ChapterTimeStart").and_then(parse_timestamp) {
+            Some(start) => start,
+            None => continue,
+        };
+        let title = xml_element(atom, "ChapterString")
+            .map(|title| unescape_xml(title.trim()))
+            .unwrap_or_default();
+        chapters.push(Chapter { start, title });
+    }
+    chapters
+}
+
+// OGM chapters, pairs of lines such as:
+//
+// CHAPTER01=00:00:00.000
+// CHAPTER01NAME=Introduction
+fn parse_ogm(text: &str) -> Vec<Chapter> {
+    let mut starts = vec![];
+    let mut titles = std::collections::HashMap::new();
+    for line in text.lines() {
+        let mut fields = line.trim().splitn(2, '=');
+        let (key, value) = match (fields.next(), fields.next()) {
+            (Some(key), Some(value)) => (key.to_uppercase(), value),
+            _ => continue,
+        };
+        if !key.starts_with("CHAPTER") {
+            continue;
+        }
+        if key.ends_with("NAME") {
+            titles.insert(key.trim_end_matches("NAME").to_string(), value.trim().to_string());
+        } else if let Some(start) = parse_timestamp(value) {
+            starts.push((key, start));
+        }
+    }
+    starts
+        .into_iter()
+        .map(|(key, start)| Chapter {
+            start,
+            title: titles.remove(&key).unwrap_or_default(),
+        })
+        .collect()
+}
+
+struct MetadataChapter {
+    timebase: (u64, u64),
+    start: Option<u64>,
+    title: String,
+}
+
+impl MetadataChapter {
+    fn to_chapter(&self) -> Option<Chapter> {
+        let (numerator, denominator) = self.timebase;
+        if denominator == 0 {
+            return None;
+        }
+        let start = u128::from(self.start?) * u128::from(numerator) * 1_000_000_000 / u128::from(denominator);
+        Some(Chapter {
+            start: start as u64,
+            title: self.title.clone(),
+        })
+    }
+}
+
+// FFmpeg metadata files, with one [CHAPTER] section per chapter and times in
+// units of its TIMEBASE.
+fn parse_ffmetadata(text: &str) -> Vec<Chapter> {
+    let mut sections = vec![];
+    let mut section: Option<MetadataChapter> = None;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            sections.extend(section.take());
+            if line.eq_ignore_ascii_case("[CHAPTER]") {
+                section = Some(MetadataChapter {
+                    timebase: (1, 1_000_000_000),
+                    start: None,
+                    title: String::new(),
+                });
+            }
+            continue;
+        }
+        let chapter = match section.as_mut() {
+            Some(chapter) => chapter,
+            None => continue,
+        };
+        let mut fields = line.splitn(2, '=');
+        let (key, value) = match (fields.next(), fields.next()) {
+            (Some(key), Some(value)) => (key.to_lowercase(), value),
+            _ => continue,
+        };
+        match key.as_str() {
+            "timebase" => {
+                let mut fraction = value.splitn(2, '/').map(|n| n.trim().parse::<u64>());
+                if let (Some(Ok(numerator)), Some(Ok(denominator))) = (fraction.next(), fraction.next()) {
+                    chapter.timebase = (numerator, denominator);
+                }
+            }
+            "start" => chapter.start = value.trim().parse::<u64>().ok(),
+            "title" => chapter.title = value.replace("\\=", "=").replace("\\;", ";").replace("\\#", "#"),
+            _ => {}
+        }
+    }
+    sections.extend(section);
+    sections.iter().filter_map(MetadataChapter::to_chapter).collect()
+}
+
+/// Read the chapters of a Matroska XML, OGM or FFmpeg metadata chapter
+/// file, sorted by start time.
+pub fn read_chapter_file(path: &Path) -> Result<Vec<Chapter>, Error> {
+    let data = fs::read_to_string(path)?;
+    let data = data.trim_start_matches('\u{feff}');
+    let mut chapters = if data.starts_with(";FFMETADATA") {
+        parse_ffmetadata(data)
+    } else if data.contains("<Chapters") {
+        parse_matroska_xml(data)
+    } else {
+        parse_ogm(data)
+    };
+    if chapters.is_empty() {
+        return Err(failure::err_msg(format!("No chapters found in {}", path.display())));
+    }
+    chapters.sort_by_key(|chapter| chapter.start);
+    Ok(chapters)
+}
+
+/// Add the chapters of a sidecar file to the embedded ones, skipping those
+/// starting less than a second away from an embedded chapter.
+pub fn merge_chapters(embedded: &[Chapter], sidecar: &[Chapter]) -> Vec<Chapter> {
+    let mut chapters = embedded.to_vec();
+    for chapter in sidecar {
+        let duplicate = embedded
+            .iter()
+            .any(|c| (c.start as i128 - chapter.start as i128).abs() < 1_000_000_000);
+        if !duplicate {
+            chapters.push(chapter.clone());
+        }
+    }
+    chapters.sort_by_key(|chapter| chapter.start);
+    chapters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(seconds: f64, title: &str) -> Chapter {
+        Chapter {
+            start: (seconds * 1_000_000_000.0).round() as u64,
+            title: title.to_string(),
+        }
+    }
+
+    fn write_chapter_file(name: &str, data: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("glide-chapters-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(name);
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn matroska_xml() {
+        let xml = r#"<?xml version="1.0"?>
+<!DOCTYPE Chapters SYSTEM "matroskachapters.dtd">
+<Chapters>
+  <EditionEntry>
+    <ChapterAtom>
+      <ChapterTimeStart>00:00:00.000000000</ChapterTimeStart>
+      <ChapterDisplay>
+        <ChapterString>Opening</ChapterString>
+        <ChapterLanguage>eng</ChapterLanguage>
+      </ChapterDisplay>
+    </ChapterAtom>
+    <ChapterAtom>
+      <ChapterTimeStart>00:01:30.500</ChapterTimeStart>
+      <ChapterFlagHidden>1</ChapterFlagHidden>
+      <ChapterDisplay>
+        <ChapterString>Hidden</ChapterString>
+      </ChapterDisplay>
+    </ChapterAtom>
+    <ChapterAtom>
+      <ChapterTimeStart>00:22:05.250</ChapterTimeStart>
+      <ChapterDisplay>
+        <ChapterString>Tom &amp; Jerry</ChapterString>
+      </ChapterDisplay>
+    </ChapterAtom>
+  </EditionEntry>
+</Chapters>
+"#;
+        let path = write_chapter_file("episode.chapters.xml", xml);
+        assert_eq!(
+            read_chapter_file(&path).unwrap(),
+            vec![chapter(0.0, "Opening"), chapter(1325.25, "Tom & Jerry")]
+        );
+    }
+
+    #[test]
+    fn ogm_text() {
+        let text = "\u{feff}CHAPTER02=00:10:00.000\r\n\
+                    CHAPTER02NAME=Second\r\n\
+                    CHAPTER01=00:00:00.000\r\n\
+                    CHAPTER01NAME=First\r\n\
+                    CHAPTER03=00:20:00.5\r\n";
+        let path = write_chapter_file("episode.txt", text);
+        assert_eq!(
+            read_chapter_file(&path).unwrap(),
+            vec![chapter(0.0, "First"), chapter(600.0, "Second"), chapter(1200.5, "")]
+        );
+    }
+
+    #[test]
+    fn ffmetadata() {
+        let text = ";FFMETADATA1\n\
+                    title=Movie\n\
+                    \n\
+                    [CHAPTER]\n\
+                    TIMEBASE=1/1000\n\
+                    START=0\n\
+                    END=60000\n\
+                    title=Part 1\\=Intro\n\
+                    \n\
+                    [CHAPTER]\n\
+                    TIMEBASE=1/90000\n\
+                    START=5400000\n\
+                    END=10800000\n\
+                    title=Part 2\n\
+                    \n\
+                    [STREAM]\n\
+                    title=Ignored\n";
+        let path = write_chapter_file("movie.ffmetadata", text);
+        assert_eq!(
+            read_chapter_file(&path).unwrap(),
+            vec![chapter(0.0, "Part 1=Intro"), chapter(60.0, "Part 2")]
+        );
+    }
+
+    #[test]
+    fn file_without_chapters() {
+        let path = write_chapter_file("notes.txt", "Nothing to see here\n");
+        assert!(read_chapter_file(&path).is_err());
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse_timestamp("01:02:03.5"), Some(3_723_500_000_000));
+        assert_eq!(parse_timestamp("00:00:00.000000001"), Some(1));
+        assert_eq!(parse_timestamp("00:60:00"), None);
+        assert_eq!(parse_timestamp("00:00:00.0000000001"), None);
+    }
+
+    #[test]
+    fn sidecar_chapters_close_to_embedded_ones_are_skipped() {
+        let embedded = vec![chapter(0.0, "Start"), chapter(300.0, "Middle")];
+        let sidecar = vec![chapter(0.5, "Beginning"), chapter(150.0, "Quarter")];
+        assert_eq!(
+            merge_chapters(&embedded, &sidecar),
+            vec![
+                chapter(0.0, "Start"),
+                chapter(150.0, "Quarter"),
+                chapter(300.0, "Middle")
+            ]
+        );
+    }
+}
@@ -0,0 +1,161 @@
+extern crate glib;
+extern crate gstreamer as gst;
+
+use failure::Error;
+use gst::prelude::*;
+use std::path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::channel_player::PlayerEvent;
+
+const NO_MORE_PADS: &str = "glide-no-more-pads";
+
+fn notify(subscribers: &[glib::Sender<PlayerEvent>], event: PlayerEvent) {
+    for sender in subscribers {
+        let _ = sender.send(event.clone());
+    }
+}
+
+// Elementary streams are blocked until the seek to the clip start was sent,
+// so that the muxer only ever sees data from the clip.
+fn build_pipeline(
+    uri: &str,
+    output: &path::Path,
+    blocked_pads: &Arc<Mutex<Vec<(gst::Pad, gst::PadProbeId)>>>,
+) -> Result<(gst::Pipeline, gst::Element), Error> {
+    let pipeline = gst::Pipeline::new(None);
+    let source = gst::ElementFactory::make("urisourcebin", None)?;
+    source.set_property("uri", &uri)?;
+    let parser = gst::ElementFactory::make("parsebin", None)?;
+    // Matroska accepts almost any codec, so streams can be copied as-is.
+    let muxer = gst::ElementFactory::make("matroskamux", None)?;
+    let sink = gst::ElementFactory::make("filesink", None)?;
+    sink.set_property("location", &output.to_string_lossy().to_value())?;
+    pipeline.add_many(&[&source, &parser, &muxer, &sink])?;
+    muxer.link(&sink)?;
+
+    let parser_weak = parser.downgrade();
+    source.connect_pad_added(move |_, pad| {
+        if let Some(parser) = parser_weak.upgrade() {
+            if let Some(sink_pad) = parser.get_static_pad("sink") {
+                let _ = pad.link(&sink_pad);
+            }
+        }
+    });
+
+    let blocked = blocked_pads.clone();
+    parser.connect_pad_added(move |_, pad| {
+        let probe = pad.add_probe(gst::PadProbeType::BLOCK_DOWNSTREAM, |_, _| gst::PadProbeReturn::Ok);
+        if let Some(probe) = probe {
+            blocked.lock().unwrap().push((pad.clone(), probe));
+        }
+    });
+
+    let pipeline_weak = pipeline.downgrade();
+    parser.connect_no_more_pads(move |parser| {
+        if let Some(pipeline) = pipeline_weak.upgrade() {
+            let structure = gst::Structure::new_empty(NO_MORE_PADS);
+            let msg = gst::message::Application::builder(structure).src(Some(parser)).build();
+            let _ = pipeline.post_message(&msg);
+        }
+    });
+
+    Ok((pipeline, muxer))
+}
+
+// Link the parsed streams to the muxer, streams it doesn't support are
+// dropped.
+fn link_streams(pipeline: &gst::Pipeline, muxer: &gst::Element, pads: &[(gst::Pad, gst::PadProbeId)]) {
+    for (pad, _) in pads {
+        let caps = pad.get_current_caps().unwrap_or_else(|| pad.query_caps(None));
+        let linked = muxer
+            .get_compatible_pad(pad, Some(&caps))
+            .map_or(false, |mux_pad| pad.link(&mux_pad).is_ok());
+        if !linked {
+            if let Ok(fakesink) = gst::ElementFactory::make("fakesink", None) {
+                let _ = pipeline.add(&fakesink);
+                let _ = fakesink.sync_state_with_parent();
+                if let Some(sink_pad) = fakesink.get_static_pad("sink") {
+                    let _ = pad.link(&sink_pad);
+                }
+            }
+        }
+    }
+}
+
+fn run(
+    uri: &str,
+    start: gst::ClockTime,
+    end: gst::ClockTime,
+    output: &path::Path,
+    subscribers: &[glib::Sender<PlayerEvent>],
+) -> Result<(), Error> {
+    let blocked_pads = Arc::new(Mutex::new(vec![]));
+    let (pipeline, muxer) = build_pipeline(uri, output, &blocked_pads)?;
+    let bus = pipeline.get_bus().unwrap();
+    pipeline.set_state(gst::State::Playing)?;
+
+    let result = loop {
+        let msg = match bus.timed_pop(gst::ClockTime::from_mseconds(250)) {
+            Some(msg) => msg,
+            None => {
+                if let Some(position) = pipeline.query_position::<gst::ClockTime>() {
+                    if position >= start && end > start {
+                        let done = (position - start).nseconds().unwrap_or(0) as f64;
+                        let total = (end - start).nseconds().unwrap_or(1) as f64;
+                        notify(subscribers, PlayerEvent::ClipExportProgress((done / total).min(1.0)));
+                    }
+                }
+                continue;
+            }
+        };
+        match msg.view() {
+            gst::MessageView::Application(app) => {
+                let is_no_more_pads = app.get_structure().map_or(false, |s| s.get_name() == NO_MORE_PADS);
+                if !is_no_more_pads {
+                    continue;
+                }
+                let pads = blocked_pads.lock().unwrap().drain(..).collect::<Vec<_>>();
+                link_streams(&pipeline, &muxer, &pads);
+                // Stream copies can only start on a keyframe, so include the
+                // one preceding the requested start.
+                let flags = gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT | gst::SeekFlags::SNAP_BEFORE;
+                let seek = gst::Event::new_seek(1.0, flags, gst::SeekType::Set, start, gst::SeekType::Set, end).build();
+                if let Some((pad, _)) = pads.first() {
+                    if !pad.send_event(seek) {
+                        break Err(failure::err_msg("Unable to seek to the clip start"));
+                    }
+                }
+                for (pad, probe) in pads {
+                    pad.remove_probe(probe);
+                }
+            }
+            gst::MessageView::Eos(_) => break Ok(()),
+            gst::MessageView::Error(err) => break Err(err.get_error().into()),
+            _ => {}
+        }
+    };
+    let _ = pipeline.set_state(gst::State::Null);
+    result
+}
+
+/// Remux the given range of a media to a Matroska file, without re-encoding,
+/// from a background thread. Progress and outcome are notified with
+/// ClipExport* events.
+pub fn export_clip(
+    uri: &str,
+    start: gst::ClockTime,
+    end: gst::ClockTime,
+    output: path::PathBuf,
+    subscribers: Vec<glib::Sender<PlayerEvent>>,
+) {
+    let uri = uri.to_string();
+    thread::spawn(move || {
+        let event = match run(&uri, start, end, &output, &subscribers) {
+            Ok(_) => PlayerEvent::ClipExported(output.to_string_lossy().into()),
+            Err(e) => PlayerEvent::ClipExportFailed(e.to_string()),
+        };
+        notify(&subscribers, event);
+    });
+}
@@ -0,0 +1,126 @@
+extern crate serde_json;
+extern crate toml;
+
+use failure::Error;
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path;
+
+// Increased when the archive layout changes. Files unknown to this version,
+// from newer ones, are skipped on import.
+const ARCHIVE_VERSION: u32 = 1;
+
+/// The settings and cache files of Glide, by name in the archive.
+pub type ConfigFiles = Vec<(&'static str, path::PathBuf)>;
+
+#[derive(Serialize, Deserialize)]
+struct Archive {
+    version: u32,
+    files: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    /// Keep the current entries of JSON objects missing from the archive,
+    /// such as the positions of other media.
+    Merge,
+    Replace,
+}
+
+fn write_atomically(path: &path::Path, data: &[u8]) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temporary_path = path.as_os_str().to_os_string();
+    temporary_path.push(".tmp");
+    let mut file = File::create(&temporary_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&temporary_path, path)?;
+    Ok(())
+}
+
+fn validate(name: &str, data: &str) -> Result<(), Error> {
+    let valid = if name.ends_with(".toml") {
+        data.parse::<toml::Value>().is_ok()
+    } else {
+        serde_json::from_str::<serde_json::Value>(data).is_ok()
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(failure::err_msg(format!("{} is corrupted", name)))
+    }
+}
+
+fn merge(current: &str, imported: &str) -> Result<String, Error> {
+    let current = serde_json::from_str::<serde_json::Value>(current)?;
+    let imported = serde_json::from_str::<serde_json::Value>(imported)?;
+    match (current, imported) {
+        (serde_json::Value::Object(mut current), serde_json::Value::Object(imported)) => {
+            current.extend(imported);
+            Ok(serde_json::to_string(&current)?)
+        }
+        (_, imported) => Ok(serde_json::to_string(&imported)?),
+    }
+}
+
+/// Bundle the existing files in a single JSON document.
+pub fn export_config(files: &[(&'static str, path::PathBuf)], destination: &path::Path) -> Result<(), Error> {
+    let mut archive = Archive {
+        version: ARCHIVE_VERSION,
+        files: BTreeMap::new(),
+    };
+    for (name, path) in files {
+        match fs::read_to_string(path) {
+            Ok(data) => {
+                archive.files.insert(name.to_string(), data);
+            }
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    write_atomically(destination, serde_json::to_string_pretty(&archive)?.as_bytes())
+}
+
+/// Write the files of an archive over the current ones. Nothing is written
+/// unless all of them are valid.
+pub fn import_config(
+    files: &[(&'static str, path::PathBuf)],
+    source: &path::Path,
+    mode: ImportMode,
+) -> Result<(), Error> {
+    let archive: Archive = serde_json::from_str(&fs::read_to_string(source)?)
+        .map_err(|_| failure::err_msg("Not a Glide settings archive"))?;
+    if archive.files.is_empty() {
+        return Err(failure::err_msg("The archive is empty"));
+    }
+    if archive.version > ARCHIVE_VERSION {
+        eprintln!("Settings archive from a newer version, some files might be skipped");
+    }
+
+    let mut contents = vec![];
+    for (name, data) in &archive.files {
+        let path = match files.iter().find(|(n, _)| n == name) {
+            Some((_, path)) => path,
+            None => {
+                eprintln!("Skipping unknown file {} of the settings archive", name);
+                continue;
+            }
+        };
+        validate(name, data)?;
+        let data = match (mode, fs::read_to_string(path)) {
+            (ImportMode::Merge, Ok(ref current)) if !name.ends_with(".toml") => {
+                merge(current, data).unwrap_or_else(|_| data.clone())
+            }
+            _ => data.clone(),
+        };
+        contents.push((path, data));
+    }
+    for (path, data) in contents {
+        write_atomically(path, data.as_bytes())?;
+    }
+    Ok(())
+}
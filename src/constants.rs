@@ -1,8 +1,14 @@
-// Only possible in nightly
-// static SEEK_BACKWARD_OFFSET: gst::ClockTime = gst::ClockTime::from_mseconds(2000);
-// static SEEK_FORWARD_OFFSET: gst::ClockTime = gst::ClockTime::from_mseconds(5000);
+pub static MIN_PLAYBACK_RATE: f64 = 0.25;
+pub static MAX_PLAYBACK_RATE: f64 = 4.0;
+// Rates gone through by the playback speed increase and decrease actions.
+pub static PLAYBACK_RATE_STEPS: [f64; 8] = [0.25, 0.5, 0.75, 1.0, 1.25, 1.5, 2.0, 4.0];
 
-pub static SEEK_BACKWARD_OFFSET: gst::ClockTime = gst::ClockTime(Some(2_000_000_000));
-pub static SEEK_FORWARD_OFFSET: gst::ClockTime = gst::ClockTime(Some(5_000_000_000));
+// Gains in dB of the equalizer bands, from 29 Hz to 15 kHz, by preset name.
+pub static EQUALIZER_PRESETS: [(&str, [f64; 10]); 4] = [
+    ("flat", [0.0; 10]),
+    ("bass-boost", [6.0, 5.0, 4.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+    ("treble-boost", [0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0, 4.0, 5.0, 6.0]),
+    ("vocal", [-2.0, -2.0, -1.0, 1.0, 3.0, 4.0, 3.0, 1.0, 0.0, -1.0]),
+];
 
-pub static SUB_FILE_EXTENSIONS: [&str; 3] = ["srt", "sub", "ass"];
+pub static SUB_FILE_EXTENSIONS: [&str; 5] = ["srt", "ass", "ssa", "vtt", "sub"];
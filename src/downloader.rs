@@ -0,0 +1,149 @@
+extern crate glib;
+extern crate gstreamer as gst;
+
+use failure::Error;
+use gst::prelude::*;
+use std::cell::Cell;
+use std::fs;
+use std::path;
+use std::rc::Rc;
+use std::string;
+
+pub enum DownloadResult {
+    Finished(path::PathBuf),
+    // Live or too large, better stream it.
+    Unsuitable,
+    Failed(string::String),
+}
+
+/// Download of a remote media to a local file, using a GStreamer pipeline
+/// so that any source element, HTTP range requests included, can be used.
+/// Partial downloads are kept next to the destination and resumed when
+/// possible. Dropping the download cancels it.
+pub struct Download {
+    pipeline: gst::Pipeline,
+}
+
+fn partial_path(destination: &path::Path) -> path::PathBuf {
+    let mut name = destination.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    destination.with_file_name(name)
+}
+
+impl Download {
+    pub fn start<P, F>(
+        uri: &str,
+        destination: path::PathBuf,
+        max_size: u64,
+        on_progress: P,
+        on_done: F,
+    ) -> Result<Self, Error>
+    where
+        P: Fn(f64) + 'static,
+        F: Fn(DownloadResult) + 'static,
+    {
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let partial = partial_path(&destination);
+        let resume_offset = fs::metadata(&partial).map(|m| m.len()).unwrap_or(0);
+
+        let pipeline = gst::Pipeline::new(None);
+        let source = gst::Element::make_from_uri(gst::URIType::Src, uri, None)?;
+        let sink = gst::ElementFactory::make("filesink", None)?;
+        sink.set_property("location", &partial.to_string_lossy().to_value())?;
+        sink.set_property("append", &(resume_offset > 0))?;
+        pipeline.add_many(&[&source, &sink])?;
+        source.link(&sink)?;
+
+        let on_done = Rc::new(on_done);
+        let pipeline_weak = pipeline.downgrade();
+        let started = Rc::new(Cell::new(false));
+        let bus = pipeline.get_bus().unwrap();
+        let watch_on_done = on_done.clone();
+        bus.add_watch_local(move |_, msg| {
+            let on_done = &watch_on_done;
+            let pipeline = match pipeline_weak.upgrade() {
+                Some(pipeline) => pipeline,
+                None => return glib::Continue(false),
+            };
+            match msg.view() {
+                gst::MessageView::AsyncDone(_) if !started.get() => {
+                    started.set(true);
+                    let size = pipeline
+                        .query_duration::<gst::format::Bytes>()
+                        .and_then(|bytes| bytes.0);
+                    let size = match size {
+                        Some(size) if size <= max_size => size,
+                        _ => {
+                            on_done(DownloadResult::Unsuitable);
+                            return glib::Continue(false);
+                        }
+                    };
+                    if resume_offset > 0 && resume_offset < size {
+                        let position = gst::format::Bytes(Some(resume_offset));
+                        if pipeline.seek_simple(gst::SeekFlags::FLUSH, position).is_err() {
+                            // Appending from the start would corrupt the file.
+                            let _ = fs::remove_file(&partial);
+                            on_done(DownloadResult::Unsuitable);
+                            return glib::Continue(false);
+                        }
+                    }
+                    let _ = pipeline.set_state(gst::State::Playing);
+                }
+                gst::MessageView::Eos(_) => {
+                    let _ = pipeline.set_state(gst::State::Null);
+                    let result = match fs::rename(&partial, &destination) {
+                        Ok(_) => DownloadResult::Finished(destination.clone()),
+                        Err(e) => DownloadResult::Failed(e.to_string()),
+                    };
+                    on_done(result);
+                    return glib::Continue(false);
+                }
+                gst::MessageView::Error(err) => {
+                    let _ = pipeline.set_state(gst::State::Null);
+                    on_done(DownloadResult::Failed(err.get_error().to_string()));
+                    return glib::Continue(false);
+                }
+                _ => {}
+            };
+            glib::Continue(true)
+        })?;
+
+        let pipeline_weak = pipeline.downgrade();
+        glib::timeout_add_local(500, move || {
+            let pipeline = match pipeline_weak.upgrade() {
+                Some(pipeline) => pipeline,
+                None => return glib::Continue(false),
+            };
+            let position = pipeline.query_position::<gst::format::Bytes>().and_then(|b| b.0);
+            let duration = pipeline.query_duration::<gst::format::Bytes>().and_then(|b| b.0);
+            if let (Some(position), Some(duration)) = (position, duration) {
+                if duration > 0 {
+                    on_progress(position as f64 / duration as f64);
+                }
+            }
+            glib::Continue(true)
+        });
+
+        // Live sources don't preroll, AsyncDone would never be posted.
+        if pipeline.set_state(gst::State::Paused)? == gst::StateChangeSuccess::NoPreroll {
+            let _ = pipeline.set_state(gst::State::Null);
+            // Reported once the caller holds the download.
+            glib::idle_add_local(move || {
+                on_done(DownloadResult::Unsuitable);
+                glib::Continue(false)
+            });
+        }
+        Ok(Self { pipeline })
+    }
+}
+
+impl Drop for Download {
+    fn drop(&mut self) {
+        let _ = self.pipeline.set_state(gst::State::Null);
+        if let Some(bus) = self.pipeline.get_bus() {
+            let _ = bus.remove_watch();
+        }
+    }
+}
@@ -0,0 +1,144 @@
+extern crate glib;
+extern crate gstreamer as gst;
+extern crate toml;
+
+use failure::Error;
+use gst::prelude::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path;
+
+/// Properties to set on pipeline elements, read from a TOML file with one
+/// table per element factory:
+///
+/// ```toml
+/// [avdec_h264]
+/// max-threads = 4
+///
+/// [queue2]
+/// max-size-bytes = 10485760
+/// ```
+///
+/// Enum and flags properties are set from their nick, as with gst-launch.
+#[derive(Default)]
+pub struct ElementProperties {
+    properties: BTreeMap<String, Vec<(String, toml::Value)>>,
+}
+
+fn to_value(value: &toml::Value, value_type: glib::Type) -> Option<glib::Value> {
+    use std::convert::TryFrom;
+    match (value, value_type) {
+        (toml::Value::Boolean(b), glib::Type::Bool) => Some(b.to_value()),
+        (toml::Value::Integer(i), glib::Type::I32) => i32::try_from(*i).ok().map(|i| i.to_value()),
+        (toml::Value::Integer(i), glib::Type::U32) => u32::try_from(*i).ok().map(|i| i.to_value()),
+        (toml::Value::Integer(i), glib::Type::I64) => Some(i.to_value()),
+        (toml::Value::Integer(i), glib::Type::U64) => u64::try_from(*i).ok().map(|i| i.to_value()),
+        (toml::Value::Integer(i), glib::Type::F32) => Some((*i as f32).to_value()),
+        (toml::Value::Integer(i), glib::Type::F64) => Some((*i as f64).to_value()),
+        (toml::Value::Float(f), glib::Type::F32) => Some((*f as f32).to_value()),
+        (toml::Value::Float(f), glib::Type::F64) => Some(f.to_value()),
+        (toml::Value::String(s), glib::Type::String) => Some(s.to_value()),
+        _ => None,
+    }
+}
+
+fn is_enum_or_flags(value_type: glib::Type) -> bool {
+    value_type.is_a(&glib::Type::BaseEnum) || value_type.is_a(&glib::Type::BaseFlags)
+}
+
+// Check the property against a throwaway instance of the element, so that
+// mistakes are reported once at startup rather than for every new pipeline.
+fn validate(element: &gst::Element, factory: &str, name: &str, value: &toml::Value) -> bool {
+    let pspec = match element.find_property(name) {
+        Some(pspec) => pspec,
+        None => {
+            eprintln!("elements.toml: {} has no property named {}", factory, name);
+            return false;
+        }
+    };
+    if !pspec.get_flags().contains(glib::ParamFlags::WRITABLE) {
+        eprintln!("elements.toml: property {} of {} is read-only", name, factory);
+        return false;
+    }
+    let value_type = pspec.get_value_type();
+    let valid = match value {
+        toml::Value::String(_) if is_enum_or_flags(value_type) => true,
+        _ => to_value(value, value_type).is_some(),
+    };
+    if !valid {
+        eprintln!(
+            "elements.toml: {} is not a valid {} value for property {} of {}",
+            value,
+            value_type.name(),
+            name,
+            factory
+        );
+    }
+    valid
+}
+
+impl ElementProperties {
+    /// Parse and validate the file. Unknown elements and properties, and
+    /// values of the wrong type are reported and ignored.
+    pub fn load(path: &path::Path) -> Result<Self, Error> {
+        let data = fs::read_to_string(path)?;
+        let tables: BTreeMap<String, toml::value::Table> = toml::from_str(&data)?;
+
+        let mut properties = BTreeMap::new();
+        for (factory, table) in tables {
+            let element = match gst::ElementFactory::make(&factory, None) {
+                Ok(element) => element,
+                Err(_) => {
+                    eprintln!("elements.toml: unknown element {}", factory);
+                    continue;
+                }
+            };
+            let valid = table
+                .into_iter()
+                .filter(|(name, value)| validate(&element, &factory, name, value))
+                .collect::<Vec<_>>();
+            if !valid.is_empty() {
+                properties.insert(factory, valid);
+            }
+        }
+        Ok(Self { properties })
+    }
+
+    fn apply_to_element(&self, element: &gst::Element) {
+        let factory = match element.get_factory() {
+            Some(factory) => factory.get_name(),
+            None => return,
+        };
+        let properties = match self.properties.get(factory.as_str()) {
+            Some(properties) => properties,
+            None => return,
+        };
+        for (name, value) in properties {
+            let value_type = match element.find_property(name.as_str()) {
+                Some(pspec) => pspec.get_value_type(),
+                None => continue,
+            };
+            match (value, to_value(value, value_type)) {
+                (_, Some(v)) => {
+                    if let Err(e) = element.set_property(name.as_str(), &v) {
+                        eprintln!("Unable to set {} on {}: {}", name, factory, e);
+                    }
+                }
+                (toml::Value::String(s), None) => element.set_property_from_str(name, s),
+                _ => {}
+            }
+        }
+    }
+
+    /// Set the configured properties on the element and, for bins, on all
+    /// the elements they contain.
+    pub fn apply(&self, element: &gst::Element) {
+        if self.properties.is_empty() {
+            return;
+        }
+        self.apply_to_element(element);
+        if let Some(bin) = element.downcast_ref::<gst::Bin>() {
+            let _ = bin.iterate_recurse().foreach(|child| self.apply_to_element(&child));
+        }
+    }
+}
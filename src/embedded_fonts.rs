@@ -0,0 +1,181 @@
+extern crate glib;
+extern crate gstreamer as gst;
+
+use std::fs;
+use std::path;
+use std::process;
+
+// Mime types used for font attachments by matroskademux.
+const FONT_MIME_TYPES: [&str; 6] = [
+    "application/x-truetype-font",
+    "application/x-font-ttf",
+    "application/x-font-otf",
+    "application/vnd.ms-opentype",
+    "font/ttf",
+    "font/otf",
+];
+
+#[cfg(target_os = "linux")]
+#[link(name = "fontconfig")]
+extern "C" {
+    fn FcConfigAppFontAddDir(config: *mut std::os::raw::c_void, dir: *const std::os::raw::c_char) -> i32;
+    fn FcConfigAppFontClear(config: *mut std::os::raw::c_void);
+}
+
+/// Fonts attached to a media, typically a Matroska file with styled ASS
+/// subtitles, made available to the subtitle renderers for as long as this
+/// is alive.
+pub struct EmbeddedFonts {
+    pub uri: String,
+    directory: Option<path::PathBuf>,
+}
+
+fn font_attachments(tags: &gst::TagList) -> Vec<(String, Vec<u8>)> {
+    let mut fonts = vec![];
+    for (index, value) in tags.iter_tag::<gst::tags::Attachment>().enumerate() {
+        let sample = match value.get() {
+            Some(sample) => sample,
+            None => continue,
+        };
+        let is_font = sample
+            .get_caps()
+            .and_then(|caps| caps.get_structure(0))
+            .map_or(false, |s| FONT_MIME_TYPES.contains(&s.get_name()));
+        if !is_font {
+            continue;
+        }
+        // Only keep the file name, attachments come from untrusted media.
+        let name = sample
+            .get_info()
+            .and_then(|info| info.get::<&str>("filename").ok().and_then(|f| f))
+            .and_then(|f| path::Path::new(f).file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| format!("font-{}.ttf", index));
+        if let Some(buffer) = sample.get_buffer() {
+            if let Ok(map) = buffer.map_readable() {
+                fonts.push((name, map.as_slice().to_vec()));
+            }
+        }
+    }
+    fonts
+}
+
+#[cfg(target_os = "linux")]
+fn register_directory(directory: &path::Path) -> bool {
+    let directory = match std::ffi::CString::new(directory.to_string_lossy().as_bytes()) {
+        Ok(directory) => directory,
+        Err(_) => return false,
+    };
+    unsafe { FcConfigAppFontAddDir(std::ptr::null_mut(), directory.as_ptr()) != 0 }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn register_directory(_directory: &path::Path) -> bool {
+    false
+}
+
+impl EmbeddedFonts {
+    /// Write the font attachments found in the tags to a temporary
+    /// directory and register it with fontconfig.
+    pub fn register(uri: &str, tags: &gst::TagList) -> Self {
+        let mut fonts = Self {
+            uri: uri.to_string(),
+            directory: None,
+        };
+        let attachments = font_attachments(tags);
+        if attachments.is_empty() {
+            return fonts;
+        }
+
+        let directory = std::env::temp_dir().join(format!("glide-fonts-{}", process::id()));
+        if let Err(e) = fs::create_dir_all(&directory) {
+            eprintln!("Unable to create {}: {}", directory.display(), e);
+            return fonts;
+        }
+        // Set it right away so that the directory is cleaned up on failure.
+        fonts.directory = Some(directory.clone());
+        for (name, data) in attachments {
+            if let Err(e) = fs::write(directory.join(&name), data) {
+                eprintln!("Unable to extract font {}: {}", name, e);
+            }
+        }
+        if !register_directory(&directory) {
+            eprintln!("Unable to register the fonts attached to {}", uri);
+        }
+        fonts
+    }
+}
+
+impl Drop for EmbeddedFonts {
+    fn drop(&mut self) {
+        if let Some(ref directory) = self.directory {
+            #[cfg(target_os = "linux")]
+            unsafe {
+                FcConfigAppFontClear(std::ptr::null_mut());
+            }
+            let _ = fs::remove_dir_all(directory);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attachment(mime_type: &str, filename: Option<&str>, data: &[u8]) -> gst::Sample {
+        let builder = gst::Sample::builder()
+            .buffer(&gst::Buffer::from_slice(data.to_vec()))
+            .caps(&gst::Caps::new_simple(mime_type, &[]));
+        match filename {
+            Some(filename) => builder
+                .info(gst::Structure::new("GstTagAttachment", &[("filename", &filename)]))
+                .build(),
+            None => builder.build(),
+        }
+    }
+
+    // Mimics what matroskademux posts for an MKV with styled ASS subtitles:
+    // two fonts, a cover image and the subtitles are not fonts.
+    fn sample_tags() -> gst::TagList {
+        gst::init().unwrap();
+        let mut tags = gst::TagList::new();
+        {
+            let tags = tags.get_mut().unwrap();
+            for sample in &[
+                attachment("application/x-truetype-font", Some("Styled Sans.ttf"), b"ttf"),
+                attachment("image/jpeg", Some("cover.jpg"), b"jpeg"),
+                attachment("font/otf", Some("../../escape.otf"), b"otf"),
+                attachment("application/vnd.ms-opentype", None, b"opentype"),
+            ] {
+                tags.add::<gst::tags::Attachment>(sample, gst::TagMergeMode::Append);
+            }
+        }
+        tags
+    }
+
+    #[test]
+    fn only_font_attachments_are_kept() {
+        let fonts = font_attachments(&sample_tags());
+        let names: Vec<&str> = fonts.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["Styled Sans.ttf", "escape.otf", "font-3.ttf"]);
+        assert_eq!(fonts[0].1, b"ttf".to_vec());
+    }
+
+    #[test]
+    fn fonts_are_extracted_until_dropped() {
+        let fonts = EmbeddedFonts::register("file:///tmp/styled.mkv", &sample_tags());
+        let directory = fonts.directory.clone().unwrap();
+        assert_eq!(fs::read(directory.join("Styled Sans.ttf")).unwrap(), b"ttf".to_vec());
+        assert!(directory.join("escape.otf").exists());
+        assert!(!directory.join("cover.jpg").exists());
+
+        drop(fonts);
+        assert!(!directory.exists());
+    }
+
+    #[test]
+    fn nothing_is_extracted_without_fonts() {
+        gst::init().unwrap();
+        let fonts = EmbeddedFonts::register("file:///tmp/plain.mkv", &gst::TagList::new());
+        assert!(fonts.directory.is_none());
+    }
+}
@@ -0,0 +1,88 @@
+extern crate gilrs;
+extern crate glib;
+
+use gilrs::{EventType, Gilrs};
+use std::collections::BTreeMap;
+
+// Controller events are polled from the main context at this interval.
+const POLL_INTERVAL_MS: u32 = 20;
+
+/// What the controllers did, dispatched on the main context.
+pub enum GamepadEvent {
+    /// Name of the action bound to the pressed button, and its string
+    /// parameter, if any.
+    Action(String, Option<String>),
+    Connected(String),
+    Disconnected(String),
+}
+
+/// Bindings used when the settings don't have any, by gilrs button name.
+pub fn default_bindings() -> BTreeMap<String, String> {
+    [
+        ("South", "pause"),
+        ("East", "fullscreen"),
+        ("West", "audio-mute"),
+        ("North", "cycle-track::video"),
+        ("LeftTrigger", "cycle-track::audio"),
+        ("RightTrigger", "cycle-track::subtitle"),
+        ("LeftTrigger2", "seek-backward"),
+        ("RightTrigger2", "seek-forward"),
+        ("DPadLeft", "seek-backward"),
+        ("DPadRight", "seek-forward"),
+        ("DPadUp", "audio-volume-increase"),
+        ("DPadDown", "audio-volume-decrease"),
+        ("Start", "instant-replay"),
+        ("Select", "toggle-previous-item"),
+    ]
+    .iter()
+    .map(|(button, action)| (button.to_string(), action.to_string()))
+    .collect()
+}
+
+/// Input from game controllers, connected before or while running, for as
+/// long as this is alive.
+pub struct Gamepad {
+    source: Option<glib::SourceId>,
+}
+
+impl Gamepad {
+    /// `bindings` maps button names, such as "South" or "RightTrigger2", to
+    /// action names, followed by "::" and a string parameter for actions
+    /// taking one, such as "cycle-track::audio".
+    pub fn new<F: Fn(GamepadEvent) + 'static>(bindings: BTreeMap<String, String>, on_event: F) -> Option<Self> {
+        let mut gilrs = match Gilrs::new() {
+            Ok(gilrs) => gilrs,
+            Err(e) => {
+                eprintln!("Game controllers unavailable: {}", e);
+                return None;
+            }
+        };
+        let source = glib::timeout_add_local(POLL_INTERVAL_MS, move || {
+            while let Some(event) = gilrs.next_event() {
+                let name = || gilrs.gamepad(event.id).name().to_string();
+                match event.event {
+                    EventType::ButtonPressed(button, _) => {
+                        if let Some(binding) = bindings.get(&format!("{:?}", button)) {
+                            let mut parts = binding.splitn(2, "::");
+                            let action = parts.next().unwrap_or_default().to_string();
+                            on_event(GamepadEvent::Action(action, parts.next().map(String::from)));
+                        }
+                    }
+                    EventType::Connected => on_event(GamepadEvent::Connected(name())),
+                    EventType::Disconnected => on_event(GamepadEvent::Disconnected(name())),
+                    _ => {}
+                }
+            }
+            glib::Continue(true)
+        });
+        Some(Self { source: Some(source) })
+    }
+}
+
+impl Drop for Gamepad {
+    fn drop(&mut self) {
+        if let Some(source) = self.source.take() {
+            glib::source_remove(source);
+        }
+    }
+}
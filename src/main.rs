@@ -1,8 +1,12 @@
 #[cfg(target_os = "macos")]
+extern crate block;
+extern crate chardetng;
+#[cfg(target_os = "macos")]
 extern crate core_foundation;
 extern crate directories;
 extern crate failure;
 extern crate gdk;
+extern crate gdk_pixbuf;
 extern crate gio;
 extern crate glib;
 extern crate gstreamer as gst;
@@ -11,36 +15,95 @@ extern crate gstreamer_video as gst_video;
 extern crate gtk;
 #[macro_use]
 extern crate lazy_static;
+#[cfg(target_os = "macos")]
+#[macro_use]
+extern crate objc;
 #[cfg(feature = "self-updater")]
 #[macro_use]
 extern crate self_update;
 
 #[macro_use]
 extern crate serde_derive;
+extern crate toml;
 
 use directories::ProjectDirs;
 use failure::Error;
 #[allow(unused_imports)]
 use gdk::prelude::*;
 use gio::prelude::*;
+use glib::SendWeakRef;
 use glib::ToVariant;
+use gtk::prelude::*;
+use gtk::Inhibit;
+use std::backtrace::Backtrace;
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::env;
-use std::fs::create_dir_all;
-use std::path::PathBuf;
+use std::fs::{create_dir_all, File};
+use std::io::Write;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use structopt::StructOpt;
 
+mod audio_devices;
+use audio_devices::AudioDeviceMonitor;
 mod channel_player;
+mod chapter_files;
+use chapter_files::Chapter;
+#[cfg(feature = "clip-export")]
+mod clip_export;
+mod config_archive;
+use config_archive::{ConfigFiles, ImportMode};
 mod constants;
-use channel_player::{AudioVisualization, ChannelPlayer, PlaybackState, PlayerEvent, SeekDirection, SubtitleTrack};
+mod downloader;
+mod element_properties;
+mod embedded_fonts;
+mod output_files;
+use channel_player::{
+    available_video_sinks, parse_ratio, AspectRatio, AudioVisualization, ChannelPlayer, DownloadConfig, MirrorOutput,
+    PlaybackState, PlayerEvent, SeekDirection, SubtitleStyle, SubtitleTrack, VideoBalance,
+};
+use element_properties::ElementProperties;
+
+mod playlist;
+mod seek_thumbnails;
+
+mod subtitle_download;
+use subtitle_download::{remove_cached_subtitles, SubtitleFetch};
+mod subtitle_files;
+#[cfg(feature = "subtitle-auto-sync")]
+mod subtitle_sync;
+
+mod session;
+use session::Session;
+
+mod settings;
+use settings::{
+    AudioProfile, ChannelRemap, ColorRange, Deinterlace, DeviceChangeAction, LiveResume, NoArgsBehavior,
+    OpenMultipleAction, OverwriteMode, PlaylistErrorAction, PlaylistSort, RepeatMode, ReplayGainMode, ResumeMode,
+    Settings, StoppedVideo, SubtitleBackground, SubtitleDownloadLocation, SubtitleScaleBase, SubtitleScript,
+    SubtitleSource,
+};
+
+mod tone_mapping;
 
 use gst_player::PlayerStreamInfoExt;
 
 mod ui_context;
 use ui_context::{initialize_and_create_app, UIContext};
 
+#[cfg(feature = "gamepad")]
+mod gamepad;
+#[cfg(feature = "gamepad")]
+use gamepad::{Gamepad, GamepadEvent};
+
 #[cfg(target_os = "macos")]
 mod iokit_sleep_disabler;
+#[cfg(target_os = "macos")]
+mod now_playing;
+#[cfg(target_os = "macos")]
+use now_playing::{NowPlaying, NowPlayingState, RemoteCommand};
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "Glide")]
@@ -49,11 +112,175 @@ struct Opt {
     #[structopt(short, long)]
     incognito: bool,
 
+    /// Start in fullscreen mode, regardless of the media type
+    #[structopt(short, long)]
+    fullscreen: bool,
+
+    /// Keep media paused on their first frame once loaded
+    #[structopt(long)]
+    paused: bool,
+
     /// Files to play
     #[structopt(name = "FILE", parse(from_os_str))]
     files: Vec<PathBuf>,
 }
 
+#[derive(Clone, Copy)]
+enum NumericInputTarget {
+    Volume,
+    Position,
+}
+
+// Percentage being typed on the keyboard, see VideoPlayer::handle_key_press().
+struct NumericInput {
+    target: NumericInputTarget,
+    digits: std::string::String,
+}
+
+impl NumericInput {
+    fn label(&self) -> std::string::String {
+        let name = match self.target {
+            NumericInputTarget::Volume => "Volume",
+            NumericInputTarget::Position => "Go to",
+        };
+        format!("{}: {}_%", name, self.digits)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TrackKind {
+    Audio,
+    Subtitle,
+    Video,
+}
+
+impl TrackKind {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "audio" => Some(TrackKind::Audio),
+            "subtitle" => Some(TrackKind::Subtitle),
+            "video" => Some(TrackKind::Video),
+            _ => None,
+        }
+    }
+}
+
+// Track being picked from the on-screen list, see VideoPlayer::cycle_track().
+struct TrackCycle {
+    kind: TrackKind,
+    // Labels and action targets, as listed in the track menus.
+    choices: Vec<(std::string::String, std::string::String)>,
+    selected: usize,
+    commit_source: glib::SourceId,
+}
+
+impl TrackCycle {
+    fn label(&self) -> std::string::String {
+        let title = match self.kind {
+            TrackKind::Audio => "Audio track",
+            TrackKind::Subtitle => "Subtitles",
+            TrackKind::Video => "Video track",
+        };
+        let mut label = title.to_string();
+        for (i, (choice, _)) in self.choices.iter().enumerate() {
+            let marker = if i == self.selected { "▶ " } else { "    " };
+            label.push_str(&format!("\n{}{}", marker, choice));
+        }
+        label
+    }
+}
+
+// How often the night mode strength is recomputed while playing.
+const NIGHT_MODE_UPDATE_INTERVAL_SECONDS: u32 = 60;
+// Duration of the fade in and out of the night mode.
+const NIGHT_MODE_TRANSITION_MINUTES: u32 = 30;
+
+// Strength of the night mode at the given minute of the day, from 0 (off) to
+// 1 (fully applied), for a night starting and ending at the given hours.
+fn night_mode_strength(minute: u32, start_hour: u32, end_hour: u32) -> f64 {
+    let day = 24 * 60;
+    let start = start_hour * 60 % day;
+    let length = (end_hour * 60 % day + day - start) % day;
+    let since_start = (minute + day - start) % day;
+    if since_start >= length {
+        return 0.0;
+    }
+    let transition = f64::from(NIGHT_MODE_TRANSITION_MINUTES);
+    let fade_in = f64::from(since_start) / transition;
+    let fade_out = f64::from(length - since_start) / transition;
+    fade_in.min(fade_out).min(1.0)
+}
+
+// Change of the color adjustments by the video-color-increase and
+// video-color-decrease actions.
+const VIDEO_COLOR_STEP: f64 = 0.05;
+
+// Zoom factor change of the video-zoom-in and video-zoom-out actions, and
+// the largest zoom.
+const VIDEO_ZOOM_STEP: f64 = 0.1;
+const VIDEO_ZOOM_MAX: f64 = 4.0;
+
+// Aspect ratios listed in the Video > Aspect ratio menu, between the
+// automatic and stretched entries.
+const ASPECT_RATIO_CHOICES: [&str; 5] = ["16:9", "4:3", "21:9", "2.35:1", "1.85:1"];
+
+// Aspect ratios the cycle-aspect-ratio action goes through, in order.
+const ASPECT_RATIO_CYCLE: [AspectRatio; 5] = [
+    AspectRatio::Auto,
+    AspectRatio::Ratio(16, 9),
+    AspectRatio::Ratio(4, 3),
+    AspectRatio::Ratio(21, 9),
+    AspectRatio::Stretch,
+];
+
+// Applying a subtitle offset to a folder is offered once it was set for that
+// many other media of the folder, all within the tolerance, in nanoseconds.
+const FOLDER_SUBTITLE_OFFSET_MIN_FILES: usize = 2;
+const FOLDER_SUBTITLE_OFFSET_TOLERANCE: i64 = 50_000_000;
+
+// Step of the subtitle and audio delay actions, in nanoseconds.
+const SUBTITLE_DELAY_STEP: i64 = 100_000_000;
+const AUDIO_DELAY_STEP: i64 = 50_000_000;
+
+// Minimal interval between two preview seeks.
+const SEEK_PREVIEW_INTERVAL: Duration = Duration::from_millis(50);
+// The preview ends when no seek was requested for this long. Longer than the
+// usual key repeat delay, so holding a key is a single preview.
+const SEEK_PREVIEW_COMMIT_DELAY_MS: u32 = 600;
+
+// Title of the current media, and the playback state shown before it when
+// the title_shows_state setting is enabled.
+#[derive(Default)]
+struct WindowTitle {
+    media_title: std::string::String,
+    paused: bool,
+    buffering: Option<i32>,
+    // The last playlist item ended.
+    finished: bool,
+}
+
+impl WindowTitle {
+    fn text(&self, show_state: bool) -> std::string::String {
+        if !show_state {
+            return self.media_title.clone();
+        }
+        match self.buffering {
+            Some(percent) => format!("⏳ {}% {}", percent, self.media_title),
+            None if self.paused => format!("⏸ {}", self.media_title),
+            None if self.finished => format!("⏹ {}", self.media_title),
+            None => self.media_title.clone(),
+        }
+    }
+}
+
+// Seek in progress, see VideoPlayer::seek_with_preview().
+struct SeekPreview {
+    target: gst::ClockTime,
+    resume_playback: bool,
+    last_preview: Instant,
+    commit_source: glib::SourceId,
+}
+
 struct VideoPlayer {
     player: ChannelPlayer,
     ui_context: UIContext,
@@ -62,6 +289,8 @@ struct VideoPlayer {
     pause_action: gio::SimpleAction,
     seek_forward_action: gio::SimpleAction,
     seek_backward_action: gio::SimpleAction,
+    seek_forward_large_action: gio::SimpleAction,
+    seek_backward_large_action: gio::SimpleAction,
     subtitle_action: gio::SimpleAction,
     audio_visualization_action: gio::SimpleAction,
     audio_track_action: gio::SimpleAction,
@@ -74,7 +303,162 @@ struct VideoPlayer {
     dump_pipeline_action: gio::SimpleAction,
     open_sync_window_action: gio::SimpleAction,
     audio_offset_reset_action: gio::SimpleAction,
+    audio_delay_increase_action: gio::SimpleAction,
+    audio_delay_decrease_action: gio::SimpleAction,
     subtitle_offset_reset_action: gio::SimpleAction,
+    subtitle_offset_folder_action: gio::SimpleAction,
+    subtitle_toggle_action: gio::SimpleAction,
+    // External subtitle files loaded for the current media, listed in the
+    // subtitle track menu.
+    external_subtitles: RefCell<Vec<std::string::String>>,
+    // Track selected while the subtitles are hidden.
+    hidden_subtitle_track: RefCell<Option<std::string::String>>,
+    subtitle_delay_increase_action: gio::SimpleAction,
+    subtitle_delay_decrease_action: gio::SimpleAction,
+    // Media and subtitle track last configured, so that choosing another track
+    // for the same media resets the offset.
+    subtitle_track_choice: RefCell<Option<(std::string::String, std::string::String)>>,
+    // Folders for which applying a subtitle offset was declined.
+    declined_offset_folders: RefCell<HashSet<PathBuf>>,
+    resume_mode_action: gio::SimpleAction,
+    live_resume_action: gio::SimpleAction,
+    resume_playback_action: gio::SimpleAction,
+    start_over_action: gio::SimpleAction,
+    subtitle_background_action: gio::SimpleAction,
+    subtitle_background_opacity_action: gio::SimpleAction,
+    subtitle_scale_action: gio::SimpleAction,
+    subtitle_scale_base_action: gio::SimpleAction,
+    subtitle_script_action: gio::SimpleAction,
+    subtitle_font_action: gio::SimpleAction,
+    subtitle_color_action: gio::SimpleAction,
+    subtitle_font_reset_action: gio::SimpleAction,
+    subtitle_encoding_action: gio::SimpleAction,
+    auto_advance_delay_action: gio::SimpleAction,
+    auto_advance_now_action: gio::SimpleAction,
+    playlist_next_action: gio::SimpleAction,
+    playlist_previous_action: gio::SimpleAction,
+    playlist_shuffle_action: gio::SimpleAction,
+    playlist_repeat_action: gio::SimpleAction,
+    playlist_save_action: gio::SimpleAction,
+    quit_at_end_of_playlist_action: gio::SimpleAction,
+    gapless_playback_action: gio::SimpleAction,
+    auto_advance_cancel_action: gio::SimpleAction,
+    auto_advance_source: RefCell<Option<glib::SourceId>>,
+    instant_replay_action: gio::SimpleAction,
+    playback_speed_action: gio::SimpleAction,
+    step_forward_action: gio::SimpleAction,
+    loop_set_a_action: gio::SimpleAction,
+    chapter_action: gio::SimpleAction,
+    next_chapter_action: gio::SimpleAction,
+    previous_chapter_action: gio::SimpleAction,
+    loop_set_b_action: gio::SimpleAction,
+    loop_clear_action: gio::SimpleAction,
+    step_backward_action: gio::SimpleAction,
+    playback_speed_increase_action: gio::SimpleAction,
+    playback_speed_decrease_action: gio::SimpleAction,
+    toggle_previous_item_action: gio::SimpleAction,
+    forget_media_action: gio::SimpleAction,
+    forget_all_media_action: gio::SimpleAction,
+    export_config_action: gio::SimpleAction,
+    import_config_action: gio::SimpleAction,
+    instant_replay_length_action: gio::SimpleAction,
+    instant_replay_rate_action: gio::SimpleAction,
+    // Rate to restore and timer checking whether the replay caught up.
+    instant_replay: RefCell<Option<(f64, glib::SourceId)>>,
+    numeric_input: RefCell<Option<NumericInput>>,
+    cycle_track_action: gio::SimpleAction,
+    track_cycle: RefCell<Option<TrackCycle>>,
+    force_fullscreen: bool,
+    // Media for which fullscreen was toggled by the user.
+    fullscreen_toggled_uri: RefCell<Option<glib::GString>>,
+    // Media for which tracks were picked by title keywords.
+    keyword_tracks_uri: RefCell<Option<std::string::String>>,
+    subtitle_fetch: RefCell<Option<SubtitleFetch>>,
+    // Where downloaded subtitles are cached, unless incognito.
+    subtitle_cache_directory: Option<PathBuf>,
+    // Where the playlist is saved on quit, unless incognito.
+    session_path: Option<PathBuf>,
+    launched_without_media: bool,
+    // Settings and cache files bundled by the configuration export.
+    config_files: ConfigFiles,
+    pedal_rewind_action: gio::SimpleAction,
+    pedal_resume_action: gio::SimpleAction,
+    pedal_rewind_amount_action: gio::SimpleAction,
+    pedal_auto_pause_action: gio::SimpleAction,
+    snapshot_raw_action: gio::SimpleAction,
+    tone_map_hdr_action: gio::SimpleAction,
+    copy_frame_action: gio::SimpleAction,
+    snapshot_action: gio::SimpleAction,
+    video_rotate_action: gio::SimpleAction,
+    borderless_action: gio::SimpleAction,
+    mirror_output_action: gio::SimpleAction,
+    mirror_output: RefCell<Option<(gtk::Window, MirrorOutput)>>,
+    playlist_sort_action: gio::SimpleAction,
+    carry_tracks_action: gio::SimpleAction,
+    playlist_error_action: gio::SimpleAction,
+    open_multiple_action: gio::SimpleAction,
+    no_args_behavior_action: gio::SimpleAction,
+    skipped_items: RefCell<Vec<std::string::String>>,
+    auto_fullscreen_action: gio::SimpleAction,
+    start_paused_action: gio::SimpleAction,
+    title_shows_state_action: gio::SimpleAction,
+    always_on_top_action: gio::SimpleAction,
+    window_title: RefCell<WindowTitle>,
+    #[cfg(target_os = "macos")]
+    now_playing: Option<NowPlaying>,
+    #[cfg(feature = "gamepad")]
+    _gamepad: Option<Gamepad>,
+    night_mode_action: gio::SimpleAction,
+    overwrite_mode_action: gio::SimpleAction,
+    seek_preview_action: gio::SimpleAction,
+    seek_preview: RefCell<Option<SeekPreview>>,
+    idle_power_saving_action: gio::SimpleAction,
+    idle_source: RefCell<Option<glib::SourceId>>,
+    show_audio_meter_action: gio::SimpleAction,
+    frame_stats_action: gio::SimpleAction,
+    show_frame_stats_action: gio::SimpleAction,
+    fullscreen_progress_bar_action: gio::SimpleAction,
+    seek_to_percent_action: gio::SimpleAction,
+    seek_to_time_action: gio::SimpleAction,
+    subtitle_languages_action: gio::SimpleAction,
+    chapter_markers_action: gio::SimpleAction,
+    replay_gain_action: gio::SimpleAction,
+    channel_remap_action: gio::SimpleAction,
+    downmix_mono_action: gio::SimpleAction,
+    audio_profile_action: gio::SimpleAction,
+    stopped_video_action: gio::SimpleAction,
+    preserve_pitch_action: gio::SimpleAction,
+    audio_normalize_action: gio::SimpleAction,
+    equalizer_action: gio::SimpleAction,
+    device_change_action: gio::SimpleAction,
+    _audio_device_monitor: Option<AudioDeviceMonitor>,
+    video_sink_action: gio::SimpleAction,
+    color_range_action: gio::SimpleAction,
+    deinterlace_action: gio::SimpleAction,
+    pixel_aspect_ratio_action: gio::SimpleAction,
+    cycle_aspect_ratio_action: gio::SimpleAction,
+    aspect_ratio_action: gio::SimpleAction,
+    zoom_to_fill_action: gio::SimpleAction,
+    video_zoom_in_action: gio::SimpleAction,
+    video_zoom_out_action: gio::SimpleAction,
+    video_zoom_reset_action: gio::SimpleAction,
+    video_color_increase_action: gio::SimpleAction,
+    video_color_decrease_action: gio::SimpleAction,
+    video_color_reset_action: gio::SimpleAction,
+    #[cfg(feature = "clip-export")]
+    clip_start_action: gio::SimpleAction,
+    #[cfg(feature = "clip-export")]
+    clip_end_action: gio::SimpleAction,
+    #[cfg(feature = "clip-export")]
+    export_clip_action: gio::SimpleAction,
+    #[cfg(feature = "clip-export")]
+    clip_range: RefCell<(gst::ClockTime, gst::ClockTime)>,
+    #[cfg(feature = "subtitle-auto-sync")]
+    subtitle_auto_sync_action: gio::SimpleAction,
+    download_first_action: gio::SimpleAction,
+    download_keep_action: gio::SimpleAction,
+    download_directory: Option<PathBuf>,
+    settings: RefCell<Settings>,
     player_receiver: Option<glib::Receiver<PlayerEvent>>,
 }
 
@@ -100,6 +484,15 @@ macro_rules! with_mut_video_player {
 
 impl VideoPlayer {
     pub fn new(gtk_app: gtk::Application, options: &Opt) -> Result<Self, Error> {
+        let project_dirs = ProjectDirs::from("net", "baseart", "Glide");
+
+        let mut settings_file_path = None;
+        if let Some(ref d) = project_dirs {
+            create_dir_all(d.config_dir()).unwrap();
+            settings_file_path = Some(d.config_dir().join("settings.json"));
+        }
+        let settings = Settings::open(settings_file_path);
+
         let fullscreen_action = gio::SimpleAction::new_stateful("fullscreen", None, &false.to_variant());
         gtk_app.add_action(&fullscreen_action);
 
@@ -115,6 +508,12 @@ impl VideoPlayer {
         let seek_backward_action = gio::SimpleAction::new_stateful("seek-backward", None, &false.to_variant());
         gtk_app.add_action(&seek_backward_action);
 
+        let seek_forward_large_action = gio::SimpleAction::new("seek-forward-large", None);
+        gtk_app.add_action(&seek_forward_large_action);
+
+        let seek_backward_large_action = gio::SimpleAction::new("seek-backward-large", None);
+        gtk_app.add_action(&seek_backward_large_action);
+
         let open_media_action = gio::SimpleAction::new("open-media", None);
         gtk_app.add_action(&open_media_action);
 
@@ -160,9 +559,453 @@ impl VideoPlayer {
         let audio_offset_reset_action = gio::SimpleAction::new("audio-offset-reset", None);
         gtk_app.add_action(&audio_offset_reset_action);
 
+        let audio_delay_increase_action = gio::SimpleAction::new("audio-delay-increase", None);
+        gtk_app.add_action(&audio_delay_increase_action);
+
+        let audio_delay_decrease_action = gio::SimpleAction::new("audio-delay-decrease", None);
+        gtk_app.add_action(&audio_delay_decrease_action);
+
         let subtitle_offset_reset_action = gio::SimpleAction::new("subtitle-offset-reset", None);
         gtk_app.add_action(&subtitle_offset_reset_action);
 
+        let subtitle_offset_folder_action = gio::SimpleAction::new("subtitle-offset-folder", None);
+        gtk_app.add_action(&subtitle_offset_folder_action);
+
+        let subtitle_toggle_action = gio::SimpleAction::new_stateful("subtitle-toggle", None, &true.to_variant());
+        gtk_app.add_action(&subtitle_toggle_action);
+
+        let subtitle_delay_increase_action = gio::SimpleAction::new("subtitle-delay-increase", None);
+        gtk_app.add_action(&subtitle_delay_increase_action);
+
+        let subtitle_delay_decrease_action = gio::SimpleAction::new("subtitle-delay-decrease", None);
+        gtk_app.add_action(&subtitle_delay_decrease_action);
+
+        let resume_mode_action = gio::SimpleAction::new_stateful(
+            "resume-mode",
+            glib::VariantTy::new("s").ok(),
+            &settings.resume_mode.name().to_variant(),
+        );
+        gtk_app.add_action(&resume_mode_action);
+
+        let live_resume_action = gio::SimpleAction::new_stateful(
+            "live-resume",
+            glib::VariantTy::new("s").ok(),
+            &settings.live_resume.name().to_variant(),
+        );
+        gtk_app.add_action(&live_resume_action);
+
+        let resume_playback_action = gio::SimpleAction::new("resume-playback", None);
+        gtk_app.add_action(&resume_playback_action);
+
+        let start_over_action = gio::SimpleAction::new("start-over", None);
+        gtk_app.add_action(&start_over_action);
+
+        let subtitle_background_action = gio::SimpleAction::new_stateful(
+            "subtitle-background",
+            glib::VariantTy::new("s").ok(),
+            &settings.subtitle_background.name().to_variant(),
+        );
+        gtk_app.add_action(&subtitle_background_action);
+
+        let subtitle_background_opacity_action = gio::SimpleAction::new_stateful(
+            "subtitle-background-opacity",
+            glib::VariantTy::new("d").ok(),
+            &settings.subtitle_background_opacity.to_variant(),
+        );
+        gtk_app.add_action(&subtitle_background_opacity_action);
+
+        let subtitle_scale_action = gio::SimpleAction::new_stateful(
+            "subtitle-scale",
+            glib::VariantTy::new("d").ok(),
+            &settings.subtitle_scale.to_variant(),
+        );
+        gtk_app.add_action(&subtitle_scale_action);
+
+        let subtitle_scale_base_action = gio::SimpleAction::new_stateful(
+            "subtitle-scale-base",
+            glib::VariantTy::new("s").ok(),
+            &settings.subtitle_scale_base.name().to_variant(),
+        );
+        gtk_app.add_action(&subtitle_scale_base_action);
+
+        let subtitle_script_action = gio::SimpleAction::new_stateful(
+            "subtitle-script",
+            glib::VariantTy::new("s").ok(),
+            &settings.subtitle_script.name().to_variant(),
+        );
+        gtk_app.add_action(&subtitle_script_action);
+
+        let subtitle_font_action = gio::SimpleAction::new("subtitle-font", None);
+        gtk_app.add_action(&subtitle_font_action);
+
+        let subtitle_color_action = gio::SimpleAction::new("subtitle-color", None);
+        gtk_app.add_action(&subtitle_color_action);
+
+        let subtitle_font_reset_action = gio::SimpleAction::new("subtitle-font-reset", None);
+        gtk_app.add_action(&subtitle_font_reset_action);
+
+        let subtitle_encoding_action = gio::SimpleAction::new_stateful(
+            "subtitle-encoding",
+            glib::VariantTy::new("s").ok(),
+            &"auto".to_variant(),
+        );
+        gtk_app.add_action(&subtitle_encoding_action);
+
+        let auto_advance_delay_action = gio::SimpleAction::new_stateful(
+            "auto-advance-delay",
+            glib::VariantTy::new("u").ok(),
+            &settings.auto_advance_delay.to_variant(),
+        );
+        gtk_app.add_action(&auto_advance_delay_action);
+
+        let auto_advance_now_action = gio::SimpleAction::new("auto-advance-now", None);
+        gtk_app.add_action(&auto_advance_now_action);
+
+        let auto_advance_cancel_action = gio::SimpleAction::new("auto-advance-cancel", None);
+        gtk_app.add_action(&auto_advance_cancel_action);
+
+        let playlist_next_action = gio::SimpleAction::new("playlist-next", None);
+        playlist_next_action.set_enabled(false);
+        gtk_app.add_action(&playlist_next_action);
+        let playlist_previous_action = gio::SimpleAction::new("playlist-previous", None);
+        playlist_previous_action.set_enabled(false);
+        gtk_app.add_action(&playlist_previous_action);
+        let playlist_shuffle_action =
+            gio::SimpleAction::new_stateful("playlist-shuffle", None, &settings.playlist_shuffle.to_variant());
+        gtk_app.add_action(&playlist_shuffle_action);
+        let playlist_repeat_action =
+            gio::SimpleAction::new_stateful("playlist-repeat", None, &settings.playlist_repeat.name().to_variant());
+        gtk_app.add_action(&playlist_repeat_action);
+        let playlist_save_action = gio::SimpleAction::new("playlist-save", None);
+        gtk_app.add_action(&playlist_save_action);
+        let quit_at_end_of_playlist_action = gio::SimpleAction::new_stateful(
+            "quit-at-end-of-playlist",
+            None,
+            &settings.quit_at_end_of_playlist.to_variant(),
+        );
+        gtk_app.add_action(&quit_at_end_of_playlist_action);
+        let gapless_playback_action =
+            gio::SimpleAction::new_stateful("gapless-playback", None, &settings.gapless_playback.to_variant());
+        gtk_app.add_action(&gapless_playback_action);
+
+        let pedal_rewind_action = gio::SimpleAction::new("pedal-rewind", None);
+        gtk_app.add_action(&pedal_rewind_action);
+
+        let pedal_resume_action = gio::SimpleAction::new("pedal-resume", None);
+        gtk_app.add_action(&pedal_resume_action);
+
+        let pedal_rewind_amount_action = gio::SimpleAction::new_stateful(
+            "pedal-rewind-amount",
+            glib::VariantTy::new("u").ok(),
+            &settings.pedal_rewind_seconds.to_variant(),
+        );
+        gtk_app.add_action(&pedal_rewind_amount_action);
+
+        let instant_replay_action = gio::SimpleAction::new("instant-replay", None);
+        gtk_app.add_action(&instant_replay_action);
+
+        let playback_speed_action = gio::SimpleAction::new_stateful(
+            "playback-speed",
+            glib::VariantTy::new("s").ok(),
+            &playback_rate_name(1.0).to_variant(),
+        );
+        gtk_app.add_action(&playback_speed_action);
+
+        let chapter_action = gio::SimpleAction::new("chapter", glib::VariantTy::new("t").ok());
+        gtk_app.add_action(&chapter_action);
+
+        let next_chapter_action = gio::SimpleAction::new("next-chapter", None);
+        next_chapter_action.set_enabled(false);
+        gtk_app.add_action(&next_chapter_action);
+
+        let previous_chapter_action = gio::SimpleAction::new("previous-chapter", None);
+        previous_chapter_action.set_enabled(false);
+        gtk_app.add_action(&previous_chapter_action);
+
+        let loop_set_a_action = gio::SimpleAction::new("loop-set-a", None);
+        gtk_app.add_action(&loop_set_a_action);
+
+        let loop_set_b_action = gio::SimpleAction::new("loop-set-b", None);
+        gtk_app.add_action(&loop_set_b_action);
+
+        let loop_clear_action = gio::SimpleAction::new("loop-clear", None);
+        gtk_app.add_action(&loop_clear_action);
+
+        let step_forward_action = gio::SimpleAction::new("step-forward", None);
+        gtk_app.add_action(&step_forward_action);
+
+        let step_backward_action = gio::SimpleAction::new("step-backward", None);
+        gtk_app.add_action(&step_backward_action);
+
+        let playback_speed_increase_action = gio::SimpleAction::new("playback-speed-increase", None);
+        gtk_app.add_action(&playback_speed_increase_action);
+
+        let playback_speed_decrease_action = gio::SimpleAction::new("playback-speed-decrease", None);
+        gtk_app.add_action(&playback_speed_decrease_action);
+
+        let toggle_previous_item_action = gio::SimpleAction::new("toggle-previous-item", None);
+        gtk_app.add_action(&toggle_previous_item_action);
+        if !settings.toggle_previous_item_key.is_empty() {
+            gtk_app.set_accels_for_action(
+                "app.toggle-previous-item",
+                &[settings.toggle_previous_item_key.as_str()],
+            );
+        }
+
+        let forget_media_action = gio::SimpleAction::new("forget-media", None);
+        gtk_app.add_action(&forget_media_action);
+
+        let forget_all_media_action = gio::SimpleAction::new("forget-all-media", None);
+        gtk_app.add_action(&forget_all_media_action);
+
+        let export_config_action = gio::SimpleAction::new("export-config", None);
+        gtk_app.add_action(&export_config_action);
+
+        let import_config_action = gio::SimpleAction::new("import-config", None);
+        gtk_app.add_action(&import_config_action);
+
+        let cycle_track_action = gio::SimpleAction::new("cycle-track", glib::VariantTy::new("s").ok());
+        gtk_app.add_action(&cycle_track_action);
+
+        let instant_replay_length_action = gio::SimpleAction::new_stateful(
+            "instant-replay-length",
+            glib::VariantTy::new("u").ok(),
+            &settings.instant_replay_seconds.to_variant(),
+        );
+        gtk_app.add_action(&instant_replay_length_action);
+
+        let instant_replay_rate_action = gio::SimpleAction::new_stateful(
+            "instant-replay-rate",
+            glib::VariantTy::new("d").ok(),
+            &settings.instant_replay_rate.to_variant(),
+        );
+        gtk_app.add_action(&instant_replay_rate_action);
+
+        let pedal_auto_pause_action =
+            gio::SimpleAction::new_stateful("pedal-auto-pause", None, &settings.pedal_auto_pause.to_variant());
+        gtk_app.add_action(&pedal_auto_pause_action);
+
+        let snapshot_raw_action =
+            gio::SimpleAction::new_stateful("snapshot-raw", None, &settings.snapshot_raw.to_variant());
+        gtk_app.add_action(&snapshot_raw_action);
+
+        let tone_map_hdr_action =
+            gio::SimpleAction::new_stateful("tone-map-hdr", None, &settings.tone_map_hdr.to_variant());
+        gtk_app.add_action(&tone_map_hdr_action);
+
+        let borderless_action =
+            gio::SimpleAction::new_stateful("toggle-borderless", None, &(!settings.window_decorations).to_variant());
+        gtk_app.add_action(&borderless_action);
+
+        let copy_frame_action = gio::SimpleAction::new("copy-frame", None);
+        copy_frame_action.set_enabled(false);
+        gtk_app.add_action(&copy_frame_action);
+
+        let snapshot_action = gio::SimpleAction::new("snapshot", None);
+        snapshot_action.set_enabled(false);
+        gtk_app.add_action(&snapshot_action);
+
+        let video_rotate_action = gio::SimpleAction::new("video-rotate", None);
+        video_rotate_action.set_enabled(false);
+        gtk_app.add_action(&video_rotate_action);
+
+        let mirror_output_action = gio::SimpleAction::new_stateful("mirror-output", None, &false.to_variant());
+        gtk_app.add_action(&mirror_output_action);
+
+        let playlist_sort_action = gio::SimpleAction::new_stateful(
+            "playlist-sort",
+            glib::VariantTy::new("s").ok(),
+            &settings.playlist_sort.name().to_variant(),
+        );
+        gtk_app.add_action(&playlist_sort_action);
+
+        let carry_tracks_action = gio::SimpleAction::new_stateful(
+            "carry-tracks",
+            None,
+            &settings.carry_tracks_across_playlist.to_variant(),
+        );
+        gtk_app.add_action(&carry_tracks_action);
+
+        let playlist_error_action = gio::SimpleAction::new_stateful(
+            "playlist-error-action",
+            glib::VariantTy::new("s").ok(),
+            &settings.playlist_error_action.name().to_variant(),
+        );
+        gtk_app.add_action(&playlist_error_action);
+
+        let open_multiple_action = gio::SimpleAction::new_stateful(
+            "open-multiple",
+            glib::VariantTy::new("s").ok(),
+            &settings.open_multiple_action.name().to_variant(),
+        );
+        gtk_app.add_action(&open_multiple_action);
+
+        let no_args_behavior_action = gio::SimpleAction::new_stateful(
+            "no-args-behavior",
+            glib::VariantTy::new("s").ok(),
+            &settings.no_args_behavior.name().to_variant(),
+        );
+        gtk_app.add_action(&no_args_behavior_action);
+
+        let auto_fullscreen_action =
+            gio::SimpleAction::new_stateful("auto-fullscreen", None, &settings.auto_fullscreen.to_variant());
+        gtk_app.add_action(&auto_fullscreen_action);
+
+        let night_mode_action = gio::SimpleAction::new_stateful("night-mode", None, &settings.night_mode.to_variant());
+        gtk_app.add_action(&night_mode_action);
+
+        let start_paused_action =
+            gio::SimpleAction::new_stateful("start-paused", None, &settings.start_paused.to_variant());
+        gtk_app.add_action(&start_paused_action);
+
+        let title_shows_state_action =
+            gio::SimpleAction::new_stateful("title-shows-state", None, &settings.title_shows_state.to_variant());
+        gtk_app.add_action(&title_shows_state_action);
+
+        let always_on_top_action =
+            gio::SimpleAction::new_stateful("always-on-top", None, &settings.always_on_top.to_variant());
+        gtk_app.add_action(&always_on_top_action);
+
+        let overwrite_mode_action = gio::SimpleAction::new_stateful(
+            "overwrite-mode",
+            glib::VariantTy::new("s").ok(),
+            &settings.overwrite_mode.name().to_variant(),
+        );
+        gtk_app.add_action(&overwrite_mode_action);
+
+        let idle_power_saving_action =
+            gio::SimpleAction::new_stateful("idle-power-saving", None, &settings.idle_power_saving.to_variant());
+        gtk_app.add_action(&idle_power_saving_action);
+
+        let seek_preview_action =
+            gio::SimpleAction::new_stateful("seek-preview", None, &settings.seek_preview.to_variant());
+        gtk_app.add_action(&seek_preview_action);
+
+        let replay_gain_action = gio::SimpleAction::new_stateful(
+            "replay-gain",
+            glib::VariantTy::new("s").ok(),
+            &settings.replaygain_mode.name().to_variant(),
+        );
+        gtk_app.add_action(&replay_gain_action);
+
+        let channel_remap_action = gio::SimpleAction::new_stateful(
+            "channel-remap",
+            glib::VariantTy::new("s").ok(),
+            &settings.channel_remap.name().to_variant(),
+        );
+        gtk_app.add_action(&channel_remap_action);
+
+        // Shortcut to the mono channel remapping, for listening on a single
+        // earbud.
+        let downmix_mono_action = gio::SimpleAction::new_stateful(
+            "audio-downmix-mono",
+            None,
+            &(settings.channel_remap == ChannelRemap::Mono).to_variant(),
+        );
+        gtk_app.add_action(&downmix_mono_action);
+
+        let audio_profile_action = gio::SimpleAction::new_stateful(
+            "audio-profile",
+            glib::VariantTy::new("s").ok(),
+            &settings.audio_profile.clone().unwrap_or_default().to_variant(),
+        );
+        gtk_app.add_action(&audio_profile_action);
+
+        let stopped_video_action = gio::SimpleAction::new_stateful(
+            "stopped-video",
+            glib::VariantTy::new("s").ok(),
+            &settings.stopped_video.name().to_variant(),
+        );
+        gtk_app.add_action(&stopped_video_action);
+
+        let preserve_pitch_action =
+            gio::SimpleAction::new_stateful("preserve-pitch", None, &settings.preserve_pitch.to_variant());
+        gtk_app.add_action(&preserve_pitch_action);
+
+        let audio_normalize_action =
+            gio::SimpleAction::new_stateful("audio-normalize", None, &settings.audio_normalization.to_variant());
+        gtk_app.add_action(&audio_normalize_action);
+
+        let equalizer_action = gio::SimpleAction::new_stateful(
+            "equalizer",
+            glib::VariantTy::new("s").ok(),
+            &equalizer_name(settings.equalizer.as_deref()).to_variant(),
+        );
+        gtk_app.add_action(&equalizer_action);
+
+        let device_change_action = gio::SimpleAction::new_stateful(
+            "device-change",
+            glib::VariantTy::new("s").ok(),
+            &settings.pause_on_device_change.name().to_variant(),
+        );
+        gtk_app.add_action(&device_change_action);
+
+        let audio_device_monitor = AudioDeviceMonitor::new(|name| {
+            with_video_player!(video_player {
+                video_player.audio_device_removed(name);
+            });
+        });
+
+        let show_audio_meter_action =
+            gio::SimpleAction::new_stateful("show-audio-meter", None, &settings.show_audio_meter.to_variant());
+        gtk_app.add_action(&show_audio_meter_action);
+
+        let frame_stats_action = gio::SimpleAction::new("frame-stats", None);
+        gtk_app.add_action(&frame_stats_action);
+
+        let show_frame_stats_action =
+            gio::SimpleAction::new_stateful("show-frame-stats", None, &settings.show_frame_stats.to_variant());
+        gtk_app.add_action(&show_frame_stats_action);
+
+        let fullscreen_progress_bar_action = gio::SimpleAction::new_stateful(
+            "fullscreen-progress-bar",
+            None,
+            &settings.fullscreen_progress_bar.to_variant(),
+        );
+        gtk_app.add_action(&fullscreen_progress_bar_action);
+
+        let seek_to_percent_action = gio::SimpleAction::new("seek-to-percent", glib::VariantTy::new("d").ok());
+        gtk_app.add_action(&seek_to_percent_action);
+
+        // Without a time, it is asked for.
+        let seek_to_time_action = gio::SimpleAction::new("seek-to-time", glib::VariantTy::new("s").ok());
+        gtk_app.add_action(&seek_to_time_action);
+
+        let subtitle_languages_action = gio::SimpleAction::new("subtitle-languages", glib::VariantTy::new("s").ok());
+        gtk_app.add_action(&subtitle_languages_action);
+
+        let chapter_markers_action =
+            gio::SimpleAction::new_stateful("chapter-markers", None, &settings.chapter_markers.to_variant());
+        gtk_app.add_action(&chapter_markers_action);
+
+        #[cfg(feature = "clip-export")]
+        let clip_start_action = gio::SimpleAction::new("clip-start", None);
+        #[cfg(feature = "clip-export")]
+        gtk_app.add_action(&clip_start_action);
+
+        #[cfg(feature = "clip-export")]
+        let clip_end_action = gio::SimpleAction::new("clip-end", None);
+        #[cfg(feature = "clip-export")]
+        gtk_app.add_action(&clip_end_action);
+
+        #[cfg(feature = "clip-export")]
+        let export_clip_action = gio::SimpleAction::new("export-clip", None);
+        #[cfg(feature = "clip-export")]
+        gtk_app.add_action(&export_clip_action);
+
+        #[cfg(feature = "subtitle-auto-sync")]
+        let subtitle_auto_sync_action = gio::SimpleAction::new("subtitle-auto-sync", None);
+        #[cfg(feature = "subtitle-auto-sync")]
+        gtk_app.add_action(&subtitle_auto_sync_action);
+
+        let download_first_action =
+            gio::SimpleAction::new_stateful("download-first", None, &settings.download_first.to_variant());
+        gtk_app.add_action(&download_first_action);
+
+        let download_keep_action =
+            gio::SimpleAction::new_stateful("download-keep", None, &settings.download_keep.to_variant());
+        gtk_app.add_action(&download_keep_action);
+
         let about = gio::SimpleAction::new("about", None);
         about.connect_activate(move |_, _| {
             with_video_player!(video_player {
@@ -192,21 +1035,192 @@ impl VideoPlayer {
             });
         });
 
-        let ui_context = UIContext::new(gtk_app);
+        let ui_context = UIContext::new(gtk_app.clone());
 
         let (player_sender, player_receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
 
         let mut cache_file_path = None;
+        let mut subtitle_cache_directory = None;
+        let mut session_path = None;
         if !options.incognito {
-            if let Some(d) = ProjectDirs::from("net", "baseart", "Glide") {
+            if let Some(ref d) = project_dirs {
                 create_dir_all(d.cache_dir()).unwrap();
                 cache_file_path = Some(d.cache_dir().join("media-cache.json"));
+                subtitle_cache_directory = Some(d.cache_dir().join("subtitles"));
+                session_path = Some(d.cache_dir().join("session.json"));
+            }
+        }
+
+        let mut config_files = vec![];
+        if let Some(ref d) = project_dirs {
+            config_files.push(("settings.json", d.config_dir().join("settings.json")));
+            config_files.push(("elements.toml", d.config_dir().join("elements.toml")));
+            config_files.push(("media-cache.json", d.cache_dir().join("media-cache.json")));
+            config_files.push(("session.json", d.cache_dir().join("session.json")));
+        }
+
+        let mut element_properties = ElementProperties::default();
+        if let Some(ref d) = project_dirs {
+            let path = d.config_dir().join("elements.toml");
+            if path.exists() {
+                match ElementProperties::load(&path) {
+                    Ok(properties) => element_properties = properties,
+                    Err(e) => eprintln!("Unable to read {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        let player = ChannelPlayer::new(
+            player_sender,
+            cache_file_path,
+            element_properties,
+            settings.video_sink.as_deref(),
+        )?;
+        player.set_subtitle_style(SubtitleStyle {
+            background: settings.subtitle_background,
+            background_opacity: settings.subtitle_background_opacity,
+            scale: settings.subtitle_scale,
+            scale_base: settings.subtitle_scale_base,
+            script: settings.subtitle_script,
+            font: settings.subtitle_font.clone(),
+            color: settings.subtitle_color.as_deref().and_then(parse_color),
+        });
+        player.set_auto_advance(settings.auto_advance_delay == 0);
+        player.set_carry_tracks(settings.carry_tracks_across_playlist);
+        player.set_shuffle(settings.playlist_shuffle);
+        player.set_repeat_mode(settings.playlist_repeat);
+        player.set_gapless(settings.gapless_playback);
+        player.set_resume_mode(settings.resume_mode);
+        player.set_live_resume(settings.live_resume);
+        player.set_start_paused(settings.start_paused || options.paused);
+        player.set_rescan_missing_subtitles(settings.rescan_missing_subtitles);
+        player.set_audio_levels_enabled(settings.show_audio_meter);
+        player.set_replay_gain_mode(settings.replaygain_mode);
+        let channel_matrix = settings.channel_remap.matrix(&settings.channel_matrix);
+        if !player.set_channel_matrix(channel_matrix.as_deref()) {
+            eprintln!("Invalid channel matrix, the audio channels are left untouched");
+        }
+        let profile_device = settings
+            .audio_profile
+            .as_deref()
+            .and_then(|name| settings.find_audio_profile(name))
+            .map(|profile| profile.device.as_str())
+            .filter(|device| !device.is_empty());
+        if let Some(device) = profile_device {
+            if let Err(e) = player.set_audio_device(Some(device)) {
+                eprintln!("{}, using the default audio output", e);
             }
         }
+        let profile_names = settings
+            .audio_profiles
+            .iter()
+            .map(|profile| profile.name.as_str())
+            .collect::<Vec<_>>();
+        ui_context.set_audio_profiles(&profile_names);
+        player.set_preserve_pitch(settings.preserve_pitch);
+        player.set_audio_normalization(settings.audio_normalization);
+        player.set_default_volume(settings.default_volume);
+        player.set_equalizer(settings.equalizer.as_deref());
+        ui_context.show_audio_meter(settings.show_audio_meter);
+        ui_context.set_fullscreen_progress_bar(
+            settings.fullscreen_progress_bar,
+            settings.fullscreen_progress_bar_height,
+            &settings.fullscreen_progress_bar_color,
+        );
+        ui_context.set_chapter_marks_enabled(settings.chapter_markers);
+        ui_context.set_seek_offsets(settings.seek_backward_seconds, settings.seek_forward_seconds);
+        ui_context.set_decorated(settings.window_decorations);
+        player.set_skip_unplayable(
+            settings.playlist_error_action != PlaylistErrorAction::Stop,
+            settings.playlist_item_retries,
+        );
+
+        // The preferred sink might not be usable anymore.
+        let video_sink_action = gio::SimpleAction::new_stateful(
+            "video-sink",
+            glib::VariantTy::new("s").ok(),
+            &player.video_sink_name().to_variant(),
+        );
+        gtk_app.add_action(&video_sink_action);
+        ui_context.set_video_sinks(&available_video_sinks());
+
+        player.set_color_range(settings.color_range);
+        player.set_pixel_aspect_ratio(settings.pixel_aspect_ratio.as_deref().and_then(parse_ratio));
+        player.set_hdr_tone_mapping(settings.tone_map_hdr);
+        let pixel_aspect_ratio_action = gio::SimpleAction::new_stateful(
+            "pixel-aspect-ratio",
+            glib::VariantTy::new("s").ok(),
+            &settings.pixel_aspect_ratio.as_deref().unwrap_or("auto").to_variant(),
+        );
+        gtk_app.add_action(&pixel_aspect_ratio_action);
+        let cycle_aspect_ratio_action = gio::SimpleAction::new("cycle-aspect-ratio", None);
+        gtk_app.add_action(&cycle_aspect_ratio_action);
+        let aspect_ratio_action =
+            gio::SimpleAction::new_stateful("aspect-ratio", glib::VariantTy::new("s").ok(), &"auto".to_variant());
+        gtk_app.add_action(&aspect_ratio_action);
+        let zoom_to_fill_action = gio::SimpleAction::new_stateful("zoom-to-fill", None, &false.to_variant());
+        gtk_app.add_action(&zoom_to_fill_action);
+        if !settings.zoom_to_fill_key.is_empty() {
+            gtk_app.set_accels_for_action("app.zoom-to-fill", &[settings.zoom_to_fill_key.as_str()]);
+        }
+        let video_zoom_in_action = gio::SimpleAction::new("video-zoom-in", None);
+        gtk_app.add_action(&video_zoom_in_action);
+        let video_zoom_out_action = gio::SimpleAction::new("video-zoom-out", None);
+        gtk_app.add_action(&video_zoom_out_action);
+        let video_zoom_reset_action = gio::SimpleAction::new("video-zoom-reset", None);
+        gtk_app.add_action(&video_zoom_reset_action);
+        let video_color_increase_action =
+            gio::SimpleAction::new("video-color-increase", glib::VariantTy::new("s").ok());
+        gtk_app.add_action(&video_color_increase_action);
+        let video_color_decrease_action =
+            gio::SimpleAction::new("video-color-decrease", glib::VariantTy::new("s").ok());
+        gtk_app.add_action(&video_color_decrease_action);
+        let video_color_reset_action = gio::SimpleAction::new("video-color-reset", None);
+        gtk_app.add_action(&video_color_reset_action);
+        let color_range_action = gio::SimpleAction::new_stateful(
+            "color-range",
+            glib::VariantTy::new("s").ok(),
+            &settings.color_range.name().to_variant(),
+        );
+        gtk_app.add_action(&color_range_action);
+        player.set_deinterlace(settings.deinterlace);
+        let deinterlace_action = gio::SimpleAction::new_stateful(
+            "deinterlace",
+            glib::VariantTy::new("s").ok(),
+            &settings.deinterlace.name().to_variant(),
+        );
+        gtk_app.add_action(&deinterlace_action);
+
+        let download_directory = project_dirs.as_ref().map(|d| d.cache_dir().join("downloads"));
+
+        #[cfg(target_os = "macos")]
+        let now_playing = if settings.media_keys {
+            let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+            receiver.attach(None, |command| {
+                with_video_player!(video_player {
+                    video_player.remote_command(command);
+                });
+                glib::Continue(true)
+            });
+            Some(NowPlaying::new(sender))
+        } else {
+            None
+        };
 
-        let player = ChannelPlayer::new(player_sender, cache_file_path)?;
+        #[cfg(feature = "gamepad")]
+        let gamepad = if settings.gamepad {
+            let bindings = if settings.gamepad_bindings.is_empty() {
+                gamepad::default_bindings()
+            } else {
+                settings.gamepad_bindings.clone()
+            };
+            let app = gtk_app.clone();
+            Gamepad::new(bindings, move |event| gamepad_event(&app, event))
+        } else {
+            None
+        };
 
-        Ok(Self {
+        let video_player = Self {
             player,
             ui_context,
             fullscreen_action,
@@ -214,6 +1228,8 @@ impl VideoPlayer {
             pause_action,
             seek_forward_action,
             seek_backward_action,
+            seek_forward_large_action,
+            seek_backward_large_action,
             subtitle_action,
             audio_visualization_action,
             audio_track_action,
@@ -226,13 +1242,162 @@ impl VideoPlayer {
             dump_pipeline_action,
             open_sync_window_action,
             audio_offset_reset_action,
+            audio_delay_increase_action,
+            audio_delay_decrease_action,
             subtitle_offset_reset_action,
+            subtitle_toggle_action,
+            external_subtitles: RefCell::new(vec![]),
+            hidden_subtitle_track: RefCell::new(None),
+            subtitle_delay_increase_action,
+            subtitle_delay_decrease_action,
+            subtitle_track_choice: RefCell::new(None),
+            subtitle_offset_folder_action,
+            declined_offset_folders: RefCell::new(HashSet::new()),
+            resume_mode_action,
+            live_resume_action,
+            resume_playback_action,
+            start_over_action,
+            subtitle_background_action,
+            subtitle_background_opacity_action,
+            subtitle_scale_action,
+            subtitle_scale_base_action,
+            subtitle_script_action,
+            subtitle_font_action,
+            subtitle_color_action,
+            subtitle_font_reset_action,
+            subtitle_encoding_action,
+            auto_advance_delay_action,
+            auto_advance_now_action,
+            playlist_next_action,
+            playlist_previous_action,
+            playlist_shuffle_action,
+            playlist_repeat_action,
+            playlist_save_action,
+            quit_at_end_of_playlist_action,
+            gapless_playback_action,
+            auto_advance_cancel_action,
+            auto_advance_source: RefCell::new(None),
+            instant_replay_action,
+            playback_speed_action,
+            step_forward_action,
+            loop_set_a_action,
+            chapter_action,
+            next_chapter_action,
+            previous_chapter_action,
+            loop_set_b_action,
+            loop_clear_action,
+            step_backward_action,
+            playback_speed_increase_action,
+            playback_speed_decrease_action,
+            toggle_previous_item_action,
+            forget_media_action,
+            forget_all_media_action,
+            export_config_action,
+            import_config_action,
+            instant_replay_length_action,
+            instant_replay_rate_action,
+            instant_replay: RefCell::new(None),
+            numeric_input: RefCell::new(None),
+            cycle_track_action,
+            track_cycle: RefCell::new(None),
+            force_fullscreen: options.fullscreen,
+            fullscreen_toggled_uri: RefCell::new(None),
+            keyword_tracks_uri: RefCell::new(None),
+            subtitle_fetch: RefCell::new(None),
+            subtitle_cache_directory,
+            session_path,
+            launched_without_media: options.files.is_empty(),
+            config_files,
+            pedal_rewind_action,
+            pedal_resume_action,
+            pedal_rewind_amount_action,
+            pedal_auto_pause_action,
+            snapshot_raw_action,
+            tone_map_hdr_action,
+            copy_frame_action,
+            snapshot_action,
+            video_rotate_action,
+            borderless_action,
+            mirror_output_action,
+            mirror_output: RefCell::new(None),
+            playlist_sort_action,
+            carry_tracks_action,
+            playlist_error_action,
+            open_multiple_action,
+            no_args_behavior_action,
+            skipped_items: RefCell::new(vec![]),
+            auto_fullscreen_action,
+            start_paused_action,
+            title_shows_state_action,
+            always_on_top_action,
+            window_title: RefCell::new(WindowTitle::default()),
+            #[cfg(target_os = "macos")]
+            now_playing,
+            #[cfg(feature = "gamepad")]
+            _gamepad: gamepad,
+            night_mode_action,
+            overwrite_mode_action,
+            seek_preview_action,
+            seek_preview: RefCell::new(None),
+            idle_power_saving_action,
+            idle_source: RefCell::new(None),
+            show_audio_meter_action,
+            frame_stats_action,
+            show_frame_stats_action,
+            fullscreen_progress_bar_action,
+            seek_to_percent_action,
+            seek_to_time_action,
+            subtitle_languages_action,
+            chapter_markers_action,
+            replay_gain_action,
+            channel_remap_action,
+            downmix_mono_action,
+            audio_profile_action,
+            stopped_video_action,
+            preserve_pitch_action,
+            audio_normalize_action,
+            equalizer_action,
+            device_change_action,
+            _audio_device_monitor: audio_device_monitor,
+            video_sink_action,
+            color_range_action,
+            deinterlace_action,
+            pixel_aspect_ratio_action,
+            cycle_aspect_ratio_action,
+            aspect_ratio_action,
+            zoom_to_fill_action,
+            video_zoom_in_action,
+            video_zoom_out_action,
+            video_zoom_reset_action,
+            video_color_increase_action,
+            video_color_decrease_action,
+            video_color_reset_action,
+            #[cfg(feature = "clip-export")]
+            clip_start_action,
+            #[cfg(feature = "clip-export")]
+            clip_end_action,
+            #[cfg(feature = "clip-export")]
+            export_clip_action,
+            #[cfg(feature = "clip-export")]
+            clip_range: RefCell::new((gst::ClockTime::none(), gst::ClockTime::none())),
+            #[cfg(feature = "subtitle-auto-sync")]
+            subtitle_auto_sync_action,
+            download_first_action,
+            download_keep_action,
+            download_directory,
+            settings: RefCell::new(settings),
             player_receiver: Some(player_receiver),
-        })
+        };
+        video_player.update_download_config();
+        Ok(video_player)
     }
 
     pub fn quit(&self) {
         self.player.write_last_known_media_position();
+        self.write_session();
+        if !self.settings.borrow().download_keep {
+            self.player.clear_downloads();
+        }
         self.leave_fullscreen();
         self.ui_context.stop();
         println!("bye!")
@@ -266,13 +1431,25 @@ impl VideoPlayer {
 
         self.seek_forward_action.connect_change_state(|_, _| {
             with_video_player!(video_player {
-                video_player.player.seek(&SeekDirection::Forward(constants::SEEK_FORWARD_OFFSET));
+                video_player.seek(video_player.seek_direction(true, false));
+            });
+        });
+
+        self.seek_forward_large_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.seek(video_player.seek_direction(true, true));
+            });
+        });
+
+        self.seek_backward_large_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.seek(video_player.seek_direction(false, true));
             });
         });
 
         self.seek_backward_action.connect_change_state(|_, _| {
             with_video_player!(video_player {
-                video_player.player.seek(&SeekDirection::Backward(constants::SEEK_BACKWARD_OFFSET));
+                video_player.seek(video_player.seek_direction(false, false));
             });
         });
 
@@ -309,6 +1486,7 @@ impl VideoPlayer {
                     }
                     let new_state = !fullscreen;
                     fullscreen_action.set_state(&new_state.to_variant());
+                    *video_player.fullscreen_toggled_uri.borrow_mut() = video_player.player.get_current_uri();
                 });
             }
         });
@@ -316,6 +1494,7 @@ impl VideoPlayer {
         self.restore_action.connect_change_state(|_, _| {
             with_video_player!(video_player {
                 video_player.leave_fullscreen();
+                *video_player.fullscreen_toggled_uri.borrow_mut() = video_player.player.get_current_uri();
             });
         });
 
@@ -342,10 +1521,7 @@ impl VideoPlayer {
 
         self.audio_track_action.connect_change_state(|action, value| {
             if let Some(val) = value {
-                if let Some(idx) = val.get::<std::string::String>() {
-                    let (_prefix, idx) = idx.split_at(6);
-                    let idx = idx.parse::<i32>().unwrap();
-
+                if let Some(idx) = val.get::<std::string::String>().as_deref().and_then(track_index) {
                     with_video_player!(video_player {
                         video_player.player.set_audio_track_index(idx);
                         action.set_state(&val);
@@ -356,12 +1532,12 @@ impl VideoPlayer {
 
         self.video_track_action.connect_change_state(|action, value| {
             if let Some(val) = value {
-                if let Some(idx) = val.get::<std::string::String>() {
-                    let (_prefix, idx) = idx.split_at(6);
-                    let idx = idx.parse::<i32>().unwrap();
-
+                if let Some(idx) = val.get::<std::string::String>().as_deref().and_then(track_index) {
                     with_video_player!(video_player {
                         video_player.player.set_video_track_index(idx);
+                        // Nothing to copy while only the audio plays.
+                        video_player.copy_frame_action.set_enabled(idx > -1);
+                        video_player.snapshot_action.set_enabled(idx > -1);
                         action.set_state(&val);
                     });
                 }
@@ -370,18 +1546,28 @@ impl VideoPlayer {
 
         self.open_media_action.connect_activate(|_, _| {
             with_video_player!(video_player {
-                if let Some(uri) = video_player.ui_context.dialog_result(video_player.player.get_current_uri()) {
-                    println!("loading {}", &uri);
-                    video_player.player.stop();
-                    video_player.player.load_uri(&uri);
+                let uris = video_player.ui_context.dialog_result(video_player.player.get_current_uri());
+                match uris.as_slice() {
+                    [] => {}
+                    [uri] => {
+                        println!("loading {}", uri);
+                        video_player.player.stop();
+                        video_player.player.load_uri(uri);
+                    }
+                    _ => video_player.open_multiple(uris.iter().map(|uri| uri.to_string()).collect()),
                 }
             });
         });
 
         self.open_subtitle_file_action.connect_activate(|_, _| {
             with_video_player!(video_player {
-                if let Some(uri) = video_player.ui_context.dialog_result(video_player.player.get_current_uri()) {
-                    video_player.player.configure_subtitle_track(Some(SubtitleTrack::External(uri)));
+                if let Some(uri) = video_player
+                    .ui_context
+                    .dialog_result(video_player.player.get_current_uri())
+                    .into_iter()
+                    .next()
+                {
+                    video_player.add_external_subtitle(uri);
                 }
                 video_player.refresh_subtitle_track_menu();
             });
@@ -399,173 +1585,2863 @@ impl VideoPlayer {
             })
         });
 
-        self.subtitle_offset_reset_action.connect_activate(|_, _| {
+        self.audio_delay_increase_action.connect_activate(|_, _| {
             with_video_player!(video_player {
-                video_player.player.set_subtitle_offset(0);
+                video_player.step_audio_delay(AUDIO_DELAY_STEP);
             })
         });
 
-        self.ui_context.set_video_area(self.player.video_area());
+        self.audio_delay_decrease_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.step_audio_delay(-AUDIO_DELAY_STEP);
+            })
+        });
 
-        self.ui_context.set_progress_bar_format_callback(|value, duration| {
-            let position = gst::ClockTime::from_seconds(value as u64);
-            let duration = gst::ClockTime::from_seconds(duration as u64);
-            if duration.is_some() {
-                format!("{:.0} / {:.0}", position, duration)
-            } else {
-                format!("{:.0}", position)
+        self.subtitle_offset_reset_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.subtitle_offset_adjusted(0);
+            })
+        });
+
+        self.subtitle_toggle_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(visible) = val.get::<bool>() {
+                    with_video_player!(video_player {
+                        action.set_state(&val);
+                        video_player.set_subtitles_visible(visible);
+                    });
+                }
             }
         });
 
-        self.ui_context.set_volume_value_changed_callback(|value| {
+        self.subtitle_delay_increase_action.connect_activate(|_, _| {
             with_video_player!(video_player {
-                video_player.player.set_volume(value);
-            });
+                video_player.step_subtitle_delay(SUBTITLE_DELAY_STEP);
+            })
         });
 
-        self.ui_context.set_position_changed_callback(|value| {
+        self.subtitle_delay_decrease_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.step_subtitle_delay(-SUBTITLE_DELAY_STEP);
+            })
+        });
+
+        self.subtitle_offset_folder_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.use_subtitle_offset_for_folder();
+            })
+        });
+
+        self.resume_mode_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    if let Some(mode) = ResumeMode::from_name(&name) {
+                        with_video_player!(video_player {
+                            video_player.settings.borrow_mut().resume_mode = mode;
+                            video_player.save_settings();
+                            video_player.player.set_resume_mode(mode);
+                            action.set_state(&val);
+                        });
+                    }
+                }
+            }
+        });
+
+        self.live_resume_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    if let Some(live_resume) = LiveResume::from_name(&name) {
+                        with_video_player!(video_player {
+                            video_player.settings.borrow_mut().live_resume = live_resume;
+                            video_player.save_settings();
+                            video_player.player.set_live_resume(live_resume);
+                            action.set_state(&val);
+                        });
+                    }
+                }
+            }
+        });
+
+        self.resume_playback_action.connect_activate(|_, _| {
             with_video_player!(video_player {
-                video_player.player.seek_to(gst::ClockTime::from_seconds(value));
+                video_player.ui_context.hide_resume_prompt();
+                video_player.player.answer_resume(true);
             });
         });
 
-        self.ui_context.set_drop_data_callback(|uri| {
+        self.start_over_action.connect_activate(|_, _| {
             with_video_player!(video_player {
-                if let Ok((path, _)) = glib::filename_from_uri(&uri) {
-                    if let Some(extension) = path.extension() {
-                        if constants::SUB_FILE_EXTENSIONS.contains(&extension.to_str().unwrap()) {
-                            video_player.player
-                                .configure_subtitle_track(Some(SubtitleTrack::External(uri.into())));
-                            return;
-                        }
+                video_player.ui_context.hide_resume_prompt();
+                video_player.player.answer_resume(false);
+            });
+        });
+
+        self.subtitle_background_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    if let Some(background) = SubtitleBackground::from_name(&name) {
+                        with_video_player!(video_player {
+                            video_player.settings.borrow_mut().subtitle_background = background;
+                            video_player.update_subtitle_style();
+                            action.set_state(&val);
+                        });
                     }
                 }
-                println!("loading {}", &uri);
-                video_player.player.stop();
-                video_player.player.load_uri(&uri);
+            }
+        });
+
+        self.subtitle_background_opacity_action
+            .connect_change_state(|action, value| {
+                if let Some(val) = value {
+                    if let Some(opacity) = val.get::<f64>() {
+                        with_video_player!(video_player {
+                            video_player.settings.borrow_mut().subtitle_background_opacity = opacity;
+                            video_player.update_subtitle_style();
+                            action.set_state(&val);
+                        });
+                    }
+                }
+            });
+
+        self.subtitle_scale_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(scale) = val.get::<f64>() {
+                    with_video_player!(video_player {
+                        video_player.settings.borrow_mut().subtitle_scale = scale;
+                        video_player.update_subtitle_style();
+                        action.set_state(&val);
+                    });
+                }
+            }
+        });
+
+        self.subtitle_scale_base_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    if let Some(scale_base) = SubtitleScaleBase::from_name(&name) {
+                        with_video_player!(video_player {
+                            video_player.settings.borrow_mut().subtitle_scale_base = scale_base;
+                            video_player.update_subtitle_style();
+                            action.set_state(&val);
+                        });
+                    }
+                }
+            }
+        });
+
+        self.subtitle_script_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    if let Some(script) = SubtitleScript::from_name(&name) {
+                        with_video_player!(video_player {
+                            video_player.settings.borrow_mut().subtitle_script = script;
+                            video_player.update_subtitle_style();
+                            action.set_state(&val);
+                        });
+                    }
+                }
+            }
+        });
+
+        self.subtitle_font_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.choose_subtitle_font();
             })
         });
 
-        self.ui_context.set_audio_offset_entry_updated_callback(|offset| {
+        self.subtitle_color_action.connect_activate(|_, _| {
             with_video_player!(video_player {
-                video_player.player.set_audio_offset(offset);
+                video_player.choose_subtitle_color();
             })
         });
 
-        self.ui_context.set_subtitle_offset_entry_updated_callback(|offset| {
+        self.subtitle_font_reset_action.connect_activate(|_, _| {
             with_video_player!(video_player {
-                video_player.player.set_subtitle_offset(offset);
+                {
+                    let mut settings = video_player.settings.borrow_mut();
+                    settings.subtitle_font = None;
+                    settings.subtitle_color = None;
+                }
+                video_player.update_subtitle_style();
             })
         });
 
-        #[cfg(feature = "self-updater")]
-        match self.check_update() {
-            Ok(o) => {
-                match o {
-                    self_update::Status::UpToDate(_version) => {}
-                    _ => println!("Update succeeded: {}", o),
-                };
+        self.subtitle_encoding_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    with_video_player!(video_player {
+                        video_player.subtitle_encoding_chosen(&name);
+                        action.set_state(&val);
+                    });
+                }
             }
-            Err(e) => eprintln!("Update failed: {}", e),
-        };
+        });
 
-        self.ui_context.start(|| {
+        self.auto_advance_delay_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(delay) = val.get::<u32>() {
+                    with_video_player!(video_player {
+                        video_player.player.set_auto_advance(delay == 0);
+                        video_player.settings.borrow_mut().auto_advance_delay = delay;
+                        video_player.save_settings();
+                        action.set_state(&val);
+                    });
+                }
+            }
+        });
+
+        self.auto_advance_now_action.connect_activate(|_, _| {
             with_video_player!(video_player {
-                video_player.quit();
+                video_player.cancel_auto_advance();
+                video_player.player.next();
             });
         });
-    }
 
-    pub fn dispatch_event(&self, event: PlayerEvent) {
-        match event {
-            PlayerEvent::MediaInfoUpdated => {
-                self.media_info_updated();
+        self.playlist_next_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.cancel_auto_advance();
+                if !video_player.player.next() {
+                    video_player.ui_context.show_osd_message("End of the playlist");
+                }
+            });
+        });
+
+        self.playlist_previous_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.cancel_auto_advance();
+                if !video_player.player.previous() {
+                    video_player.ui_context.show_osd_message("Start of the playlist");
+                }
+            });
+        });
+
+        self.playlist_shuffle_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().playlist_shuffle = enabled;
+                    video_player.save_settings();
+                    video_player.player.set_shuffle(enabled);
+                    let message = if enabled { "Shuffle on" } else { "Shuffle off" };
+                    video_player.ui_context.show_osd_message(message);
+                });
+                action.set_state(&enabled.to_variant());
             }
-            PlayerEvent::PositionUpdated => {
-                self.position_updated();
+        });
+
+        self.playlist_repeat_action.connect_activate(|action, _| {
+            let mode = action
+                .get_state()
+                .and_then(|state| state.get::<std::string::String>())
+                .and_then(|name| RepeatMode::from_name(&name))
+                .unwrap_or(RepeatMode::Off)
+                .next();
+            with_video_player!(video_player {
+                video_player.settings.borrow_mut().playlist_repeat = mode;
+                video_player.save_settings();
+                video_player.player.set_repeat_mode(mode);
+                video_player
+                    .ui_context
+                    .show_osd_message(&format!("Repeat: {}", mode.name()));
+            });
+            action.set_state(&mode.name().to_variant());
+        });
+
+        self.playlist_save_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.save_playlist();
+            });
+        });
+
+        self.quit_at_end_of_playlist_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().quit_at_end_of_playlist = enabled;
+                    video_player.save_settings();
+                });
+                action.set_state(&enabled.to_variant());
             }
-            PlayerEvent::VideoDimensionsChanged(width, height) => {
-                self.video_dimensions_changed(width, height);
+        });
+
+        self.gapless_playback_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().gapless_playback = enabled;
+                    video_player.save_settings();
+                    video_player.player.set_gapless(enabled);
+                });
+                action.set_state(&enabled.to_variant());
             }
-            PlayerEvent::StateChanged(ref s) => {
-                self.playback_state_changed(s);
+        });
+
+        self.auto_advance_cancel_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.cancel_auto_advance();
+            });
+        });
+
+        self.pedal_rewind_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.pedal_rewind();
+            });
+        });
+
+        self.pedal_resume_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.player.play();
+                video_player.pause_action.set_state(&false.to_variant());
+            });
+        });
+
+        self.pedal_rewind_amount_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(seconds) = val.get::<u32>() {
+                    with_video_player!(video_player {
+                        video_player.settings.borrow_mut().pedal_rewind_seconds = seconds;
+                        video_player.save_settings();
+                        action.set_state(&val);
+                    });
+                }
             }
-            PlayerEvent::VolumeChanged(volume) => {
-                self.volume_changed(volume);
+        });
+
+        self.instant_replay_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.instant_replay();
+            });
+        });
+
+        self.cycle_aspect_ratio_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.cycle_aspect_ratio();
+            });
+        });
+
+        self.aspect_ratio_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(ratio) = val
+                    .get::<std::string::String>()
+                    .as_deref()
+                    .and_then(AspectRatio::from_name)
+                {
+                    with_video_player!(video_player {
+                        video_player.set_aspect_ratio(ratio);
+                    });
+                    action.set_state(&ratio.name().to_variant());
+                }
             }
-            PlayerEvent::Error(msg) => {
-                self.player_error(msg);
+        });
+
+        self.zoom_to_fill_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    if video_player.player.set_zoom_to_fill(enabled) {
+                        let message = if enabled { "Zoom to fill" } else { "Fit to window" };
+                        video_player.ui_context.show_osd_message(message);
+                        action.set_state(&enabled.to_variant());
+                    } else {
+                        video_player.ui_context.show_osd_message("Zooming requires the videocrop element");
+                    }
+                });
             }
-            PlayerEvent::AudioVideoOffsetChanged(offset) => {
-                self.audio_video_offset_changed(offset);
+        });
+
+        self.video_zoom_in_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.step_video_zoom(VIDEO_ZOOM_STEP);
+            });
+        });
+
+        self.video_zoom_out_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.step_video_zoom(-VIDEO_ZOOM_STEP);
+            });
+        });
+
+        self.video_zoom_reset_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.step_video_zoom(1.0 - video_player.player.get_video_zoom());
+            });
+        });
+
+        self.video_color_increase_action.connect_activate(|_, value| {
+            if let Some(channel) = value.and_then(|val| val.get::<std::string::String>()) {
+                with_video_player!(video_player {
+                    video_player.step_video_color(&channel, VIDEO_COLOR_STEP);
+                });
             }
-            PlayerEvent::SubtitleVideoOffsetChanged(offset) => {
-                self.subtitle_video_offset_changed(offset);
+        });
+
+        self.video_color_decrease_action.connect_activate(|_, value| {
+            if let Some(channel) = value.and_then(|val| val.get::<std::string::String>()) {
+                with_video_player!(video_player {
+                    video_player.step_video_color(&channel, -VIDEO_COLOR_STEP);
+                });
             }
-            _ => {}
-        };
-    }
+        });
 
-    pub fn player_error(&self, msg: std::string::String) {
-        // FIXME: display some GTK error dialog...
-        eprintln!("Internal player error: {}", msg);
-        with_video_player!(video_player { video_player.quit() });
-    }
+        self.video_color_reset_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                let balance = video_player.player.set_video_balance(VideoBalance::default());
+                video_player.show_video_balance(&balance);
+            });
+        });
 
-    pub fn volume_changed(&self, volume: f64) {
-        self.ui_context.volume_changed(volume);
-    }
+        self.toggle_previous_item_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.toggle_previous_item();
+            });
+        });
 
-    pub fn audio_video_offset_changed(&self, offset: i64) {
-        self.ui_context.audio_video_offset_changed(offset);
-    }
+        self.forget_media_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.forget_media();
+            });
+        });
 
-    pub fn subtitle_video_offset_changed(&self, offset: i64) {
-        self.ui_context.subtitle_video_offset_changed(offset);
+        self.forget_all_media_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.forget_all_media();
+            });
+        });
+
+        self.export_config_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.export_config();
+            });
+        });
+
+        self.import_config_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.import_config();
+            });
+        });
+
+        self.cycle_track_action.connect_activate(|_, value| {
+            if let Some(kind) = value
+                .and_then(|val| val.get::<std::string::String>())
+                .and_then(|name| TrackKind::from_name(&name))
+            {
+                with_video_player!(video_player {
+                    video_player.cycle_track(kind);
+                });
+            }
+        });
+
+        self.instant_replay_length_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(seconds) = val.get::<u32>() {
+                    with_video_player!(video_player {
+                        video_player.settings.borrow_mut().instant_replay_seconds = seconds;
+                        video_player.save_settings();
+                        action.set_state(&val);
+                    });
+                }
+            }
+        });
+
+        self.playback_speed_action.connect_change_state(|_, value| {
+            if let Some(rate) = value
+                .and_then(|val| val.get::<std::string::String>())
+                .and_then(|name| name.parse::<f64>().ok())
+            {
+                with_video_player!(video_player {
+                    video_player.set_playback_speed(rate);
+                });
+            }
+        });
+
+        self.chapter_action.connect_activate(|_, value| {
+            if let Some(start) = value.and_then(|val| val.get::<u64>()) {
+                with_video_player!(video_player {
+                    video_player.player.seek_to(gst::ClockTime::from_nseconds(start));
+                });
+            }
+        });
+
+        self.next_chapter_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                if let Some(chapter) = video_player.player.seek_to_next_chapter() {
+                    video_player.ui_context.show_osd_message(&chapter_label(&chapter));
+                }
+            });
+        });
+
+        self.previous_chapter_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                if let Some(chapter) = video_player.player.seek_to_previous_chapter() {
+                    video_player.ui_context.show_osd_message(&chapter_label(&chapter));
+                }
+            });
+        });
+
+        self.loop_set_a_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                if let Some(position) = video_player.player.set_loop_point_a() {
+                    video_player.ui_context.show_osd_message(&format!("Loop start {:.0}", position));
+                }
+            });
+        });
+
+        self.loop_set_b_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                if let Some(position) = video_player.player.set_loop_point_b() {
+                    video_player.ui_context.show_osd_message(&format!("Loop end {:.0}", position));
+                }
+            });
+        });
+
+        self.loop_clear_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.player.clear_loop();
+                video_player.ui_context.show_osd_message("Loop cleared");
+            });
+        });
+
+        self.step_forward_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.player.step_frame_forward();
+            });
+        });
+
+        self.step_backward_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.player.step_frame_backward();
+            });
+        });
+
+        self.playback_speed_increase_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                let rate = video_player.player.get_playback_rate();
+                let next = constants::PLAYBACK_RATE_STEPS.iter().find(|&&step| step > rate + 0.01);
+                video_player.set_playback_speed(*next.unwrap_or(&constants::MAX_PLAYBACK_RATE));
+            });
+        });
+
+        self.playback_speed_decrease_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                let rate = video_player.player.get_playback_rate();
+                let previous = constants::PLAYBACK_RATE_STEPS.iter().rev().find(|&&step| step < rate - 0.01);
+                video_player.set_playback_speed(*previous.unwrap_or(&constants::MIN_PLAYBACK_RATE));
+            });
+        });
+
+        self.instant_replay_rate_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(rate) = val.get::<f64>() {
+                    with_video_player!(video_player {
+                        video_player.settings.borrow_mut().instant_replay_rate = rate;
+                        video_player.save_settings();
+                        action.set_state(&val);
+                    });
+                }
+            }
+        });
+
+        self.pedal_auto_pause_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().pedal_auto_pause = enabled;
+                    video_player.save_settings();
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.snapshot_raw_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().snapshot_raw = enabled;
+                    video_player.save_settings();
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.tone_map_hdr_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().tone_map_hdr = enabled;
+                    video_player.save_settings();
+                    video_player.player.set_hdr_tone_mapping(enabled);
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.borderless_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let borderless = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().window_decorations = !borderless;
+                    video_player.save_settings();
+                    video_player.ui_context.set_decorated(!borderless);
+                });
+                action.set_state(&borderless.to_variant());
+            }
+        });
+
+        self.copy_frame_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.copy_frame();
+            });
+        });
+
+        self.snapshot_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.take_snapshot();
+            });
+        });
+
+        self.video_rotate_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.rotate_video();
+            });
+        });
+
+        self.mirror_output_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    if enabled {
+                        video_player.stop_mirror_output();
+                    } else if video_player.start_mirror_output() {
+                        action.set_state(&true.to_variant());
+                    }
+                });
+            }
+        });
+
+        self.playlist_sort_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    if let Some(sort) = PlaylistSort::from_name(&name) {
+                        with_video_player!(video_player {
+                            video_player.settings.borrow_mut().playlist_sort = sort;
+                            video_player.save_settings();
+                            action.set_state(&val);
+                        });
+                    }
+                }
+            }
+        });
+
+        self.carry_tracks_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().carry_tracks_across_playlist = enabled;
+                    video_player.save_settings();
+                    video_player.player.set_carry_tracks(enabled);
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.open_multiple_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(open_action) = val
+                    .get::<std::string::String>()
+                    .and_then(|name| OpenMultipleAction::from_name(&name))
+                {
+                    with_video_player!(video_player {
+                        video_player.settings.borrow_mut().open_multiple_action = open_action;
+                        video_player.save_settings();
+                        action.set_state(&val);
+                    });
+                }
+            }
+        });
+
+        self.no_args_behavior_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(behavior) = val
+                    .get::<std::string::String>()
+                    .and_then(|name| NoArgsBehavior::from_name(&name))
+                {
+                    with_video_player!(video_player {
+                        video_player.settings.borrow_mut().no_args_behavior = behavior;
+                        video_player.save_settings();
+                        action.set_state(&val);
+                    });
+                }
+            }
+        });
+
+        self.playlist_error_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    if let Some(error_action) = PlaylistErrorAction::from_name(&name) {
+                        with_video_player!(video_player {
+                            let retries = {
+                                let mut settings = video_player.settings.borrow_mut();
+                                settings.playlist_error_action = error_action;
+                                settings.playlist_item_retries
+                            };
+                            video_player.save_settings();
+                            video_player
+                                .player
+                                .set_skip_unplayable(error_action != PlaylistErrorAction::Stop, retries);
+                            action.set_state(&val);
+                        });
+                    }
+                }
+            }
+        });
+
+        self.auto_fullscreen_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().auto_fullscreen = enabled;
+                    video_player.save_settings();
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.video_sink_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    with_mut_video_player!(video_player {
+                        if name.as_str() == video_player.player.video_sink_name() {
+                            return;
+                        }
+                        match video_player.player.set_video_sink(&name) {
+                            Ok(_) => {
+                                video_player.ui_context.set_video_area(video_player.player.video_area());
+                                video_player.settings.borrow_mut().video_sink = Some(name);
+                                video_player.save_settings();
+                                action.set_state(&val);
+                            }
+                            Err(e) => {
+                                video_player.ui_context.display_error_dialog("Unable to switch the video output", Some(&e.to_string()));
+                            }
+                        }
+                    });
+                }
+            }
+        });
+
+        self.start_paused_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().start_paused = enabled;
+                    video_player.save_settings();
+                    video_player.player.set_start_paused(enabled);
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.title_shows_state_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().title_shows_state = enabled;
+                    video_player.save_settings();
+                    video_player.update_window_title();
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.always_on_top_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().always_on_top = enabled;
+                    video_player.save_settings();
+                    video_player.ui_context.set_keep_above(enabled);
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.night_mode_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().night_mode = enabled;
+                    video_player.save_settings();
+                    video_player.update_night_mode();
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.color_range_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    if let Some(range) = ColorRange::from_name(&name) {
+                        with_video_player!(video_player {
+                            video_player.settings.borrow_mut().color_range = range;
+                            video_player.save_settings();
+                            video_player.player.set_color_range(range);
+                            action.set_state(&val);
+                        });
+                    }
+                }
+            }
+        });
+
+        self.deinterlace_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    if let Some(mode) = Deinterlace::from_name(&name) {
+                        with_video_player!(video_player {
+                            video_player.settings.borrow_mut().deinterlace = mode;
+                            video_player.save_settings();
+                            video_player.player.set_deinterlace(mode);
+                            action.set_state(&val);
+                        });
+                    }
+                }
+            }
+        });
+
+        self.pixel_aspect_ratio_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    let ratio = parse_ratio(&name);
+                    if ratio.is_some() || name == "auto" {
+                        with_video_player!(video_player {
+                            video_player.settings.borrow_mut().pixel_aspect_ratio = ratio.map(|_| name.clone());
+                            video_player.save_settings();
+                            video_player.player.set_pixel_aspect_ratio(ratio);
+                            action.set_state(&val);
+                        });
+                    }
+                }
+            }
+        });
+
+        self.overwrite_mode_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    if let Some(mode) = OverwriteMode::from_name(&name) {
+                        with_video_player!(video_player {
+                            video_player.settings.borrow_mut().overwrite_mode = mode;
+                            video_player.save_settings();
+                            action.set_state(&val);
+                        });
+                    }
+                }
+            }
+        });
+
+        self.idle_power_saving_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().idle_power_saving = enabled;
+                    video_player.save_settings();
+                    video_player.cancel_idle_timeout();
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.seek_preview_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().seek_preview = enabled;
+                    video_player.save_settings();
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.replay_gain_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    if let Some(mode) = ReplayGainMode::from_name(&name) {
+                        with_video_player!(video_player {
+                            video_player.settings.borrow_mut().replaygain_mode = mode;
+                            video_player.save_settings();
+                            video_player.player.set_replay_gain_mode(mode);
+                            action.set_state(&val);
+                        });
+                    }
+                }
+            }
+        });
+
+        self.channel_remap_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(remap) = val
+                    .get::<std::string::String>()
+                    .and_then(|name| ChannelRemap::from_name(&name))
+                {
+                    with_video_player!(video_player {
+                        let matrix = remap.matrix(&video_player.settings.borrow().channel_matrix);
+                        if video_player.player.set_channel_matrix(matrix.as_deref()) {
+                            video_player.settings.borrow_mut().channel_remap = remap;
+                            video_player.save_settings();
+                            action.set_state(&val);
+                            video_player
+                                .downmix_mono_action
+                                .set_state(&(remap == ChannelRemap::Mono).to_variant());
+                        } else {
+                            video_player
+                                .ui_context
+                                .show_osd_message("Invalid channel matrix in the settings file");
+                        }
+                    });
+                }
+            }
+        });
+
+        // Turning mono off goes back to the channels of the media.
+        self.downmix_mono_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                let remap = if enabled { ChannelRemap::Mono } else { ChannelRemap::Off };
+                with_video_player!(video_player {
+                    video_player
+                        .channel_remap_action
+                        .change_state(&remap.name().to_variant());
+                });
+            }
+        });
+
+        self.audio_profile_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    with_video_player!(video_player {
+                        if video_player.set_audio_profile(&name) {
+                            action.set_state(&val);
+                        }
+                    });
+                }
+            }
+        });
+
+        self.stopped_video_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(mode) = val
+                    .get::<std::string::String>()
+                    .and_then(|name| StoppedVideo::from_name(&name))
+                {
+                    with_video_player!(video_player {
+                        video_player.settings.borrow_mut().stopped_video = mode;
+                        video_player.save_settings();
+                        action.set_state(&val);
+                    });
+                }
+            }
+        });
+
+        self.preserve_pitch_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().preserve_pitch = enabled;
+                    video_player.save_settings();
+                    video_player.player.set_preserve_pitch(enabled);
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.audio_normalize_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().audio_normalization = enabled;
+                    video_player.save_settings();
+                    video_player.player.set_audio_normalization(enabled);
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        // Either a preset, "off", or the gains of the ten bands separated by
+        // commas.
+        self.equalizer_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(gains) = val.get::<std::string::String>().and_then(|name| equalizer_gains(&name)) {
+                    with_video_player!(video_player {
+                        video_player.player.set_equalizer(gains.as_deref());
+                        action.set_state(&equalizer_name(gains.as_deref()).to_variant());
+                        video_player.settings.borrow_mut().equalizer = gains;
+                        video_player.save_settings();
+                    });
+                }
+            }
+        });
+
+        self.device_change_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(name) = val.get::<std::string::String>() {
+                    if let Some(device_action) = DeviceChangeAction::from_name(&name) {
+                        with_video_player!(video_player {
+                            video_player.settings.borrow_mut().pause_on_device_change = device_action;
+                            video_player.save_settings();
+                            action.set_state(&val);
+                        });
+                    }
+                }
+            }
+        });
+
+        self.show_audio_meter_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().show_audio_meter = enabled;
+                    video_player.save_settings();
+                    video_player.player.set_audio_levels_enabled(enabled);
+                    video_player.ui_context.show_audio_meter(enabled);
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.frame_stats_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.display_frame_stats();
+            });
+        });
+
+        self.show_frame_stats_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().show_frame_stats = enabled;
+                    video_player.save_settings();
+                    video_player.update_frame_stats();
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.fullscreen_progress_bar_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    let mut settings = video_player.settings.borrow_mut();
+                    settings.fullscreen_progress_bar = enabled;
+                    video_player.ui_context.set_fullscreen_progress_bar(
+                        enabled,
+                        settings.fullscreen_progress_bar_height,
+                        &settings.fullscreen_progress_bar_color,
+                    );
+                    drop(settings);
+                    video_player.save_settings();
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.seek_to_percent_action.connect_activate(|_, value| {
+            if let Some(percent) = value.and_then(|val| val.get::<f64>()) {
+                with_video_player!(video_player {
+                    video_player.player.seek_to_percent(percent);
+                });
+            }
+        });
+
+        self.seek_to_time_action.connect_activate(|_, value| {
+            if let Some(time) = value.and_then(|val| val.get::<std::string::String>()) {
+                with_video_player!(video_player {
+                    video_player.seek_to_time(&time);
+                });
+            }
+        });
+
+        self.subtitle_languages_action.connect_activate(|_, value| {
+            if let Some(languages) = value.and_then(|val| val.get::<std::string::String>()) {
+                with_video_player!(video_player {
+                    video_player.set_subtitle_languages(&languages);
+                });
+            }
+        });
+
+        self.chapter_markers_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().chapter_markers = enabled;
+                    video_player.ui_context.set_chapter_marks_enabled(enabled);
+                    video_player.save_settings();
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        #[cfg(feature = "clip-export")]
+        {
+            self.clip_start_action.connect_activate(|_, _| {
+                with_video_player!(video_player {
+                    let position = video_player.player.get_position();
+                    video_player.clip_range.borrow_mut().0 = position;
+                    video_player.ui_context.show_osd_message(&format!("Clip start: {:.0}", position));
+                });
+            });
+
+            self.clip_end_action.connect_activate(|_, _| {
+                with_video_player!(video_player {
+                    let position = video_player.player.get_position();
+                    video_player.clip_range.borrow_mut().1 = position;
+                    video_player.ui_context.show_osd_message(&format!("Clip end: {:.0}", position));
+                });
+            });
+
+            self.export_clip_action.connect_activate(|_, _| {
+                with_video_player!(video_player {
+                    video_player.export_clip();
+                });
+            });
+        }
+
+        #[cfg(feature = "subtitle-auto-sync")]
+        self.subtitle_auto_sync_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.ui_context.show_osd_message("Analyzing subtitles synchronization…");
+                video_player.player.suggest_subtitle_offset();
+            });
+        });
+
+        self.download_first_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().download_first = enabled;
+                    video_player.save_settings();
+                    video_player.update_download_config();
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.download_keep_action.connect_change_state(|action, _| {
+            if let Some(is_enabled) = action.get_state() {
+                let enabled = !is_enabled.get::<bool>().unwrap();
+                with_video_player!(video_player {
+                    video_player.settings.borrow_mut().download_keep = enabled;
+                    video_player.save_settings();
+                });
+                action.set_state(&enabled.to_variant());
+            }
+        });
+
+        self.ui_context.set_video_area(self.player.video_area());
+
+        self.ui_context.set_progress_bar_format_callback(|value, duration| {
+            let position = gst::ClockTime::from_seconds(value as u64);
+            let duration = gst::ClockTime::from_seconds(duration as u64);
+            if duration.is_some() {
+                format!("{:.0} / {:.0}", position, duration)
+            } else {
+                format!("{:.0}", position)
+            }
+        });
+
+        self.ui_context.set_volume_value_changed_callback(|value| {
+            with_video_player!(video_player {
+                video_player.player.set_volume(value);
+            });
+        });
+
+        self.ui_context.set_seek_thumbnail_callback(|value| {
+            let mut thumbnail = None;
+            with_video_player!(video_player {
+                thumbnail = video_player
+                    .player
+                    .get_seek_thumbnail(gst::ClockTime::from_nseconds((value * 1e9) as u64));
+            });
+            thumbnail
+        });
+
+        self.ui_context.set_position_changed_callback(|value| {
+            with_video_player!(video_player {
+                video_player.seek_with_preview(gst::ClockTime::from_seconds(value));
+            });
+        });
+
+        self.ui_context.set_display_changed_callback(|| {
+            with_mut_video_player!(video_player {
+                video_player.display_changed();
+            });
+        });
+
+        self.ui_context.set_chapter_mark_clicked_callback(|start| {
+            with_video_player!(video_player {
+                video_player.player.seek_to(gst::ClockTime::from_nseconds(start));
+            });
+        });
+
+        self.ui_context.set_key_press_callback(|event| {
+            let mut handled = false;
+            with_video_player!(video_player {
+                handled = video_player.handle_key_press(event);
+            });
+            handled
+        });
+
+        self.ui_context.set_drop_data_callback(|uri| {
+            with_video_player!(video_player {
+                if let Ok((path, _)) = glib::filename_from_uri(&uri) {
+                    if subtitle_files::is_subtitle_file(&path) {
+                        video_player.add_external_subtitle(uri.into());
+                        video_player.refresh_subtitle_track_menu();
+                        return;
+                    }
+                }
+                println!("loading {}", &uri);
+                video_player.player.stop();
+                video_player.player.load_uri(&uri);
+            })
+        });
+
+        self.ui_context.set_audio_offset_entry_updated_callback(|offset| {
+            with_video_player!(video_player {
+                video_player.player.set_audio_offset(offset);
+            })
+        });
+
+        self.ui_context.set_subtitle_offset_entry_updated_callback(|offset| {
+            with_video_player!(video_player {
+                video_player.subtitle_offset_adjusted(offset);
+            })
+        });
+
+        #[cfg(feature = "self-updater")]
+        match self.check_update() {
+            Ok(o) => {
+                match o {
+                    self_update::Status::UpToDate(_version) => {}
+                    _ => println!("Update succeeded: {}", o),
+                };
+            }
+            Err(e) => eprintln!("Update failed: {}", e),
+        };
+
+        self.ui_context.start(|| {
+            with_video_player!(video_player {
+                video_player.quit();
+            });
+        });
+        // Once the window is shown, for the macOS window level.
+        if self.settings.borrow().always_on_top {
+            self.ui_context.set_keep_above(true);
+        }
+
+        if self.force_fullscreen {
+            self.enter_fullscreen();
+        }
+
+        if self.launched_without_media {
+            // Once the window is shown, the file dialog being modal.
+            glib::idle_add_local(|| {
+                with_video_player!(video_player {
+                    video_player.start_without_media();
+                });
+                glib::Continue(false)
+            });
+        }
+
+        glib::timeout_add_seconds_local(NIGHT_MODE_UPDATE_INTERVAL_SECONDS, || {
+            with_video_player!(video_player {
+                let paused = video_player
+                    .pause_action
+                    .get_state()
+                    .and_then(|state| state.get::<bool>())
+                    .unwrap_or(true);
+                if !paused && video_player.settings.borrow().night_mode {
+                    video_player.update_night_mode();
+                }
+            });
+            glib::Continue(true)
+        });
+    }
+
+    pub fn dispatch_event(&self, event: PlayerEvent) {
+        match event {
+            PlayerEvent::MediaInfoUpdated => {
+                self.media_info_updated();
+            }
+            PlayerEvent::PositionUpdated => {
+                self.position_updated();
+            }
+            PlayerEvent::RateChanged(rate) => {
+                self.playback_speed_action
+                    .set_state(&playback_rate_name(rate).to_variant());
+            }
+            PlayerEvent::ChaptersUpdated => {
+                self.chapters_updated();
+            }
+            // Nothing to do until the new video sink is in place.
+            PlayerEvent::VideoSinkRebuilding => {}
+            PlayerEvent::VideoSinkRebuilt => {
+                let fullscreen = self.fullscreen_action.get_state().and_then(|state| state.get::<bool>());
+                if fullscreen == Some(true) {
+                    self.ui_context.reapply_fullscreen();
+                }
+            }
+            PlayerEvent::VideoDimensionsChanged(width, height) => {
+                self.video_dimensions_changed(width, height);
+            }
+            PlayerEvent::StateChanged(ref s) => {
+                self.playback_state_changed(s);
+            }
+            PlayerEvent::VolumeChanged(volume) => {
+                self.volume_changed(volume);
+            }
+            PlayerEvent::Error(msg) => {
+                self.player_error(msg);
+            }
+            PlayerEvent::AudioVideoOffsetChanged(offset) => {
+                self.audio_video_offset_changed(offset);
+            }
+            PlayerEvent::SubtitleVideoOffsetChanged(offset) => {
+                self.subtitle_video_offset_changed(offset);
+            }
+            PlayerEvent::EndOfStream(_) => {
+                self.end_of_stream();
+            }
+            #[cfg(feature = "clip-export")]
+            PlayerEvent::ClipExportProgress(fraction) => {
+                self.ui_context
+                    .show_osd_message(&format!("Exporting clip… {:.0}%", fraction * 100.0));
+            }
+            PlayerEvent::SnapshotSaved(path) => {
+                self.ui_context.show_osd_message(&format!("Snapshot saved to {}", path));
+            }
+            PlayerEvent::SnapshotFailed(msg) => {
+                self.ui_context
+                    .display_error_dialog("Unable to save the snapshot", Some(&msg));
+            }
+            PlayerEvent::HdrDetected(hdr) => {
+                self.player.update_tone_mapping();
+                if hdr && !self.settings.borrow().tone_map_hdr {
+                    self.ui_context
+                        .show_osd_message("HDR video, Video > Tone-map HDR adapts it to SDR displays");
+                }
+            }
+            #[cfg(feature = "clip-export")]
+            PlayerEvent::ClipExported(path) => {
+                self.ui_context.show_osd_message(&format!("Clip saved to {}", path));
+            }
+            #[cfg(feature = "clip-export")]
+            PlayerEvent::ClipExportFailed(msg) => {
+                self.ui_context
+                    .display_error_dialog("Unable to export the clip", Some(&msg));
+            }
+            PlayerEvent::ResumedAt(position) => {
+                let position = gst::ClockTime::from_nseconds(position);
+                self.ui_context.show_osd_message(&format!("Resumed at {:.0}", position));
+            }
+            PlayerEvent::LiveResumeApplied(live_resume) => {
+                let message = match live_resume {
+                    LiveResume::LiveEdge => "Joined the live stream",
+                    LiveResume::LastPosition => "Resumed the live stream where it was left",
+                };
+                self.ui_context.show_osd_message(message);
+            }
+            PlayerEvent::ResumeAvailable(position) => {
+                let position = gst::ClockTime::from_nseconds(position);
+                self.ui_context.show_resume_prompt(&format!("{:.0}", position));
+            }
+            PlayerEvent::PlaylistItemSkipped(uri, msg) => {
+                self.playlist_item_skipped(uri, msg);
+            }
+            PlayerEvent::EndOfPlaylist => {
+                self.end_of_playlist();
+            }
+            #[cfg(feature = "subtitle-auto-sync")]
+            PlayerEvent::SubtitleSyncSuggested(offset) => {
+                let message = format!("Apply a subtitle offset of {:.2}s?", offset as f64 / 1_000_000_000.0);
+                let details = "Based on the speech detected in the next two minutes of the media.";
+                if self.ui_context.confirm_dialog(&message, Some(details)) {
+                    self.subtitle_offset_adjusted(offset);
+                }
+            }
+            #[cfg(feature = "subtitle-auto-sync")]
+            PlayerEvent::SubtitleSyncFailed(msg) => {
+                self.ui_context
+                    .display_error_dialog("Unable to synchronize the subtitles", Some(&msg));
+            }
+            PlayerEvent::ReplayGainApplied(gain) => {
+                self.ui_context.show_osd_message(&format!("ReplayGain {:+.1} dB", gain));
+            }
+            PlayerEvent::AudioLevels(levels) => {
+                self.ui_context.update_audio_meter(&levels);
+            }
+            PlayerEvent::Buffering(percent) => {
+                self.buffering(percent);
+            }
+            PlayerEvent::DownloadProgress(fraction) => {
+                self.ui_context
+                    .show_osd_message(&format!("Downloading… {:.0}%", fraction * 100.0));
+            }
+        };
+    }
+
+    pub fn player_error(&self, msg: std::string::String) {
+        eprintln!("Internal player error: {}", msg);
+        let details = crash_report_dir().map(|dir| {
+            format!(
+                "If Glide crashed previously, crash reports can be found in {}",
+                dir.display()
+            )
+        });
+        self.ui_context
+            .display_error_dialog(&format!("Internal player error: {}", msg), details.as_deref());
+        with_video_player!(video_player { video_player.quit() });
+    }
+
+    pub fn crash_report_state(&self, incognito: bool) -> std::string::String {
+        let mut state = std::string::String::new();
+        if !incognito {
+            if let Some(uri) = self.player.get_current_uri() {
+                state.push_str(&format!("URI: {}\n", uri));
+            }
+        }
+        state.push_str(&format!("Position: {}\n", self.player.get_position()));
+        if let Some(dot) = self.player.pipeline_dot_data() {
+            state.push_str(&format!("\nPipeline:\n{}\n", dot));
+        }
+        state
+    }
+
+    pub fn volume_changed(&self, volume: f64) {
+        self.ui_context.volume_changed(volume);
+    }
+
+    pub fn audio_video_offset_changed(&self, offset: i64) {
+        self.ui_context.audio_video_offset_changed(offset);
+    }
+
+    pub fn subtitle_video_offset_changed(&self, offset: i64) {
+        self.ui_context.subtitle_video_offset_changed(offset);
+    }
+
+    pub fn playback_state_changed(&self, playback_state: &PlaybackState) {
+        self.cancel_idle_timeout();
+        {
+            let mut window_title = self.window_title.borrow_mut();
+            window_title.paused = matches!(playback_state, PlaybackState::Paused);
+            if let PlaybackState::Stopped = playback_state {
+                window_title.buffering = None;
+            } else {
+                window_title.finished = false;
+            }
+        }
+        self.update_window_title();
+        #[cfg(target_os = "macos")]
+        {
+            if let Some(ref now_playing) = self.now_playing {
+                let state = match playback_state {
+                    PlaybackState::Playing => NowPlayingState::Playing,
+                    PlaybackState::Paused => NowPlayingState::Paused,
+                    PlaybackState::Stopped => NowPlayingState::Stopped,
+                };
+                now_playing.set_state(state, self.position_seconds(), self.player.get_playback_rate());
+            }
+        }
+        match playback_state {
+            PlaybackState::Playing => {
+                // Playback was started some other way, the prompt is stale.
+                self.ui_context.hide_resume_prompt();
+                self.pause_action.set_state(&false.to_variant());
+            }
+            // Media can also stay paused after loading, see start_paused.
+            PlaybackState::Paused => {
+                self.pause_action.set_state(&true.to_variant());
+                self.start_idle_timeout();
+            }
+            PlaybackState::Stopped => {}
+        }
+        self.ui_context.playback_state_changed(playback_state);
+    }
+
+    pub fn buffering(&self, percent: i32) {
+        self.window_title.borrow_mut().buffering = if percent < 100 { Some(percent) } else { None };
+        self.update_window_title();
+    }
+
+    fn set_media_title(&self, title: &str) {
+        self.window_title.borrow_mut().media_title = title.to_string();
+        self.update_window_title();
+    }
+
+    fn update_window_title(&self) {
+        let show_state = self.settings.borrow().title_shows_state;
+        self.ui_context
+            .set_window_title(&self.window_title.borrow().text(show_state));
+    }
+
+    pub fn video_dimensions_changed(&self, width: i32, height: i32) {
+        self.ui_context.resize_window(width, height);
+    }
+
+    pub fn playlist_item_skipped(&self, uri: std::string::String, msg: std::string::String) {
+        eprintln!("Skipping {}: {}", uri, msg);
+        if self.settings.borrow().playlist_error_action == PlaylistErrorAction::Notify {
+            let name = match glib::filename_from_uri(&uri) {
+                Ok((path, _)) => path
+                    .file_name()
+                    .map_or(uri.clone(), |n| n.to_string_lossy().into_owned()),
+                Err(_) => uri.clone(),
+            };
+            self.ui_context.show_osd_message(&format!("Skipped {}", name));
+        }
+        self.skipped_items.borrow_mut().push(uri);
+    }
+
+    pub fn report_skipped_items(&self) {
+        let skipped = self.skipped_items.replace(vec![]);
+        if skipped.is_empty() || self.settings.borrow().playlist_error_action != PlaylistErrorAction::Notify {
+            return;
+        }
+        self.ui_context.display_error_dialog(
+            &format!("{} playlist item(s) could not be played", skipped.len()),
+            Some(&skipped.join("\n")),
+        );
+    }
+
+    // Skipped items are only reported when staying, they were already logged.
+    fn end_of_playlist(&self) {
+        if self.settings.borrow().quit_at_end_of_playlist {
+            self.quit();
+            return;
+        }
+        self.report_skipped_items();
+        self.show_stopped_video();
+        self.window_title.borrow_mut().finished = true;
+        self.update_window_title();
+        self.ui_context.show_osd_message("End of the playlist");
+    }
+
+    pub fn end_of_stream(&self) {
+        self.stop_instant_replay();
+        let delay = self.settings.borrow().auto_advance_delay;
+        // Repeated items start over right away.
+        if delay == 0 || !self.player.has_next() || self.settings.borrow().playlist_repeat == RepeatMode::One {
+            return;
+        }
+
+        self.cancel_auto_advance();
+        let mut remaining = delay;
+        self.ui_context.show_auto_advance_countdown(remaining);
+        let source = glib::timeout_add_seconds_local(1, move || {
+            remaining -= 1;
+            with_video_player!(video_player {
+                if remaining == 0 {
+                    // The source is about to be destroyed, don't remove it twice.
+                    video_player.auto_advance_source.borrow_mut().take();
+                    video_player.ui_context.hide_auto_advance_countdown();
+                    video_player.player.next();
+                } else {
+                    video_player.ui_context.show_auto_advance_countdown(remaining);
+                }
+            });
+            glib::Continue(remaining > 0)
+        });
+        *self.auto_advance_source.borrow_mut() = Some(source);
+    }
+
+    /// Typing the volume or position trigger key, digits and Enter sets the
+    /// volume or jumps to a percentage of the media. Escape cancels.
+    pub fn handle_key_press(&self, event: &gdk::EventKey) -> bool {
+        let key = event.get_keyval();
+        if self.track_cycle.borrow().is_some() {
+            let commit = key == gdk::keys::constants::Return || key == gdk::keys::constants::KP_Enter;
+            if commit || key == gdk::keys::constants::Escape {
+                let cycle = self.track_cycle.borrow_mut().take();
+                if let Some(cycle) = cycle {
+                    self.ui_context.hide_osd_message();
+                    if commit {
+                        self.select_track(&cycle);
+                    }
+                    glib::source_remove(cycle.commit_source);
+                }
+                return true;
+            }
+        }
+
+        let mut numeric_input = self.numeric_input.borrow_mut();
+        let input = match *numeric_input {
+            Some(ref mut input) => input,
+            None => {
+                let modifiers = gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::MOD1_MASK;
+                if event.get_state().intersects(modifiers) {
+                    return false;
+                }
+                let name = match key.name() {
+                    Some(name) => name,
+                    None => return false,
+                };
+                let settings = self.settings.borrow();
+                let target = if settings.volume_input_key.as_deref() == Some(name.as_str()) {
+                    NumericInputTarget::Volume
+                } else if settings.position_input_key.as_deref() == Some(name.as_str()) {
+                    NumericInputTarget::Position
+                } else {
+                    return false;
+                };
+                let input = NumericInput {
+                    target,
+                    digits: std::string::String::new(),
+                };
+                self.ui_context.show_osd_input(&input.label());
+                *numeric_input = Some(input);
+                return true;
+            }
+        };
+
+        if key == gdk::keys::constants::Escape {
+            *numeric_input = None;
+            self.ui_context.hide_osd_message();
+        } else if key == gdk::keys::constants::Return || key == gdk::keys::constants::KP_Enter {
+            let target = input.target;
+            let value = input.digits.parse::<u32>().ok();
+            *numeric_input = None;
+            self.ui_context.hide_osd_message();
+            if let Some(value) = value {
+                let percent = f64::from(value.min(100));
+                match target {
+                    NumericInputTarget::Volume => self.player.set_volume(percent / 100.0),
+                    NumericInputTarget::Position => self.player.seek_to_percent(percent),
+                };
+            }
+        } else if key == gdk::keys::constants::BackSpace {
+            input.digits.pop();
+            self.ui_context.show_osd_input(&input.label());
+        } else if let Some(digit) = key.to_unicode().filter(|c| c.is_ascii_digit()) {
+            if input.digits.len() < 3 {
+                input.digits.push(digit);
+            }
+            self.ui_context.show_osd_input(&input.label());
+        }
+        // Swallow everything else while typing.
+        true
+    }
+
+    #[cfg(feature = "clip-export")]
+    pub fn export_clip(&self) {
+        let (start, end) = *self.clip_range.borrow();
+        let start = if start.is_some() {
+            start
+        } else {
+            gst::ClockTime::from_seconds(0)
+        };
+        let end = if end.is_some() {
+            end
+        } else {
+            self.player
+                .get_media_info()
+                .map_or(gst::ClockTime::none(), |i| i.get_duration())
+        };
+        if end.is_none() || end <= start {
+            self.ui_context.display_error_dialog(
+                "Unable to export the clip",
+                Some("The clip end must be after its start."),
+            );
+            return;
+        }
+
+        let uri = self.player.get_current_uri();
+        let file_name = uri
+            .as_ref()
+            .and_then(|uri| glib::filename_from_uri(uri).ok())
+            .and_then(|(path, _)| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "clip".to_string());
+        let file_name = format!("{}-clip.mkv", file_name);
+        if let Some(output) = self.ui_context.save_dialog_result(uri, &file_name) {
+            if let Some(output) = self.output_path(output) {
+                self.player.export_clip(start, end, output);
+            }
+        }
+    }
+
+    /// Where to save a file the user asked for, according to the overwrite
+    /// mode, or None if they don't want to replace the existing file.
+    fn output_path(&self, path: PathBuf) -> Option<PathBuf> {
+        if !path.exists() {
+            return Some(path);
+        }
+        match self.settings.borrow().overwrite_mode {
+            OverwriteMode::Rename => Some(output_files::non_existing_path(&path)),
+            OverwriteMode::Ask => {
+                let message = format!("Replace {}?", path.display());
+                if self
+                    .ui_context
+                    .confirm_dialog(&message, Some("A file with this name already exists."))
+                {
+                    Some(path)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    pub fn pedal_rewind(&self) {
+        let (seconds, auto_pause) = {
+            let settings = self.settings.borrow();
+            (settings.pedal_rewind_seconds, settings.pedal_auto_pause)
+        };
+        if let Some(position) = self.player.get_position().nseconds() {
+            let offset = gst::ClockTime::from_seconds(u64::from(seconds)).nseconds().unwrap();
+            let destination = position.saturating_sub(offset);
+            self.player.seek_to(gst::ClockTime::from_nseconds(destination));
+        }
+        if auto_pause {
+            self.player.pause();
+            self.pause_action.set_state(&true.to_variant());
+        }
+        self.ui_context.show_osd_message(&format!("Rewind {}s", seconds));
+    }
+
+    /// Save the current video frame in the Pictures directory, named after
+    /// the time it was taken.
+    pub fn take_snapshot(&self) {
+        let directory = match glib::get_user_special_dir(glib::UserDirectory::Pictures).or_else(glib::get_home_dir) {
+            Some(directory) => directory,
+            None => return,
+        };
+        let time = glib::DateTime::new_now_local()
+            .format("%Y-%m-%d %H-%M-%S")
+            .map_or_else(|| "snapshot".to_string(), |time| time.to_string());
+        let path = directory.join(format!("Glide {}.png", time));
+        if let Some(path) = self.output_path(path) {
+            let raw = self.settings.borrow().snapshot_raw;
+            self.player.take_snapshot(raw, path);
+        }
+    }
+
+    // Seek by the offsets from the settings, the large one being used both
+    // forward and backward.
+    fn seek_direction(&self, forward: bool, large: bool) -> SeekDirection {
+        let settings = self.settings.borrow();
+        let seconds = match (forward, large) {
+            (_, true) => settings.large_seek_seconds,
+            (true, false) => settings.seek_forward_seconds,
+            (false, false) => settings.seek_backward_seconds,
+        };
+        let offset = gst::ClockTime::from_nseconds((seconds.max(0.0) * 1_000_000_000.0) as u64);
+        if forward {
+            SeekDirection::Forward(offset)
+        } else {
+            SeekDirection::Backward(offset)
+        }
+    }
+
+    pub fn seek(&self, direction: SeekDirection) {
+        if !self.settings.borrow().seek_preview {
+            self.player.seek(&direction);
+            return;
+        }
+        // Repeated seeks add up, even if the previews lag behind.
+        let base = match *self.seek_preview.borrow() {
+            Some(ref preview) => preview.target,
+            None => self.player.get_position(),
+        };
+        if base.is_none() {
+            return;
+        }
+        let target = match direction {
+            SeekDirection::Backward(offset) if base >= offset => base - offset,
+            SeekDirection::Backward(_) => gst::ClockTime::from_seconds(0),
+            SeekDirection::Forward(offset) => {
+                let duration = self.player.get_duration();
+                if !duration.is_none() && base + offset > duration {
+                    duration
+                } else {
+                    base + offset
+                }
+            }
+        };
+        self.seek_with_preview(target);
+    }
+
+    /// With the seek preview setting, pause and show the keyframe closest to
+    /// the target, throttled, then seek accurately and resume playback once
+    /// seeks stopped coming.
+    pub fn seek_with_preview(&self, target: gst::ClockTime) {
+        if !self.settings.borrow().seek_preview {
+            self.player.seek_to(target);
+            return;
+        }
+        let previous = self.seek_preview.borrow_mut().take();
+        let (resume_playback, last_preview) = match previous {
+            Some(preview) => {
+                glib::source_remove(preview.commit_source);
+                (preview.resume_playback, Some(preview.last_preview))
+            }
+            None => {
+                let paused = self
+                    .pause_action
+                    .get_state()
+                    .and_then(|state| state.get::<bool>())
+                    .unwrap_or(false);
+                if !paused {
+                    self.player.pause();
+                }
+                (!paused, None)
+            }
+        };
+        let last_preview = match last_preview {
+            Some(last) if last.elapsed() < SEEK_PREVIEW_INTERVAL => last,
+            _ => {
+                self.player.preview_seek(target);
+                Instant::now()
+            }
+        };
+        let commit_source = glib::timeout_add_local(SEEK_PREVIEW_COMMIT_DELAY_MS, || {
+            with_video_player!(video_player {
+                video_player.commit_seek_preview();
+            });
+            glib::Continue(false)
+        });
+        *self.seek_preview.borrow_mut() = Some(SeekPreview {
+            target,
+            resume_playback,
+            last_preview,
+            commit_source,
+        });
+    }
+
+    // Called from the commit timeout, which is about to be destroyed.
+    fn commit_seek_preview(&self) {
+        let preview = match self.seek_preview.borrow_mut().take() {
+            Some(preview) => preview,
+            None => return,
+        };
+        self.player.accurate_seek(preview.target);
+        if preview.resume_playback {
+            self.player.play();
+        }
+    }
+
+    fn track_action(&self, kind: TrackKind) -> &gio::SimpleAction {
+        match kind {
+            TrackKind::Audio => &self.audio_track_action,
+            TrackKind::Subtitle => &self.subtitle_action,
+            TrackKind::Video => &self.video_track_action,
+        }
+    }
+
+    fn track_choices(&self, kind: TrackKind) -> Vec<(std::string::String, std::string::String)> {
+        match kind {
+            TrackKind::Subtitle => self.subtitle_track_choices(),
+            TrackKind::Audio => self
+                .player
+                .get_media_info()
+                .map_or_else(Vec::new, |info| audio_track_choices(&info)),
+            TrackKind::Video => self
+                .player
+                .get_media_info()
+                .map_or_else(Vec::new, |info| video_track_choices(&info)),
+        }
+    }
+
+    /// Show the tracks of the given kind on screen and highlight the next
+    /// one, or the one after the highlighted one if the list is already
+    /// shown. The highlighted track is selected with Enter, or once the
+    /// key wasn't pressed again for a while.
+    pub fn cycle_track(&self, kind: TrackKind) {
+        let previous = self.track_cycle.borrow_mut().take();
+        let (choices, selected) = match previous {
+            Some(cycle) if cycle.kind == kind => {
+                glib::source_remove(cycle.commit_source);
+                let selected = (cycle.selected + 1) % cycle.choices.len();
+                (cycle.choices, selected)
+            }
+            previous => {
+                if let Some(cycle) = previous {
+                    glib::source_remove(cycle.commit_source);
+                    self.ui_context.hide_osd_message();
+                }
+                let choices = self.track_choices(kind);
+                if choices.is_empty() {
+                    return;
+                }
+                let current = self
+                    .track_action(kind)
+                    .get_state()
+                    .and_then(|state| state.get::<std::string::String>());
+                let selected = current
+                    .and_then(|current| choices.iter().position(|(_, target)| *target == current))
+                    .map_or(0, |i| (i + 1) % choices.len());
+                (choices, selected)
+            }
+        };
+        let timeout = self.settings.borrow().track_cycle_timeout_ms;
+        let commit_source = glib::timeout_add_local(timeout, || {
+            with_video_player!(video_player {
+                video_player.commit_track_cycle();
+            });
+            glib::Continue(false)
+        });
+        let cycle = TrackCycle {
+            kind,
+            choices,
+            selected,
+            commit_source,
+        };
+        self.ui_context.show_osd_input(&cycle.label());
+        *self.track_cycle.borrow_mut() = Some(cycle);
+    }
+
+    // Called from the commit timeout, which is about to be destroyed.
+    fn commit_track_cycle(&self) {
+        let cycle = self.track_cycle.borrow_mut().take();
+        if let Some(cycle) = cycle {
+            self.ui_context.hide_osd_message();
+            self.select_track(&cycle);
+        }
+    }
+
+    fn select_track(&self, cycle: &TrackCycle) {
+        let (_, target) = &cycle.choices[cycle.selected];
+        self.track_action(cycle.kind).change_state(&target.to_variant());
+    }
+
+    // Release the pipeline once paused for long enough, to save power.
+    fn start_idle_timeout(&self) {
+        let minutes = {
+            let settings = self.settings.borrow();
+            if !settings.idle_power_saving {
+                return;
+            }
+            settings.idle_power_saving_minutes
+        };
+        let source = glib::timeout_add_seconds_local(minutes.max(1) * 60, || {
+            with_video_player!(video_player {
+                *video_player.idle_source.borrow_mut() = None;
+                // Keep showing the paused frame once the sink is gone.
+                video_player.ui_context.show_still_frame(video_player.player.snapshot_pixbuf(false));
+                video_player.player.release_while_paused();
+            });
+            glib::Continue(false)
+        });
+        *self.idle_source.borrow_mut() = Some(source);
+    }
+
+    // Look for subtitles online, for local media only, since the hash of
+    // the file is needed by most sources.
+    fn download_subtitles(&self, uri: &str) {
+        let settings = self.settings.borrow();
+        if !settings.subtitle_auto_download || settings.subtitle_sources.is_empty() {
+            return;
+        }
+        let path = match glib::filename_from_uri(uri) {
+            Ok((path, _)) => path,
+            Err(_) => return,
+        };
+        let directory = match settings.subtitle_download_location {
+            SubtitleDownloadLocation::NextToMedia => None,
+            SubtitleDownloadLocation::Cache => match self.subtitle_cache_directory {
+                Some(ref directory) => Some(directory.clone()),
+                None => return,
+            },
+        };
+        let media_uri = uri.to_string();
+        let fetch = SubtitleFetch::start(&path, &settings.subtitle_sources, directory, move |result| {
+            with_video_player!(video_player {
+                video_player.subtitles_downloaded(&media_uri, result);
+            });
+        });
+        *self.subtitle_fetch.borrow_mut() = Some(fetch);
+    }
+
+    fn subtitles_downloaded(&self, uri: &str, result: Result<(SubtitleSource, PathBuf), std::string::String>) {
+        if self.player.get_current_uri().as_deref() != Some(uri) {
+            return;
+        }
+        match result {
+            Ok((source, path)) => match glib::filename_to_uri(&path, None) {
+                Ok(suburi) => {
+                    self.add_external_subtitle(suburi);
+                    self.refresh_subtitle_track_menu();
+                    self.ui_context
+                        .show_osd_message(&format!("Subtitles downloaded from {}", source.name));
+                }
+                Err(e) => eprintln!("Unable to use the downloaded subtitles: {}", e),
+            },
+            Err(errors) => {
+                eprintln!("No subtitles found online: {}", errors);
+                self.ui_context.show_osd_message("No subtitles found online");
+            }
+        }
+    }
+
+    // Cover the video of the finished playlist according to the settings.
+    fn show_stopped_video(&self) {
+        let has_video = self
+            .player
+            .get_media_info()
+            .map_or(false, |info| info.get_number_of_video_streams() > 0);
+        if !has_video {
+            return;
+        }
+        match self.settings.borrow().stopped_video {
+            StoppedVideo::LastFrame => self.ui_context.show_still_frame(self.player.snapshot_pixbuf(false)),
+            StoppedVideo::Black => self.ui_context.show_still_frame(None),
+            StoppedVideo::Logo => self.ui_context.show_logo(),
+        }
+    }
+
+    fn cancel_idle_timeout(&self) {
+        if let Some(source) = self.idle_source.borrow_mut().take() {
+            glib::source_remove(source);
+        }
+    }
+
+    // Some sinks render black, or at the wrong size, after the window moved
+    // to a monitor with another scale factor or a display was unplugged.
+    fn display_changed(&mut self) {
+        if !self.settings.borrow().rebuild_video_sink_on_display_change {
+            return;
+        }
+        match self.player.rebuild_video_sink() {
+            Ok(_) => self.ui_context.set_video_area(self.player.video_area()),
+            Err(e) => eprintln!("Unable to rebuild the video sink: {}", e),
+        }
+    }
+
+    /// Switch to the output device, channel mapping and ReplayGain mode of
+    /// the audio profile, or back to the default output with an empty name.
+    fn set_audio_profile(&self, name: &str) -> bool {
+        let profile = self.settings.borrow().find_audio_profile(name).cloned();
+        if profile.is_none() && !name.is_empty() {
+            return false;
+        }
+        let device = profile
+            .as_ref()
+            .map(|profile| profile.device.as_str())
+            .filter(|device| !device.is_empty());
+        if let Err(e) = self.player.set_audio_device(device) {
+            self.ui_context
+                .display_error_dialog("Unable to switch the audio output", Some(&e.to_string()));
+            return false;
+        }
+        if let Some(AudioProfile {
+            channel_remap,
+            replaygain_mode,
+            ..
+        }) = profile
+        {
+            self.channel_remap_action
+                .change_state(&channel_remap.name().to_variant());
+            self.replay_gain_action
+                .change_state(&replaygain_mode.name().to_variant());
+        }
+        self.settings.borrow_mut().audio_profile = Some(name.to_string()).filter(|name| !name.is_empty());
+        self.save_settings();
+        let label = if name.is_empty() { "Default output" } else { name };
+        self.ui_context.show_osd_message(&format!("Audio profile: {}", label));
+        true
+    }
+
+    /// Pause or mute, as configured, when an audio output goes away, so that
+    /// unplugging headphones doesn't switch playback to the speakers. Playback
+    /// is never resumed automatically.
+    fn audio_device_removed(&self, name: &str) {
+        let device_action = self.settings.borrow().pause_on_device_change;
+        let (action, verb) = match device_action {
+            DeviceChangeAction::Ignore => return,
+            DeviceChangeAction::Pause => (&self.pause_action, "paused"),
+            DeviceChangeAction::Mute => (&self.audio_mute_action, "muted"),
+        };
+        let already_applied = action.get_state().and_then(|state| state.get::<bool>()).unwrap_or(true);
+        if !already_applied {
+            action.change_state(&true.to_variant());
+            self.ui_context
+                .show_osd_message(&format!("{} removed, playback {}", name, verb));
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn position_seconds(&self) -> f64 {
+        self.player.get_position().nseconds().map_or(0.0, |ns| ns as f64 / 1e9)
+    }
+
+    #[cfg(target_os = "macos")]
+    fn remote_command(&self, command: RemoteCommand) {
+        let paused = self
+            .pause_action
+            .get_state()
+            .and_then(|state| state.get::<bool>())
+            .unwrap_or(false);
+        match command {
+            RemoteCommand::TogglePlayPause => self.pause_action.change_state(&(!paused).to_variant()),
+            RemoteCommand::Play if paused => self.pause_action.change_state(&false.to_variant()),
+            RemoteCommand::Pause if !paused => self.pause_action.change_state(&true.to_variant()),
+            RemoteCommand::Play | RemoteCommand::Pause => {}
+            RemoteCommand::Next => {
+                self.cancel_auto_advance();
+                self.player.next();
+            }
+            RemoteCommand::Previous => self.playlist_previous_action.activate(None),
+            RemoteCommand::SkipForward => self.seek(self.seek_direction(true, false)),
+            RemoteCommand::SkipBackward => self.seek(self.seek_direction(false, false)),
+        }
+    }
+
+    pub fn cycle_aspect_ratio(&self) {
+        let current = self.player.get_aspect_ratio();
+        let index = ASPECT_RATIO_CYCLE
+            .iter()
+            .position(|ratio| *ratio == current)
+            .map_or(0, |index| (index + 1) % ASPECT_RATIO_CYCLE.len());
+        self.aspect_ratio_action
+            .change_state(&ASPECT_RATIO_CYCLE[index].name().to_variant());
+    }
+
+    // Override the aspect ratio of the current media, and remember it.
+    fn set_aspect_ratio(&self, ratio: AspectRatio) {
+        let uri = match self.player.get_current_uri() {
+            Some(uri) => uri,
+            None => return,
+        };
+        self.player.set_aspect_ratio(ratio);
+        self.player.update_media_overrides(&uri, |overrides| {
+            overrides.aspect_ratio = Some(ratio.name()).filter(|_| ratio != AspectRatio::Auto);
+        });
+        let label = match ratio {
+            AspectRatio::Auto => "automatic".to_string(),
+            AspectRatio::Stretch => "stretched".to_string(),
+            AspectRatio::Ratio(..) => ratio.name(),
+        };
+        self.ui_context.show_osd_message(&format!("Aspect ratio: {}", label));
+    }
+
+    pub fn toggle_previous_item(&self) {
+        self.cancel_auto_advance();
+        match self.player.toggle_previous_item() {
+            Some(uri) => self
+                .ui_context
+                .show_osd_message(&format!("Now playing {}", uri_display_name(&uri))),
+            None => self.ui_context.show_osd_message("No previous media"),
+        }
+    }
+
+    // Moving through the playlist needs more than one item.
+    fn update_playlist_actions(&self) {
+        let (playlist, _) = self.player.get_playlist();
+        self.playlist_next_action.set_enabled(playlist.len() > 1);
+        self.playlist_previous_action.set_enabled(playlist.len() > 1);
+    }
+
+    /// Play the media as a new playlist, starting with the first one, or add
+    /// them to the current one, as configured.
+    pub fn open_multiple(&self, uris: Vec<std::string::String>) {
+        match self.settings.borrow().open_multiple_action {
+            OpenMultipleAction::Replace => {
+                self.player.stop();
+                self.player.load_playlist(uris);
+            }
+            OpenMultipleAction::Append => {
+                let count = uris.len();
+                self.player.append_to_playlist(uris);
+                self.update_playlist_actions();
+                self.ui_context
+                    .show_osd_message(&format!("{} files added to the playlist", count));
+            }
+        }
+    }
+
+    /// Forget the resume position, the overrides and the downloaded
+    /// subtitles of the current media.
+    pub fn forget_media(&self) {
+        let uri = match self.player.get_current_uri() {
+            Some(uri) => uri,
+            None => return,
+        };
+        let result = self.player.forget_media(&uri);
+        if let (Some(directory), Ok((path, _))) = (&self.subtitle_cache_directory, glib::filename_from_uri(&uri)) {
+            if let Err(e) = remove_cached_subtitles(directory, Some(&path)) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    eprintln!("Unable to remove the downloaded subtitles: {}", e);
+                }
+            }
+        }
+        match result {
+            Ok(_) => self
+                .ui_context
+                .show_osd_message(&format!("Forgot {}", uri_display_name(&uri))),
+            Err(e) => self
+                .ui_context
+                .display_error_dialog("Unable to forget the media", Some(&e.to_string())),
+        }
+    }
+
+    /// Write the playlist to an M3U file, with the duration and title of the
+    /// current media, the only ones known.
+    pub fn save_playlist(&self) {
+        let (playlist, index) = self.player.get_playlist();
+        if playlist.is_empty() {
+            self.ui_context.show_osd_message("The playlist is empty");
+            return;
+        }
+        let current_uri = playlist.get(index).map(|uri| glib::GString::from(uri.as_str()));
+        let mut path = match self.ui_context.save_dialog_result(current_uri, "playlist.m3u") {
+            Some(path) => path,
+            None => return,
+        };
+        if path.extension().is_none() {
+            path.set_extension("m3u");
+        }
+        let path = match self.output_path(path) {
+            Some(path) => path,
+            None => return,
+        };
+
+        let info = self.player.get_media_info();
+        let items = playlist
+            .into_iter()
+            .map(|uri| {
+                let (duration, title) = match info {
+                    Some(ref info) if info.get_uri().as_str() == uri => (
+                        info.get_duration().seconds(),
+                        info.get_title().map(std::string::String::from),
+                    ),
+                    _ => (None, None),
+                };
+                playlist::PlaylistFileItem { uri, duration, title }
+            })
+            .collect::<Vec<_>>();
+        match playlist::write_m3u(&path, &items, index) {
+            Ok(_) => self
+                .ui_context
+                .show_osd_message(&format!("Playlist saved to {}", path.display())),
+            Err(e) => self
+                .ui_context
+                .display_error_dialog("Unable to save the playlist", Some(&e.to_string())),
+        }
+    }
+
+    /// Save the settings, the resume positions and the per-file settings to
+    /// a single file, to be imported on another machine.
+    pub fn export_config(&self) {
+        let path = match self.ui_context.save_dialog_result(None, "glide-settings.json") {
+            Some(path) => path,
+            None => return,
+        };
+        if path.exists() {
+            let message = format!("Replace {}?", path.display());
+            if !self.ui_context.confirm_dialog(&message, None) {
+                return;
+            }
+        }
+        self.player.write_last_known_media_position();
+        self.write_session();
+        match config_archive::export_config(&self.config_files, &path) {
+            Ok(_) => self.ui_context.show_osd_message("Settings exported"),
+            Err(e) => self
+                .ui_context
+                .display_error_dialog("Unable to export the settings", Some(&e.to_string())),
+        }
+    }
+
+    /// Import an exported file, merged with the current settings or
+    /// replacing them, then quit so that the next launch uses it.
+    pub fn import_config(&self) {
+        let path = match self.ui_context.open_file_dialog_result("Import settings") {
+            Some(path) => path,
+            None => return,
+        };
+        let details =
+            "Merging keeps the resume positions and per-file settings of media missing from the imported file.";
+        let mode = match self.ui_context.choice_dialog(
+            "Merge with the current settings?",
+            Some(details),
+            &["Merge", "Replace"],
+        ) {
+            Some(0) => ImportMode::Merge,
+            Some(_) => ImportMode::Replace,
+            None => return,
+        };
+        if let Err(e) = config_archive::import_config(&self.config_files, &path, mode) {
+            self.ui_context
+                .display_error_dialog("Unable to import the settings", Some(&e.to_string()));
+            return;
+        }
+        self.ui_context.display_info_dialog(
+            "Settings imported",
+            Some("Glide will now close, the imported settings apply from its next launch."),
+        );
+        // Without saving the current state over the imported one.
+        self.leave_fullscreen();
+        self.ui_context.stop();
+    }
+
+    // Save the playlist for the next launch without media, leaving the
+    // previous session untouched when nothing is loaded.
+    fn write_session(&self) {
+        let path = match self.session_path {
+            Some(ref path) => path,
+            None => return,
+        };
+        let (playlist, index) = self.player.get_playlist();
+        if playlist.is_empty() {
+            return;
+        }
+        if let Err(e) = (Session { playlist, index }).write(path) {
+            eprintln!("Unable to save the session: {}", e);
+        }
+    }
+
+    fn start_without_media(&self) {
+        let behavior = self.settings.borrow().no_args_behavior;
+        let session = match behavior {
+            NoArgsBehavior::EmptyWindow => return,
+            NoArgsBehavior::OpenDialog => {
+                self.open_media_action.activate(None);
+                return;
+            }
+            NoArgsBehavior::ContinueWatching | NoArgsBehavior::ResumeLastSession => {
+                match self.session_path.as_ref().map(Session::read) {
+                    Some(Ok(session)) => session,
+                    Some(Err(e)) => {
+                        eprintln!("No previous session to resume: {}", e);
+                        return;
+                    }
+                    None => return,
+                }
+            }
+        };
+        if behavior == NoArgsBehavior::ResumeLastSession && session.index < session.playlist.len() {
+            self.player.load_playlist_at(session.playlist, session.index);
+        } else if let Some(uri) = session.current_uri() {
+            self.player.load_uri(uri);
+        }
+    }
+
+    /// Forget the resume positions, the overrides and the downloaded
+    /// subtitles of all media, once confirmed.
+    pub fn forget_all_media(&self) {
+        let details = "Resume positions, per-file settings and downloaded subtitles will be removed.";
+        if !self.ui_context.confirm_dialog("Forget all files?", Some(details)) {
+            return;
+        }
+        let result = self.player.forget_all_media();
+        if let Some(ref directory) = self.subtitle_cache_directory {
+            if let Err(e) = remove_cached_subtitles(directory, None) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    eprintln!("Unable to remove the downloaded subtitles: {}", e);
+                }
+            }
+        }
+        if let Some(ref path) = self.session_path {
+            let _ = std::fs::remove_file(path);
+        }
+        match result {
+            Ok(_) => self.ui_context.show_osd_message("Forgot all files"),
+            Err(e) => self
+                .ui_context
+                .display_error_dialog("Unable to forget the media", Some(&e.to_string())),
+        }
+    }
+
+    // Offsets set manually are remembered for the media, unless they are the
+    // one of its folder.
+    fn subtitle_offset_adjusted(&self, offset: i64) {
+        self.player.set_subtitle_offset(offset);
+        let uri = match self.player.get_current_uri() {
+            Some(uri) => uri,
+            None => return,
+        };
+        let folder_offset = media_folder(&uri)
+            .and_then(|folder| self.player.folder_overrides(&folder).subtitle_offset)
+            .unwrap_or(0);
+        self.player.update_media_overrides(&uri, |overrides| {
+            overrides.subtitle_offset = Some(offset).filter(|offset| *offset != folder_offset);
+        });
+    }
+
+    // Turn the video a quarter clockwise, back to upright after four turns.
+    fn rotate_video(&self) {
+        let rotation = (self.player.get_video_rotation() + 90) % 360;
+        if self.player.set_video_rotation(rotation) {
+            self.ui_context.show_osd_message(&format!("Rotation {}°", rotation));
+        } else {
+            self.ui_context
+                .display_error_dialog("Unable to rotate the video", Some("The videoflip element is missing"));
+        }
+    }
+
+    // Step one of the color adjustments, named as in the targets of the
+    // video-color-increase and video-color-decrease actions.
+    fn step_video_color(&self, channel: &str, step: f64) {
+        let mut balance = self.player.get_video_balance();
+        let value = match channel {
+            "brightness" => &mut balance.brightness,
+            "contrast" => &mut balance.contrast,
+            "saturation" => &mut balance.saturation,
+            "hue" => &mut balance.hue,
+            _ => {
+                eprintln!("Unknown video color channel: {}", channel);
+                return;
+            }
+        };
+        *value = ((*value + step) * 100.0).round() / 100.0;
+        let balance = self.player.set_video_balance(balance);
+        self.show_video_balance(&balance);
+    }
+
+    fn show_video_balance(&self, balance: &VideoBalance) {
+        self.ui_context.show_osd_message(&format!(
+            "Brightness {:+.2}, contrast {:.2}, saturation {:.2}, hue {:+.2}",
+            balance.brightness, balance.contrast, balance.saturation, balance.hue
+        ));
+    }
+
+    // Zooming by hand replaces the zoom to fill.
+    fn step_video_zoom(&self, step: f64) {
+        if self
+            .zoom_to_fill_action
+            .get_state()
+            .and_then(|state| state.get::<bool>())
+            == Some(true)
+        {
+            self.zoom_to_fill_action.change_state(&false.to_variant());
+        }
+        let factor = ((self.player.get_video_zoom() + step) * 10.0).round() / 10.0;
+        let factor = factor.max(1.0).min(VIDEO_ZOOM_MAX);
+        if self.player.set_video_zoom(factor) {
+            self.ui_context
+                .show_osd_message(&format!("Zoom {}%", (factor * 100.0).round()));
+        } else {
+            self.ui_context
+                .show_osd_message("Zooming requires the videocrop element");
+        }
+    }
+
+    fn step_audio_delay(&self, step: i64) {
+        let offset = self.player.get_audio_offset() + step;
+        self.player.set_audio_offset(offset);
+        self.ui_context
+            .show_osd_message(&format!("Audio delay {:+} ms", offset / 1_000_000));
+    }
+
+    fn step_subtitle_delay(&self, step: i64) {
+        let offset = self.player.get_subtitle_offset() + step;
+        self.subtitle_offset_adjusted(offset);
+        self.ui_context
+            .show_osd_message(&format!("Subtitle delay {:+} ms", offset / 1_000_000));
+    }
+
+    // Apply the subtitle offset stored for the media, or else for its
+    // folder. Without any, the current offset is kept.
+    fn apply_stored_subtitle_offset(&self, uri: &str) {
+        let folder = media_folder(uri);
+        let offset = self.player.media_overrides(uri).subtitle_offset.or_else(|| {
+            folder
+                .as_ref()
+                .and_then(|folder| self.player.folder_overrides(folder).subtitle_offset)
+        });
+        match (offset, folder) {
+            (Some(offset), _) => self.player.set_subtitle_offset(offset),
+            (None, Some(folder)) => {
+                let uri = uri.to_string();
+                glib::idle_add_local(move || {
+                    with_video_player!(video_player {
+                        video_player.offer_folder_subtitle_offset(&uri, &folder);
+                    });
+                    glib::Continue(false)
+                });
+            }
+            (None, None) => {}
+        }
+    }
+
+    // Media of a folder usually come from the same release, and share its
+    // synchronization errors. Once the same offset was set for several of
+    // them, offer to use it for the whole folder.
+    fn offer_folder_subtitle_offset(&self, uri: &str, folder: &Path) {
+        if !self.settings.borrow().subtitle_offset_folder_prompt
+            || self.declined_offset_folders.borrow().contains(folder)
+            || self.player.get_current_uri().as_deref() != Some(uri)
+        {
+            return;
+        }
+        let offsets = playlist::folder_playlist(folder, PlaylistSort::Alphabetical)
+            .iter()
+            .filter(|other| other.as_str() != uri)
+            .filter_map(|other| self.player.media_overrides(other).subtitle_offset)
+            .collect::<Vec<_>>();
+        let offset = match offsets.first() {
+            Some(first)
+                if offsets.len() >= FOLDER_SUBTITLE_OFFSET_MIN_FILES
+                    && offsets
+                        .iter()
+                        .all(|offset| (offset - first).abs() <= FOLDER_SUBTITLE_OFFSET_TOLERANCE) =>
+            {
+                *first
+            }
+            _ => return,
+        };
+        let message = format!(
+            "Apply a subtitle offset of {:.2}s to the whole folder?",
+            offset as f64 / 1_000_000_000.0
+        );
+        let details = format!(
+            "The same offset was set for {} other files of {}.",
+            offsets.len(),
+            folder.display()
+        );
+        if self.ui_context.confirm_dialog(&message, Some(&details)) {
+            self.player.set_subtitle_offset(offset);
+            self.use_subtitle_offset_for_folder();
+        } else {
+            self.declined_offset_folders.borrow_mut().insert(folder.to_path_buf());
+        }
+    }
+
+    /// Use the current subtitle offset for the media of the folder of the
+    /// current media without an offset of their own.
+    pub fn use_subtitle_offset_for_folder(&self) {
+        let uri = match self.player.get_current_uri() {
+            Some(uri) => uri,
+            None => return,
+        };
+        let folder = match media_folder(&uri) {
+            Some(folder) => folder,
+            None => {
+                self.ui_context.show_osd_message("Only local files have a folder");
+                return;
+            }
+        };
+        let offset = self.player.get_subtitle_offset();
+        self.player.update_folder_overrides(&folder, |overrides| {
+            overrides.subtitle_offset = Some(offset).filter(|offset| *offset != 0);
+        });
+        self.player
+            .update_media_overrides(&uri, |overrides| overrides.subtitle_offset = None);
+        self.ui_context.show_osd_message(&format!(
+            "Subtitle offset of {:.2}s used for the folder",
+            offset as f64 / 1_000_000_000.0
+        ));
+    }
+
+    /// Put the current video frame on the clipboard, tone-mapped like
+    /// snapshots unless they are kept untouched.
+    pub fn copy_frame(&self) {
+        let raw = self.settings.borrow().snapshot_raw;
+        match self.player.snapshot_pixbuf(raw) {
+            Some(pixbuf) => {
+                let clipboard = gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD);
+                clipboard.set_image(&pixbuf);
+                self.ui_context.show_osd_message("Frame copied");
+            }
+            None => self.ui_context.show_osd_message("No frame to copy"),
+        }
+    }
+
+    /// Dim and desaturate the video according to the night mode schedule,
+    /// or restore the original colors if the night mode is disabled.
+    pub fn update_night_mode(&self) {
+        let settings = self.settings.borrow();
+        let strength = if settings.night_mode {
+            let now = glib::DateTime::new_now_local();
+            let minute = now.get_hour() * 60 + now.get_minute();
+            night_mode_strength(
+                minute as u32,
+                settings.night_mode_start_hour,
+                settings.night_mode_end_hour,
+            )
+        } else {
+            0.0
+        };
+        // 0.5 leaves the channel untouched.
+        let blend = |target: f64| 0.5 + (target - 0.5) * strength;
+        self.player.set_color_balance(
+            gst_player::PlayerColorBalanceType::Brightness,
+            blend(settings.night_mode_brightness),
+        );
+        self.player.set_color_balance(
+            gst_player::PlayerColorBalanceType::Saturation,
+            blend(settings.night_mode_saturation),
+        );
+    }
+
+    /// Jump back and play the last seconds again, possibly slowed down, then
+    /// return to the previous playback rate once the replay caught up.
+    pub fn instant_replay(&self) {
+        let (seconds, replay_rate) = {
+            let settings = self.settings.borrow();
+            (settings.instant_replay_seconds, settings.instant_replay_rate)
+        };
+        let position = match self.player.get_position().nseconds() {
+            Some(position) => position,
+            None => return,
+        };
+        // Replaying during a replay keeps the rate from before the first one.
+        let previous_rate = self
+            .stop_instant_replay()
+            .unwrap_or_else(|| self.player.get_playback_rate());
+
+        let offset = gst::ClockTime::from_seconds(u64::from(seconds)).nseconds().unwrap();
+        self.player
+            .seek_to(gst::ClockTime::from_nseconds(position.saturating_sub(offset)));
+        self.player.play();
+        self.pause_action.set_state(&false.to_variant());
+        self.ui_context.show_osd_message(&format!("Replay {}s", seconds));
+
+        if (replay_rate - previous_rate).abs() < std::f64::EPSILON {
+            return;
+        }
+        self.player.set_playback_rate(replay_rate);
+        let source = glib::timeout_add_local(250, move || {
+            let mut done = false;
+            with_video_player!(video_player {
+                let caught_up = video_player
+                    .player
+                    .get_position()
+                    .nseconds()
+                    .map_or(true, |current| current >= position);
+                if caught_up {
+                    // The source is about to be destroyed, don't remove it twice.
+                    video_player.instant_replay.borrow_mut().take();
+                    video_player.player.set_playback_rate(previous_rate);
+                    done = true;
+                }
+            });
+            glib::Continue(!done)
+        });
+        *self.instant_replay.borrow_mut() = Some((previous_rate, source));
+    }
+
+    /// Seek to a time such as "1:23:45", "83:45" or "12.5", asking for it
+    /// when empty. Invalid times are ignored.
+    pub fn seek_to_time(&self, time: &str) {
+        let time = if time.is_empty() {
+            match self
+                .ui_context
+                .text_dialog_result("Go to time", "Hours, minutes and seconds, e.g. 1:23:45")
+            {
+                Some(time) => time,
+                None => return,
+            }
+        } else {
+            time.to_string()
+        };
+        if let Some(position) = parse_time(&time) {
+            self.player.seek_to(position);
+        }
+    }
+
+    /// Comma-separated languages, asked for when empty. They apply from the
+    /// next media.
+    pub fn set_subtitle_languages(&self, languages: &str) {
+        let languages = if languages.is_empty() {
+            let current = self.settings.borrow().subtitle_languages.join(", ");
+            let details = format!(
+                "Language codes separated by commas, such as en, fr. Currently: {}",
+                if current.is_empty() { "none" } else { &current }
+            );
+            match self
+                .ui_context
+                .text_dialog_result("Preferred subtitle languages", &details)
+            {
+                Some(languages) => languages,
+                None => return,
+            }
+        } else {
+            languages.to_string()
+        };
+        self.settings.borrow_mut().subtitle_languages = languages
+            .split(',')
+            .map(|language| language.trim().to_string())
+            .filter(|language| !language.is_empty())
+            .collect();
+        self.save_settings();
+    }
+
+    /// Change the playback speed chosen by the user, ending any instant
+    /// replay first so that it doesn't restore its own rate afterwards.
+    pub fn set_playback_speed(&self, rate: f64) {
+        self.stop_instant_replay();
+        self.player.set_playback_rate(rate);
+        self.player.write_media_playback_rate();
+        let rate = self.player.get_playback_rate();
+        self.ui_context
+            .show_osd_message(&format!("Speed {}×", playback_rate_name(rate)));
+    }
+
+    // Restore the playback rate of an ongoing replay, returning it.
+    fn stop_instant_replay(&self) -> Option<f64> {
+        let (rate, source) = self.instant_replay.borrow_mut().take()?;
+        glib::source_remove(source);
+        self.player.set_playback_rate(rate);
+        Some(rate)
+    }
+
+    pub fn start_mirror_output(&self) -> bool {
+        let (widget, mirror) = match self.player.add_mirror_output() {
+            Some(output) => output,
+            None => {
+                eprintln!("Unable to create the mirror output, make sure gtksink is available");
+                return false;
+            }
+        };
+        let monitor = self.settings.borrow().mirror_output_monitor;
+        let window = self.ui_context.create_mirror_window(&widget, monitor, || {
+            let mut pending = false;
+            with_video_player!(video_player {
+                pending = video_player.stop_mirror_output();
+            });
+            Inhibit(pending)
+        });
+        *self.mirror_output.borrow_mut() = Some((window, mirror));
+        true
     }
 
-    pub fn playback_state_changed(&self, playback_state: &PlaybackState) {
-        self.ui_context.playback_state_changed(playback_state);
+    /// Returns true if the mirror window has to be kept around until its
+    /// video branch is removed from the pipeline. The window is then closed
+    /// asynchronously.
+    pub fn stop_mirror_output(&self) -> bool {
+        self.mirror_output_action.set_state(&false.to_variant());
+        let mirror_output = self.mirror_output.borrow_mut().take();
+        if let Some((window, mirror)) = mirror_output {
+            window.hide();
+            let window_weak = SendWeakRef::from(window.downgrade());
+            self.player.remove_mirror_output(mirror, move || {
+                glib::idle_add(move || {
+                    if let Some(window) = window_weak.upgrade() {
+                        window.close();
+                    }
+                    glib::Continue(false)
+                });
+            });
+            return true;
+        }
+        false
     }
 
-    pub fn video_dimensions_changed(&self, width: i32, height: i32) {
-        self.ui_context.resize_window(width, height);
+    pub fn cancel_auto_advance(&self) {
+        if let Some(source) = self.auto_advance_source.borrow_mut().take() {
+            glib::source_remove(source);
+        }
+        self.ui_context.hide_auto_advance_countdown();
     }
 
     pub fn media_info_updated(&self) {
+        self.cancel_auto_advance();
+        if self.settings.borrow().night_mode {
+            self.update_night_mode();
+        }
         if let Some(info) = self.player.get_media_info() {
             if let Some(uri) = self.player.get_current_uri() {
                 if let Some(title) = info.get_title() {
-                    self.ui_context.set_window_title(&*title);
+                    self.set_media_title(&*title);
                 } else if let Ok((filename, _)) = glib::filename_from_uri(&uri) {
-                    self.ui_context
-                        .set_window_title(&filename.as_os_str().to_string_lossy());
+                    self.set_media_title(&filename.as_os_str().to_string_lossy());
                 } else {
-                    self.ui_context.set_window_title(&uri);
+                    self.set_media_title(&uri);
                 }
 
                 if let Some(duration) = info.get_duration().seconds() {
                     self.ui_context.set_position_range_end(duration as f64);
                 }
 
-                // Look for a matching subtitle file in same directory.
-                if let Ok((mut path, _)) = glib::filename_from_uri(&uri) {
-                    for extension in constants::SUB_FILE_EXTENSIONS.iter() {
-                        path.set_extension(extension);
-                        let subfile = path.as_path();
-                        if subfile.is_file() {
-                            if let Ok(suburi) = glib::filename_to_uri(subfile, None) {
-                                self.player
-                                    .configure_subtitle_track(Some(SubtitleTrack::External(suburi)));
-                                break;
+                #[cfg(target_os = "macos")]
+                {
+                    if let Some(ref now_playing) = self.now_playing {
+                        let duration = info.get_duration().nseconds().map(|ns| ns as f64 / 1e9);
+                        now_playing.set_media(&self.window_title.borrow().media_title, duration);
+                    }
+                }
+
+                let carried_tracks = self.player.restore_carried_tracks();
+                let subtitles_visible = carried_tracks.unwrap_or(true);
+
+                let subtitle_encoding = self.player.media_overrides(&uri).subtitle_encoding;
+                self.player.set_subtitle_encoding(subtitle_encoding.as_deref());
+                self.subtitle_encoding_action
+                    .set_state(&subtitle_encoding.as_deref().unwrap_or("auto").to_variant());
+
+                let first_update = self.keyword_tracks_uri.borrow().as_deref() != Some(uri.as_str());
+                if first_update {
+                    self.external_subtitles.borrow_mut().clear();
+                }
+
+                // Look for a matching subtitle file in same directory, unless
+                // subtitles were turned off on the previous playlist item. Once
+                // loaded, another external subtitle file may be selected instead.
+                let mut external_subtitle = false;
+                let media_path = glib::filename_from_uri(&uri).ok().filter(|_| subtitles_visible);
+                if let Some((path, _)) = media_path {
+                    if let Some(subfile) = subtitle_files::find_matching_subtitle(&path) {
+                        if let Ok(suburi) = glib::filename_to_uri(&subfile, None) {
+                            if !self.external_subtitles.borrow().contains(&suburi.to_string()) {
+                                self.add_external_subtitle(suburi);
                             }
+                            external_subtitle = true;
                         }
                     }
                 }
+
+                // Tracks carried over from the previous playlist item win over
+                // the keywords, and a subtitle file over the subtitle keywords.
+                if first_update {
+                    *self.keyword_tracks_uri.borrow_mut() = Some(uri.to_string());
+                    self.ui_context.hide_still_frame();
+                    *self.subtitle_fetch.borrow_mut() = None;
+                    if subtitles_visible && !external_subtitle && info.get_subtitle_streams().is_empty() {
+                        self.download_subtitles(&uri);
+                    }
+                    let aspect_ratio = self
+                        .player
+                        .media_overrides(&uri)
+                        .aspect_ratio
+                        .as_deref()
+                        .and_then(AspectRatio::from_name)
+                        .unwrap_or(AspectRatio::Auto);
+                    self.player.set_aspect_ratio(aspect_ratio);
+                    self.player.set_video_zoom(1.0);
+                    if self.settings.borrow().seek_thumbnails && info.get_number_of_video_streams() > 0 {
+                        self.player.extract_seek_thumbnails(&uri);
+                    } else {
+                        self.player.clear_seek_thumbnails();
+                    }
+                    self.aspect_ratio_action.set_state(&aspect_ratio.name().to_variant());
+                    self.apply_stored_subtitle_offset(&uri);
+                    // Playbin keeps tracks disabled across media.
+                    if track_disabled(&self.audio_track_action) {
+                        self.audio_track_action.change_state(&"audio-0".to_variant());
+                    }
+                    if track_disabled(&self.video_track_action) {
+                        self.video_track_action.change_state(&"video-0".to_variant());
+                    }
+                    if carried_tracks.is_none() {
+                        self.select_tracks_by_keywords(&info, !external_subtitle);
+                    }
+                }
             }
             self.refresh_subtitle_track_menu();
             self.fill_audio_track_menu(&info);
             self.fill_video_track_menu(&info);
+            self.fill_aspect_ratio_menu(&info);
+            self.update_playlist_actions();
+
+            let toggled_by_user = *self.fullscreen_toggled_uri.borrow() == self.player.get_current_uri();
+            if self.settings.borrow().auto_fullscreen && !self.force_fullscreen && !toggled_by_user {
+                if info.get_number_of_video_streams() > 0 {
+                    self.enter_fullscreen();
+                } else {
+                    self.leave_fullscreen();
+                }
+            }
+
+            let has_video = info.get_number_of_video_streams() > 0 && !track_disabled(&self.video_track_action);
+            self.copy_frame_action.set_enabled(has_video);
+            self.snapshot_action.set_enabled(has_video);
+            self.video_rotate_action.set_enabled(has_video);
 
             if info.get_number_of_video_streams() == 0 {
                 self.fill_audio_visualization_menu();
@@ -582,9 +4458,51 @@ impl VideoPlayer {
     }
 
     pub fn position_updated(&self) {
+        if let Some((start, end)) = self.player.get_loop() {
+            if self.player.get_position() >= end {
+                self.player.seek_to(start);
+            }
+        }
         if let Some(position) = self.player.get_position().seconds() {
             self.ui_context.set_position_range_value(position);
         }
+        self.update_frame_stats();
+        #[cfg(target_os = "macos")]
+        {
+            if let Some(ref now_playing) = self.now_playing {
+                now_playing.set_position(self.position_seconds(), self.player.get_playback_rate());
+            }
+        }
+    }
+
+    fn frame_stats_text(&self) -> String {
+        let stats = self.player.get_frame_stats();
+        let count = |count: Option<u64>| count.map_or_else(|| "unknown".to_string(), |count| count.to_string());
+        format!("Rendered: {}, dropped: {}", count(stats.rendered), count(stats.dropped))
+    }
+
+    pub fn update_frame_stats(&self) {
+        if self.settings.borrow().show_frame_stats {
+            self.ui_context.show_frame_stats(Some(&self.frame_stats_text()));
+        } else {
+            self.ui_context.show_frame_stats(None);
+        }
+    }
+
+    pub fn display_frame_stats(&self) {
+        let stats = self.player.get_frame_stats();
+        let mut details = format!(
+            "{}\nVideo output: {}",
+            self.frame_stats_text(),
+            self.player.video_sink_name()
+        );
+        if let (Some(rendered), Some(dropped)) = (stats.rendered, stats.dropped) {
+            if rendered + dropped > 0 {
+                let ratio = dropped as f64 * 100.0 / (rendered + dropped) as f64;
+                details.push_str(&format!("\nDropped: {:.1}%", ratio));
+            }
+        }
+        self.ui_context.display_info_dialog("Video statistics", Some(&details));
     }
 
     pub fn update_subtitle_track(&self, value: Option<&glib::Variant>) {
@@ -602,71 +4520,268 @@ impl VideoPlayer {
                     }
                 };
                 self.player.configure_subtitle_track(track);
+                if self.subtitles_hidden() {
+                    let hidden_track = Some(val.clone()).filter(|track| track != "none");
+                    if hidden_track.is_some() {
+                        self.player.set_subtitle_track_enabled(false);
+                    }
+                    *self.hidden_subtitle_track.borrow_mut() = hidden_track;
+                }
+                self.subtitle_track_chosen(val);
             }
             self.subtitle_action.set_state(&val);
         }
     }
 
-    pub fn refresh_subtitle_track_menu(&self) {
-        let section = gio::Menu::new();
-        let mut selected_action: Option<std::string::String> = None;
+    fn subtitles_hidden(&self) -> bool {
+        self.subtitle_toggle_action
+            .get_state()
+            .and_then(|state| state.get::<bool>())
+            == Some(false)
+    }
 
-        if let Some(info) = self.player.get_media_info() {
-            let item = gio::MenuItem::new(Some("Disable"), Some("none"));
-            item.set_detailed_action("app.subtitle::none");
-            section.append_item(&item);
+    // Hiding keeps the selected track, shown again as is. Without any, the
+    // first track of the media is shown.
+    fn set_subtitles_visible(&self, visible: bool) {
+        let current = self
+            .subtitle_action
+            .get_state()
+            .and_then(|state| state.get::<std::string::String>())
+            .filter(|track| track != "none");
+        if !visible {
+            *self.hidden_subtitle_track.borrow_mut() = current;
+            self.player.set_subtitle_track_enabled(false);
+            self.ui_context.show_osd_message("Subtitles hidden");
+            return;
+        }
+        let track = self.hidden_subtitle_track.borrow_mut().take().or(current).or_else(|| {
+            self.subtitle_track_choices()
+                .into_iter()
+                .map(|(_, target)| target)
+                .find(|target| target != "none")
+        });
+        match track {
+            Some(track) => {
+                self.update_subtitle_track(Some(&track.to_variant()));
+                self.ui_context.show_osd_message("Subtitles shown");
+            }
+            None => self.ui_context.show_osd_message("No subtitles"),
+        }
+    }
 
-            let current_subtitle_track = self.player.get_current_subtitle_track();
-            for (i, sub_stream) in info.get_subtitle_streams().into_iter().enumerate() {
-                let default_title = format!("Track {}", i + 1);
-                let title = match sub_stream.get_tags() {
-                    Some(tags) => match tags.get::<gst::tags::Title>() {
-                        Some(val) => std::string::String::from(val.get().unwrap()),
-                        None => default_title,
-                    },
-                    None => default_title,
-                };
-                let lang = sub_stream.get_language().map(|l| {
-                    if l == title {
-                        "".to_string()
-                    } else {
-                        format!(" - [{}]", l)
-                    }
-                });
+    // The offset of a subtitle track rarely suits another one. The first track
+    // configured for a media keeps the stored offset.
+    fn subtitle_track_chosen(&self, track: std::string::String) {
+        let uri = match self.player.get_current_uri() {
+            Some(uri) => uri,
+            None => return,
+        };
+        let uri = uri.to_string();
+        let previous = self.subtitle_track_choice.replace(Some((uri.clone(), track.clone())));
+        let changed = match previous {
+            Some((previous_uri, previous_track)) => previous_uri == uri && previous_track != track,
+            None => false,
+        };
+        if changed && self.player.get_subtitle_offset() != 0 {
+            self.subtitle_offset_adjusted(0);
+        }
+    }
 
-                let action_label = format!("{}{}", title, lang.unwrap_or_else(|| "".to_string()));
-                let action_id = format!("app.subtitle::sub-{}", i);
-                let item = gio::MenuItem::new(Some(&action_label), Some(&action_id));
-                item.set_detailed_action(&*action_id);
-                section.append_item(&item);
+    pub fn save_settings(&self) {
+        if let Err(e) = self.settings.borrow().write() {
+            eprintln!("Unable to save settings: {}", e);
+        }
+    }
 
-                if selected_action.is_none() {
-                    if let Some(ref track) = current_subtitle_track {
-                        if track.get_language() == sub_stream.get_language() {
-                            selected_action = Some(format!("sub-{}", i));
-                        }
-                    }
-                }
+    pub fn update_download_config(&self) {
+        let settings = self.settings.borrow();
+        let config = match self.download_directory {
+            Some(ref directory) if settings.download_first => Some(DownloadConfig {
+                directory: directory.clone(),
+                max_size: settings.download_max_size * 1024 * 1024,
+            }),
+            _ => None,
+        };
+        self.player.set_download_config(config);
+    }
+
+    pub fn update_subtitle_style(&self) {
+        let settings = self.settings.borrow();
+        self.player.set_subtitle_style(SubtitleStyle {
+            background: settings.subtitle_background,
+            background_opacity: settings.subtitle_background_opacity,
+            scale: settings.subtitle_scale,
+            scale_base: settings.subtitle_scale_base,
+            script: settings.subtitle_script,
+            font: settings.subtitle_font.clone(),
+            color: settings.subtitle_color.as_deref().and_then(parse_color),
+        });
+        drop(settings);
+        self.save_settings();
+    }
+
+    fn choose_subtitle_font(&self) {
+        let current = self.settings.borrow().subtitle_font.clone();
+        if let Some(font) = self.ui_context.font_dialog_result("Subtitle font", current.as_deref()) {
+            self.settings.borrow_mut().subtitle_font = Some(font);
+            self.update_subtitle_style();
+        }
+    }
+
+    fn choose_subtitle_color(&self) {
+        let current = self.settings.borrow().subtitle_color.as_deref().and_then(parse_color);
+        if let Some(color) = self.ui_context.color_dialog_result("Subtitle color", current) {
+            self.settings.borrow_mut().subtitle_color = Some(color_name(color));
+            self.update_subtitle_style();
+        }
+    }
+
+    // Remembered for the media, its subtitle file being reloaded with the
+    // encoding. "auto" goes back to guessing it.
+    fn subtitle_encoding_chosen(&self, name: &str) {
+        let encoding = Some(name).filter(|name| *name != "auto");
+        self.player.set_subtitle_encoding(encoding);
+        if let Some(uri) = self.player.get_current_uri() {
+            self.player.update_media_overrides(&uri, |overrides| {
+                overrides.subtitle_encoding = encoding.map(std::string::String::from);
+            });
+        }
+        let track = self.subtitle_action.get_state();
+        self.update_subtitle_track(track.as_ref());
+    }
+
+    /// Load an external subtitle file and keep it in the subtitle track menu
+    /// until another media is loaded.
+    fn add_external_subtitle(&self, uri: glib::GString) {
+        {
+            let mut external_subtitles = self.external_subtitles.borrow_mut();
+            let known = uri.to_string();
+            if !external_subtitles.contains(&known) {
+                external_subtitles.push(known);
             }
         }
+        self.player.configure_subtitle_track(Some(SubtitleTrack::External(uri)));
+    }
 
+    fn subtitle_track_choices(&self) -> Vec<(std::string::String, std::string::String)> {
+        let mut choices = vec![];
+        if let Some(info) = self.player.get_media_info() {
+            choices.push(("Disable".to_string(), "none".to_string()));
+            for (i, sub_stream) in info.get_subtitle_streams().iter().enumerate() {
+                choices.push((subtitle_stream_label(i, sub_stream), format!("sub-{}", i)));
+            }
+        }
+        let mut uris = self.external_subtitles.borrow().clone();
         if let Some(uri) = self.player.get_subtitle_uri() {
-            if let Ok((path, _)) = glib::filename_from_uri(&uri) {
-                let subfile = path.as_path();
-                if let Some(filename) = subfile.file_name() {
-                    if let Some(f) = filename.to_str() {
-                        let v = format!("ext-{}", uri);
-                        let action_id = format!("app.subtitle::{}", v);
-                        let item = gio::MenuItem::new(Some(f), Some(&action_id));
-                        item.set_detailed_action(&*action_id);
-                        section.append_item(&item);
-                        selected_action = Some(v);
-                    }
+            let uri = uri.to_string();
+            if !uris.contains(&uri) {
+                uris.push(uri);
+            }
+        }
+        for uri in &uris {
+            if let Ok((path, _)) = glib::filename_from_uri(uri) {
+                if let Some(filename) = path.file_name() {
+                    choices.push((filename.to_string_lossy().into_owned(), format!("ext-{}", uri)));
                 }
             }
         }
+        choices
+    }
+
+    /// Select the audio and subtitle tracks according to the keywords of the
+    /// settings, matched against the track titles.
+    fn select_tracks_by_keywords(&self, info: &gst_player::PlayerMediaInfo, subtitles: bool) {
+        let settings = self.settings.borrow();
+        let streams = info.get_audio_streams();
+        let titles = streams.iter().map(stream_title).collect::<Vec<_>>();
+        let current = self
+            .player
+            .get_current_audio_track()
+            .and_then(|track| streams.iter().position(|s| s.get_index() == track.get_index()));
+        let audio = track_by_keywords(
+            &titles,
+            current,
+            &settings.audio_prefer_keywords,
+            &settings.audio_avoid_keywords,
+        );
+
+        let streams = info.get_subtitle_streams();
+        let titles = streams.iter().map(stream_title).collect::<Vec<_>>();
+        let current = self
+            .player
+            .get_current_subtitle_track()
+            .and_then(|track| streams.iter().position(|s| s.get_index() == track.get_index()));
+        let subtitle = if settings.subtitle_languages.is_empty() {
+            track_by_keywords(
+                &titles,
+                current,
+                &settings.subtitle_prefer_keywords,
+                &settings.subtitle_avoid_keywords,
+            )
+            .map(|index| format!("sub-{}", index))
+        } else {
+            let languages = streams
+                .iter()
+                .map(|stream| stream_languages(stream, stream.get_language()))
+                .collect::<Vec<_>>();
+            let candidates = tracks_by_language(&languages, &settings.subtitle_languages);
+            if candidates.is_empty() {
+                Some("none".to_string())
+            } else {
+                let candidate_titles = candidates
+                    .iter()
+                    .map(|&index| titles[index].clone())
+                    .collect::<Vec<_>>();
+                let index = track_by_keywords(
+                    &candidate_titles,
+                    Some(0),
+                    &settings.subtitle_prefer_keywords,
+                    &settings.subtitle_avoid_keywords,
+                )
+                .unwrap_or(0);
+                Some(format!("sub-{}", candidates[index]))
+            }
+        }
+        .filter(|_| subtitles);
+        drop(settings);
 
-        self.ui_context.update_subtitle_track_menu(&section);
+        if let Some(index) = audio {
+            self.audio_track_action
+                .change_state(&format!("audio-{}", index).to_variant());
+        }
+        if let Some(target) = subtitle {
+            self.subtitle_action.change_state(&target.to_variant());
+        }
+    }
+
+    pub fn refresh_subtitle_track_menu(&self) {
+        let choices = self.subtitle_track_choices();
+        self.ui_context
+            .update_subtitle_track_menu(&track_menu_section("subtitle", &choices));
+
+        let mut selected_action = self
+            .player
+            .get_subtitle_uri()
+            .map(|uri| format!("ext-{}", uri))
+            .filter(|target| choices.iter().any(|(_, choice)| choice == target));
+        // Playbin doesn't report the track of hidden subtitles.
+        if selected_action.is_none() && self.subtitles_hidden() {
+            selected_action = self
+                .hidden_subtitle_track
+                .borrow()
+                .clone()
+                .filter(|track| choices.iter().any(|(_, target)| target == track));
+        }
+        if selected_action.is_none() {
+            if let (Some(info), Some(track)) = (self.player.get_media_info(), self.player.get_current_subtitle_track())
+            {
+                selected_action = info
+                    .get_subtitle_streams()
+                    .iter()
+                    .position(|sub_stream| sub_stream.get_language() == track.get_language())
+                    .map(|i| format!("sub-{}", i));
+            }
+        }
 
         let v = match selected_action {
             Some(a) => a.to_variant(),
@@ -695,47 +4810,43 @@ impl VideoPlayer {
         self.ui_context.update_audio_visualization_menu(&section);
     }
 
-    pub fn fill_audio_track_menu(&self, info: &gst_player::PlayerMediaInfo) {
-        let section = gio::Menu::new();
-
-        let item = gio::MenuItem::new(Some("Disable"), Some("subtitle"));
-        item.set_detailed_action("app.audio-track::audio--1");
-        section.append_item(&item);
+    fn chapters_updated(&self) {
+        let chapters = self.player.get_chapters();
+        let items = chapters
+            .iter()
+            .map(|chapter| (chapter_label(chapter), chapter.start))
+            .collect::<Vec<_>>();
+        self.ui_context.update_chapter_menu(&items);
+        self.next_chapter_action.set_enabled(!chapters.is_empty());
+        self.previous_chapter_action.set_enabled(!chapters.is_empty());
+        self.ui_context.set_chapter_marks(chapters);
+    }
 
-        for (i, audio_stream) in info.get_audio_streams().iter().enumerate() {
-            let mut label = format!("{} channels", audio_stream.get_channels());
-            if let Some(l) = audio_stream.get_language() {
-                label = format!("{} - [{}]", label, l);
-            }
-            let action_id = format!("app.audio-track::audio-{}", i);
-            let item = gio::MenuItem::new(Some(&label), Some(&action_id));
-            item.set_detailed_action(&*action_id);
-            section.append_item(&item);
-        }
+    pub fn fill_audio_track_menu(&self, info: &gst_player::PlayerMediaInfo) {
+        let section = track_menu_section("audio-track", &audio_track_choices(info));
         self.ui_context.update_audio_track_menu(&section);
     }
 
     pub fn fill_video_track_menu(&self, info: &gst_player::PlayerMediaInfo) {
-        let section = gio::Menu::new();
-
-        let item = gio::MenuItem::new(Some("Disable"), Some("subtitle"));
-        item.set_detailed_action("app.video-track::video--1");
-        section.append_item(&item);
-
-        for (i, video_stream) in info.get_video_streams().iter().enumerate() {
-            let action_id = format!("app.video-track::video-{}", i);
-            let description = format!("{}x{}", video_stream.get_width(), video_stream.get_height());
-            let item = gio::MenuItem::new(Some(&description), Some(&action_id));
-            item.set_detailed_action(&*action_id);
-            section.append_item(&item);
-        }
+        let section = track_menu_section("video-track", &video_track_choices(info));
         self.ui_context.update_video_track_menu(&section);
     }
 
+    // The automatic entry tells the aspect ratio of the video itself.
+    pub fn fill_aspect_ratio_menu(&self, info: &gst_player::PlayerMediaInfo) {
+        let section = track_menu_section("aspect-ratio", &aspect_ratio_choices(info));
+        self.ui_context.update_aspect_ratio_menu(&section);
+    }
+
     pub fn open_files(&mut self, files: &[gio::File]) {
         let mut playlist = vec![];
+        let sort = self.settings.borrow().playlist_sort;
         for file in files.to_vec() {
-            let uri = if let Some(_path) = file.get_path() {
+            let uri = if let Some(path) = file.get_path() {
+                if path.is_dir() {
+                    playlist.extend(playlist::folder_playlist(&path, sort));
+                    continue;
+                }
                 Some(std::string::String::from(file.get_uri().as_str()))
             } else {
                 // Gio built an invalid URI, so try to find the original CLI
@@ -754,6 +4865,11 @@ impl VideoPlayer {
             }
         }
 
+        if playlist.is_empty() {
+            eprintln!("No media to play");
+            return;
+        }
+        self.skipped_items.borrow_mut().clear();
         self.player.load_playlist(playlist);
     }
 
@@ -774,6 +4890,18 @@ impl VideoPlayer {
         Ok(self_update::Status::UpToDate(std::string::String::from("OK")))
     }
 
+    pub fn enter_fullscreen(&self) {
+        let fullscreen_action = &self.fullscreen_action;
+        if let Some(is_fullscreen) = fullscreen_action.get_state() {
+            let fullscreen = is_fullscreen.get::<bool>().unwrap();
+
+            if !fullscreen {
+                self.ui_context.enter_fullscreen();
+                fullscreen_action.set_state(&true.to_variant());
+            }
+        }
+    }
+
     pub fn leave_fullscreen(&self) {
         let fullscreen_action = &self.fullscreen_action;
         if let Some(is_fullscreen) = fullscreen_action.get_state() {
@@ -787,6 +4915,373 @@ impl VideoPlayer {
     }
 }
 
+// Activate the action bound to a controller button, if it exists and takes
+// a string parameter exactly when one is given.
+#[cfg(feature = "gamepad")]
+fn gamepad_event(app: &gtk::Application, event: GamepadEvent) {
+    let message = match event {
+        GamepadEvent::Action(name, parameter) => {
+            let expected = app
+                .lookup_action(&name)
+                .map(|action| action.get_parameter_type().map(|t| t.to_str() == "s"));
+            match (expected, parameter) {
+                (Some(None), None) => app.activate_action(&name, None),
+                (Some(Some(true)), Some(parameter)) => app.activate_action(&name, Some(&parameter.to_variant())),
+                _ => eprintln!("Invalid gamepad binding: {}", name),
+            }
+            return;
+        }
+        GamepadEvent::Connected(name) => format!("{} connected", name),
+        GamepadEvent::Disconnected(name) => format!("{} disconnected", name),
+    };
+    with_video_player!(video_player {
+        video_player.ui_context.show_osd_message(&message);
+    });
+}
+
+// Folder of local media.
+fn media_folder(uri: &str) -> Option<PathBuf> {
+    let (path, _) = glib::filename_from_uri(uri).ok()?;
+    path.parent().map(Path::to_path_buf)
+}
+
+// File name of local media, the URI itself otherwise.
+fn uri_display_name(uri: &str) -> std::string::String {
+    glib::filename_from_uri(uri)
+        .ok()
+        .and_then(|(path, _)| path.file_name().map(|name| name.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| uri.to_string())
+}
+
+fn stream_title<S: PlayerStreamInfoExt>(stream: &S) -> Option<std::string::String> {
+    let tags = stream.get_tags()?;
+    let title = tags.get::<gst::tags::Title>()?;
+    title.get().map(std::string::String::from)
+}
+
+// Language code of the stream, from its tags, and the language reported by
+// GstPlayer, which may be a name such as "English", lowercase. The latter is
+// only available from the audio and subtitle stream types.
+fn stream_languages<S: PlayerStreamInfoExt>(stream: &S, language: Option<glib::GString>) -> Vec<std::string::String> {
+    let mut languages = vec![];
+    if let Some(tags) = stream.get_tags() {
+        if let Some(code) = tags.get::<gst::tags::LanguageCode>().and_then(|code| code.get()) {
+            languages.push(code.to_lowercase());
+        }
+    }
+    if let Some(language) = language {
+        languages.push(language.to_lowercase());
+    }
+    languages
+}
+
+/// Indices of the tracks in the earliest preferred language possible. A
+/// two-letter preference also matches the three-letter codes starting like
+/// it, as "en" and "eng".
+fn tracks_by_language(languages: &[Vec<std::string::String>], prefer: &[std::string::String]) -> Vec<usize> {
+    let matches = |track_languages: &[std::string::String], preference: &str| {
+        track_languages.iter().any(|language| {
+            *language == preference
+                || (preference.len() == 2 && language.len() == 3 && language.starts_with(preference))
+        })
+    };
+    prefer
+        .iter()
+        .map(|preference| preference.trim().to_lowercase())
+        .filter(|preference| !preference.is_empty())
+        .map(|preference| {
+            (0..languages.len())
+                .filter(|&index| matches(&languages[index], &preference))
+                .collect::<Vec<_>>()
+        })
+        .find(|indices| !indices.is_empty())
+        .unwrap_or_default()
+}
+
+/// Index of the track to select by title: the first one containing the
+/// earliest possible preferred keyword, ignoring those containing an avoided
+/// keyword. Without such a track, a `current` track containing an avoided
+/// keyword is replaced by the first one which doesn't. None keeps the current
+/// track. Keywords are matched case-insensitively.
+fn track_by_keywords(
+    titles: &[Option<std::string::String>],
+    current: Option<usize>,
+    prefer: &[std::string::String],
+    avoid: &[std::string::String],
+) -> Option<usize> {
+    let titles = titles
+        .iter()
+        .map(|title| title.as_deref().unwrap_or("").to_lowercase())
+        .collect::<Vec<_>>();
+    let contains = |index: usize, keyword: &std::string::String| titles[index].contains(&keyword.to_lowercase());
+    let avoided = |index: usize| avoid.iter().any(|keyword| contains(index, keyword));
+
+    let preferred = prefer
+        .iter()
+        .find_map(|keyword| (0..titles.len()).find(|&index| !avoided(index) && contains(index, keyword)));
+    if preferred.is_some() {
+        return preferred;
+    }
+    match current {
+        Some(index) if avoided(index) => (0..titles.len()).find(|&index| !avoided(index)),
+        _ => None,
+    }
+}
+
+fn subtitle_stream_label(index: usize, sub_stream: &gst_player::PlayerSubtitleInfo) -> std::string::String {
+    let title = stream_title(sub_stream).unwrap_or_else(|| format!("Track {}", index + 1));
+    let lang = sub_stream.get_language().map(|l| {
+        if l == title {
+            "".to_string()
+        } else {
+            format!(" - [{}]", l)
+        }
+    });
+    format!("{}{}", title, lang.unwrap_or_else(|| "".to_string()))
+}
+
+fn audio_track_choices(info: &gst_player::PlayerMediaInfo) -> Vec<(std::string::String, std::string::String)> {
+    let mut choices = vec![("Disable".to_string(), "audio--1".to_string())];
+    for (i, audio_stream) in info.get_audio_streams().iter().enumerate() {
+        let mut label = format!("{} channels", audio_stream.get_channels());
+        if let Some(l) = audio_stream.get_language() {
+            label = format!("{} - [{}]", label, l);
+        }
+        choices.push((label, format!("audio-{}", i)));
+    }
+    choices
+}
+
+fn video_track_choices(info: &gst_player::PlayerMediaInfo) -> Vec<(std::string::String, std::string::String)> {
+    let mut choices = vec![("Disable".to_string(), "video--1".to_string())];
+    for (i, video_stream) in info.get_video_streams().iter().enumerate() {
+        let description = format!("{}x{}", video_stream.get_width(), video_stream.get_height());
+        choices.push((description, format!("video-{}", i)));
+    }
+    choices
+}
+
+// Display aspect ratio of the first video stream, such as "16:9", or
+// "2.39:1" when it doesn't reduce to a short ratio.
+fn native_aspect_ratio(info: &gst_player::PlayerMediaInfo) -> Option<std::string::String> {
+    let stream = info.get_video_streams().into_iter().next()?;
+    let par = stream.get_pixel_aspect_ratio();
+    let width = i64::from(stream.get_width()) * i64::from(*par.numer());
+    let height = i64::from(stream.get_height()) * i64::from(*par.denom());
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+    let (mut a, mut b) = (width, height);
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    if height / a <= 16 {
+        Some(format!("{}:{}", width / a, height / a))
+    } else {
+        Some(format!("{:.2}:1", width as f64 / height as f64))
+    }
+}
+
+fn aspect_ratio_choices(info: &gst_player::PlayerMediaInfo) -> Vec<(std::string::String, std::string::String)> {
+    let automatic = match native_aspect_ratio(info) {
+        Some(ratio) => format!("Automatic ({})", ratio),
+        None => "Automatic".to_string(),
+    };
+    let mut choices = vec![(automatic, "auto".to_string())];
+    for ratio in ASPECT_RATIO_CHOICES.iter() {
+        choices.push((ratio.to_string(), ratio.to_string()));
+    }
+    choices.push(("Stretched".to_string(), "stretch".to_string()));
+    choices
+}
+
+// Index of the track targeted by an audio-track or video-track action state,
+// such as "audio-1", -1 standing for the "Disable" entry ("audio--1").
+fn track_index(state: &str) -> Option<i32> {
+    let index = state.splitn(2, '-').nth(1)?;
+    match index.parse::<i32>() {
+        Ok(index) if index >= -1 => Some(index),
+        _ => {
+            eprintln!("Invalid track action state: {}", state);
+            None
+        }
+    }
+}
+
+fn track_disabled(action: &gio::SimpleAction) -> bool {
+    action
+        .get_state()
+        .and_then(|state| state.get::<std::string::String>())
+        .and_then(|state| track_index(&state))
+        == Some(-1)
+}
+
+// Title and start time of a chapter, for menus and messages.
+fn chapter_label(chapter: &Chapter) -> std::string::String {
+    let start = gst::ClockTime::from_nseconds(chapter.start);
+    if chapter.title.is_empty() {
+        format!("Chapter at {:.0}", start)
+    } else {
+        format!("{} ({:.0})", chapter.title, start)
+    }
+}
+
+fn track_menu_section(action: &str, choices: &[(std::string::String, std::string::String)]) -> gio::Menu {
+    let section = gio::Menu::new();
+    for (label, target) in choices {
+        let action_id = format!("app.{}::{}", action, target);
+        let item = gio::MenuItem::new(Some(label), Some(&action_id));
+        item.set_detailed_action(&*action_id);
+        section.append_item(&item);
+    }
+    section
+}
+
+fn crash_report_dir() -> Option<PathBuf> {
+    ProjectDirs::from("net", "baseart", "Glide").map(|d| d.cache_dir().join("crash-reports"))
+}
+
+// Write a crash report with the backtrace and the player state, and save the
+// resume position, before handing over to the default panic handler.
+fn install_panic_hook(incognito: bool) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let mut report = format!("Glide {} crashed: {}\n\n", env!("CARGO_PKG_VERSION"), info);
+        report.push_str(&format!("Backtrace:\n{}\n", Backtrace::force_capture()));
+
+        let _ = GLOBAL.try_with(|global| {
+            if let Ok(global) = global.try_borrow() {
+                if let Some(ref video_player) = *global {
+                    // Borrowing the player data again would abort without a
+                    // crash report.
+                    if video_player.player.data_in_use() {
+                        report.push_str("Player state unavailable, the panic occurred while updating it\n");
+                    } else {
+                        report.push_str(&video_player.crash_report_state(incognito));
+                        video_player.player.write_last_known_media_position();
+                    }
+                }
+            }
+        });
+
+        if let Some(dir) = crash_report_dir() {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let path = dir.join(format!("crash-{}.txt", timestamp));
+            let written = create_dir_all(&dir)
+                .and_then(|_| File::create(&path))
+                .and_then(|mut file| file.write_all(report.as_bytes()));
+            match written {
+                Ok(_) => eprintln!("Crash report written to {}", path.display()),
+                Err(e) => eprintln!("Unable to write crash report: {}", e),
+            }
+        }
+
+        default_hook(info);
+    }));
+}
+
+// Local files are passed to GApplication as URIs, which are always valid
+// UTF-8, even for paths that are not.
+fn command_line_file_argument(path: &Path) -> std::string::String {
+    if path.exists() {
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            env::current_dir()
+                .map(|d| d.join(path))
+                .unwrap_or_else(|_| path.to_path_buf())
+        };
+        if let Ok(uri) = glib::filename_to_uri(&path, None) {
+            return uri.into();
+        }
+    }
+    path.to_string_lossy().into_owned()
+}
+
+// Gains of an equalizer preset or of a list of ten gains, none to bypass the
+// equalizer.
+fn equalizer_gains(name: &str) -> Option<Option<Vec<f64>>> {
+    if name == "off" {
+        return Some(None);
+    }
+    if let Some((_, gains)) = constants::EQUALIZER_PRESETS.iter().find(|(preset, _)| *preset == name) {
+        return Some(Some(gains.to_vec()));
+    }
+    let gains = name
+        .split(',')
+        .map(|gain| gain.trim().parse::<f64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if gains.len() == 10 {
+        Some(Some(gains))
+    } else {
+        None
+    }
+}
+
+fn equalizer_name(gains: Option<&[f64]>) -> &'static str {
+    match gains {
+        None => "off",
+        Some(gains) => constants::EQUALIZER_PRESETS
+            .iter()
+            .find(|(_, preset)| preset[..] == *gains)
+            .map_or("custom", |(name, _)| name),
+    }
+}
+
+// ARGB value of a "#rrggbb" or "#rrggbbaa" color.
+fn parse_color(color: &str) -> Option<u32> {
+    let hex = color.trim().strip_prefix('#')?;
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    match hex.len() {
+        6 => Some(0xff00_0000 | value),
+        8 => Some(value.rotate_right(8)),
+        _ => None,
+    }
+}
+
+fn color_name(color: u32) -> std::string::String {
+    if color >> 24 == 0xff {
+        format!("#{:06x}", color & 0x00ff_ffff)
+    } else {
+        format!("#{:08x}", color.rotate_left(8))
+    }
+}
+
+// "[[hours:]minutes:]seconds", the seconds possibly having a fraction. Only
+// the first part may exceed 59.
+fn parse_time(time: &str) -> Option<gst::ClockTime> {
+    let parts = time.trim().split(':').collect::<Vec<_>>();
+    if parts.len() > 3 {
+        return None;
+    }
+    let (seconds, units) = parts.split_last()?;
+    let seconds = seconds.parse::<f64>().ok().filter(|s| s.is_finite() && *s >= 0.0)?;
+    if !units.is_empty() && seconds >= 60.0 {
+        return None;
+    }
+    let mut total = 0;
+    for (index, unit) in units.iter().enumerate() {
+        let value = unit.parse::<u64>().ok()?;
+        if index > 0 && value >= 60 {
+            return None;
+        }
+        total = total * 60 + value;
+    }
+    let nanoseconds = (total as f64 * 60.0 + seconds) * 1_000_000_000.0;
+    Some(gst::ClockTime::from_nseconds(nanoseconds as u64))
+}
+
+// Rate as used by the targets of the playback speed menu, such as "1.0" or
+// "0.75".
+fn playback_rate_name(rate: f64) -> std::string::String {
+    format!("{:?}", (rate * 100.0).round() / 100.0)
+}
+
 fn main() -> Result<(), Error> {
     #[cfg(not(unix))]
     {
@@ -799,6 +5294,8 @@ fn main() -> Result<(), Error> {
 
     let opt = Opt::from_args();
 
+    install_panic_hook(opt.incognito);
+
     let gtk_app = initialize_and_create_app();
 
     let gtk_app_clone = gtk_app.clone();
@@ -811,7 +5308,8 @@ fn main() -> Result<(), Error> {
     let files: Vec<std::string::String> = opt
         .files
         .iter()
-        .map(|p| std::string::String::from(p.to_str().unwrap()))
+        .map(PathBuf::as_path)
+        .map(command_line_file_argument)
         .collect();
 
     let mut args = vec![env::args().next().unwrap()];
@@ -823,3 +5321,42 @@ fn main() -> Result<(), Error> {
     //     gst::deinit();
     // }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn command_line_file_argument_escapes_tricky_file_names() {
+        let dir = env::temp_dir().join(format!("glide-arguments-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in &["with spaces.mkv", "hash#tag.mkv", "100% done.mkv", "日本語 ë.mkv"] {
+            let path = dir.join(name);
+            fs::write(&path, b"").unwrap();
+
+            let uri = command_line_file_argument(&path);
+            assert!(uri.starts_with("file:///"), "{}", uri);
+            assert!(!uri.contains(' ') && !uri.contains('#'), "{}", uri);
+            let (decoded, _) = glib::filename_from_uri(&uri).unwrap();
+            assert_eq!(decoded, path);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parse_ratio_accepts_positive_ratios() {
+        assert_eq!(parse_ratio("32:27"), Some((32, 27)));
+        assert_eq!(parse_ratio(" 8 : 9 "), Some((8, 9)));
+        assert_eq!(parse_ratio("auto"), None);
+        assert_eq!(parse_ratio("16"), None);
+        assert_eq!(parse_ratio("0:1"), None);
+        assert_eq!(parse_ratio("4:-3"), None);
+    }
+
+    #[test]
+    fn command_line_file_argument_keeps_uris() {
+        let uri = "https://example.com/video%20file.mkv";
+        assert_eq!(command_line_file_argument(Path::new(uri)), uri);
+    }
+}
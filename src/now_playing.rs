@@ -0,0 +1,168 @@
+extern crate glib;
+
+use block::ConcreteBlock;
+use objc::runtime::{Object, YES};
+use std::cell::{Cell, RefCell};
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+// MPRemoteCommandHandlerStatusSuccess
+const HANDLER_STATUS_SUCCESS: i64 = 0;
+
+// MPNowPlayingPlaybackState values.
+const PLAYBACK_STATE_PLAYING: u64 = 1;
+const PLAYBACK_STATE_PAUSED: u64 = 2;
+const PLAYBACK_STATE_STOPPED: u64 = 3;
+
+#[link(name = "MediaPlayer", kind = "framework")]
+extern "C" {
+    static MPMediaItemPropertyTitle: *mut Object;
+    static MPMediaItemPropertyPlaybackDuration: *mut Object;
+    static MPNowPlayingInfoPropertyElapsedPlaybackTime: *mut Object;
+    static MPNowPlayingInfoPropertyPlaybackRate: *mut Object;
+}
+
+/// Commands received from the media keys, the Touch Bar or the Control
+/// Center.
+#[derive(Clone, Copy, Debug)]
+pub enum RemoteCommand {
+    TogglePlayPause,
+    Play,
+    Pause,
+    Next,
+    Previous,
+    SkipForward,
+    SkipBackward,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum NowPlayingState {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+/// Registration with the macOS Now Playing Info Center and remote command
+/// center, for as long as this is alive.
+pub struct NowPlaying {
+    // Commands and the targets added to them, removed on drop.
+    targets: Vec<(*mut Object, *mut Object)>,
+    title: RefCell<String>,
+    duration: Cell<Option<f64>>,
+    state: Cell<NowPlayingState>,
+    // Last published position, in whole seconds.
+    elapsed: Cell<Option<u64>>,
+}
+
+fn ns_string(text: &str) -> *mut Object {
+    let text = CString::new(text.replace('\0', "")).unwrap();
+    let ptr: *const c_char = text.as_ptr();
+    unsafe { msg_send![class!(NSString), stringWithUTF8String: ptr] }
+}
+
+fn ns_number(value: f64) -> *mut Object {
+    unsafe { msg_send![class!(NSNumber), numberWithDouble: value] }
+}
+
+impl NowPlaying {
+    pub fn new(sender: glib::Sender<RemoteCommand>) -> Self {
+        let commands = [
+            (sel!(togglePlayPauseCommand), RemoteCommand::TogglePlayPause),
+            (sel!(playCommand), RemoteCommand::Play),
+            (sel!(pauseCommand), RemoteCommand::Pause),
+            (sel!(nextTrackCommand), RemoteCommand::Next),
+            (sel!(previousTrackCommand), RemoteCommand::Previous),
+            (sel!(skipForwardCommand), RemoteCommand::SkipForward),
+            (sel!(skipBackwardCommand), RemoteCommand::SkipBackward),
+        ];
+        let mut targets = vec![];
+        unsafe {
+            let center: *mut Object = msg_send![class!(MPRemoteCommandCenter), sharedCommandCenter];
+            for (selector, command) in commands.iter() {
+                let remote_command: *mut Object = msg_send![center, performSelector: *selector];
+                let sender = sender.clone();
+                let command = *command;
+                let handler = ConcreteBlock::new(move |_event: *mut Object| -> i64 {
+                    let _ = sender.send(command);
+                    HANDLER_STATUS_SUCCESS
+                })
+                .copy();
+                let target: *mut Object = msg_send![remote_command, addTargetWithHandler: &*handler];
+                let _: *mut Object = msg_send![target, retain];
+                let _: () = msg_send![remote_command, setEnabled: YES];
+                targets.push((remote_command, target));
+            }
+        }
+        let now_playing = Self {
+            targets,
+            title: RefCell::new(String::new()),
+            duration: Cell::new(None),
+            state: Cell::new(NowPlayingState::Stopped),
+            elapsed: Cell::new(None),
+        };
+        now_playing.publish(0.0, 1.0);
+        now_playing
+    }
+
+    /// Describe a new media, published with the next state or position.
+    pub fn set_media(&self, title: &str, duration: Option<f64>) {
+        *self.title.borrow_mut() = title.to_string();
+        self.duration.set(duration);
+        self.elapsed.set(None);
+    }
+
+    pub fn set_state(&self, state: NowPlayingState, position: f64, rate: f64) {
+        self.state.set(state);
+        self.publish(position, rate);
+    }
+
+    /// Publish the position, at most once per second. The system
+    /// extrapolates it from the playback rate in between.
+    pub fn set_position(&self, position: f64, rate: f64) {
+        if self.elapsed.get() != Some(position as u64) {
+            self.publish(position, rate);
+        }
+    }
+
+    fn publish(&self, position: f64, rate: f64) {
+        self.elapsed.set(Some(position as u64));
+        let rate = if self.state.get() == NowPlayingState::Playing {
+            rate
+        } else {
+            0.0
+        };
+        let state = match self.state.get() {
+            NowPlayingState::Playing => PLAYBACK_STATE_PLAYING,
+            NowPlayingState::Paused => PLAYBACK_STATE_PAUSED,
+            NowPlayingState::Stopped => PLAYBACK_STATE_STOPPED,
+        };
+        unsafe {
+            let info: *mut Object = msg_send![class!(NSMutableDictionary), dictionary];
+            let _: () = msg_send![info, setObject: ns_string(&self.title.borrow()) forKey: MPMediaItemPropertyTitle];
+            if let Some(duration) = self.duration.get() {
+                let _: () = msg_send![info, setObject: ns_number(duration) forKey: MPMediaItemPropertyPlaybackDuration];
+            }
+            let _: () =
+                msg_send![info, setObject: ns_number(position) forKey: MPNowPlayingInfoPropertyElapsedPlaybackTime];
+            let _: () = msg_send![info, setObject: ns_number(rate) forKey: MPNowPlayingInfoPropertyPlaybackRate];
+
+            let center: *mut Object = msg_send![class!(MPNowPlayingInfoCenter), defaultCenter];
+            let _: () = msg_send![center, setNowPlayingInfo: info];
+            let _: () = msg_send![center, setPlaybackState: state];
+        }
+    }
+}
+
+impl Drop for NowPlaying {
+    fn drop(&mut self) {
+        unsafe {
+            for (remote_command, target) in self.targets.drain(..) {
+                let _: () = msg_send![remote_command, removeTarget: target];
+                let _: () = msg_send![target, release];
+            }
+            let center: *mut Object = msg_send![class!(MPNowPlayingInfoCenter), defaultCenter];
+            let nil: *mut Object = std::ptr::null_mut();
+            let _: () = msg_send![center, setNowPlayingInfo: nil];
+        }
+    }
+}
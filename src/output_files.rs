@@ -0,0 +1,23 @@
+use std::path::{Path, PathBuf};
+
+/// The given path if no file exists there, otherwise the first free
+/// "name-N.ext" path next to it.
+pub fn non_existing_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_os_string();
+    let extension = path.extension().map(|e| e.to_os_string());
+    (1..)
+        .map(|index| {
+            let mut name = stem.clone();
+            name.push(format!("-{}", index));
+            if let Some(ref extension) = extension {
+                name.push(".");
+                name.push(extension);
+            }
+            path.with_file_name(name)
+        })
+        .find(|candidate| !candidate.exists())
+        .unwrap()
+}
@@ -0,0 +1,467 @@
+extern crate gio;
+extern crate glib;
+extern crate glib_sys;
+
+use failure::Error;
+use std::cmp::Ordering;
+use std::ffi::CStr;
+use std::fs;
+use std::iter::Peekable;
+use std::path::{Component, Path, PathBuf};
+use std::str::Chars;
+use std::string;
+
+use crate::settings::PlaylistSort;
+
+fn take_number(chars: &mut Peekable<Chars>) -> string::String {
+    let mut number = string::String::new();
+    while let Some(c) = chars.peek() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        number.push(*c);
+        chars.next();
+    }
+    number
+}
+
+fn compare_numbers(a: &str, b: &str) -> Ordering {
+    let a_trimmed = a.trim_start_matches('0');
+    let b_trimmed = b.trim_start_matches('0');
+    a_trimmed
+        .len()
+        .cmp(&b_trimmed.len())
+        .then_with(|| a_trimmed.cmp(b_trimmed))
+        .then_with(|| a.len().cmp(&b.len()))
+}
+
+/// Compare two strings, case-insensitively, with runs of digits compared by
+/// numeric value, so that "Episode 2" sorts before "Episode 10".
+///
+/// Letters are compared by their lowercase code points rather than by the
+/// collation rules of the locale, so accented letters sort after "z".
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    loop {
+        let ordering = match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a_chars);
+                let y = take_number(&mut b_chars);
+                compare_numbers(&x, &y)
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                a_chars.next();
+                b_chars.next();
+                ordering
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+fn is_media_file(path: &Path) -> bool {
+    let (content_type, _) = gio::content_type_guess(path.to_str(), &[]);
+    let content_type = content_type.as_str();
+    // Playlist files are reported as audio, skip them.
+    (content_type.starts_with("audio/") || content_type.starts_with("video/"))
+        && !content_type.contains("mpegurl")
+        && !content_type.contains("scpls")
+}
+
+fn file_name(path: &Path) -> string::String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// Key sorting strings in the order of the current locale, see
+// g_utf8_collate_key().
+fn collate_key(s: &str) -> Vec<u8> {
+    unsafe {
+        let key = glib_sys::g_utf8_collate_key(s.as_ptr() as *const _, s.len() as isize);
+        let bytes = CStr::from_ptr(key).to_bytes().to_vec();
+        glib_sys::g_free(key as glib_sys::gpointer);
+        bytes
+    }
+}
+
+// The file type comes from the directory listing on most platforms, only
+// symbolic links need an extra stat() to know where they point to.
+fn is_file(entry: &fs::DirEntry) -> bool {
+    match entry.file_type() {
+        Ok(file_type) if file_type.is_symlink() => entry.path().is_file(),
+        Ok(file_type) => file_type.is_file(),
+        Err(_) => false,
+    }
+}
+
+/// List the URIs of the media files found in `dir`, in the requested order.
+///
+/// Only the directory listing is read, media are not probed, so that
+/// folders with thousands of files load quickly. Sort keys are computed once
+/// per file rather than for every comparison.
+pub fn folder_playlist(dir: &Path, sort: PlaylistSort) -> Vec<string::String> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(is_file)
+            .map(|entry| entry.path())
+            .filter(|path| is_media_file(path))
+            .collect(),
+        Err(e) => {
+            eprintln!("Unable to read directory {}: {}", dir.display(), e);
+            return vec![];
+        }
+    };
+
+    match sort {
+        PlaylistSort::Natural => {
+            let mut named = paths
+                .into_iter()
+                .map(|path| (file_name(&path), path))
+                .collect::<Vec<_>>();
+            named.sort_by(|(a, _), (b, _)| natural_cmp(a, b));
+            paths = named.into_iter().map(|(_, path)| path).collect();
+        }
+        PlaylistSort::Alphabetical => paths.sort_by_cached_key(|path| collate_key(&file_name(path))),
+        PlaylistSort::ModifiedTime => {
+            paths.sort_by_cached_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok());
+        }
+    };
+
+    paths
+        .iter()
+        .filter_map(|path| glib::filename_to_uri(path, None).ok())
+        .map(string::String::from)
+        .collect()
+}
+
+// Non-standard M3U directive recording the item playing when the playlist
+// was saved, ignored by other players.
+const CURRENT_ITEM_DIRECTIVE: &str = "#GLIDE-CURRENT:";
+
+/// A playlist item to write to a playlist file, with its duration in
+/// seconds and title when known.
+pub struct PlaylistFileItem {
+    pub uri: string::String,
+    pub duration: Option<u64>,
+    pub title: Option<string::String>,
+}
+
+// The path leading from the folder `base` to `path`, or None if they only
+// share the file system root.
+fn relative_path(path: &Path, base: &Path) -> Option<PathBuf> {
+    let mut path_components = path.components().peekable();
+    let mut base_components = base.components().peekable();
+    let mut shares_folder = false;
+    while let (Some(a), Some(b)) = (path_components.peek(), base_components.peek()) {
+        if a != b {
+            break;
+        }
+        if let Component::Normal(_) = a {
+            shares_folder = true;
+        }
+        path_components.next();
+        base_components.next();
+    }
+    if !shares_folder {
+        return None;
+    }
+    let mut relative = PathBuf::new();
+    for _ in base_components {
+        relative.push("..");
+    }
+    relative.extend(path_components);
+    Some(relative)
+}
+
+fn m3u_location(uri: &str, base: Option<&Path>) -> string::String {
+    let path = match glib::filename_from_uri(uri) {
+        Ok((path, _)) => path,
+        Err(_) => return uri.to_string(),
+    };
+    base.and_then(|base| relative_path(&path, base))
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Write the items to an extended M3U file, with the local files relative
+/// to its folder when possible.
+pub fn write_m3u(path: &Path, items: &[PlaylistFileItem], current: usize) -> Result<(), Error> {
+    let base = path.parent();
+    let mut data = format!("#EXTM3U\n{}{}\n", CURRENT_ITEM_DIRECTIVE, current);
+    for item in items {
+        if let Some(duration) = item.duration {
+            let title = match item.title {
+                Some(ref title) => title.replace('\n', " "),
+                None => glib::filename_from_uri(&item.uri)
+                    .map(|(path, _)| file_name(&path))
+                    .unwrap_or_else(|_| item.uri.clone()),
+            };
+            data.push_str(&format!("#EXTINF:{},{}\n", duration, title));
+        }
+        data.push_str(&m3u_location(&item.uri, base));
+        data.push('\n');
+    }
+    fs::write(path, data)?;
+    Ok(())
+}
+
+fn is_playlist_path(path: &Path) -> bool {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => ["m3u", "m3u8", "pls"].contains(&extension.to_lowercase().as_str()),
+        None => false,
+    }
+}
+
+// Network URLs are kept, paths are made absolute against the folder of the
+// playlist file.
+fn resolve_entry(entry: &str, base: &Path) -> Option<string::String> {
+    if entry.contains("://") {
+        return Some(entry.to_string());
+    }
+    let path = base.join(entry);
+    glib::filename_to_uri(&path, None).ok().map(string::String::from)
+}
+
+fn parse_m3u(data: &str, base: &Path) -> (Vec<string::String>, usize) {
+    let mut uris = vec![];
+    let mut current = 0;
+    for line in data.lines().map(str::trim) {
+        if let Some(index) = line.strip_prefix(CURRENT_ITEM_DIRECTIVE) {
+            current = index.trim().parse().unwrap_or(0);
+        } else if !line.is_empty() && !line.starts_with('#') {
+            uris.extend(resolve_entry(line, base));
+        }
+    }
+    (uris, current)
+}
+
+// Entries are the File1, File2… keys of the [playlist] section.
+fn parse_pls(data: &str, base: &Path) -> Vec<string::String> {
+    let mut entries = vec![];
+    for line in data.lines().map(str::trim) {
+        let (key, value) = match line.find('=') {
+            Some(separator) => (&line[..separator], line[separator + 1..].trim()),
+            None => continue,
+        };
+        let number = match key.trim().strip_prefix("File").and_then(|n| n.parse::<u32>().ok()) {
+            Some(number) => number,
+            None => continue,
+        };
+        entries.extend(resolve_entry(value, base).map(|uri| (number, uri)));
+    }
+    entries.sort_by_key(|(number, _)| *number);
+    entries.into_iter().map(|(_, uri)| uri).collect()
+}
+
+/// The items of the local M3U or PLS playlist file at `uri`, and the index
+/// of the one to start with, or None if it isn't such a file or lists
+/// nothing. HLS playlists, also using the .m3u8 extension, are media.
+pub fn read_playlist_file(uri: &str) -> Option<(Vec<string::String>, usize)> {
+    let (path, _) = glib::filename_from_uri(uri).ok()?;
+    if !is_playlist_path(&path) {
+        return None;
+    }
+    let data = match fs::read(&path) {
+        Ok(data) => string::String::from_utf8_lossy(&data).into_owned(),
+        Err(e) => {
+            eprintln!("Unable to read playlist {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    let data = data.trim_start_matches('\u{feff}');
+    if data.contains("#EXT-X-") {
+        return None;
+    }
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let is_pls = data.trim_start().to_lowercase().starts_with("[playlist]");
+    let (uris, current) = if is_pls {
+        (parse_pls(data, base), 0)
+    } else {
+        parse_m3u(data, base)
+    };
+    if uris.is_empty() {
+        return None;
+    }
+    let current = current.min(uris.len() - 1);
+    Some((uris, current))
+}
+
+/// Replace the playlist files among the URIs by their items, `index` being
+/// moved to the item to start with. Playlist files listed by others are
+/// expanded too, so that the playlist only holds media.
+pub fn expand_playlist_files(uris: Vec<string::String>, index: usize) -> (Vec<string::String>, usize) {
+    expand_nested_playlist_files(uris, index, &mut vec![])
+}
+
+// `parents` are the playlist files being expanded, a playlist file listing
+// one of them is dropped rather than expanded forever. A playlist file left
+// without items is kept, as when it lists nothing.
+fn expand_nested_playlist_files(
+    uris: Vec<string::String>,
+    index: usize,
+    parents: &mut Vec<string::String>,
+) -> (Vec<string::String>, usize) {
+    let mut expanded = vec![];
+    let mut expanded_index = 0;
+    for (i, uri) in uris.into_iter().enumerate() {
+        if i == index {
+            expanded_index = expanded.len();
+        }
+        if parents.contains(&uri) {
+            continue;
+        }
+        let (items, current) = match read_playlist_file(&uri) {
+            Some((items, current)) => {
+                parents.push(uri.clone());
+                let (items, current) = expand_nested_playlist_files(items, current, parents);
+                parents.pop();
+                if items.is_empty() {
+                    (vec![uri], 0)
+                } else {
+                    (items, current)
+                }
+            }
+            None => (vec![uri], 0),
+        };
+        if i == index {
+            expanded_index += current;
+        }
+        expanded.extend(items);
+    }
+    let last = expanded.len().saturating_sub(1);
+    (expanded, expanded_index.min(last))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_compare_by_value() {
+        assert_eq!(natural_cmp("Episode 2", "Episode 10"), Ordering::Less);
+        assert_eq!(natural_cmp("Episode 10", "Episode 9"), Ordering::Greater);
+        assert_eq!(natural_cmp("S01E02 Title", "S01E10 Title"), Ordering::Less);
+        assert_eq!(natural_cmp("track9.flac", "track10.flac"), Ordering::Less);
+    }
+
+    #[test]
+    fn case_is_ignored() {
+        assert_eq!(natural_cmp("apple", "Banana"), Ordering::Less);
+        assert_eq!(natural_cmp("Episode 2", "episode 2"), Ordering::Equal);
+    }
+
+    #[test]
+    fn leading_zeros() {
+        assert_eq!(natural_cmp("Episode 01", "Episode 2"), Ordering::Less);
+        assert_eq!(natural_cmp("Episode 09", "Episode 10"), Ordering::Less);
+        // Same value, the shorter number first so that the order is total.
+        assert_eq!(natural_cmp("1", "01"), Ordering::Less);
+        assert_eq!(natural_cmp("01", "1"), Ordering::Greater);
+        assert_eq!(natural_cmp("0", "00"), Ordering::Less);
+    }
+
+    #[test]
+    fn prefixes_come_first() {
+        assert_eq!(natural_cmp("Episode", "Episode 1"), Ordering::Less);
+        assert_eq!(natural_cmp("", "a"), Ordering::Less);
+    }
+
+    fn file_uri(path: &Path) -> string::String {
+        glib::filename_to_uri(path, None).unwrap().into()
+    }
+
+    // A folder of the temporary directory, for the playlist files of a test.
+    fn playlist_folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("glide-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    #[test]
+    fn m3u_entries_are_resolved_against_the_playlist_folder() {
+        let data = "#EXTM3U\n#GLIDE-CURRENT:2\n#EXTINF:60,One\none.mkv\n\n\
+                    sub/two.mkv\n/music/three.flac\nhttps://example.com/four.mp3\n";
+        let (uris, current) = parse_m3u(data, Path::new("/videos"));
+        assert_eq!(
+            uris,
+            vec![
+                file_uri(Path::new("/videos/one.mkv")),
+                file_uri(Path::new("/videos/sub/two.mkv")),
+                file_uri(Path::new("/music/three.flac")),
+                "https://example.com/four.mp3".to_string(),
+            ]
+        );
+        assert_eq!(current, 2);
+    }
+
+    #[test]
+    fn pls_entries_follow_their_numbers() {
+        let data =
+            "[playlist]\nFile2=b.mkv\nTitle2=B\nFile10=j.mkv\nFile1=http://example.com/a.mp3\nNumberOfEntries=3\n";
+        let uris = parse_pls(data, Path::new("/videos"));
+        assert_eq!(
+            uris,
+            vec![
+                "http://example.com/a.mp3".to_string(),
+                file_uri(Path::new("/videos/b.mkv")),
+                file_uri(Path::new("/videos/j.mkv")),
+            ]
+        );
+    }
+
+    #[test]
+    fn playlist_files_are_read_after_their_bom() {
+        let folder = playlist_folder("playlist-bom");
+        let m3u = folder.join("list.m3u");
+        fs::write(&m3u, "\u{feff}#EXTM3U\none.mkv\n").unwrap();
+        let pls = folder.join("list.pls");
+        fs::write(&pls, "\u{feff}[playlist]\nFile1=two.mkv\n").unwrap();
+
+        let m3u_items = read_playlist_file(&file_uri(&m3u));
+        let pls_items = read_playlist_file(&file_uri(&pls));
+        fs::remove_dir_all(&folder).unwrap();
+        assert_eq!(m3u_items, Some((vec![file_uri(&folder.join("one.mkv"))], 0)));
+        assert_eq!(pls_items, Some((vec![file_uri(&folder.join("two.mkv"))], 0)));
+    }
+
+    #[test]
+    fn hls_playlists_are_media() {
+        let folder = playlist_folder("playlist-hls");
+        let hls = folder.join("live.m3u8");
+        fs::write(&hls, "#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\nsegment0.ts\n").unwrap();
+
+        let items = read_playlist_file(&file_uri(&hls));
+        fs::remove_dir_all(&folder).unwrap();
+        assert!(items.is_none());
+        assert!(read_playlist_file("https://example.com/list.m3u").is_none());
+    }
+
+    #[test]
+    fn nested_playlist_files_are_expanded() {
+        let folder = playlist_folder("playlist-nested");
+        fs::write(folder.join("outer.m3u"), "#GLIDE-CURRENT:1\ninner.m3u\nc.mkv\n").unwrap();
+        fs::write(folder.join("inner.m3u"), "a.mkv\nb.mkv\n").unwrap();
+        // Listing itself, it is only expanded once.
+        fs::write(folder.join("loop.m3u"), "d.mkv\nloop.m3u\n").unwrap();
+
+        let uris = vec![file_uri(&folder.join("outer.m3u")), file_uri(&folder.join("loop.m3u"))];
+        let (expanded, index) = expand_playlist_files(uris, 0);
+        fs::remove_dir_all(&folder).unwrap();
+        let names = expanded
+            .iter()
+            .map(|uri| file_name(&glib::filename_from_uri(uri).unwrap().0))
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["a.mkv", "b.mkv", "c.mkv", "d.mkv"]);
+        assert_eq!(index, 2);
+    }
+}
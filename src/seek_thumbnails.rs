@@ -0,0 +1,145 @@
+extern crate gdk_pixbuf;
+extern crate glib;
+extern crate gstreamer as gst;
+extern crate gstreamer_video as gst_video;
+
+use failure::Error;
+use gdk_pixbuf::prelude::*;
+use gst::prelude::*;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// At most this many frames are extracted over the whole media, no closer
+// than the minimum interval.
+const MAX_THUMBNAILS: u64 = 100;
+const MIN_INTERVAL: u64 = 5_000_000_000;
+const THUMBNAIL_WIDTH: i32 = 160;
+// Time allowed to a seek, or to the conversion of a frame.
+const STEP_TIMEOUT_SECONDS: u64 = 10;
+
+/// Small JPEG frames of a media by position in nanoseconds, extracted from
+/// a background thread with a playbin of its own. The extraction stops when
+/// dropped.
+pub struct SeekThumbnails {
+    frames: Arc<Mutex<BTreeMap<u64, Vec<u8>>>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl SeekThumbnails {
+    pub fn extract(uri: &str) -> Self {
+        let frames = Arc::new(Mutex::new(BTreeMap::new()));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let uri = uri.to_string();
+        {
+            let frames = frames.clone();
+            let cancelled = cancelled.clone();
+            thread::spawn(move || {
+                if let Err(e) = extract_frames(&uri, &frames, &cancelled) {
+                    eprintln!("Unable to extract the seek thumbnails of {}: {}", uri, e);
+                }
+            });
+        }
+        SeekThumbnails { frames, cancelled }
+    }
+
+    /// The frame extracted closest to the position, if any yet.
+    pub fn get(&self, position: gst::ClockTime) -> Option<gdk_pixbuf::Pixbuf> {
+        let position = position.nseconds()?;
+        let data = {
+            let frames = self.frames.lock().unwrap();
+            let before = frames.range(..=position).next_back();
+            let after = frames.range(position..).next();
+            match (before, after) {
+                (Some(before), Some(after)) if after.0 - position < position - before.0 => after.1.clone(),
+                (Some(before), _) => before.1.clone(),
+                (None, Some(after)) => after.1.clone(),
+                (None, None) => return None,
+            }
+        };
+        let loader = gdk_pixbuf::PixbufLoader::new();
+        loader.write(&data).ok()?;
+        loader.close().ok()?;
+        loader.get_pixbuf()
+    }
+}
+
+impl Drop for SeekThumbnails {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+fn wait_for_preroll(bus: &gst::Bus) -> Result<(), Error> {
+    let msg = bus
+        .timed_pop_filtered(
+            gst::ClockTime::from_seconds(STEP_TIMEOUT_SECONDS),
+            &[gst::MessageType::AsyncDone, gst::MessageType::Error],
+        )
+        .ok_or_else(|| failure::err_msg("Timed out"))?;
+    match msg.view() {
+        gst::MessageView::Error(err) => Err(err.get_error().into()),
+        _ => Ok(()),
+    }
+}
+
+// Seek to evenly spaced keyframes with the video sink paused, converting the
+// prerolled frame each time.
+fn extract_frames(
+    uri: &str,
+    frames: &Arc<Mutex<BTreeMap<u64, Vec<u8>>>>,
+    cancelled: &Arc<AtomicBool>,
+) -> Result<(), Error> {
+    let playbin = gst::ElementFactory::make("playbin", None)?;
+    playbin.set_property("uri", &uri)?;
+    playbin.set_property_from_str("flags", "video");
+    let sink = gst::ElementFactory::make("fakesink", None)?;
+    playbin.set_property("video-sink", &sink)?;
+
+    let bus = playbin.get_bus().unwrap();
+    playbin.set_state(gst::State::Paused)?;
+    let result = (|| {
+        wait_for_preroll(&bus)?;
+        let duration = playbin
+            .query_duration::<gst::ClockTime>()
+            .and_then(|duration| duration.nseconds())
+            .ok_or_else(|| failure::err_msg("Unknown duration"))?;
+        let interval = (duration / MAX_THUMBNAILS).max(MIN_INTERVAL);
+        let caps = gst::Caps::builder("image/jpeg")
+            .field("width", &THUMBNAIL_WIDTH)
+            .field("pixel-aspect-ratio", &gst::Fraction::new(1, 1))
+            .build();
+
+        let mut position = interval / 2;
+        while position < duration && !cancelled.load(Ordering::Relaxed) {
+            playbin.seek_simple(
+                gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT,
+                gst::ClockTime::from_nseconds(position),
+            )?;
+            wait_for_preroll(&bus)?;
+            let sample = playbin
+                .get_property("sample")
+                .ok()
+                .and_then(|value| value.get::<gst::Sample>().ok())
+                .flatten();
+            if let Some(sample) = sample {
+                let jpeg =
+                    gst_video::convert_sample(&sample, &caps, gst::ClockTime::from_seconds(STEP_TIMEOUT_SECONDS))?;
+                if let Some(buffer) = jpeg.get_buffer() {
+                    let map = buffer.map_readable()?;
+                    // Keyframes are seldom right at the requested position.
+                    let time = playbin
+                        .query_position::<gst::ClockTime>()
+                        .and_then(|time| time.nseconds())
+                        .unwrap_or(position);
+                    frames.lock().unwrap().insert(time, map.as_slice().to_vec());
+                }
+            }
+            position += interval;
+        }
+        Ok(())
+    })();
+    let _ = playbin.set_state(gst::State::Null);
+    result
+}
@@ -0,0 +1,42 @@
+extern crate serde_json;
+
+use failure::Error;
+use std::fs;
+use std::fs::File;
+use std::io::Read;
+use std::io::Write;
+use std::path;
+
+/// Playlist and item played when Glide was last closed, kept in the cache
+/// directory to be resumed on a launch without media.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Session {
+    pub playlist: Vec<String>,
+    pub index: usize,
+}
+
+impl Session {
+    pub fn read<T: AsRef<path::Path>>(path: T) -> Result<Self, Error> {
+        let mut file = File::open(path.as_ref())?;
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub fn write<T: AsRef<path::Path>>(&self, path: T) -> Result<(), Error> {
+        let path = path.as_ref();
+        let temporary_path = path.with_extension("json.tmp");
+        let mut file = File::create(&temporary_path)?;
+        let json = serde_json::to_string(self)?;
+        file.write_all(json.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temporary_path, path)?;
+        Ok(())
+    }
+
+    /// The media played last.
+    pub fn current_uri(&self) -> Option<&str> {
+        self.playlist.get(self.index).map(String::as_str)
+    }
+}
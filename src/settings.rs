@@ -0,0 +1,881 @@
+extern crate serde_json;
+
+use failure::Error;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::io::Write;
+use std::path;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SubtitleBackground {
+    None,
+    Box,
+    Outline,
+}
+
+impl SubtitleBackground {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(SubtitleBackground::None),
+            "box" => Some(SubtitleBackground::Box),
+            "outline" => Some(SubtitleBackground::Outline),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SubtitleBackground::None => "none",
+            SubtitleBackground::Box => "box",
+            SubtitleBackground::Outline => "outline",
+        }
+    }
+}
+
+/// What the size of plain-text subtitles follows.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SubtitleScaleBase {
+    /// The video picture, so that subtitles keep their place in the frame.
+    Video,
+    /// The height of the video area, whatever the video resolution.
+    Window,
+}
+
+impl SubtitleScaleBase {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "video" => Some(SubtitleScaleBase::Video),
+            "window" => Some(SubtitleScaleBase::Window),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SubtitleScaleBase::Video => "video",
+            SubtitleScaleBase::Window => "window",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum PlaylistSort {
+    Natural,
+    Alphabetical,
+    ModifiedTime,
+}
+
+impl PlaylistSort {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "natural" => Some(PlaylistSort::Natural),
+            "alphabetical" => Some(PlaylistSort::Alphabetical),
+            "modified-time" => Some(PlaylistSort::ModifiedTime),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PlaylistSort::Natural => "natural",
+            PlaylistSort::Alphabetical => "alphabetical",
+            PlaylistSort::ModifiedTime => "modified-time",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum RepeatMode {
+    /// Stop at the end of the playlist.
+    Off,
+    /// Play the current item over and over.
+    One,
+    /// Start the playlist over once it ends.
+    All,
+}
+
+impl RepeatMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(RepeatMode::Off),
+            "one" => Some(RepeatMode::One),
+            "all" => Some(RepeatMode::All),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RepeatMode::Off => "off",
+            RepeatMode::One => "one",
+            RepeatMode::All => "all",
+        }
+    }
+
+    /// The mode following this one in the Off, All, One cycle.
+    pub fn next(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::All,
+            RepeatMode::All => RepeatMode::One,
+            RepeatMode::One => RepeatMode::Off,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ResumeMode {
+    Always,
+    Ask,
+    Never,
+}
+
+impl ResumeMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "always" => Some(ResumeMode::Always),
+            "ask" => Some(ResumeMode::Ask),
+            "never" => Some(ResumeMode::Never),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ResumeMode::Always => "always",
+            ResumeMode::Ask => "ask",
+            ResumeMode::Never => "never",
+        }
+    }
+}
+
+/// Where to resume seekable live streams, such as DVR-enabled IPTV
+/// channels. Other live streams always play from the live edge.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum LiveResume {
+    LiveEdge,
+    LastPosition,
+}
+
+impl LiveResume {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "live-edge" => Some(LiveResume::LiveEdge),
+            "last-position" => Some(LiveResume::LastPosition),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LiveResume::LiveEdge => "live-edge",
+            LiveResume::LastPosition => "last-position",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ColorRange {
+    Auto,
+    Full,
+    Limited,
+}
+
+impl ColorRange {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(ColorRange::Auto),
+            "full" => Some(ColorRange::Full),
+            "limited" => Some(ColorRange::Limited),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorRange::Auto => "auto",
+            ColorRange::Full => "full",
+            ColorRange::Limited => "limited",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Deinterlace {
+    /// Only the frames flagged as interlaced.
+    Auto,
+    On,
+    Off,
+}
+
+impl Deinterlace {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Deinterlace::Auto),
+            "on" => Some(Deinterlace::On),
+            "off" => Some(Deinterlace::Off),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Deinterlace::Auto => "auto",
+            Deinterlace::On => "on",
+            Deinterlace::Off => "off",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ReplayGainMode {
+    Off,
+    Track,
+    Album,
+}
+
+impl ReplayGainMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(ReplayGainMode::Off),
+            "track" => Some(ReplayGainMode::Track),
+            "album" => Some(ReplayGainMode::Album),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ReplayGainMode::Off => "off",
+            ReplayGainMode::Track => "track",
+            ReplayGainMode::Album => "album",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ChannelRemap {
+    Off,
+    Stereo,
+    Mono,
+    SwapStereo,
+    Custom,
+}
+
+impl ChannelRemap {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(ChannelRemap::Off),
+            "stereo" => Some(ChannelRemap::Stereo),
+            "mono" => Some(ChannelRemap::Mono),
+            "swap-stereo" => Some(ChannelRemap::SwapStereo),
+            "custom" => Some(ChannelRemap::Custom),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChannelRemap::Off => "off",
+            ChannelRemap::Stereo => "stereo",
+            ChannelRemap::Mono => "mono",
+            ChannelRemap::SwapStereo => "swap-stereo",
+            ChannelRemap::Custom => "custom",
+        }
+    }
+
+    /// Mixing matrix of the preset, one row per output channel and one
+    /// column per input channel, or the custom one.
+    pub fn matrix(self, custom: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+        match self {
+            ChannelRemap::Off => None,
+            ChannelRemap::Stereo => Some(vec![vec![1.0, 0.0], vec![0.0, 1.0]]),
+            ChannelRemap::Mono => Some(vec![vec![0.5, 0.5], vec![0.5, 0.5]]),
+            ChannelRemap::SwapStereo => Some(vec![vec![0.0, 1.0], vec![1.0, 0.0]]),
+            ChannelRemap::Custom => Some(custom.to_vec()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum StoppedVideo {
+    LastFrame,
+    Black,
+    Logo,
+}
+
+impl StoppedVideo {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "last-frame" => Some(StoppedVideo::LastFrame),
+            "black" => Some(StoppedVideo::Black),
+            "logo" => Some(StoppedVideo::Logo),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StoppedVideo::LastFrame => "last-frame",
+            StoppedVideo::Black => "black",
+            StoppedVideo::Logo => "logo",
+        }
+    }
+}
+
+/// Audio output settings switched together, such as surround output on an
+/// AV receiver or a stereo downmix on headphones.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AudioProfile {
+    pub name: String,
+    /// Display name of the output device, the default device if empty.
+    pub device: String,
+    pub channel_remap: ChannelRemap,
+    pub replaygain_mode: ReplayGainMode,
+}
+
+impl Default for AudioProfile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            device: String::new(),
+            channel_remap: ChannelRemap::Off,
+            replaygain_mode: ReplayGainMode::Off,
+        }
+    }
+}
+
+/// A service providing subtitle files, tried by the automatic subtitle
+/// download. The `{name}`, `{language}`, `{hash}` and `{size}` placeholders
+/// of the URL are replaced by the file name of the media without extension,
+/// the language, and the OpenSubtitles hash and size in bytes of the media.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SubtitleSource {
+    pub name: String,
+    pub url: String,
+    pub language: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SubtitleDownloadLocation {
+    NextToMedia,
+    Cache,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum DeviceChangeAction {
+    Ignore,
+    Pause,
+    Mute,
+}
+
+impl DeviceChangeAction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ignore" => Some(DeviceChangeAction::Ignore),
+            "pause" => Some(DeviceChangeAction::Pause),
+            "mute" => Some(DeviceChangeAction::Mute),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DeviceChangeAction::Ignore => "ignore",
+            DeviceChangeAction::Pause => "pause",
+            DeviceChangeAction::Mute => "mute",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum OverwriteMode {
+    Rename,
+    Ask,
+}
+
+impl OverwriteMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rename" => Some(OverwriteMode::Rename),
+            "ask" => Some(OverwriteMode::Ask),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OverwriteMode::Rename => "rename",
+            OverwriteMode::Ask => "ask",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum PlaylistErrorAction {
+    Skip,
+    Notify,
+    Stop,
+}
+
+impl PlaylistErrorAction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "skip" => Some(PlaylistErrorAction::Skip),
+            "notify" => Some(PlaylistErrorAction::Notify),
+            "stop" => Some(PlaylistErrorAction::Stop),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PlaylistErrorAction::Skip => "skip",
+            PlaylistErrorAction::Notify => "notify",
+            PlaylistErrorAction::Stop => "stop",
+        }
+    }
+}
+
+/// Script of plain-text subtitles, picking the fonts preferred for it. Han
+/// characters are drawn differently in Japanese and Chinese fonts, and
+/// fallback fonts might lack Arabic or Hebrew shaping. Vertical layout isn't
+/// handled, text subtitles are always drawn horizontally.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SubtitleScript {
+    Auto,
+    Japanese,
+    SimplifiedChinese,
+    TraditionalChinese,
+    Korean,
+    Arabic,
+    Hebrew,
+}
+
+impl SubtitleScript {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(SubtitleScript::Auto),
+            "ja" => Some(SubtitleScript::Japanese),
+            "zh-hans" => Some(SubtitleScript::SimplifiedChinese),
+            "zh-hant" => Some(SubtitleScript::TraditionalChinese),
+            "ko" => Some(SubtitleScript::Korean),
+            "ar" => Some(SubtitleScript::Arabic),
+            "he" => Some(SubtitleScript::Hebrew),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SubtitleScript::Auto => "auto",
+            SubtitleScript::Japanese => "ja",
+            SubtitleScript::SimplifiedChinese => "zh-hans",
+            SubtitleScript::TraditionalChinese => "zh-hant",
+            SubtitleScript::Korean => "ko",
+            SubtitleScript::Arabic => "ar",
+            SubtitleScript::Hebrew => "he",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum OpenMultipleAction {
+    Replace,
+    Append,
+}
+
+impl OpenMultipleAction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "replace" => Some(OpenMultipleAction::Replace),
+            "append" => Some(OpenMultipleAction::Append),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OpenMultipleAction::Replace => "replace",
+            OpenMultipleAction::Append => "append",
+        }
+    }
+}
+
+/// What Glide does when launched without media.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum NoArgsBehavior {
+    EmptyWindow,
+    OpenDialog,
+    /// Resume the media played last.
+    ContinueWatching,
+    /// Restore the whole playlist played last, at its current item.
+    ResumeLastSession,
+}
+
+impl NoArgsBehavior {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "empty-window" => Some(NoArgsBehavior::EmptyWindow),
+            "open-dialog" => Some(NoArgsBehavior::OpenDialog),
+            "continue-watching" => Some(NoArgsBehavior::ContinueWatching),
+            "resume-last-session" => Some(NoArgsBehavior::ResumeLastSession),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NoArgsBehavior::EmptyWindow => "empty-window",
+            NoArgsBehavior::OpenDialog => "open-dialog",
+            NoArgsBehavior::ContinueWatching => "continue-watching",
+            NoArgsBehavior::ResumeLastSession => "resume-last-session",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    #[serde(skip)]
+    path: Option<path::PathBuf>,
+    /// Whether media are resumed from their last known position.
+    pub resume_mode: ResumeMode,
+    pub live_resume: LiveResume,
+    /// Look for a replacement next to the media when an external subtitle
+    /// file to attach is missing.
+    pub rescan_missing_subtitles: bool,
+    pub subtitle_background: SubtitleBackground,
+    pub subtitle_background_opacity: f64,
+    pub subtitle_scale_base: SubtitleScaleBase,
+    pub subtitle_script: SubtitleScript,
+    /// Pango font description of plain-text subtitles, such as "Sans Bold
+    /// 28". Its size is still multiplied by `subtitle_scale`.
+    pub subtitle_font: Option<String>,
+    /// Color of plain-text subtitles, as #rrggbb or #rrggbbaa.
+    pub subtitle_color: Option<String>,
+    /// Factor applied to the size of plain-text subtitles.
+    pub subtitle_scale: f64,
+    /// Offer to apply the subtitle offset set manually for several media of
+    /// a folder to the whole folder.
+    pub subtitle_offset_folder_prompt: bool,
+    /// Download subtitles for local media without any, trying each of the
+    /// `subtitle_sources` in order.
+    pub subtitle_auto_download: bool,
+    pub subtitle_sources: Vec<SubtitleSource>,
+    /// Where downloaded subtitles are kept. In the cache directory they
+    /// aren't found by other players, next to the media the directory has
+    /// to be writable.
+    pub subtitle_download_location: SubtitleDownloadLocation,
+    /// Seconds to wait before loading the next playlist item.
+    pub auto_advance_delay: u32,
+    /// Quit once the last playlist item ends, rather than staying on it.
+    pub quit_at_end_of_playlist: bool,
+    /// Start the next playlist item right as the current one ends, without
+    /// a gap, for albums split into tracks. Applies without auto-advance
+    /// delay only. Items reached that way start from their beginning, their
+    /// stored position isn't resumed.
+    pub gapless_playback: bool,
+    /// Seconds to jump back with the transcription rewind action.
+    pub pedal_rewind_seconds: u32,
+    pub pedal_auto_pause: bool,
+    /// Seconds replayed by the instant replay action.
+    pub instant_replay_seconds: u32,
+    /// Playback rate of instant replays, the previous rate is restored
+    /// once the replay caught up.
+    pub instant_replay_rate: f64,
+    /// Keep snapshots of HDR content as decoded instead of tone-mapping them.
+    pub snapshot_raw: bool,
+    /// Tone-map HDR video to the SDR range of the display.
+    pub tone_map_hdr: bool,
+    /// Monitor used for the mirror output, the first one not showing the
+    /// main window if unset.
+    pub mirror_output_monitor: Option<i32>,
+    /// Order of the items of playlists built from a folder.
+    pub playlist_sort: PlaylistSort,
+    /// Play the playlist items in a random order.
+    pub playlist_shuffle: bool,
+    /// Last repeat mode chosen with the playlist-repeat action.
+    pub playlist_repeat: RepeatMode,
+    /// Keep the subtitle visibility and track languages when moving to the
+    /// next playlist item.
+    pub carry_tracks_across_playlist: bool,
+    /// What to do with playlist items failing to play.
+    pub playlist_error_action: PlaylistErrorAction,
+    /// Whether several files opened at once replace the playlist or are
+    /// appended to it.
+    pub open_multiple_action: OpenMultipleAction,
+    pub no_args_behavior: NoArgsBehavior,
+    /// Times a failing playlist item is reloaded before giving up on it.
+    pub playlist_item_retries: u32,
+    /// Enter fullscreen when a media with video starts, leave it for
+    /// audio-only media.
+    pub auto_fullscreen: bool,
+    /// Key switching back and forth between the current and the previously
+    /// played media, in the GTK accelerator format, or empty to disable it.
+    pub toggle_previous_item_key: String,
+    /// Key zooming the video to fill the window, removing the black bars,
+    /// and back, in the GTK accelerator format, or empty to disable it.
+    pub zoom_to_fill_key: String,
+    /// Prefix the window title with the playback state, for setups without
+    /// visible controls.
+    pub title_shows_state: bool,
+    /// Keep the window above the other ones.
+    pub always_on_top: bool,
+    /// On macOS, let the media keys, the Touch Bar and the Control Center
+    /// control playback, and show the current media there.
+    pub media_keys: bool,
+    /// With the gamepad feature, control playback from game controllers.
+    pub gamepad: bool,
+    /// Actions triggered by the buttons of game controllers, by button name,
+    /// such as "South": "pause" or "LeftTrigger": "cycle-track::audio". The
+    /// default bindings are used if empty.
+    pub gamepad_bindings: BTreeMap<String, String>,
+    /// In fullscreen, show a thin progress line at the bottom of the video
+    /// instead of the toolbar.
+    pub fullscreen_progress_bar: bool,
+    /// Thickness of the fullscreen progress line, in pixels.
+    pub fullscreen_progress_bar_height: u32,
+    /// Color of the fullscreen progress line, such as "#3584e4" or
+    /// "rgba(255,255,255,0.6)".
+    pub fullscreen_progress_bar_color: String,
+    /// Mark the chapters of the media on the position slider.
+    pub chapter_markers: bool,
+    /// Keywords looked for in the titles of the audio tracks when a media is
+    /// loaded, such as "commentary". A track containing the earliest possible
+    /// preferred keyword is selected, tracks with an avoided keyword are
+    /// skipped. Tracks carried over from the previous playlist item win.
+    pub audio_prefer_keywords: Vec<String>,
+    pub audio_avoid_keywords: Vec<String>,
+    /// Same as the audio keywords, for subtitle tracks, such as "forced" or
+    /// "signs". Subtitle files found next to the media win.
+    pub subtitle_prefer_keywords: Vec<String>,
+    /// Languages of the subtitle tracks to select, by order of preference,
+    /// such as "en" or "fre". Subtitles are turned off for media without
+    /// any, the keywords choosing among the tracks of the language.
+    pub subtitle_languages: Vec<String>,
+    pub subtitle_avoid_keywords: Vec<String>,
+    /// Stop the pipeline once paused for `idle_power_saving_minutes`,
+    /// resuming from the same position on play.
+    pub idle_power_saving: bool,
+    pub idle_power_saving_minutes: u32,
+    /// What the video area shows once the playlist ended. Paused media,
+    /// even released by idle_power_saving, always keep their frame.
+    pub stopped_video: StoppedVideo,
+    /// Show the title bar and borders of the main window.
+    pub window_decorations: bool,
+    /// Keep the pitch of the audio when playing faster or slower.
+    pub preserve_pitch: bool,
+    /// Even out the loudness of the media, for those without ReplayGain
+    /// tags such as movies.
+    pub audio_normalization: bool,
+    /// Volume, from 0 to 1, of the media whose volume was never changed.
+    /// Changing it on a media remembers it for that media.
+    pub default_volume: f64,
+    /// Gains in dB of the ten equalizer bands, from 29 Hz to 15 kHz. Without
+    /// any, the equalizer is bypassed.
+    pub equalizer: Option<Vec<f64>>,
+    /// What to do when an audio output device, such as headphones, is
+    /// removed.
+    pub pause_on_device_change: DeviceChangeAction,
+    /// Dim and desaturate the video between the night mode hours.
+    pub night_mode: bool,
+    /// Local hour, from 0 to 23, at which the night mode starts fading in.
+    pub night_mode_start_hour: u32,
+    /// Local hour at which the night mode has faded out.
+    pub night_mode_end_hour: u32,
+    /// Brightness reached during the night, from 0 to 1, 0.5 being unchanged.
+    pub night_mode_brightness: f64,
+    /// Saturation reached during the night, from 0 to 1, 0.5 being unchanged.
+    pub night_mode_saturation: f64,
+    /// Delay, in milliseconds, after which the track highlighted in the
+    /// on-screen list shown by the cycle-track keys is selected.
+    pub track_cycle_timeout_ms: u32,
+    /// What to do when saving a clip or snapshot over an existing file.
+    pub overwrite_mode: OverwriteMode,
+    /// Apply the ReplayGain tags of audio files, per track or per album.
+    pub replaygain_mode: ReplayGainMode,
+    /// Remap the audio channels, for unusual or miswired speaker setups.
+    pub channel_remap: ChannelRemap,
+    /// Mixing matrix of the custom channel remapping, one row per output
+    /// channel with the gain of each input channel, such as
+    /// [[0.0, 1.0], [1.0, 0.0]] to swap the left and right channels.
+    pub channel_matrix: Vec<Vec<f64>>,
+    /// Audio output profiles, listed in the Audio menu.
+    pub audio_profiles: Vec<AudioProfile>,
+    /// Name of the audio profile in use, if any.
+    pub audio_profile: Option<String>,
+    /// Offsets of the seek forward and backward actions, in seconds.
+    pub seek_forward_seconds: f64,
+    pub seek_backward_seconds: f64,
+    /// Offset of the large seeks, forward and backward, in seconds.
+    pub large_seek_seconds: f64,
+    /// While seeking repeatedly, show approximate frames and only seek
+    /// accurately, and resume playback, once done.
+    pub seek_preview: bool,
+    /// Extract frames of local videos in the background, shown when hovering
+    /// the position slider.
+    pub seek_thumbnails: bool,
+    /// Range of the YUV samples of videos, overriding the one they declare.
+    pub color_range: ColorRange,
+    /// Deinterlacing of the video, with the deinterlace element.
+    pub deinterlace: Deinterlace,
+    /// Pixel aspect ratio of videos, such as "32:27", overriding the one
+    /// they declare.
+    pub pixel_aspect_ratio: Option<String>,
+    /// Keep new media paused on their first frame once loaded.
+    pub start_paused: bool,
+    /// Video sink used for the main window, see `channel_player::VIDEO_SINKS`.
+    pub video_sink: Option<String>,
+    /// Recreate the video sink when the scale factor of the window changes
+    /// or when a monitor is plugged or unplugged.
+    pub rebuild_video_sink_on_display_change: bool,
+    /// Display the level of each audio channel over the video area.
+    pub show_audio_meter: bool,
+    /// Display the number of frames rendered and dropped over the video
+    /// area.
+    pub show_frame_stats: bool,
+    /// Keys starting the typing of a volume or position percentage, named
+    /// as in GDK (e.g. "v"). Unset by default, as unlike the other
+    /// shortcuts these keys have no modifier and are easily hit by mistake.
+    pub volume_input_key: Option<String>,
+    pub position_input_key: Option<String>,
+    /// Download remote media to the cache directory before playing them.
+    pub download_first: bool,
+    /// Largest media downloaded with `download_first`, in MiB.
+    pub download_max_size: u64,
+    /// Keep completed downloads across sessions.
+    pub download_keep: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            path: None,
+            resume_mode: ResumeMode::Always,
+            live_resume: LiveResume::LiveEdge,
+            rescan_missing_subtitles: true,
+            subtitle_background: SubtitleBackground::Outline,
+            subtitle_background_opacity: 0.5,
+            subtitle_scale_base: SubtitleScaleBase::Video,
+            subtitle_script: SubtitleScript::Auto,
+            subtitle_font: None,
+            subtitle_color: None,
+            subtitle_scale: 1.0,
+            subtitle_offset_folder_prompt: true,
+            subtitle_auto_download: false,
+            subtitle_sources: vec![],
+            subtitle_download_location: SubtitleDownloadLocation::Cache,
+            auto_advance_delay: 0,
+            quit_at_end_of_playlist: false,
+            gapless_playback: false,
+            pedal_rewind_seconds: 3,
+            pedal_auto_pause: true,
+            instant_replay_seconds: 10,
+            instant_replay_rate: 1.0,
+            snapshot_raw: false,
+            tone_map_hdr: false,
+            mirror_output_monitor: None,
+            playlist_sort: PlaylistSort::Natural,
+            playlist_shuffle: false,
+            playlist_repeat: RepeatMode::Off,
+            carry_tracks_across_playlist: true,
+            playlist_error_action: PlaylistErrorAction::Notify,
+            open_multiple_action: OpenMultipleAction::Replace,
+            no_args_behavior: NoArgsBehavior::EmptyWindow,
+            playlist_item_retries: 0,
+            auto_fullscreen: false,
+            toggle_previous_item_key: "<Primary>BackSpace".to_string(),
+            zoom_to_fill_key: "<Primary>z".to_string(),
+            title_shows_state: false,
+            always_on_top: false,
+            media_keys: true,
+            gamepad: true,
+            gamepad_bindings: BTreeMap::new(),
+            fullscreen_progress_bar: false,
+            fullscreen_progress_bar_height: 4,
+            fullscreen_progress_bar_color: "#3584e4".to_string(),
+            chapter_markers: true,
+            audio_prefer_keywords: vec![],
+            audio_avoid_keywords: vec![],
+            subtitle_prefer_keywords: vec![],
+            subtitle_languages: vec![],
+            subtitle_avoid_keywords: vec![],
+            idle_power_saving: false,
+            idle_power_saving_minutes: 10,
+            stopped_video: StoppedVideo::Black,
+            window_decorations: true,
+            preserve_pitch: true,
+            audio_normalization: false,
+            default_volume: 1.0,
+            equalizer: None,
+            pause_on_device_change: DeviceChangeAction::Pause,
+            night_mode: false,
+            night_mode_start_hour: 21,
+            night_mode_end_hour: 7,
+            night_mode_brightness: 0.4,
+            night_mode_saturation: 0.35,
+            track_cycle_timeout_ms: 1500,
+            overwrite_mode: OverwriteMode::Rename,
+            replaygain_mode: ReplayGainMode::Off,
+            channel_remap: ChannelRemap::Off,
+            channel_matrix: vec![vec![1.0, 0.0], vec![0.0, 1.0]],
+            audio_profiles: vec![],
+            audio_profile: None,
+            seek_forward_seconds: 5.0,
+            seek_backward_seconds: 2.0,
+            large_seek_seconds: 60.0,
+            seek_preview: false,
+            seek_thumbnails: true,
+            color_range: ColorRange::Auto,
+            deinterlace: Deinterlace::Off,
+            pixel_aspect_ratio: None,
+            start_paused: false,
+            video_sink: None,
+            rebuild_video_sink_on_display_change: true,
+            show_audio_meter: false,
+            show_frame_stats: false,
+            volume_input_key: None,
+            position_input_key: None,
+            download_first: false,
+            download_max_size: 2048,
+            download_keep: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn open(path: Option<path::PathBuf>) -> Self {
+        let mut settings = match path {
+            Some(ref p) => Settings::read(p).unwrap_or_default(),
+            None => Settings::default(),
+        };
+        settings.path = path;
+        settings
+    }
+
+    pub fn find_audio_profile(&self, name: &str) -> Option<&AudioProfile> {
+        self.audio_profiles.iter().find(|profile| profile.name == name)
+    }
+
+    fn read<T: AsRef<path::Path>>(path: T) -> Result<Self, Error> {
+        let mut file = File::open(path.as_ref())?;
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+
+        let settings: Settings = serde_json::from_str(&data)?;
+        Ok(settings)
+    }
+
+    pub fn write(&self) -> Result<(), Error> {
+        if let Some(ref path) = self.path {
+            let mut file = File::create(path)?;
+
+            let json = serde_json::to_string(&self)?;
+            file.write_all(json.as_bytes())?;
+            file.sync_all()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+extern crate glib;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+
+use crate::channel_player::uri_to_sha256;
+use crate::constants;
+use crate::downloader::{Download, DownloadResult};
+use crate::settings::SubtitleSource;
+
+// Anything larger is not a subtitle file.
+const MAX_SUBTITLE_SIZE: u64 = 10 * 1024 * 1024;
+
+// Size of the chunks read at the start and at the end of the media to compute
+// its hash.
+const HASH_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// The hash used by OpenSubtitles and other subtitle databases: the file
+/// size plus the sum of the first and last 64 KiB, as little-endian 64-bit
+/// words.
+pub fn media_hash(path: &Path) -> std::io::Result<u64> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut hash = size;
+    let mut buffer = vec![0u8; HASH_CHUNK_SIZE.min(size) as usize];
+    for offset in [0, size.saturating_sub(HASH_CHUNK_SIZE)].iter() {
+        file.seek(SeekFrom::Start(*offset))?;
+        file.read_exact(&mut buffer)?;
+        for word in buffer.chunks_exact(8) {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(word);
+            hash = hash.wrapping_add(u64::from_le_bytes(bytes));
+        }
+    }
+    Ok(hash)
+}
+
+fn escape(text: &str) -> String {
+    glib::uri_escape_string(text, None, true).map_or_else(|| text.to_string(), String::from)
+}
+
+// Replace the {name}, {language}, {hash} and {size} placeholders of the
+// source URL.
+fn source_uri(source: &SubtitleSource, media_path: &Path) -> Option<String> {
+    let mut uri = source.url.replace("{language}", &escape(&source.language));
+    if uri.contains("{name}") {
+        let name = media_path.file_stem()?.to_string_lossy();
+        uri = uri.replace("{name}", &escape(&name));
+    }
+    if uri.contains("{hash}") || uri.contains("{size}") {
+        let size = media_path.metadata().ok()?.len();
+        let hash = media_hash(media_path).ok()?;
+        uri = uri
+            .replace("{hash}", &format!("{:016x}", hash))
+            .replace("{size}", &size.to_string());
+    }
+    Some(uri)
+}
+
+fn subtitle_extension(uri: &str) -> &str {
+    let path = uri.split(|c| c == '?' || c == '#').next().unwrap_or(uri);
+    let extension = path.rsplit('.').next().unwrap_or("");
+    constants::SUB_FILE_EXTENSIONS
+        .iter()
+        .find(|e| e.eq_ignore_ascii_case(extension))
+        .unwrap_or(&"srt")
+}
+
+struct FetchState {
+    media_path: PathBuf,
+    // Downloaded subtitles are stored there, by media and language, or next
+    // to the media without a directory.
+    directory: Option<PathBuf>,
+    sources: RefCell<VecDeque<SubtitleSource>>,
+    errors: RefCell<Vec<String>>,
+    download: RefCell<Option<Download>>,
+    on_done: Box<dyn Fn(Result<(SubtitleSource, PathBuf), String>)>,
+}
+
+impl FetchState {
+    fn destination(&self, source: &SubtitleSource, uri: &str) -> Option<PathBuf> {
+        let extension = subtitle_extension(uri);
+        match self.directory {
+            Some(ref directory) => {
+                let id = uri_to_sha256(&self.media_path.to_string_lossy());
+                Some(directory.join(format!("{}.{}.{}", id, source.language, extension)))
+            }
+            None => {
+                let mut name = self.media_path.file_stem()?.to_os_string();
+                name.push(".");
+                name.push(extension);
+                Some(self.media_path.with_file_name(name))
+            }
+        }
+    }
+
+    fn fail(&self, source: &SubtitleSource, message: &str) {
+        self.errors.borrow_mut().push(format!("{}: {}", source.name, message));
+    }
+}
+
+// Try the remaining sources in order, until one of them provides a file.
+fn try_next(state: &Rc<FetchState>) {
+    *state.download.borrow_mut() = None;
+    loop {
+        let source = match state.sources.borrow_mut().pop_front() {
+            Some(source) => source,
+            None => {
+                (state.on_done)(Err(state.errors.borrow().join(", ")));
+                return;
+            }
+        };
+        let (uri, destination) = match source_uri(&source, &state.media_path)
+            .and_then(|uri| state.destination(&source, &uri).map(|destination| (uri, destination)))
+        {
+            Some(target) => target,
+            None => {
+                state.fail(&source, "unable to build the URL");
+                continue;
+            }
+        };
+        if destination.is_file() {
+            (state.on_done)(Ok((source, destination)));
+            return;
+        }
+
+        let state_weak = Rc::downgrade(state);
+        let download_source = source.clone();
+        let on_done = move |result| on_download_done(&state_weak, &download_source, result);
+        match Download::start(&uri, destination, MAX_SUBTITLE_SIZE, |_| {}, on_done) {
+            Ok(download) => {
+                *state.download.borrow_mut() = Some(download);
+                return;
+            }
+            Err(e) => state.fail(&source, &e.to_string()),
+        }
+    }
+}
+
+fn on_download_done(state: &Weak<FetchState>, source: &SubtitleSource, result: DownloadResult) {
+    let state = match state.upgrade() {
+        Some(state) => state,
+        None => return,
+    };
+    match result {
+        DownloadResult::Finished(path) if path.metadata().map_or(false, |m| m.len() > 0) => {
+            (state.on_done)(Ok((source.clone(), path)));
+            return;
+        }
+        DownloadResult::Finished(path) => {
+            let _ = std::fs::remove_file(path);
+            state.fail(source, "empty file");
+        }
+        DownloadResult::Unsuitable => state.fail(source, "unknown or excessive size"),
+        DownloadResult::Failed(message) => state.fail(source, &message),
+    }
+    // Not from the bus watch of the download being replaced.
+    let state_weak = Rc::downgrade(&state);
+    glib::idle_add_local(move || {
+        if let Some(state) = state_weak.upgrade() {
+            try_next(&state);
+        }
+        glib::Continue(false)
+    });
+}
+
+/// Remove the subtitles downloaded to the cache directory for the media, or
+/// for all media without one.
+pub fn remove_cached_subtitles(directory: &Path, media_path: Option<&Path>) -> std::io::Result<()> {
+    let prefix = match media_path {
+        Some(path) => format!("{}.", uri_to_sha256(&path.to_string_lossy())),
+        None => return std::fs::remove_dir_all(directory),
+    };
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            std::fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Download of a subtitle file for a media, trying each source in order
+/// until one provides it. Dropping the fetch cancels it.
+pub struct SubtitleFetch {
+    _state: Rc<FetchState>,
+}
+
+impl SubtitleFetch {
+    /// `on_done` receives the source and the file of the downloaded
+    /// subtitles, or the errors of all the sources.
+    pub fn start<F>(media_path: &Path, sources: &[SubtitleSource], directory: Option<PathBuf>, on_done: F) -> Self
+    where
+        F: Fn(Result<(SubtitleSource, PathBuf), String>) + 'static,
+    {
+        let state = Rc::new(FetchState {
+            media_path: media_path.to_path_buf(),
+            directory,
+            sources: RefCell::new(sources.iter().cloned().collect()),
+            errors: RefCell::new(vec![]),
+            download: RefCell::new(None),
+            on_done: Box::new(on_done),
+        });
+        // Report even cached files asynchronously, like downloads.
+        let state_weak = Rc::downgrade(&state);
+        glib::idle_add_local(move || {
+            if let Some(state) = state_weak.upgrade() {
+                try_next(&state);
+            }
+            glib::Continue(false)
+        });
+        Self { _state: state }
+    }
+}
@@ -0,0 +1,193 @@
+use chardetng::EncodingDetector;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::constants;
+
+pub fn is_subtitle_file(path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => constants::SUB_FILE_EXTENSIONS.contains(&extension.to_lowercase().as_str()),
+        None => false,
+    }
+}
+
+// The file stem is used as-is, without going through any URI or UTF-8
+// conversion, so that names with '#', '%', spaces or invalid UTF-8 work too.
+fn find_next_to(media_path: &Path) -> Option<PathBuf> {
+    let stem = media_path.file_stem()?;
+    let directory = media_path.parent()?;
+    constants::SUB_FILE_EXTENSIONS.iter().find_map(|extension| {
+        let mut name = stem.to_os_string();
+        name.push(".");
+        name.push(extension);
+        let candidate = directory.join(name);
+        if candidate.is_file() {
+            Some(candidate)
+        } else {
+            None
+        }
+    })
+}
+
+fn extension_rank(path: &Path) -> usize {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    constants::SUB_FILE_EXTENSIONS
+        .iter()
+        .position(|e| *e == extension)
+        .unwrap_or(constants::SUB_FILE_EXTENSIONS.len())
+}
+
+// Files named like the media with a suffix, usually a language as in
+// movie.en.srt. Their names are compared lossily, the directory listing
+// being needed anyway.
+fn find_with_suffix(media_path: &Path) -> Option<PathBuf> {
+    let prefix = format!("{}.", media_path.file_stem()?.to_string_lossy());
+    let directory = media_path.parent()?;
+    let mut candidates = fs::read_dir(directory)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned());
+            match stem {
+                Some(stem) => stem.starts_with(&prefix) && is_subtitle_file(path) && path.is_file(),
+                None => false,
+            }
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| extension_rank(a).cmp(&extension_rank(b)).then_with(|| a.cmp(b)));
+    candidates.into_iter().next()
+}
+
+/// Look for a subtitle file named like the media, next to it and, for
+/// symbolic links, next to the file they point to. Files named exactly like
+/// the media win over ones with a suffix.
+pub fn find_matching_subtitle(media_path: &Path) -> Option<PathBuf> {
+    if let Some(path) = find_next_to(media_path).or_else(|| find_with_suffix(media_path)) {
+        return Some(path);
+    }
+    let is_symlink = fs::symlink_metadata(media_path)
+        .map(|m| m.file_type().is_symlink())
+        .unwrap_or(false);
+    if is_symlink {
+        if let Ok(target) = fs::canonicalize(media_path) {
+            return find_next_to(&target).or_else(|| find_with_suffix(&target));
+        }
+    }
+    None
+}
+
+// Amount of the file read to guess its encoding.
+const ENCODING_DETECTION_BYTES: u64 = 64 * 1024;
+
+/// Guess the character encoding of a text subtitle file, as a name known to
+/// iconv, for the subtitle-encoding property of playbin.
+pub fn detect_encoding(path: &Path) -> Option<&'static str> {
+    let mut data = vec![];
+    fs::File::open(path)
+        .ok()?
+        .take(ENCODING_DETECTION_BYTES)
+        .read_to_end(&mut data)
+        .ok()?;
+    if data.starts_with(&[0xff, 0xfe]) {
+        return Some("UTF-16LE");
+    }
+    if data.starts_with(&[0xfe, 0xff]) {
+        return Some("UTF-16BE");
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(&data, (data.len() as u64) < ENCODING_DETECTION_BYTES);
+    let name = detector.guess(None, true).name();
+    // The few names of the Encoding Standard iconv doesn't know.
+    Some(match name {
+        "x-mac-cyrillic" => "MAC-CYRILLIC",
+        "windows-874" => "CP874",
+        name => name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gio::prelude::*;
+
+    // CJK and right-to-left subtitles, in the legacy encodings they are
+    // often found in.
+    const SAMPLES: [(&str, &str); 5] = [
+        (
+            "Shift_JIS",
+            "1\n00:00:01,000 --> 00:00:04,000\n今日はいい天気ですね。\n\n\
+             2\n00:00:05,000 --> 00:00:08,000\nどこへ行きたいですか？\n",
+        ),
+        (
+            "GBK",
+            "1\n00:00:01,000 --> 00:00:04,000\n你好，欢迎来到北京。\n\n\
+             2\n00:00:05,000 --> 00:00:08,000\n我们明天一起去吃饭吧。\n",
+        ),
+        (
+            "EUC-KR",
+            "1\n00:00:01,000 --> 00:00:04,000\n안녕하세요, 만나서 반갑습니다.\n\n\
+             2\n00:00:05,000 --> 00:00:08,000\n내일 같이 점심 먹을까요?\n",
+        ),
+        (
+            "windows-1256",
+            "1\n00:00:01,000 --> 00:00:04,000\nمرحبا، كيف حالك اليوم؟\n\n\
+             2\n00:00:05,000 --> 00:00:08,000\nأنا بخير، شكرا جزيلا.\n",
+        ),
+        (
+            "windows-1255",
+            "1\n00:00:01,000 --> 00:00:04,000\nשלום, מה שלומך היום?\n\n\
+             2\n00:00:05,000 --> 00:00:08,000\nאני בסדר, תודה רבה.\n",
+        ),
+    ];
+
+    // Convert with iconv, as subparse does with the detected encoding.
+    fn convert(data: &[u8], to: &str, from: &str) -> Vec<u8> {
+        let converter = gio::CharsetConverter::new(to, from).unwrap();
+        let mut output = vec![0; data.len() * 4];
+        let (_, _, written) = converter
+            .convert(data, &mut output[..], gio::ConverterFlags::INPUT_AT_END)
+            .unwrap();
+        output.truncate(written);
+        output
+    }
+
+    fn write_subtitles(name: &str, data: &[u8]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("glide-subtitles-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(name);
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn legacy_encodings_of_cjk_and_rtl_subtitles_are_detected() {
+        for (index, (encoding, text)) in SAMPLES.iter().enumerate() {
+            let path = write_subtitles(
+                &format!("legacy-{}.srt", index),
+                &convert(text.as_bytes(), encoding, "UTF-8"),
+            );
+            let detected = detect_encoding(&path).unwrap();
+            assert_eq!(detected, *encoding);
+            let decoded = convert(&fs::read(&path).unwrap(), "UTF-8", detected);
+            assert_eq!(std::str::from_utf8(&decoded).unwrap(), *text);
+        }
+    }
+
+    #[test]
+    fn utf8_cjk_and_rtl_subtitles_are_left_alone() {
+        for (index, (_, text)) in SAMPLES.iter().enumerate() {
+            let path = write_subtitles(&format!("utf8-{}.srt", index), text.as_bytes());
+            assert_eq!(detect_encoding(&path), Some("UTF-8"));
+        }
+    }
+
+    #[test]
+    fn utf16_subtitles_are_detected_from_their_byte_order_mark() {
+        let text = "\u{feff}1\n00:00:01,000 --> 00:00:04,000\nשלום 你好\n";
+        let path = write_subtitles("utf16le.srt", &convert(text.as_bytes(), "UTF-16LE", "UTF-8"));
+        assert_eq!(detect_encoding(&path), Some("UTF-16LE"));
+        let path = write_subtitles("utf16be.srt", &convert(text.as_bytes(), "UTF-16BE", "UTF-8"));
+        assert_eq!(detect_encoding(&path), Some("UTF-16BE"));
+    }
+}
@@ -0,0 +1,214 @@
+extern crate glib;
+extern crate gstreamer as gst;
+
+use failure::Error;
+use gst::prelude::*;
+use std::fs;
+use std::thread;
+
+use crate::channel_player::PlayerEvent;
+
+// Length of the analyzed playback window.
+const WINDOW_MS: u64 = 120_000;
+// Offsets are searched in [-MAX_OFFSET_MS, MAX_OFFSET_MS].
+const MAX_OFFSET_MS: i64 = 10_000;
+const OFFSET_STEP_MS: i64 = 50;
+// How close a cue start must be to a speech onset to count as a match.
+const MATCH_TOLERANCE_MS: i64 = 250;
+const LEVEL_INTERVAL_MS: u64 = 50;
+// Silence required before a speech onset, to skip pauses between words.
+const MIN_SILENCE_MS: u64 = 300;
+// Speech is considered to be this much louder than the noise floor.
+const SPEECH_THRESHOLD_DB: f64 = 10.0;
+
+// "01:02:03,456" (SRT), "01:02:03.456" (WebVTT) or "1:02:03.45" (ASS).
+fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let timestamp = timestamp.trim().replace(',', ".");
+    let mut parts = timestamp.rsplit(':');
+    let seconds = parts.next()?.parse::<f64>().ok()?;
+    let minutes = parts.next().map_or(Ok(0), |m| m.parse::<u64>()).ok()?;
+    let hours = parts.next().map_or(Ok(0), |h| h.parse::<u64>()).ok()?;
+    Some((hours * 3600 + minutes * 60) * 1000 + (seconds * 1000.0).round() as u64)
+}
+
+/// Start times of the cues of an SRT, WebVTT or ASS file, in milliseconds.
+pub fn parse_cue_starts(text: &str) -> Vec<u64> {
+    let mut starts = text
+        .lines()
+        .filter_map(|line| {
+            if let Some(index) = line.find("-->") {
+                parse_timestamp(&line[..index])
+            } else if line.starts_with("Dialogue:") {
+                line.split(',').nth(1).and_then(parse_timestamp)
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    starts.sort_unstable();
+    starts.dedup();
+    starts
+}
+
+// Decode the audio of the window and return the loudness of every level
+// interval, as (stream time in ms, peak RMS over channels in dB) pairs.
+fn measure_levels(uri: &str, start_ms: u64) -> Result<Vec<(u64, f64)>, Error> {
+    let playbin = gst::ElementFactory::make("playbin", None)?;
+    playbin.set_property("uri", &uri)?;
+    playbin.set_property_from_str("flags", "audio");
+    let description = format!(
+        "audioconvert ! level interval={} ! fakesink sync=false",
+        LEVEL_INTERVAL_MS * 1_000_000
+    );
+    let sink = gst::parse_bin_from_description(&description, true)?;
+    playbin.set_property("audio-sink", &sink)?;
+
+    let bus = playbin.get_bus().unwrap();
+    playbin.set_state(gst::State::Paused)?;
+    let mut levels = vec![];
+    let mut started = false;
+    let result = loop {
+        let msg = match bus.timed_pop(gst::CLOCK_TIME_NONE) {
+            Some(msg) => msg,
+            None => break Ok(()),
+        };
+        match msg.view() {
+            gst::MessageView::AsyncDone(_) if !started => {
+                started = true;
+                let seek = gst::Event::new_seek(
+                    1.0,
+                    gst::SeekFlags::FLUSH | gst::SeekFlags::ACCURATE,
+                    gst::SeekType::Set,
+                    gst::ClockTime::from_mseconds(start_ms),
+                    gst::SeekType::Set,
+                    gst::ClockTime::from_mseconds(start_ms + WINDOW_MS),
+                )
+                .build();
+                if !playbin.send_event(seek) {
+                    break Err(failure::err_msg("Unable to seek in the media"));
+                }
+                playbin.set_state(gst::State::Playing)?;
+            }
+            gst::MessageView::Element(element) => {
+                let s = match element.get_structure() {
+                    Some(s) if s.get_name() == "level" => s,
+                    _ => continue,
+                };
+                let time = s.get_some::<u64>("stream-time").unwrap_or(0) / 1_000_000;
+                if let Ok(Some(rms)) = s.get::<glib::ValueArray>("rms") {
+                    let peak = (0..)
+                        .map(|i| rms.get_nth(i))
+                        .take_while(Option::is_some)
+                        .flatten()
+                        .filter_map(|v| v.get_some::<f64>().ok())
+                        .fold(std::f64::NEG_INFINITY, f64::max);
+                    levels.push((time, peak));
+                }
+            }
+            gst::MessageView::Eos(_) => break Ok(()),
+            gst::MessageView::Error(err) => break Err(err.get_error().into()),
+            _ => {}
+        }
+    };
+    let _ = playbin.set_state(gst::State::Null);
+    result.map(|_| levels)
+}
+
+// Simple energy-based voice activity detection: speech starts when the level
+// rises well above the noise floor after a pause.
+fn detect_speech_onsets(levels: &[(u64, f64)]) -> Vec<u64> {
+    let mut sorted = levels
+        .iter()
+        .map(|(_, db)| *db)
+        .filter(|db| db.is_finite())
+        .collect::<Vec<_>>();
+    if sorted.is_empty() {
+        return vec![];
+    }
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let noise_floor = sorted[sorted.len() / 5];
+    let threshold = noise_floor + SPEECH_THRESHOLD_DB;
+
+    let mut onsets = vec![];
+    let mut silence_start = None;
+    for (time, db) in levels {
+        if *db < threshold {
+            silence_start.get_or_insert(*time);
+        } else if let Some(start) = silence_start.take() {
+            if time - start >= MIN_SILENCE_MS {
+                onsets.push(*time);
+            }
+        }
+    }
+    onsets
+}
+
+// Offset, in milliseconds, to add to the cue times to best match the speech
+// onsets, if any cue matched at all.
+fn best_offset(cues: &[u64], onsets: &[u64]) -> Option<i64> {
+    let mut best: Option<(usize, i64)> = None;
+    let mut offset = -MAX_OFFSET_MS;
+    while offset <= MAX_OFFSET_MS {
+        let matches = cues
+            .iter()
+            .filter(|cue| {
+                let shifted = **cue as i64 + offset;
+                onsets
+                    .iter()
+                    .any(|onset| (*onset as i64 - shifted).abs() <= MATCH_TOLERANCE_MS)
+            })
+            .count();
+        // Prefer the smallest correction on ties.
+        let better = match best {
+            Some((count, best_offset)) => matches > count || (matches == count && offset.abs() < best_offset.abs()),
+            None => matches > 0,
+        };
+        if better {
+            best = Some((matches, offset));
+        }
+        offset += OFFSET_STEP_MS;
+    }
+    best.map(|(_, offset)| offset)
+}
+
+fn analyze(uri: &str, subtitle_uri: &str, position: gst::ClockTime) -> Result<i64, Error> {
+    let (path, _) = glib::filename_from_uri(subtitle_uri)?;
+    let text = fs::read(path)?;
+    let cues = parse_cue_starts(&String::from_utf8_lossy(&text));
+
+    let start_ms = position.mseconds().unwrap_or(0);
+    let window_start = start_ms.saturating_sub(MAX_OFFSET_MS as u64);
+    let window_end = start_ms + WINDOW_MS + MAX_OFFSET_MS as u64;
+    let cues = cues
+        .into_iter()
+        .filter(|cue| *cue >= window_start && *cue <= window_end)
+        .collect::<Vec<_>>();
+    if cues.is_empty() {
+        return Err(failure::err_msg("No subtitle cue around the current position"));
+    }
+
+    let levels = measure_levels(uri, start_ms)?;
+    let onsets = detect_speech_onsets(&levels);
+    best_offset(&cues, &onsets).ok_or_else(|| failure::err_msg("Speech could not be matched to the subtitles"))
+}
+
+/// Analyze a window of the media starting at `position` from a background
+/// thread, then suggest a subtitle offset with a SubtitleSyncSuggested event.
+pub fn suggest_offset(
+    uri: &str,
+    subtitle_uri: &str,
+    position: gst::ClockTime,
+    subscribers: Vec<glib::Sender<PlayerEvent>>,
+) {
+    let uri = uri.to_string();
+    let subtitle_uri = subtitle_uri.to_string();
+    thread::spawn(move || {
+        let event = match analyze(&uri, &subtitle_uri, position) {
+            Ok(offset_ms) => PlayerEvent::SubtitleSyncSuggested(offset_ms * 1_000_000),
+            Err(e) => PlayerEvent::SubtitleSyncFailed(e.to_string()),
+        };
+        for sender in &subscribers {
+            let _ = sender.send(event.clone());
+        }
+    });
+}
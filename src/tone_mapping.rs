@@ -0,0 +1,122 @@
+// Reference white of SDR content, in nits, as recommended by ITU-R BT.2408.
+const SDR_REFERENCE_WHITE: f64 = 203.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum HdrTransfer {
+    Pq,
+    Hlg,
+}
+
+/// Detect the HDR transfer function from a caps colorimetry string, either
+/// named ("bt2100-pq") or in its "range:matrix:transfer:primaries" form.
+pub fn hdr_transfer_from_colorimetry(colorimetry: &str) -> Option<HdrTransfer> {
+    match colorimetry {
+        "bt2100-pq" => return Some(HdrTransfer::Pq),
+        "bt2100-hlg" => return Some(HdrTransfer::Hlg),
+        _ => {}
+    };
+    // GST_VIDEO_TRANSFER_SMPTE2084 and GST_VIDEO_TRANSFER_ARIB_STD_B67.
+    match colorimetry.split(':').nth(2) {
+        Some("14") => Some(HdrTransfer::Pq),
+        Some("15") => Some(HdrTransfer::Hlg),
+        _ => None,
+    }
+}
+
+fn pq_to_nits(value: f64) -> f64 {
+    let m1 = 2610.0 / 16384.0;
+    let m2 = 2523.0 / 4096.0 * 128.0;
+    let c1 = 3424.0 / 4096.0;
+    let c2 = 2413.0 / 4096.0 * 32.0;
+    let c3 = 2392.0 / 4096.0 * 32.0;
+
+    let p = value.powf(1.0 / m2);
+    let l = ((p - c1).max(0.0) / (c2 - c3 * p)).powf(1.0 / m1);
+    l * 10000.0
+}
+
+fn hlg_to_nits(value: f64) -> f64 {
+    let a: f64 = 0.178_832_77;
+    let b = 1.0 - 4.0 * a;
+    let c = 0.5 - a * (4.0 * a).ln();
+
+    let scene = if value <= 0.5 {
+        value * value / 3.0
+    } else {
+        (((value - c) / a).exp() + b) / 12.0
+    };
+    // Nominal peak luminance of a 1000 nits HLG display.
+    scene * 1000.0
+}
+
+fn srgb_encode(linear: f64) -> f64 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Build a lookup table mapping 8-bit HDR-encoded samples to tone-mapped
+/// sRGB samples, using an extended Reinhard operator. Primaries are left
+/// untouched, so saturated BT.2020 colours will look a bit dull.
+pub fn build_sdr_lut(transfer: HdrTransfer, peak_nits: f64) -> [u8; 256] {
+    let mut lut = [0; 256];
+    let white = peak_nits / SDR_REFERENCE_WHITE;
+    for (i, entry) in lut.iter_mut().enumerate() {
+        let value = i as f64 / 255.0;
+        let nits = match transfer {
+            HdrTransfer::Pq => pq_to_nits(value),
+            HdrTransfer::Hlg => hlg_to_nits(value),
+        };
+        let x = nits / SDR_REFERENCE_WHITE;
+        let mapped = x * (1.0 + x / (white * white)) / (1.0 + x);
+        *entry = (srgb_encode(mapped.min(1.0)) * 255.0).round() as u8;
+    }
+    lut
+}
+
+/// Tone-map 8-bit HDR-encoded samples in place, see `build_sdr_lut()`.
+pub fn tone_map_samples(data: &mut [u8], transfer: HdrTransfer, peak_nits: f64) {
+    let lut = build_sdr_lut(transfer, peak_nits);
+    for byte in data.iter_mut() {
+        *byte = lut[*byte as usize];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // PQ code of the SDR reference white, 203 nits.
+    const PQ_REFERENCE_WHITE: u8 = 149;
+
+    #[test]
+    fn colorimetry() {
+        assert!(hdr_transfer_from_colorimetry("bt2100-pq") == Some(HdrTransfer::Pq));
+        assert!(hdr_transfer_from_colorimetry("2:4:15:7") == Some(HdrTransfer::Hlg));
+        assert!(hdr_transfer_from_colorimetry("bt709").is_none());
+    }
+
+    #[test]
+    fn lut_is_monotonic() {
+        for transfer in &[HdrTransfer::Pq, HdrTransfer::Hlg] {
+            let lut = build_sdr_lut(*transfer, 1000.0);
+            assert_eq!(lut[0], 0);
+            assert!(lut.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+    }
+
+    #[test]
+    fn peak_maps_to_white() {
+        let lut = build_sdr_lut(HdrTransfer::Pq, 10000.0);
+        assert_eq!(lut[255], 255);
+    }
+
+    #[test]
+    fn reference_white_is_not_washed_out() {
+        let lut = build_sdr_lut(HdrTransfer::Pq, 1000.0);
+        let white = lut[PQ_REFERENCE_WHITE as usize];
+        assert!(white > 180 && white < 200, "reference white mapped to {}", white);
+    }
+}
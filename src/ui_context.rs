@@ -9,26 +9,72 @@ use gdk::prelude::*;
 use gio::prelude::*;
 #[allow(unused_imports)]
 use glib::SendWeakRef;
+use glib::ToVariant;
 use gtk::prelude::*;
+use std::cell::{Cell, RefCell};
 use std::cmp;
 #[allow(unused_imports)]
 use std::os::raw::c_void;
+use std::path;
+use std::rc::Rc;
 use std::string;
 use std::sync::Mutex;
 
+use crate::chapter_files::Chapter;
 use crate::PlaybackState;
 
+// Width of the area along the borders of an undecorated window resizing it.
+const RESIZE_BORDER: f64 = 8.0;
+
+// Chapters closer than this to the previous marker, in pixels, aren't
+// marked on the position slider, and the pointer has to be at most that
+// far from a marker to show its title or seek to it on click.
+const CHAPTER_MARK_SPACING: f64 = 8.0;
+const CHAPTER_MARK_TOLERANCE: f64 = 4.0;
+
+// Display changes come in bursts, such as the window being moved while a
+// monitor is unplugged, handled once they settled.
+const DISPLAY_CHANGE_DELAY_MS: u32 = 500;
+
 lazy_static! {
     pub static ref INHIBIT_COOKIE: Mutex<Option<u32>> = Mutex::new(None);
     pub static ref INITIAL_POSITION: Mutex<Option<(i32, i32)>> = Mutex::new(None);
     pub static ref INITIAL_SIZE: Mutex<Option<(i32, i32)>> = Mutex::new(None);
     pub static ref MOUSE_NOTIFY_SIGNAL_ID: Mutex<Option<glib::SignalHandlerId>> = Mutex::new(None);
     pub static ref AUTOHIDE_SOURCE: Mutex<Option<glib::SourceId>> = Mutex::new(None);
+    pub static ref OSD_SOURCE: Mutex<Option<glib::SourceId>> = Mutex::new(None);
 }
 
 #[cfg(target_os = "macos")]
 use crate::iokit_sleep_disabler;
 
+// Levels of the Cocoa window server.
+#[cfg(target_os = "macos")]
+const NS_NORMAL_WINDOW_LEVEL: i64 = 0;
+#[cfg(target_os = "macos")]
+const NS_FLOATING_WINDOW_LEVEL: i64 = 3;
+
+// Floating windows stay above the normal ones, also while the application
+// isn't active.
+#[cfg(target_os = "macos")]
+fn set_ns_window_floating(gdk_window: &gdk::Window, floating: bool) {
+    extern "C" {
+        fn gdk_quartz_window_get_nswindow(window: *mut c_void) -> *mut objc::runtime::Object;
+    }
+
+    let level = if floating {
+        NS_FLOATING_WINDOW_LEVEL
+    } else {
+        NS_NORMAL_WINDOW_LEVEL
+    };
+    unsafe {
+        let ns_window = gdk_quartz_window_get_nswindow(gdk_window.as_ptr() as *mut c_void);
+        if !ns_window.is_null() {
+            let _: () = msg_send![ns_window, setLevel: level];
+        }
+    }
+}
+
 pub fn initialize_and_create_app() -> gtk::Application {
     #[cfg(target_os = "linux")]
     {
@@ -57,11 +103,114 @@ pub fn initialize_and_create_app() -> gtk::Application {
     gtk_app
 }
 
+// Thin progress line shown instead of the toolbar in fullscreen.
+struct ProgressLine {
+    enabled: bool,
+    fraction: f64,
+    color: gdk::RGBA,
+}
+
+// Chapter boundaries marked on the position slider.
+#[derive(Default)]
+struct ChapterMarks {
+    enabled: bool,
+    chapters: Vec<Chapter>,
+    // The chapters marked for the current width of the slider, by start
+    // time in nanoseconds, with their titles.
+    shown: Vec<(u64, string::String)>,
+    width: i32,
+}
+
+impl ChapterMarks {
+    fn update(&mut self, scale: &gtk::Scale) {
+        scale.clear_marks();
+        self.shown.clear();
+        self.width = scale.get_range_rect().width;
+        let end = scale.get_adjustment().get_upper();
+        if !self.enabled || end <= 0.0 || self.width <= 0 {
+            return;
+        }
+        let min_gap = CHAPTER_MARK_SPACING / f64::from(self.width) * end;
+        for (index, chapter) in self.chapters.iter().enumerate() {
+            let start = chapter.start as f64 / 1_000_000_000_f64;
+            if start <= 0.0 || start >= end {
+                continue;
+            }
+            let crowded = self.shown.last().map_or(false, |(previous, _)| {
+                start - (*previous as f64 / 1_000_000_000_f64) < min_gap
+            });
+            if crowded {
+                continue;
+            }
+            let title = if chapter.title.is_empty() {
+                format!("Chapter {}", index + 1)
+            } else {
+                chapter.title.clone()
+            };
+            scale.add_mark(start, gtk::PositionType::Bottom, None);
+            self.shown.push((chapter.start, title));
+        }
+    }
+
+    // The marker closest to the horizontal position, if near enough.
+    fn find(&self, scale: &gtk::Scale, x: f64) -> Option<&(u64, string::String)> {
+        let end = scale.get_adjustment().get_upper();
+        let rect = scale.get_range_rect();
+        if end <= 0.0 || rect.width <= 0 {
+            return None;
+        }
+        let distance = |start: u64| {
+            let mark_x = f64::from(rect.x) + start as f64 / 1_000_000_000_f64 / end * f64::from(rect.width);
+            (mark_x - x).abs()
+        };
+        self.shown
+            .iter()
+            .filter(|(start, _)| distance(*start) <= CHAPTER_MARK_TOLERANCE)
+            .min_by(|a, b| {
+                distance(a.0)
+                    .partial_cmp(&distance(b.0))
+                    .unwrap_or(cmp::Ordering::Equal)
+            })
+    }
+}
+
+// Gives the frame to preview at a position of the slider, in seconds.
+type SeekThumbnailSource = Rc<RefCell<Option<Box<dyn Fn(f64) -> Option<gdk_pixbuf::Pixbuf>>>>>;
+
+// Value of the slider under the horizontal position.
+fn slider_value_at(scale: &gtk::Scale, x: f64) -> Option<f64> {
+    let end = scale.get_adjustment().get_upper();
+    let rect = scale.get_range_rect();
+    if end <= 0.0 || rect.width <= 0 {
+        return None;
+    }
+    let fraction = (x - f64::from(rect.x)) / f64::from(rect.width);
+    Some(fraction.max(0.0).min(1.0) * end)
+}
+
 pub struct UIContext {
     window: gtk::ApplicationWindow,
-    main_box: gtk::Box,
+    video_overlay: gtk::Overlay,
+    osd_label: gtk::Label,
+    frame_stats_label: gtk::Label,
+    auto_advance_box: gtk::Box,
+    auto_advance_label: gtk::Label,
+    resume_box: gtk::Box,
+    resume_button: gtk::Button,
+    audio_meter: gtk::DrawingArea,
+    audio_levels: Rc<RefCell<Vec<f64>>>,
+    progress_line: gtk::DrawingArea,
+    progress_line_state: Rc<RefCell<ProgressLine>>,
+    // Covers the video area with a picture, scaled to fit if the flag is
+    // set, while the pipeline can't show the video.
+    still_frame: gtk::DrawingArea,
+    still_frame_image: Rc<RefCell<Option<(gdk_pixbuf::Pixbuf, bool)>>>,
     pause_button: gtk::Button,
     progress_bar: gtk::Scale,
+    chapter_marks: Rc<RefCell<ChapterMarks>>,
+    seek_thumbnail_source: SeekThumbnailSource,
+    // Backward and forward offsets of the seek actions, in seconds.
+    seek_offsets: Rc<Cell<(f64, f64)>>,
     volume_button: gtk::VolumeButton,
     toolbar_box: gtk::Box,
     track_synchronization_window: gtk::ApplicationWindow,
@@ -69,8 +218,12 @@ pub struct UIContext {
     subtitle_offset_entry: gtk::SpinButton,
     subtitle_track_menu: gio::Menu,
     audio_track_menu: gio::Menu,
+    chapter_list_menu: gio::Menu,
     video_track_menu: gio::Menu,
+    aspect_ratio_menu: gio::Menu,
+    video_sink_menu: gio::Menu,
     audio_visualization_menu: gio::Menu,
+    audio_profile_menu: gio::Menu,
     volume_signal_handler_id: Option<glib::SignalHandlerId>,
     position_signal_handler_id: Option<glib::SignalHandlerId>,
     audio_offset_entry_signal_handler_id: Option<glib::SignalHandlerId>,
@@ -78,7 +231,10 @@ pub struct UIContext {
     app: gtk::Application,
 }
 
+// Levels below this are drawn as silence.
+const AUDIO_METER_FLOOR_DB: f64 = -60.0;
 const MINIMAL_WINDOW_SIZE: (i32, i32) = (640, 480);
+const OSD_TIMEOUT_SECONDS: u32 = 2;
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 impl UIContext {
@@ -111,12 +267,220 @@ impl UIContext {
             .upcast::<gtk::Actionable>()
             .set_action_name(Some("app.fullscreen"));
 
-        let main_box: gtk::Box = builder.get_object("main-box").unwrap();
+        let button: gtk::Button = builder.get_object("auto-advance-now-button").unwrap();
+        button
+            .upcast::<gtk::Actionable>()
+            .set_action_name(Some("app.auto-advance-now"));
+
+        let button: gtk::Button = builder.get_object("auto-advance-cancel-button").unwrap();
+        button
+            .upcast::<gtk::Actionable>()
+            .set_action_name(Some("app.auto-advance-cancel"));
+
+        let resume_button: gtk::Button = builder.get_object("resume-button").unwrap();
+        resume_button
+            .clone()
+            .upcast::<gtk::Actionable>()
+            .set_action_name(Some("app.resume-playback"));
+
+        let button: gtk::Button = builder.get_object("start-over-button").unwrap();
+        button
+            .upcast::<gtk::Actionable>()
+            .set_action_name(Some("app.start-over"));
+
+        let video_overlay: gtk::Overlay = builder.get_object("video-overlay").unwrap();
+        let osd_label: gtk::Label = builder.get_object("osd-label").unwrap();
+        let frame_stats_label: gtk::Label = builder.get_object("frame-stats-label").unwrap();
+        let auto_advance_box: gtk::Box = builder.get_object("auto-advance-box").unwrap();
+        let auto_advance_label: gtk::Label = builder.get_object("auto-advance-label").unwrap();
+        let resume_box: gtk::Box = builder.get_object("resume-box").unwrap();
+
+        let audio_meter: gtk::DrawingArea = builder.get_object("audio-meter").unwrap();
+        let audio_levels = Rc::new(RefCell::new(vec![]));
+        let levels = audio_levels.clone();
+        audio_meter.connect_draw(move |area, cairo_context| {
+            let width = f64::from(area.get_allocated_width());
+            let height = f64::from(area.get_allocated_height());
+            cairo_context.set_source_rgba(0.0, 0.0, 0.0, 0.5);
+            cairo_context.rectangle(0., 0., width, height);
+            cairo_context.fill();
+
+            // One bar per channel.
+            let levels = levels.borrow();
+            let bar_width = width / cmp::max(levels.len(), 1) as f64;
+            for (i, db) in levels.iter().enumerate() {
+                let fraction = (1.0 - db / AUDIO_METER_FLOOR_DB).max(0.0).min(1.0);
+                let bar_height = (height - 4.0) * fraction;
+                if fraction > 0.9 {
+                    cairo_context.set_source_rgb(0.9, 0.2, 0.2);
+                } else {
+                    cairo_context.set_source_rgb(0.3, 0.8, 0.3);
+                }
+                cairo_context.rectangle(
+                    i as f64 * bar_width + 2.0,
+                    height - 2.0 - bar_height,
+                    bar_width - 4.0,
+                    bar_height,
+                );
+                cairo_context.fill();
+            }
+            Inhibit(false)
+        });
+
+        let still_frame: gtk::DrawingArea = builder.get_object("still-frame").unwrap();
+        let still_frame_image: Rc<RefCell<Option<(gdk_pixbuf::Pixbuf, bool)>>> = Rc::new(RefCell::new(None));
+        let image = still_frame_image.clone();
+        still_frame.connect_draw(move |area, cairo_context| {
+            let width = f64::from(area.get_allocated_width());
+            let height = f64::from(area.get_allocated_height());
+            cairo_context.set_source_rgb(0., 0., 0.);
+            cairo_context.paint();
+            if let Some((ref pixbuf, fit)) = *image.borrow() {
+                let image_width = f64::from(pixbuf.get_width());
+                let image_height = f64::from(pixbuf.get_height());
+                let mut scale = (width / image_width).min(height / image_height);
+                if !fit {
+                    scale = scale.min(1.0);
+                }
+                cairo_context.translate((width - image_width * scale) / 2., (height - image_height * scale) / 2.);
+                cairo_context.scale(scale, scale);
+                cairo_context.set_source_pixbuf(pixbuf, 0., 0.);
+                cairo_context.paint();
+            }
+            Inhibit(false)
+        });
+
+        let progress_line: gtk::DrawingArea = builder.get_object("progress-line").unwrap();
+        let progress_line_state = Rc::new(RefCell::new(ProgressLine {
+            enabled: false,
+            fraction: 0.0,
+            color: gdk::RGBA {
+                red: 0.2,
+                green: 0.5,
+                blue: 0.9,
+                alpha: 1.0,
+            },
+        }));
+        let state = progress_line_state.clone();
+        progress_line.connect_draw(move |area, cairo_context| {
+            let width = f64::from(area.get_allocated_width());
+            let height = f64::from(area.get_allocated_height());
+            let state = state.borrow();
+            cairo_context.set_source_rgba(1.0, 1.0, 1.0, 0.2);
+            cairo_context.rectangle(0., 0., width, height);
+            cairo_context.fill();
+            let color = &state.color;
+            cairo_context.set_source_rgba(color.red, color.green, color.blue, color.alpha);
+            cairo_context.rectangle(0., 0., width * state.fraction, height);
+            cairo_context.fill();
+            Inhibit(false)
+        });
+        progress_line.add_events(gdk::EventMask::BUTTON_PRESS_MASK);
+        let app = gtk_app.clone();
+        progress_line.connect_button_press_event(move |area, event| {
+            let width = f64::from(area.get_allocated_width().max(1));
+            let (x, _) = event.get_position();
+            let percent = (x / width * 100.0).max(0.0).min(100.0);
+            app.activate_action("seek-to-percent", Some(&percent.to_variant()));
+            Inhibit(true)
+        });
+
         let toolbar_box: gtk::Box = builder.get_object("toolbar-box").unwrap();
         let progress_bar: gtk::Scale = builder.get_object("progress-bar").unwrap();
+        // The arrow keys move the focused slider by the offsets of the seek
+        // actions, the page keys use the increments set with the duration.
+        let seek_offsets = Rc::new(Cell::new((2.0, 5.0)));
+        let offsets = seek_offsets.clone();
+        progress_bar.connect_key_press_event(move |scale, event| {
+            let modifiers =
+                gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::MOD1_MASK | gdk::ModifierType::SHIFT_MASK;
+            if event.get_state().intersects(modifiers) {
+                return Inhibit(false);
+            }
+            let key = event.get_keyval();
+            let (backward, forward) = offsets.get();
+            let offset = if key == gdk::keys::constants::Left || key == gdk::keys::constants::Down {
+                -backward
+            } else if key == gdk::keys::constants::Right || key == gdk::keys::constants::Up {
+                forward
+            } else {
+                return Inhibit(false);
+            };
+            scale.set_value(scale.get_value() + offset);
+            Inhibit(true)
+        });
+        let chapter_marks = Rc::new(RefCell::new(ChapterMarks::default()));
+        let marks = chapter_marks.clone();
+        progress_bar.connect_size_allocate(move |scale, _| {
+            // Marks are only redone when the width changes, since they change
+            // the size requested by the slider.
+            let mut marks = marks.borrow_mut();
+            if marks.width != scale.get_range_rect().width {
+                marks.update(scale);
+            }
+        });
+        progress_bar.set_has_tooltip(true);
+        let marks = chapter_marks.clone();
+        let seek_thumbnail_source: SeekThumbnailSource = Rc::new(RefCell::new(None));
+        let thumbnail_source = seek_thumbnail_source.clone();
+        progress_bar.connect_query_tooltip(move |scale, x, _, keyboard_mode, tooltip| {
+            if keyboard_mode {
+                return false;
+            }
+            let marks = marks.borrow();
+            let chapter = marks.find(scale, f64::from(x));
+            if let Some((_, title)) = chapter {
+                tooltip.set_text(Some(title));
+            }
+            let thumbnail = thumbnail_source
+                .borrow()
+                .as_ref()
+                .and_then(|source| slider_value_at(scale, f64::from(x)).and_then(|value| source(value)));
+            if let Some(ref thumbnail) = thumbnail {
+                tooltip.set_icon(Some(thumbnail));
+            }
+            chapter.is_some() || thumbnail.is_some()
+        });
         let volume_button: gtk::VolumeButton = builder.get_object("volume-button").unwrap();
 
         let window: gtk::ApplicationWindow = builder.get_object("application-window").unwrap();
+        // Without decorations, the window is moved by dragging the video and
+        // resized from its borders. The compositor handles the drag, which
+        // also works on Wayland where clients can't position themselves.
+        window.connect_button_press_event(|window, event| {
+            if window.get_decorated()
+                || event.get_event_type() != gdk::EventType::ButtonPress
+                || event.get_button() != 1
+            {
+                return Inhibit(false);
+            }
+            let (x, y) = event.get_position();
+            let (root_x, root_y) = event.get_root();
+            let (width, height) = window.get_size();
+            let (width, height) = (f64::from(width), f64::from(height));
+            let west = x < RESIZE_BORDER;
+            let east = x > width - RESIZE_BORDER;
+            let north = y < RESIZE_BORDER;
+            let south = y > height - RESIZE_BORDER;
+            let edge = match (north, south, west, east) {
+                (true, _, true, _) => Some(gdk::WindowEdge::NorthWest),
+                (true, _, _, true) => Some(gdk::WindowEdge::NorthEast),
+                (_, true, true, _) => Some(gdk::WindowEdge::SouthWest),
+                (_, true, _, true) => Some(gdk::WindowEdge::SouthEast),
+                (true, _, _, _) => Some(gdk::WindowEdge::North),
+                (_, true, _, _) => Some(gdk::WindowEdge::South),
+                (_, _, true, _) => Some(gdk::WindowEdge::West),
+                (_, _, _, true) => Some(gdk::WindowEdge::East),
+                _ => None,
+            };
+            let button = event.get_button() as i32;
+            let time = event.get_time();
+            match edge {
+                Some(edge) => window.begin_resize_drag(edge, button, root_x as i32, root_y as i32, time),
+                None => window.begin_move_drag(button, root_x as i32, root_y as i32, time),
+            }
+            Inhibit(true)
+        });
         window.connect_map_event(move |widget, _| {
             if let Ok(size) = INITIAL_SIZE.lock() {
                 if let Some((width, height)) = *size {
@@ -161,11 +525,31 @@ impl UIContext {
 
         let subtitle_track_menu: gio::Menu = builder.get_object("subtitle-track-menu").unwrap();
         let audio_track_menu: gio::Menu = builder.get_object("audio-track-menu").unwrap();
+        let chapter_list_menu: gio::Menu = builder.get_object("chapter-list-menu").unwrap();
         let video_track_menu: gio::Menu = builder.get_object("video-track-menu").unwrap();
+        let aspect_ratio_menu: gio::Menu = builder.get_object("aspect-ratio-menu").unwrap();
+        let video_sink_menu: gio::Menu = builder.get_object("video-sink-menu").unwrap();
         let audio_visualization_menu: gio::Menu = builder.get_object("audio-visualization-menu").unwrap();
+        let audio_profile_menu: gio::Menu = builder.get_object("audio-profile-menu").unwrap();
 
         let menu: gio::Menu = builder.get_object("main-menu").unwrap();
 
+        #[cfg(feature = "subtitle-auto-sync")]
+        {
+            let subtitles_menu: gio::Menu = builder.get_object("subtitles-menu").unwrap();
+            subtitles_menu.append(Some("Auto-sync subtitles"), Some("app.subtitle-auto-sync"));
+        }
+
+        #[cfg(feature = "clip-export")]
+        {
+            let video_menu: gio::Menu = builder.get_object("video-menu").unwrap();
+            let section = gio::Menu::new();
+            section.append(Some("Set clip start"), Some("app.clip-start"));
+            section.append(Some("Set clip end"), Some("app.clip-end"));
+            section.append(Some("Export clip…"), Some("app.export-clip"));
+            video_menu.append_section(None, &section);
+        }
+
         #[cfg(not(target_os = "linux"))]
         {
             menu.append(Some("Quit"), Some("app.quit"));
@@ -182,11 +566,45 @@ impl UIContext {
                 ("pause", ["space"]),
                 ("seek-forward", ["<Primary>Right"]),
                 ("seek-backward", ["<Primary>Left"]),
+                ("seek-forward-large", ["<Primary><Shift>Right"]),
+                ("seek-backward-large", ["<Primary><Shift>Left"]),
                 ("audio-volume-increase", ["<Primary>Up"]),
                 ("audio-volume-decrease", ["<Primary>Down"]),
                 ("audio-mute", ["<Primary>m"]),
                 ("open-subtitle-file", ["<Primary>s"]),
                 ("dump-pipeline", ["<Ctrl>d"]),
+                ("pedal-rewind", ["F7"]),
+                ("pedal-resume", ["F8"]),
+                ("instant-replay", ["<Primary>r"]),
+                ("copy-frame", ["<Primary>c"]),
+                ("snapshot", ["<Primary><Shift>s"]),
+                ("video-rotate", ["<Primary><Shift>r"]),
+                ("video-zoom-in", ["<Primary>plus"]),
+                ("video-zoom-out", ["<Primary>minus"]),
+                ("video-zoom-reset", ["<Primary><Shift>z"]),
+                ("playlist-next", ["<Primary>n"]),
+                ("playlist-previous", ["<Primary>p"]),
+                ("playlist-repeat", ["<Primary><Shift>p"]),
+                ("always-on-top", ["<Primary>t"]),
+                ("cycle-track::audio", ["<Primary>a"]),
+                ("cycle-track::subtitle", ["<Primary>j"]),
+                ("cycle-track::video", ["<Primary>k"]),
+                ("cycle-aspect-ratio", ["<Primary>e"]),
+                ("playback-speed-increase", ["<Primary>bracketright"]),
+                ("playback-speed-decrease", ["<Primary>bracketleft"]),
+                ("playback-speed::1.0", ["<Primary>0"]),
+                ("step-forward", ["<Primary>period"]),
+                ("step-backward", ["<Primary>comma"]),
+                ("loop-set-a", ["<Primary>l"]),
+                ("next-chapter", ["<Primary>Page_Down"]),
+                ("seek-to-time('')", ["<Primary>g"]),
+                ("previous-chapter", ["<Primary>Page_Up"]),
+                ("loop-set-b", ["<Primary><Shift>l"]),
+                ("subtitle-toggle", ["<Primary><Shift>j"]),
+                ("subtitle-delay-decrease", ["<Primary>h"]),
+                ("subtitle-delay-increase", ["<Primary><Shift>h"]),
+                ("audio-delay-decrease", ["<Primary>u"]),
+                ("audio-delay-increase", ["<Primary><Shift>u"]),
             ];
             for (action, accels) in accels_per_action.iter() {
                 app.set_accels_for_action(&format!("app.{}", action), accels);
@@ -218,9 +636,24 @@ impl UIContext {
 
         Self {
             window,
-            main_box,
+            video_overlay,
+            osd_label,
+            frame_stats_label,
+            auto_advance_box,
+            auto_advance_label,
+            resume_box,
+            resume_button,
+            audio_meter,
+            audio_levels,
+            progress_line,
+            progress_line_state,
+            still_frame,
+            still_frame_image,
             pause_button,
             progress_bar,
+            chapter_marks,
+            seek_thumbnail_source,
+            seek_offsets,
             volume_button,
             toolbar_box,
             track_synchronization_window,
@@ -228,8 +661,12 @@ impl UIContext {
             subtitle_offset_entry,
             subtitle_track_menu,
             audio_track_menu,
+            chapter_list_menu,
             video_track_menu,
+            aspect_ratio_menu,
+            video_sink_menu,
             audio_visualization_menu,
+            audio_profile_menu,
             volume_signal_handler_id: None,
             position_signal_handler_id: None,
             audio_offset_entry_signal_handler_id: None,
@@ -248,7 +685,12 @@ impl UIContext {
 
     #[cfg(target_os = "linux")]
     pub fn start_autohide_toolbar(&self) {
-        let toolbar_weak = self.toolbar_box.downgrade();
+        let controls = if self.progress_line_state.borrow().enabled {
+            self.progress_line.clone().upcast::<gtk::Widget>()
+        } else {
+            self.toolbar_box.clone().upcast::<gtk::Widget>()
+        };
+        let toolbar_weak = controls.downgrade();
         let notify_signal_id = self.window.connect_motion_notify_event(move |window, _| {
             if let Some(source) = AUTOHIDE_SOURCE.lock().unwrap().take() {
                 glib::source_remove(source);
@@ -302,6 +744,9 @@ impl UIContext {
         *INITIAL_POSITION.lock().unwrap() = Some(window.get_position());
         window.set_show_menubar(false);
         self.toolbar_box.set_visible(false);
+        if self.progress_line_state.borrow().enabled {
+            self.progress_line.show();
+        }
         window.fullscreen();
         let gdk_window = window.get_window().unwrap();
         let cursor = gdk::Cursor::new_for_display(&gdk_window.get_display(), gdk::CursorType::BlankCursor);
@@ -311,6 +756,55 @@ impl UIContext {
         self.start_autohide_toolbar();
     }
 
+    /// Fullscreen the window again, in case it lost its state when the
+    /// displays changed.
+    pub fn reapply_fullscreen(&self) {
+        self.window.fullscreen();
+    }
+
+    /// The callback is invoked once the window settled after its scale
+    /// factor changed, such as when moved to a monitor with another scale,
+    /// or after a monitor was added or removed.
+    pub fn set_display_changed_callback<F: Fn() + 'static>(&self, f: F) {
+        let f = Rc::new(f);
+        let pending: Rc<RefCell<Option<glib::SourceId>>> = Rc::new(RefCell::new(None));
+        let schedule = Rc::new(move || {
+            if let Some(source) = pending.borrow_mut().take() {
+                glib::source_remove(source);
+            }
+            let done = pending.clone();
+            let f = f.clone();
+            *pending.borrow_mut() = Some(glib::timeout_add_local(DISPLAY_CHANGE_DELAY_MS, move || {
+                done.borrow_mut().take();
+                f();
+                glib::Continue(false)
+            }));
+        });
+
+        let changed = schedule.clone();
+        self.window.connect_property_scale_factor_notify(move |_| changed());
+        let display = self.window.get_display();
+        let changed = schedule.clone();
+        display.connect_monitor_added(move |_, _| changed());
+        display.connect_monitor_removed(move |_, _| schedule());
+    }
+
+    pub fn set_decorated(&self, decorated: bool) {
+        self.window.set_decorated(decorated);
+    }
+
+    /// Keep the window above the others, which the window manager might
+    /// ignore.
+    pub fn set_keep_above(&self, enabled: bool) {
+        self.window.set_keep_above(enabled);
+        #[cfg(target_os = "macos")]
+        {
+            if let Some(gdk_window) = self.window.get_window() {
+                set_ns_window_floating(&gdk_window, enabled);
+            }
+        }
+    }
+
     pub fn leave_fullscreen(&self) {
         let window = &self.window;
         let gdk_window = window.get_window().unwrap();
@@ -327,12 +821,14 @@ impl UIContext {
             }
         }
         window.unfullscreen();
+        self.progress_line.hide();
         self.toolbar_box.set_visible(true);
         window.set_show_menubar(true);
         gdk_window.set_cursor(None);
     }
 
-    pub fn dialog_result(&self, relative_uri: Option<glib::GString>) -> Option<glib::GString> {
+    /// The URIs of the files chosen, in the order of the dialog.
+    pub fn dialog_result(&self, relative_uri: Option<glib::GString>) -> Vec<glib::GString> {
         let dialog = gtk::FileChooserDialog::with_buttons(
             Some("Choose a file"),
             Some(&self.window),
@@ -349,13 +845,187 @@ impl UIContext {
             }
         }
 
-        let result_uri = if dialog.run() == gtk::ResponseType::Ok {
-            dialog.get_uri()
+        let result_uris = if dialog.run() == gtk::ResponseType::Ok {
+            dialog.get_uris()
+        } else {
+            vec![]
+        };
+        dialog.close();
+        result_uris
+    }
+
+    pub fn save_dialog_result(&self, relative_uri: Option<glib::GString>, file_name: &str) -> Option<path::PathBuf> {
+        let dialog = gtk::FileChooserDialog::with_buttons(
+            Some("Save as"),
+            Some(&self.window),
+            gtk::FileChooserAction::Save,
+            &[("Save", gtk::ResponseType::Ok), ("Cancel", gtk::ResponseType::Cancel)],
+        );
+
+        // Existing files are handled by the caller, see OverwriteMode.
+        dialog.set_current_name(file_name);
+        if let Some(uri) = relative_uri {
+            if let Ok((filename, _)) = glib::filename_from_uri(&uri) {
+                if let Some(folder) = filename.parent() {
+                    dialog.set_current_folder(folder);
+                }
+            }
+        }
+
+        let result = if dialog.run() == gtk::ResponseType::Ok {
+            dialog.get_filename()
+        } else {
+            None
+        };
+        dialog.close();
+        result
+    }
+
+    pub fn open_file_dialog_result(&self, title: &str) -> Option<path::PathBuf> {
+        let dialog = gtk::FileChooserDialog::with_buttons(
+            Some(title),
+            Some(&self.window),
+            gtk::FileChooserAction::Open,
+            &[("Open", gtk::ResponseType::Ok), ("Cancel", gtk::ResponseType::Cancel)],
+        );
+
+        let result = if dialog.run() == gtk::ResponseType::Ok {
+            dialog.get_filename()
+        } else {
+            None
+        };
+        dialog.close();
+        result
+    }
+
+    /// Text entered by the user, none if the dialog was cancelled.
+    pub fn text_dialog_result(&self, message: &str, details: &str) -> Option<string::String> {
+        let dialog = gtk::MessageDialog::new(
+            Some(&self.window),
+            gtk::DialogFlags::MODAL,
+            gtk::MessageType::Question,
+            gtk::ButtonsType::OkCancel,
+            message,
+        );
+        dialog.set_property_secondary_text(Some(details));
+        dialog.set_default_response(gtk::ResponseType::Ok);
+        let entry = gtk::Entry::new();
+        entry.set_activates_default(true);
+        if let Some(area) = dialog
+            .get_message_area()
+            .and_then(|area| area.downcast::<gtk::Box>().ok())
+        {
+            area.pack_start(&entry, false, false, 0);
+        }
+        entry.show();
+        let result = if dialog.run() == gtk::ResponseType::Ok {
+            Some(entry.get_text().to_string())
+        } else {
+            None
+        };
+        dialog.close();
+        result
+    }
+
+    /// Pango description of the font picked, none if the dialog was cancelled.
+    pub fn font_dialog_result(&self, title: &str, current: Option<&str>) -> Option<string::String> {
+        let dialog = gtk::FontChooserDialog::new(Some(title), Some(&self.window));
+        if let Some(font) = current {
+            dialog.set_font(font);
+        }
+        let result = if dialog.run() == gtk::ResponseType::Ok {
+            dialog.get_font().map(|font| font.to_string())
+        } else {
+            None
+        };
+        dialog.close();
+        result
+    }
+
+    /// ARGB value of the color picked, none if the dialog was cancelled.
+    pub fn color_dialog_result(&self, title: &str, current: Option<u32>) -> Option<u32> {
+        let dialog = gtk::ColorChooserDialog::new(Some(title), Some(&self.window));
+        dialog.set_use_alpha(true);
+        if let Some(color) = current {
+            let channel = |shift: u32| f64::from((color >> shift) & 0xff) / 255.0;
+            dialog.set_rgba(&gdk::RGBA {
+                red: channel(16),
+                green: channel(8),
+                blue: channel(0),
+                alpha: channel(24),
+            });
+        }
+        let result = if dialog.run() == gtk::ResponseType::Ok {
+            let rgba = dialog.get_rgba();
+            let channel = |value: f64, shift: u32| ((value.max(0.0).min(1.0) * 255.0).round() as u32) << shift;
+            Some(channel(rgba.alpha, 24) | channel(rgba.red, 16) | channel(rgba.green, 8) | channel(rgba.blue, 0))
         } else {
             None
         };
         dialog.close();
-        result_uri
+        result
+    }
+
+    pub fn confirm_dialog(&self, message: &str, details: Option<&str>) -> bool {
+        let dialog = gtk::MessageDialog::new(
+            Some(&self.window),
+            gtk::DialogFlags::MODAL,
+            gtk::MessageType::Question,
+            gtk::ButtonsType::YesNo,
+            message,
+        );
+        dialog.set_property_secondary_text(details);
+        let confirmed = dialog.run() == gtk::ResponseType::Yes;
+        dialog.close();
+        confirmed
+    }
+
+    /// Index of the choice picked, none if the dialog was cancelled.
+    pub fn choice_dialog(&self, message: &str, details: Option<&str>, choices: &[&str]) -> Option<usize> {
+        let dialog = gtk::MessageDialog::new(
+            Some(&self.window),
+            gtk::DialogFlags::MODAL,
+            gtk::MessageType::Question,
+            gtk::ButtonsType::None,
+            message,
+        );
+        dialog.set_property_secondary_text(details);
+        dialog.add_button("Cancel", gtk::ResponseType::Cancel);
+        for (index, choice) in choices.iter().enumerate() {
+            dialog.add_button(choice, gtk::ResponseType::Other(index as u16));
+        }
+        let response = dialog.run();
+        dialog.close();
+        match response {
+            gtk::ResponseType::Other(index) => Some(usize::from(index)),
+            _ => None,
+        }
+    }
+
+    pub fn display_error_dialog(&self, message: &str, details: Option<&str>) {
+        let dialog = gtk::MessageDialog::new(
+            Some(&self.window),
+            gtk::DialogFlags::MODAL,
+            gtk::MessageType::Error,
+            gtk::ButtonsType::Close,
+            message,
+        );
+        dialog.set_property_secondary_text(details);
+        dialog.run();
+        dialog.close();
+    }
+
+    pub fn display_info_dialog(&self, message: &str, details: Option<&str>) {
+        let dialog = gtk::MessageDialog::new(
+            Some(&self.window),
+            gtk::DialogFlags::MODAL,
+            gtk::MessageType::Info,
+            gtk::ButtonsType::Close,
+            message,
+        );
+        dialog.set_property_secondary_text(details);
+        dialog.run();
+        dialog.close();
     }
 
     pub fn start<F: Fn() + Send + Sync + 'static>(&self, f: F) {
@@ -382,6 +1052,45 @@ impl UIContext {
             });
     }
 
+    /// The callback receives the start time, in nanoseconds, of the chapter
+    /// marker clicked on the position slider.
+    pub fn set_chapter_mark_clicked_callback<F: Fn(u64) + 'static>(&self, f: F) {
+        let marks = self.chapter_marks.clone();
+        self.progress_bar.connect_button_press_event(move |scale, event| {
+            if event.get_event_type() != gdk::EventType::ButtonPress || event.get_button() != 1 {
+                return Inhibit(false);
+            }
+            let (x, _) = event.get_position();
+            match marks.borrow().find(scale, x) {
+                Some((start, _)) => {
+                    f(*start);
+                    Inhibit(true)
+                }
+                None => Inhibit(false),
+            }
+        });
+    }
+
+    /// Mark the chapter boundaries on the position slider, leaving out
+    /// those too close to the previous marker.
+    pub fn set_chapter_marks(&self, chapters: Vec<Chapter>) {
+        let mut marks = self.chapter_marks.borrow_mut();
+        marks.chapters = chapters;
+        marks.update(&self.progress_bar);
+    }
+
+    /// The callback receives a position of the slider in seconds, and gives
+    /// the frame previewed when hovering it.
+    pub fn set_seek_thumbnail_callback<F: Fn(f64) -> Option<gdk_pixbuf::Pixbuf> + 'static>(&self, f: F) {
+        *self.seek_thumbnail_source.borrow_mut() = Some(Box::new(f));
+    }
+
+    pub fn set_chapter_marks_enabled(&self, enabled: bool) {
+        let mut marks = self.chapter_marks.borrow_mut();
+        marks.enabled = enabled;
+        marks.update(&self.progress_bar);
+    }
+
     pub fn set_volume_value_changed_callback<F: Fn(f64) + Send + Sync + 'static>(&mut self, f: F) {
         let volume_scale = self.volume_button.clone().upcast::<gtk::ScaleButton>();
         self.volume_signal_handler_id = Some(volume_scale.connect_value_changed(move |_, value| {
@@ -405,11 +1114,24 @@ impl UIContext {
             .drag_dest_set(gtk::DestDefaults::ALL, &targets, gdk::DragAction::COPY);
         self.window.connect_drag_data_received(move |_, _, _, _, data, _, _| {
             if let Some(s) = data.get_text() {
-                f(&s.trim());
+                // Only the first item of URI lists is used. Some file managers
+                // provide plain paths, which need escaping to be valid URIs.
+                let text = s.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+                if glib::uri_parse_scheme(text).is_some() {
+                    f(text);
+                } else if let Ok(uri) = glib::filename_to_uri(text, None) {
+                    f(&uri);
+                }
             }
         });
     }
 
+    /// The callback is invoked before accelerators are processed, and key
+    /// presses it handles, by returning true, don't trigger them.
+    pub fn set_key_press_callback<F: Fn(&gdk::EventKey) -> bool + Send + Sync + 'static>(&self, f: F) {
+        self.window.connect_key_press_event(move |_, event| Inhibit(f(event)));
+    }
+
     pub fn set_audio_offset_entry_updated_callback<F: Fn(i64) + Send + Sync + 'static>(&mut self, f: F) {
         let entry = self.audio_offset_entry.clone();
         self.audio_offset_entry_signal_handler_id = Some(entry.connect_value_changed(move |button| {
@@ -459,14 +1181,169 @@ impl UIContext {
             range.set_value(position as f64);
             glib::signal_handler_unblock(&range, &handler_id);
         }
+
+        let end = range.get_adjustment().get_upper();
+        if end > 0.0 {
+            self.progress_line_state.borrow_mut().fraction = (position as f64 / end).min(1.0);
+            if self.progress_line.is_visible() {
+                self.progress_line.queue_draw();
+            }
+        }
+    }
+
+    /// Configure the thin progress line replacing the toolbar in fullscreen,
+    /// taking effect the next time fullscreen is entered.
+    pub fn set_fullscreen_progress_bar(&self, enabled: bool, height: u32, color: &str) {
+        let mut state = self.progress_line_state.borrow_mut();
+        state.enabled = enabled;
+        match color.parse::<gdk::RGBA>() {
+            Ok(color) => state.color = color,
+            Err(_) => eprintln!("Invalid progress bar color: {}", color),
+        }
+        self.progress_line.set_size_request(-1, height.max(1) as i32);
+        if !enabled {
+            self.progress_line.hide();
+        }
+    }
+
+    /// Cover the video area with the frame, scaled to fit, or with black.
+    pub fn show_still_frame(&self, frame: Option<gdk_pixbuf::Pixbuf>) {
+        *self.still_frame_image.borrow_mut() = frame.map(|frame| (frame, true));
+        self.still_frame.show();
+        self.still_frame.queue_draw();
+    }
+
+    /// Cover the video area with the application icon.
+    pub fn show_logo(&self) {
+        let logo = gtk::IconTheme::get_default()
+            .and_then(|theme| {
+                theme
+                    .load_icon("net.baseart.Glide", 128, gtk::IconLookupFlags::empty())
+                    .ok()
+            })
+            .and_then(|logo| logo);
+        *self.still_frame_image.borrow_mut() = logo.map(|logo| (logo, false));
+        self.still_frame.show();
+        self.still_frame.queue_draw();
+    }
+
+    pub fn hide_still_frame(&self) {
+        self.still_frame.hide();
+        *self.still_frame_image.borrow_mut() = None;
     }
 
     pub fn set_video_area(&self, video_area: &gtk::Widget) {
-        self.main_box.pack_start(&*video_area, true, true, 0);
-        self.main_box.reorder_child(&*video_area, 0);
+        if let Some(previous) = self.video_overlay.get_child() {
+            self.video_overlay.remove(&previous);
+        }
+        self.video_overlay.add(&*video_area);
         video_area.show();
     }
 
+    /// Create a window for a mirror video output, fullscreen on the given
+    /// monitor or, by default, on the first one not showing the main window.
+    pub fn create_mirror_window<F: Fn() -> Inhibit + 'static>(
+        &self,
+        video_widget: &gtk::Widget,
+        monitor: Option<i32>,
+        on_close: F,
+    ) -> gtk::Window {
+        let window = gtk::Window::new(gtk::WindowType::Toplevel);
+        window.set_title("Glide");
+        window.set_default_size(MINIMAL_WINDOW_SIZE.0, MINIMAL_WINDOW_SIZE.1);
+        window.add(video_widget);
+        window.connect_delete_event(move |_, _| on_close());
+        window.show_all();
+
+        let display = self.window.get_display();
+        let monitor = monitor.or_else(|| {
+            let current = self.window.get_window().and_then(|w| display.get_monitor_at_window(&w));
+            (0..display.get_n_monitors()).find(|i| display.get_monitor(*i) != current)
+        });
+        if let (Some(monitor), Some(screen)) = (monitor, window.get_screen()) {
+            window.fullscreen_on_monitor(&screen, monitor);
+        }
+        window
+    }
+
+    pub fn show_osd_message(&self, message: &str) {
+        let label = &self.osd_label;
+        label.set_text(message);
+        label.show();
+
+        if let Some(source) = OSD_SOURCE.lock().unwrap().take() {
+            glib::source_remove(source);
+        }
+        let label_weak = SendWeakRef::from(label.downgrade());
+        *OSD_SOURCE.lock().unwrap() = Some(glib::timeout_add_seconds(OSD_TIMEOUT_SECONDS, move || {
+            if let Some(label) = label_weak.upgrade() {
+                label.hide();
+            }
+            *OSD_SOURCE.lock().unwrap() = None;
+            glib::Continue(false)
+        }));
+    }
+
+    /// Show a message in the OSD until `hide_osd_message()` is called.
+    pub fn show_osd_input(&self, text: &str) {
+        if let Some(source) = OSD_SOURCE.lock().unwrap().take() {
+            glib::source_remove(source);
+        }
+        self.osd_label.set_text(text);
+        self.osd_label.show();
+    }
+
+    pub fn hide_osd_message(&self) {
+        if let Some(source) = OSD_SOURCE.lock().unwrap().take() {
+            glib::source_remove(source);
+        }
+        self.osd_label.hide();
+    }
+
+    pub fn show_auto_advance_countdown(&self, remaining_seconds: u32) {
+        self.auto_advance_label
+            .set_text(&format!("Next item in {}s", remaining_seconds));
+        self.auto_advance_box.show();
+    }
+
+    pub fn hide_auto_advance_countdown(&self) {
+        self.auto_advance_box.hide();
+    }
+
+    pub fn show_resume_prompt(&self, position: &str) {
+        self.resume_button.set_label(&format!("Resume at {}", position));
+        self.resume_box.show();
+    }
+
+    pub fn hide_resume_prompt(&self) {
+        self.resume_box.hide();
+    }
+
+    pub fn show_audio_meter(&self, visible: bool) {
+        if visible {
+            self.audio_meter.show();
+        } else {
+            self.audio_levels.borrow_mut().clear();
+            self.audio_meter.hide();
+        }
+    }
+
+    /// Show the frame counters over the video, or hide them without text.
+    pub fn show_frame_stats(&self, text: Option<&str>) {
+        match text {
+            Some(text) => {
+                self.frame_stats_label.set_text(text);
+                self.frame_stats_label.show();
+            }
+            None => self.frame_stats_label.hide(),
+        }
+    }
+
+    pub fn update_audio_meter(&self, levels: &[f64]) {
+        self.audio_levels.replace(levels.to_vec());
+        self.audio_meter.queue_draw();
+    }
+
     pub fn resize_window(&self, width: i32, height: i32) {
         let mut width = width;
         let mut height = height;
@@ -488,6 +1365,12 @@ impl UIContext {
         self.window.set_title(title);
     }
 
+    /// Move the focused position slider by these offsets, in seconds, with
+    /// the arrow keys.
+    pub fn set_seek_offsets(&self, backward: f64, forward: f64) {
+        self.seek_offsets.set((backward, forward));
+    }
+
     pub fn set_position_range_end(&self, end: f64) {
         let progress_bar = &self.progress_bar;
         let range = progress_bar.clone().upcast::<gtk::Range>();
@@ -496,6 +1379,9 @@ impl UIContext {
             range.set_range(0.0, end);
             glib::signal_handler_unblock(&range, &handler_id);
         }
+        let step = self.seek_offsets.get().1;
+        range.set_increments(step, (end / 10.0).max(step));
+        self.chapter_marks.borrow_mut().update(progress_bar);
 
         // Force the GtkScale to recompute its label widget size.
         progress_bar.set_draw_value(false);
@@ -533,6 +1419,7 @@ impl UIContext {
                 let image =
                     gtk::Image::from_icon_name(Some("media-playback-pause-symbolic"), gtk::IconSize::SmallToolbar);
                 self.pause_button.set_image(Some(&image));
+                self.hide_still_frame();
             }
             _ => {}
         };
@@ -544,16 +1431,51 @@ impl UIContext {
         self.subtitle_track_menu.append_section(None, section);
     }
 
+    /// List the chapters, or leave the section empty without any.
+    pub fn update_chapter_menu(&self, chapters: &[(string::String, u64)]) {
+        self.chapter_list_menu.remove_all();
+        for (label, start) in chapters {
+            let item = gio::MenuItem::new(Some(label), None);
+            item.set_action_and_target_value(Some("app.chapter"), Some(&start.to_variant()));
+            self.chapter_list_menu.append_item(&item);
+        }
+    }
+
     pub fn update_audio_track_menu(&self, section: &gio::Menu) {
         self.audio_track_menu.remove_all();
         self.audio_track_menu.append_section(None, section);
     }
 
+    pub fn set_video_sinks(&self, names: &[&str]) {
+        self.video_sink_menu.remove_all();
+        for name in names {
+            self.video_sink_menu
+                .append(Some(name), Some(&format!("app.video-sink::{}", name)));
+        }
+    }
+
+    /// List the audio profiles after the default output, which has an empty
+    /// name.
+    pub fn set_audio_profiles(&self, names: &[&str]) {
+        self.audio_profile_menu.remove_all();
+        for name in std::iter::once(&"").chain(names) {
+            let label = if name.is_empty() { "Default output" } else { name };
+            let item = gio::MenuItem::new(Some(label), None);
+            item.set_action_and_target_value(Some("app.audio-profile"), Some(&name.to_variant()));
+            self.audio_profile_menu.append_item(&item);
+        }
+    }
+
     pub fn update_video_track_menu(&self, section: &gio::Menu) {
         self.video_track_menu.remove_all();
         self.video_track_menu.append_section(None, section);
     }
 
+    pub fn update_aspect_ratio_menu(&self, section: &gio::Menu) {
+        self.aspect_ratio_menu.remove_all();
+        self.aspect_ratio_menu.append_section(None, section);
+    }
+
     pub fn clear_audio_visualization_menu(&self) {
         self.audio_visualization_menu.remove_all();
     }